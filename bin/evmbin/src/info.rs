@@ -22,7 +22,13 @@ use ethcore::{
 };
 use ethereum_types::{H256, U256};
 use ethjson;
-use std::time::{Duration, Instant};
+use hyperproofs::ShardContext;
+use shard;
+use std::{
+    cmp,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 use types::transaction;
 use vm::ActionParams;
 
@@ -190,6 +196,76 @@ pub fn run_transaction<T: Informant>(
     T::finish(result, &mut sink)
 }
 
+/// Execute given shard transaction on the shard of `shard` in a block on top of genesis,
+/// and return its outcome there.
+pub fn run_shard_transaction<T: Informant>(
+    spec: &spec::Spec,
+    shard: Arc<ShardContext>,
+    pre_state: &pod_state::PodState,
+    transaction: transaction::SignedTransaction,
+    mut informant: T,
+    trie_spec: TrieSpec,
+) -> Option<shard::Outcome> {
+    let genesis = spec.genesis_header();
+    let env_info = &client::EnvInfo {
+        number: genesis.number(),
+        author: *genesis.author(),
+        timestamp: genesis.timestamp(),
+        difficulty: *genesis.difficulty(),
+        last_hashes: Arc::new([H256::default(); 256].to_vec()),
+        gas_used: 0.into(),
+        // leave room for the transaction, whatever the genesis gas limit
+        gas_limit: cmp::max(*genesis.gas_limit(), transaction.tx().gas),
+        base_fee: genesis.base_fee(),
+    };
+
+    informant.before_test(&format!("{:#x}", transaction.hash()), "starting");
+    informant.set_gas(env_info.gas_limit);
+
+    let mut sink = informant.clone_sink();
+    let mut outcome = None;
+    let result = run_with_shard(
+        spec,
+        trie_spec,
+        shard,
+        transaction.tx().gas,
+        pre_state,
+        |mut client| match client.transact_shard(
+            env_info,
+            transaction,
+            trace::NoopTracer,
+            informant,
+        ) {
+            Ok(success) => {
+                outcome = Some(shard::Outcome::from(&success));
+                let TransactSuccess {
+                    state_root,
+                    gas_left,
+                    output,
+                    vm_trace,
+                    end_state,
+                    ..
+                } = success.result;
+                (Ok(output), state_root, end_state, Some(gas_left), vm_trace)
+            }
+            Err(TransactErr {
+                state_root,
+                error,
+                end_state,
+            }) => (
+                Err(EvmTestError::ClientError(error)),
+                state_root,
+                end_state,
+                None,
+                None,
+            ),
+        },
+    );
+
+    T::finish(result, &mut sink);
+    outcome
+}
+
 fn dump_state(state: &state::State<state_db::StateDB>) -> Option<pod_state::PodState> {
     state.to_pod_full().ok()
 }
@@ -202,6 +278,36 @@ pub fn run<'a, F, X>(
     pre_state: &'a pod_state::PodState,
     run: F,
 ) -> RunResult<X>
+where
+    F: FnOnce(
+        EvmTestClient,
+    ) -> (
+        Result<Vec<u8>, EvmTestError>,
+        H256,
+        Option<pod_state::PodState>,
+        Option<U256>,
+        Option<X>,
+    ),
+{
+    run_with_shard(
+        spec,
+        trie_spec,
+        Default::default(),
+        initial_gas,
+        pre_state,
+        run,
+    )
+}
+
+/// Execute VM on the shard of `shard`
+pub fn run_with_shard<'a, F, X>(
+    spec: &'a spec::Spec,
+    trie_spec: TrieSpec,
+    shard: Arc<ShardContext>,
+    initial_gas: U256,
+    pre_state: &'a pod_state::PodState,
+    run: F,
+) -> RunResult<X>
 where
     F: FnOnce(
         EvmTestClient,
@@ -216,16 +322,15 @@ where
     let do_dump = trie_spec == TrieSpec::Fat;
 
     let mut test_client =
-        EvmTestClient::from_pod_state_with_trie(spec, pre_state.clone(), trie_spec).map_err(
-            |error| Failure {
+        EvmTestClient::from_pod_state_with_shard(spec, pre_state.clone(), trie_spec, shard)
+            .map_err(|error| Failure {
                 gas_used: 0.into(),
                 error,
                 time: Duration::from_secs(0),
                 traces: None,
                 state_root: H256::default(),
                 end_state: None,
-            },
-        )?;
+            })?;
 
     if do_dump {
         test_client.set_dump_state_fn(dump_state);
@@ -264,7 +369,7 @@ pub mod tests {
     use super::*;
     use ethereum_types::Address;
     use rustc_hex::FromHex;
-    use std::sync::Arc;
+    use serde_json;
     use tempdir::TempDir;
 
     pub fn run_test<T, I, F>(informant: I, compare: F, code: &str, gas: T, expected: &str)
@@ -313,4 +418,32 @@ pub mod tests {
 "#
         );
     }
+
+    #[test]
+    fn should_complete_shard_transfer() {
+        use display::{config::Config, std_json::tests::informant};
+
+        let (inf, _) = informant(Config::default());
+        let tempdir = TempDir::new("").unwrap();
+        let spec = ::ethcore::ethereum::new_foundation(&tempdir.path());
+        let transaction: shard::Json = serde_json::from_value(json!({
+            "from": "0x00000000000000000000000000000000000000aa",
+            "to": "0x00000000000000000000000000000000000000bb",
+            "gas": "0x5208",
+        }))
+        .unwrap();
+
+        let outcome = run_shard_transaction(
+            &spec,
+            Default::default(),
+            spec.genesis_state(),
+            transaction.into_transaction(0),
+            inf,
+            TrieSpec::Secure,
+        )
+        .unwrap();
+
+        assert_eq!(outcome.status, shard::Status::Complete);
+        assert_eq!(outcome.next_shard, None);
+    }
 }
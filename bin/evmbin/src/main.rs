@@ -31,6 +31,7 @@ extern crate docopt;
 extern crate env_logger;
 extern crate ethereum_types;
 extern crate evm;
+extern crate hyperproofs;
 extern crate panic_hook;
 extern crate parity_bytes as bytes;
 extern crate vm;
@@ -44,16 +45,18 @@ extern crate tempdir;
 
 use bytes::Bytes;
 use docopt::Docopt;
-use ethcore::{json_tests, spec, TrieSpec};
+use ethcore::{json_tests, pod_state::PodState, spec, TrieSpec};
 use ethereum_types::{Address, U256};
 use ethjson::spec::ForkSpec;
 use evm::EnvInfo;
+use hyperproofs::{ShardConfig, ShardContext};
 use rustc_hex::FromHex;
 use std::{fmt, fs, path::PathBuf, sync::Arc};
 use vm::{ActionParams, CallType};
 
 mod display;
 mod info;
+mod shard;
 
 use info::Informant;
 
@@ -63,6 +66,7 @@ EVM implementation for Parity.
 
 Usage:
     openethereum-evm state-test <file> [--json --std-json --std-dump-json --only NAME --chain CHAIN --std-out-only --std-err-only --omit-storage-output --omit-memory-output]
+    openethereum-evm shard-tx <tx> --shard-id SHARD --shard-count COUNT [--pre FILE --chain CHAIN --json --std-json --std-dump-json --std-out-only --std-err-only --omit-storage-output --omit-memory-output]
     openethereum-evm stats [options]
     openethereum-evm stats-jsontests-vm <file>
     openethereum-evm [options]
@@ -70,6 +74,12 @@ Usage:
 
 Commands:
     state-test         Run a state test from a json file.
+    shard-tx           Execute a shard transaction the way the node of a shard
+                       does and print its status, next shard, data list and
+                       the frame the next hop resumes from.
+                       The transaction is given as RLP hex or as a json or RLP
+                       file; legacy transactions are turned into shard
+                       transactions first.
     stats              Execute EVM runtime code and return the statistics.
     stats-jsontests-vm Execute standard json-tests format VMTests and return
                        timing statistics in tsv format.
@@ -89,6 +99,12 @@ State test options:
                        HomesteadToDaoAt5, HomesteadToEIP150At5, Berlin, Yolo3).
     --only NAME        Runs only a single test matching the name.
 
+Shard transaction options:
+    --shard-id SHARD   Shard executing the transaction.
+    --shard-count COUNT  Number of shards in the network.
+    --pre FILE         Json file with the accounts before the transaction, in
+                       the format of state tests. Defaults to the genesis state.
+
 General options:
     --json                    Display verbose results in JSON.
     --std-json                Display results in standardized JSON format.
@@ -116,6 +132,8 @@ fn main() {
 
     if args.cmd_state_test {
         run_state_test(args)
+    } else if args.cmd_shard_tx {
+        run_shard_tx(args)
     } else if args.cmd_stats_jsontests_vm {
         run_stats_jsontests_vm(args)
     } else if args.flag_json {
@@ -289,6 +307,75 @@ fn run_state_test(args: Args) {
     }
 }
 
+fn run_shard_tx(args: Args) {
+    let config = args.config();
+    let trie_spec = if args.flag_std_dump_json {
+        TrieSpec::Fat
+    } else {
+        TrieSpec::Secure
+    };
+
+    if args.flag_json {
+        run_shard_tx_with(args, display::json::Informant::new(config), trie_spec)
+    } else if args.flag_std_dump_json || args.flag_std_json {
+        if args.flag_std_err_only {
+            run_shard_tx_with(
+                args,
+                display::std_json::Informant::err_only(config),
+                trie_spec,
+            )
+        } else if args.flag_std_out_only {
+            run_shard_tx_with(
+                args,
+                display::std_json::Informant::out_only(config),
+                trie_spec,
+            )
+        } else {
+            run_shard_tx_with(
+                args,
+                display::std_json::Informant::new_default(config),
+                trie_spec,
+            )
+        }
+    } else {
+        run_shard_tx_with(args, display::simple::Informant::new(config), trie_spec)
+    }
+}
+
+fn run_shard_tx_with<T: Informant>(args: Args, informant: T, trie_spec: TrieSpec) {
+    let spec = arg(args.spec(), "--chain");
+    let shard_id = arg(args.shard_id(), "--shard-id");
+    let shard_count = arg(args.shard_count(), "--shard-count");
+    if shard_id >= shard_count {
+        die(format!(
+            "Shard {} is not part of a network of {} shards.",
+            shard_id, shard_count
+        ));
+    }
+    let shard = Arc::new(ShardContext::new(ShardConfig {
+        shard_count,
+        ..spec.params().shard_config()
+    }));
+    shard.set_shard(shard_id);
+
+    let pre_state = arg(args.pre_state(), "--pre").unwrap_or_else(|| spec.genesis_state().clone());
+    let transaction = arg(
+        shard::load(args.arg_tx.as_ref().expect("TX is required"), shard_id),
+        "<tx>",
+    );
+    let transaction = if transaction.is_shard() {
+        transaction
+    } else {
+        transaction.to_shard_txn(&|address| shard.address_shard(address))
+    };
+
+    let outcome =
+        info::run_shard_transaction(&spec, shard, &pre_state, transaction, informant, trie_spec);
+    if let Some(outcome) = outcome {
+        println!("{}", json!({ "shard": outcome }));
+    }
+}
+
 fn run_call<T: Informant>(args: Args, informant: T) {
     let from = arg(args.from(), "--from");
     let to = arg(args.to(), "--to");
@@ -339,7 +426,9 @@ struct Args {
     cmd_stats: bool,
     cmd_state_test: bool,
     cmd_stats_jsontests_vm: bool,
+    cmd_shard_tx: bool,
     arg_file: Option<PathBuf>,
+    arg_tx: Option<String>,
     flag_only: Option<String>,
     flag_from: Option<String>,
     flag_to: Option<String>,
@@ -348,6 +437,9 @@ struct Args {
     flag_gas_price: Option<String>,
     flag_input: Option<String>,
     flag_chain: Option<String>,
+    flag_shard_id: Option<String>,
+    flag_shard_count: Option<String>,
+    flag_pre: Option<PathBuf>,
     flag_json: bool,
     flag_std_json: bool,
     flag_std_dump_json: bool,
@@ -400,6 +492,32 @@ impl Args {
         }
     }
 
+    pub fn shard_id(&self) -> Result<u64, String> {
+        match self.flag_shard_id {
+            Some(ref shard) => shard.parse().map_err(to_string),
+            None => Ok(0),
+        }
+    }
+
+    pub fn shard_count(&self) -> Result<u64, String> {
+        match self.flag_shard_count {
+            Some(ref count) => count.parse().map_err(to_string),
+            None => Ok(1),
+        }
+    }
+
+    pub fn pre_state(&self) -> Result<Option<PodState>, String> {
+        match self.flag_pre {
+            Some(ref file) => {
+                let file = fs::File::open(file).map_err(to_string)?;
+                let state: ethjson::state::AccountState =
+                    serde_json::from_reader(file).map_err(to_string)?;
+                Ok(Some(state.into()))
+            }
+            None => Ok(None),
+        }
+    }
+
     pub fn spec(&self) -> Result<spec::Spec, String> {
         Ok(match self.flag_chain {
             Some(ref spec_name) => {
@@ -508,4 +626,30 @@ mod tests {
         assert_eq!(args.flag_chain, Some("homestead".to_owned()));
         assert_eq!(args.flag_only, Some("add11".to_owned()));
     }
+
+    #[test]
+    fn should_parse_shard_tx_command() {
+        let args = run(&[
+            "openethereum-evm",
+            "shard-tx",
+            "./tx.json",
+            "--shard-id",
+            "1",
+            "--shard-count",
+            "4",
+            "--pre",
+            "./pre.json",
+            "--chain",
+            "./chain.json",
+            "--std-json",
+        ]);
+
+        assert_eq!(args.cmd_shard_tx, true);
+        assert_eq!(args.arg_tx, Some("./tx.json".to_owned()));
+        assert_eq!(args.shard_id(), Ok(1));
+        assert_eq!(args.shard_count(), Ok(4));
+        assert_eq!(args.flag_pre, Some("./pre.json".into()));
+        assert_eq!(args.flag_chain, Some("./chain.json".to_owned()));
+        assert_eq!(args.flag_std_json, true);
+    }
 }
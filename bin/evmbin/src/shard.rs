@@ -0,0 +1,222 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Shard transaction input and outcome.
+
+use ethcore::client::ShardTransactSuccess;
+use ethereum_types::{Address, H256, U256};
+use ethjson;
+use rustc_hex::{FromHex, ToHex};
+use serde_json;
+use std::{collections::BTreeMap, fs, path::Path};
+use types::transaction::{
+    Action, ShardTransactionTx, SignedTransaction, Transaction, TypedTransaction,
+};
+
+/// Shard transaction given as json, signed on behalf of `from`.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub struct Json {
+    /// Sender of this hop.
+    pub from: Address,
+    /// Recipient, `None` for contract creation.
+    pub to: Option<Address>,
+    /// Nonce of `from`.
+    #[serde(default)]
+    pub nonce: U256,
+    /// Gas price.
+    #[serde(default)]
+    pub gas_price: U256,
+    /// Gas limit of the transaction.
+    pub gas: U256,
+    /// Transferred value.
+    #[serde(default)]
+    pub value: U256,
+    /// Call data or contract code.
+    #[serde(default)]
+    pub data: ethjson::bytes::Bytes,
+    /// Shard the transaction is sealed on, the executing shard if not given.
+    pub shard: Option<u64>,
+    /// Shard the transaction was routed to by its previous hop.
+    pub next_shard: Option<u64>,
+    /// Whether the transaction continues a call left incomplete on another shard.
+    #[serde(default)]
+    pub incomplete: bool,
+    /// Number of shards the call already executed on.
+    #[serde(default)]
+    pub hop_count: u64,
+    /// Sender of the first hop, `from` if not given.
+    pub original_sender: Option<Address>,
+    /// Balances and storage values supplied by the previous hops, keyed by shard key.
+    #[serde(default)]
+    pub data_list: BTreeMap<H256, U256>,
+    /// Frame the call resumes from, as left by the previous hop.
+    #[serde(default)]
+    pub suspended_frame: ethjson::bytes::Bytes,
+}
+
+impl Json {
+    /// Shard transaction executed on `shard`.
+    pub fn into_transaction(self, shard: u64) -> SignedTransaction {
+        let action = match self.to {
+            Some(to) => Action::Call(to),
+            None => Action::Create,
+        };
+        let transaction = Transaction {
+            nonce: self.nonce,
+            gas_price: self.gas_price,
+            gas: self.gas,
+            action,
+            value: self.value,
+            data: self.data.into(),
+        };
+        let mut shard_transaction = ShardTransactionTx::new(
+            transaction,
+            self.shard.unwrap_or(shard),
+            self.next_shard.unwrap_or(999u64),
+            self.incomplete as u64,
+            self.hop_count,
+            self.original_sender.unwrap_or(self.from),
+            self.data_list.into_iter().collect(),
+            Vec::new(),
+            String::new(),
+        );
+        shard_transaction.suspended_frame = self.suspended_frame.into();
+        TypedTransaction::ShardTransaction(shard_transaction).fake_sign(self.from)
+    }
+}
+
+/// Load a transaction from `input`, a json or RLP file, or RLP as hex.
+pub fn load(input: &str, shard: u64) -> Result<SignedTransaction, String> {
+    let content = if Path::new(input).is_file() {
+        fs::read_to_string(input).map_err(|e| format!("Unable to read {}: {}", input, e))?
+    } else {
+        input.to_owned()
+    };
+    let content = content.trim();
+
+    if content.starts_with('{') {
+        let json: Json = serde_json::from_str(content).map_err(|e| format!("{}", e))?;
+        return Ok(json.into_transaction(shard));
+    }
+    let rlp = content
+        .trim_start_matches("0x")
+        .from_hex()
+        .map_err(|e| format!("{}", e))?;
+    let unverified = TypedTransaction::decode(&rlp).map_err(|e| format!("{}", e))?;
+    SignedTransaction::new(unverified).map_err(|e| format!("{}", e))
+}
+
+/// Completion status of a shard transaction on the executing shard.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    /// The call finished on this shard.
+    Complete,
+    /// The call needs data of another shard.
+    Incomplete,
+    /// The call reverted on this shard.
+    Reverted,
+}
+
+/// What a shard node records of a shard transaction after executing it.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Outcome {
+    /// Completion status.
+    pub status: Status,
+    /// Shard to continue on, if incomplete.
+    pub next_shard: Option<u64>,
+    /// Data list carried to the next hop, keyed by shard key.
+    pub data_list: BTreeMap<H256, U256>,
+    /// Frame the next hop resumes from as RLP hex, if the call stopped in a resumable frame.
+    pub suspended_frame: Option<String>,
+}
+
+impl<'a, T, V> From<&'a ShardTransactSuccess<T, V>> for Outcome {
+    fn from(success: &'a ShardTransactSuccess<T, V>) -> Self {
+        let status = if success.incomplete {
+            Status::Incomplete
+        } else if success.reverted {
+            Status::Reverted
+        } else {
+            Status::Complete
+        };
+        Outcome {
+            status,
+            next_shard: success.next_shard,
+            data_list: success
+                .data_list
+                .iter()
+                .map(|(key, value)| (*key, *value))
+                .collect(),
+            suspended_frame: if success.suspended_frame.is_empty() {
+                None
+            } else {
+                Some(format!("0x{}", success.suspended_frame.to_hex()))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_load_json_transaction() {
+        let transaction = load(
+            r#"{
+                "from": "0x0000000000000000000000000000000000000001",
+                "to": "0x0000000000000000000000000000000000000002",
+                "gas": "0x5208",
+                "value": "0x10",
+                "incomplete": true,
+                "hopCount": 1,
+                "nextShard": 1,
+                "dataList": {
+                    "0x0000000000000000000000000000000000000000000000000000000000000001": "0x64"
+                },
+                "suspendedFrame": "0xc0"
+            }"#,
+            1,
+        )
+        .unwrap();
+
+        assert!(transaction.is_shard());
+        assert!(transaction.is_incomplete());
+        assert!(transaction.contains_balance());
+        assert_eq!(transaction.sender(), Address::from_low_u64_be(1));
+        assert_eq!(transaction.original_sender(), Address::from_low_u64_be(1));
+        assert_eq!(transaction.get_next_shard(), 1);
+        assert_eq!(transaction.get_hop_count(), 1);
+        assert_eq!(transaction.tx().value, 0x10.into());
+        assert_eq!(
+            transaction
+                .shard_data_hashmap()
+                .get(&H256::from_low_u64_be(1)),
+            Some(&0x64.into())
+        );
+        assert_eq!(transaction.suspended_frame(), &[0xc0][..]);
+    }
+
+    #[test]
+    fn should_reject_malformed_input() {
+        assert!(load("0x1234", 0).is_err());
+        assert!(load(r#"{"from": "0x01"}"#, 0).is_err());
+    }
+}
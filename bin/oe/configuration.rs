@@ -617,6 +617,9 @@ impl Configuration {
 
             pool_limits: self.pool_limits()?,
             pool_verification_options: self.pool_verification_options()?,
+            proof_aggregation_timeout: Duration::from_millis(
+                self.args.arg_proof_aggregation_timeout,
+            ),
         };
 
         Ok(options)
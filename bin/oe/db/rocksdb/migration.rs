@@ -44,10 +44,23 @@ pub const TO_V12: ChangeColumns = ChangeColumns {
     version: 12,
 };
 
+/// The migration from v16 to v17.
+/// Adds a column for shard round state.
+#[cfg(feature = "shard")]
+pub const TO_V17: ChangeColumns = ChangeColumns {
+    pre_columns: Some(7),
+    post_columns: Some(8),
+    version: 17,
+};
+
 /// Database is assumed to be at default version, when no version file is found.
 const DEFAULT_VERSION: u32 = 5;
 /// Current version of database models.
+#[cfg(not(feature = "shard"))]
 const CURRENT_VERSION: u32 = 16;
+/// Current version of database models.
+#[cfg(feature = "shard")]
+const CURRENT_VERSION: u32 = 17;
 /// Until this version please use upgrade tool.
 const USE_MIGRATION_TOOL: u32 = 15;
 /// A version of database at which blooms-db was introduced
@@ -161,6 +174,10 @@ fn consolidated_database_migrations(
     manager
         .add_migration(TO_V12)
         .map_err(|_| Error::MigrationImpossible)?;
+    #[cfg(feature = "shard")]
+    manager
+        .add_migration(TO_V17)
+        .map_err(|_| Error::MigrationImpossible)?;
     Ok(manager)
 }
 
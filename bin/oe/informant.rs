@@ -35,7 +35,6 @@ use crate::{
     types::BlockNumber,
 };
 use atty;
-use hyperproofs::AggProof;
 use ethcore::{
     client::{
         BlockChainClient, BlockChainInfo, BlockId, BlockInfo, BlockQueueInfo, ChainInfo,
@@ -352,7 +351,6 @@ impl<T: InformantData> Informant<T> {
 impl ChainNotify for Informant<FullNodeInformantData> {
     // t_nb 11.2 Informant. Prints new block inclusiong to console/log.
     fn new_blocks(&self, new_blocks: NewBlocks) {
-        println!("entering informant");
         if new_blocks.has_more_blocks_to_import {
             return;
         }
@@ -382,6 +380,8 @@ impl ChainNotify for Informant<FullNodeInformantData> {
             {
                 let header_view = block.header_view();
                 let size = block.rlp().as_raw().len();
+                let shard_context = client.shard_context();
+                let counters = shard_context.counters();
                 let (skipped, skipped_txs) = (
                     self.skipped.load(AtomicOrdering::Relaxed) + new_blocks.imported.len() - 1,
                     self.skipped_txs.load(AtomicOrdering::Relaxed) + txs_imported,
@@ -393,18 +393,18 @@ impl ChainNotify for Informant<FullNodeInformantData> {
                     Colour::Yellow.bold().paint(format!("{:.2}", header_view.gas_used().low_u64() as f32 / 1000000f32)),
                     Colour::Purple.bold().paint(format!("{}", new_blocks.duration.as_milliseconds())),
                     Colour::Blue.bold().paint(format!("{:.2}", size as f32 / 1024f32)),
-                    Colour::Yellow.bold().paint(format!("{}", AggProof::get_sload_count())),
-                    Colour::Yellow.bold().paint(format!("{}", AggProof::get_sstore_count())),
-                    Colour::Yellow.bold().paint(format!("{}", AggProof::get_bal_read_count())),
-                    Colour::Yellow.bold().paint(format!("{}", AggProof::get_bal_write_count())),
-                    Colour::Yellow.bold().paint(format!("{}", AggProof::get_hop_count(1u64))),
-                    Colour::Yellow.bold().paint(format!("{}", AggProof::get_hop_count(2u64))),
-                    Colour::Yellow.bold().paint(format!("{}", AggProof::get_hop_count(3u64))),
-                    Colour::Yellow.bold().paint(format!("{}", AggProof::get_hop_count(4u64))),
-                    Colour::Yellow.bold().paint(format!("{}", AggProof::get_hop_count(5u64))),
-                    Colour::Yellow.bold().paint(format!("{}", AggProof::get_hop_count(6u64))),
-                    Colour::Yellow.bold().paint(format!("{}", AggProof::get_hop_count(7u64))),
-                    Colour::Yellow.bold().paint(format!("{}", AggProof::get_reverted_count())),
+                    Colour::Yellow.bold().paint(format!("{}", counters.sload())),
+                    Colour::Yellow.bold().paint(format!("{}", counters.sstore())),
+                    Colour::Yellow.bold().paint(format!("{}", counters.bal_read())),
+                    Colour::Yellow.bold().paint(format!("{}", counters.bal_write())),
+                    Colour::Yellow.bold().paint(format!("{}", counters.hop(1u64))),
+                    Colour::Yellow.bold().paint(format!("{}", counters.hop(2u64))),
+                    Colour::Yellow.bold().paint(format!("{}", counters.hop(3u64))),
+                    Colour::Yellow.bold().paint(format!("{}", counters.hop(4u64))),
+                    Colour::Yellow.bold().paint(format!("{}", counters.hop(5u64))),
+                    Colour::Yellow.bold().paint(format!("{}", counters.hop(6u64))),
+                    Colour::Yellow.bold().paint(format!("{}", counters.hop(7u64))),
+                    Colour::Yellow.bold().paint(format!("{}", counters.reverted())),
                     if skipped > 0 {
                         format!(" + another {} block(s) containing {} tx(s)",
                             Colour::Red.bold().paint(format!("{}", skipped)),
@@ -68,6 +68,9 @@ pub enum Api {
     ParitySet,
     /// SecretStore (UNSAFE: arbitrary hash signing)
     SecretStore,
+    /// Shard - Cross-shard round and transaction inspection (Safe)
+    #[cfg(feature = "shard")]
+    Shard,
     /// Geth-compatible (best-effort) debug API (Potentially UNSAFE)
     /// NOTE We don't aim to support all methods, only the ones that are useful.
     Debug,
@@ -91,6 +94,8 @@ impl FromStr for Api {
             "pubsub" => Ok(EthPubSub),
             "rpc" => Ok(Rpc),
             "secretstore" => Ok(SecretStore),
+            #[cfg(feature = "shard")]
+            "shard" => Ok(Shard),
             "signer" => Ok(Signer),
             "traces" => Ok(Traces),
             "web3" => Ok(Web3),
@@ -171,6 +176,8 @@ fn to_modules(apis: &HashSet<Api>) -> BTreeMap<String, String> {
             Api::Personal => ("personal", "1.0"),
             Api::Rpc => ("rpc", "1.0"),
             Api::SecretStore => ("secretstore", "1.0"),
+            #[cfg(feature = "shard")]
+            Api::Shard => ("shard", "1.0"),
             Api::Signer => ("signer", "1.0"),
             Api::Traces => ("traces", "1.0"),
             Api::Web3 => ("web3", "1.0"),
@@ -410,6 +417,8 @@ impl FullDependencies {
                     );
                 }
                 Api::Traces => handler.extend_with(TracesClient::new(&self.client).to_delegate()),
+                #[cfg(feature = "shard")]
+                Api::Shard => handler.extend_with(ShardClient::new(&self.client).to_delegate()),
                 Api::Rpc => {
                     let modules = to_modules(&apis);
                     handler.extend_with(RpcClient::new(modules).to_delegate());
@@ -458,6 +467,8 @@ impl ApiSet {
         .iter()
         .cloned()
         .collect();
+        #[cfg(feature = "shard")]
+        public_list.insert(Api::Shard);
 
         match *self {
             ApiSet::List(ref apis) => apis.clone(),
@@ -501,6 +512,11 @@ impl ApiSet {
 mod test {
     use super::{Api, ApiSet};
 
+    #[cfg(feature = "shard")]
+    const SHARD_APIS: &[Api] = &[Api::Shard];
+    #[cfg(not(feature = "shard"))]
+    const SHARD_APIS: &[Api] = &[];
+
     #[test]
     fn test_api_parsing() {
         assert_eq!(Api::Debug, "debug".parse().unwrap());
@@ -516,6 +532,8 @@ mod test {
         assert_eq!(Api::Traces, "traces".parse().unwrap());
         assert_eq!(Api::Rpc, "rpc".parse().unwrap());
         assert_eq!(Api::SecretStore, "secretstore".parse().unwrap());
+        #[cfg(feature = "shard")]
+        assert_eq!(Api::Shard, "shard".parse().unwrap());
         assert!("rp".parse::<Api>().is_err());
     }
 
@@ -546,6 +564,7 @@ mod test {
             Api::Rpc,
         ]
         .into_iter()
+        .chain(SHARD_APIS.iter().cloned())
         .collect();
         assert_eq!(ApiSet::UnsafeContext.list_apis(), expected);
     }
@@ -566,6 +585,7 @@ mod test {
             Api::ParityAccounts,
         ]
         .into_iter()
+        .chain(SHARD_APIS.iter().cloned())
         .collect();
         assert_eq!(ApiSet::IpcContext.list_apis(), expected);
     }
@@ -592,6 +612,7 @@ mod test {
                     Api::Debug,
                 ]
                 .into_iter()
+                .chain(SHARD_APIS.iter().cloned())
                 .collect()
             )
         );
@@ -618,6 +639,7 @@ mod test {
                     Api::Debug,
                 ]
                 .into_iter()
+                .chain(SHARD_APIS.iter().cloned())
                 .collect()
             )
         );
@@ -639,6 +661,7 @@ mod test {
                     Api::Rpc,
                 ]
                 .into_iter()
+                .chain(SHARD_APIS.iter().cloned())
                 .collect()
             )
         );
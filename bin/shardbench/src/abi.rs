@@ -0,0 +1,154 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Solidity ABI encoding of the benchmark contract calls.
+
+use ethereum_types::{Address, U256};
+use hash::keccak;
+use rustc_hex::FromHex;
+
+/// Creation code of the `ERC20Basic` token of the experiments.
+const ERC20_CODE: &str = include_str!("../res/erc20.hex");
+
+/// Argument of a contract call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Address(Address),
+    Uint(U256),
+    /// Dynamic `address[]`.
+    Addresses(Vec<Address>),
+}
+
+/// First four bytes of the hash of a function signature.
+pub fn selector(signature: &str) -> [u8; 4] {
+    let mut selector = [0u8; 4];
+    selector.copy_from_slice(&keccak(signature).as_bytes()[..4]);
+    selector
+}
+
+/// Call data of the function `signature`, e.g. `transfer(address,uint256)`.
+pub fn encode_call(signature: &str, tokens: &[Token]) -> Vec<u8> {
+    let mut data = selector(signature).to_vec();
+    data.extend(encode(tokens));
+    data
+}
+
+/// Head and tail encoding of `tokens`.
+pub fn encode(tokens: &[Token]) -> Vec<u8> {
+    let head_len = 32 * tokens.len();
+    let mut head = Vec::with_capacity(head_len);
+    let mut tail = Vec::new();
+    for token in tokens {
+        match *token {
+            Token::Address(ref address) => head.extend_from_slice(&address_word(address)),
+            Token::Uint(ref value) => head.extend_from_slice(&uint_word(value)),
+            Token::Addresses(ref addresses) => {
+                head.extend_from_slice(&uint_word(&(head_len + tail.len()).into()));
+                tail.extend_from_slice(&uint_word(&addresses.len().into()));
+                for address in addresses {
+                    tail.extend_from_slice(&address_word(address));
+                }
+            }
+        }
+    }
+    head.extend(tail);
+    head
+}
+
+/// Creation data of an `ERC20Basic` token minting `supply` to its creator.
+pub fn erc20_creation(supply: U256) -> Vec<u8> {
+    let mut data: Vec<u8> = ERC20_CODE
+        .trim()
+        .from_hex()
+        .expect("embedded creation code is valid hex; qed");
+    data.extend(encode(&[Token::Uint(supply)]));
+    data
+}
+
+fn uint_word(value: &U256) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    value.to_big_endian(&mut word);
+    word
+}
+
+fn address_word(address: &Address) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[12..].copy_from_slice(address.as_bytes());
+    word
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_hex::ToHex;
+
+    #[test]
+    fn selects_functions() {
+        assert_eq!(
+            selector("transfer(address,uint256)"),
+            [0xa9, 0x05, 0x9c, 0xbb]
+        );
+        assert_eq!(
+            selector("swapExactTokensForTokens(uint256,uint256,address[],address,uint256)"),
+            [0x38, 0xed, 0x17, 0x39]
+        );
+    }
+
+    #[test]
+    fn encodes_static_arguments() {
+        let data = encode_call(
+            "transfer(address,uint256)",
+            &[
+                Token::Address(Address::from_low_u64_be(0xff)),
+                Token::Uint(0x10.into()),
+            ],
+        );
+        assert_eq!(
+            data.to_hex(),
+            "a9059cbb\
+             00000000000000000000000000000000000000000000000000000000000000ff\
+             0000000000000000000000000000000000000000000000000000000000000010"
+        );
+    }
+
+    #[test]
+    fn encodes_address_arrays_after_the_head() {
+        let data = encode(&[
+            Token::Uint(1.into()),
+            Token::Addresses(vec![
+                Address::from_low_u64_be(0xa),
+                Address::from_low_u64_be(0xb),
+            ]),
+            Token::Address(Address::from_low_u64_be(0xc)),
+        ]);
+        assert_eq!(
+            data.to_hex(),
+            "0000000000000000000000000000000000000000000000000000000000000001\
+             0000000000000000000000000000000000000000000000000000000000000060\
+             000000000000000000000000000000000000000000000000000000000000000c\
+             0000000000000000000000000000000000000000000000000000000000000002\
+             000000000000000000000000000000000000000000000000000000000000000a\
+             000000000000000000000000000000000000000000000000000000000000000b"
+        );
+    }
+
+    #[test]
+    fn appends_supply_to_erc20_creation_code() {
+        let data = erc20_creation(50000.into());
+        assert_eq!(&data[..4], &[0x60, 0x80, 0x60, 0x40]);
+        assert_eq!(&data[data.len() - 2..], &[0xc3, 0x50]);
+    }
+}
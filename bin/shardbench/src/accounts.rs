@@ -0,0 +1,171 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Benchmark accounts, kept in a `Privkey,PubKey` csv or in a key directory.
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    path::Path,
+};
+
+use crypto::publickey::{Generator, KeyPair, Random, Secret};
+use ethereum_types::U256;
+use ethkey::{Brain, Password};
+use ethstore::{
+    accounts_dir::{KeyDirectory, RootDiskDirectory},
+    EthStore, SecretVaultRef, SimpleSecretStore,
+};
+use serde_json;
+
+use Error;
+
+/// Header of the accounts csv.
+const CSV_HEADER: &str = "Privkey,PubKey";
+
+/// `count` new accounts.
+///
+/// Accounts derived from a `brain` seed are the same on every run.
+pub fn generate(count: usize, brain: Option<&str>) -> Vec<KeyPair> {
+    (0..count)
+        .map(|i| match brain {
+            Some(seed) => Brain::new(format!("{} {}", seed, i)).generate(),
+            None => Random.generate(),
+        })
+        .collect()
+}
+
+/// Write the secrets and addresses of `accounts` as csv.
+pub fn write_csv(path: &Path, accounts: &[KeyPair]) -> Result<(), Error> {
+    let mut file = fs::File::create(path)?;
+    writeln!(file, "{}", CSV_HEADER)?;
+    for account in accounts {
+        writeln!(file, "0x{:x},0x{:x}", account.secret(), account.address())?;
+    }
+    Ok(())
+}
+
+/// Read accounts written by `write_csv`.
+///
+/// Addresses are derived from the secrets, the second column is informational.
+pub fn read_csv(path: &Path) -> Result<Vec<KeyPair>, Error> {
+    let file = fs::File::open(path).map_err(|e| {
+        Error::Custom(format!(
+            "Error opening accounts '{}': {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    let mut accounts = Vec::new();
+    for line in BufReader::new(file).lines().skip(1) {
+        let line = line?;
+        let secret = line.split(',').next().unwrap_or_default().trim();
+        if secret.is_empty() {
+            continue;
+        }
+        let secret = secret
+            .trim_start_matches("0x")
+            .parse::<Secret>()
+            .map_err(|_| Error::Custom(format!("Invalid secret in '{}'", path.display())))?;
+        accounts.push(KeyPair::from_secret(secret)?);
+    }
+    Ok(accounts)
+}
+
+/// Insert `accounts` into the key directory `dir`, encrypted with `password`.
+pub fn insert_into_store(
+    dir: &Path,
+    password: &Password,
+    accounts: &[KeyPair],
+) -> Result<(), Error> {
+    let store = EthStore::open(Box::new(RootDiskDirectory::create(dir)?))?;
+    for account in accounts {
+        store.insert_account(SecretVaultRef::Root, account.secret().clone(), password)?;
+    }
+    Ok(())
+}
+
+/// Decrypt all accounts of the key directory `dir` with `password`.
+pub fn load_from_store(dir: &Path, password: &Password) -> Result<Vec<KeyPair>, Error> {
+    let mut accounts = RootDiskDirectory::at(dir).load()?;
+    accounts.sort_by_key(|account| account.address);
+    accounts
+        .into_iter()
+        .map(|account| Ok(KeyPair::from_secret(account.crypto.secret(password)?)?))
+        .collect()
+}
+
+/// Write a chain spec `accounts` section giving each of `accounts` `balance` wei at genesis.
+pub fn write_alloc(path: &Path, accounts: &[KeyPair], balance: U256) -> Result<(), Error> {
+    let alloc: BTreeMap<_, _> = accounts
+        .iter()
+        .map(|account| {
+            (
+                format!("0x{:x}", account.address()),
+                json!({ "balance": format!("{}", balance) }),
+            )
+        })
+        .collect();
+    let mut file = fs::File::create(path)?;
+    serde_json::to_writer_pretty(&mut file, &alloc)?;
+    writeln!(file)?;
+    Ok(())
+}
+
+/// Read a password file, dropping the trailing newline.
+pub fn load_password(path: &Path) -> Result<Password, Error> {
+    let mut password = String::new();
+    fs::File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut password))
+        .map_err(|e| {
+            Error::Custom(format!(
+                "Error reading password file '{}': {}",
+                path.display(),
+                e
+            ))
+        })?;
+    Ok(password
+        .trim_end_matches(&['\r', '\n'][..])
+        .to_owned()
+        .into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process};
+
+    #[test]
+    fn csv_round_trips_accounts() {
+        let path = env::temp_dir().join(format!("shardbench-accounts-{}.csv", process::id()));
+        let accounts = generate(3, None);
+        write_csv(&path, &accounts).unwrap();
+
+        let read = read_csv(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(read, accounts);
+    }
+
+    #[test]
+    fn brain_accounts_are_reproducible() {
+        assert_eq!(
+            generate(2, Some("shardbench")),
+            generate(2, Some("shardbench"))
+        );
+    }
+}
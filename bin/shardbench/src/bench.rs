@@ -0,0 +1,259 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Signing, rate-limited submission and confirmation of benchmark transactions.
+
+use std::{
+    collections::HashMap,
+    sync::mpsc::{self, Receiver, TryRecvError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crypto::publickey::KeyPair;
+use ethereum_types::{Address, H256, U256};
+use hyperproofs::{ModuloPlacement, ShardPlacement};
+use types::transaction::{Transaction, TypedTransaction};
+
+use report::{Confirmation, Report, Sample};
+use rpc::{Client, Receipt};
+use workload::{Call, Workload};
+use Error;
+
+/// Signs transactions of benchmark accounts, tracking their nonces.
+pub struct Signer {
+    chain_id: Option<u64>,
+    gas: U256,
+    gas_price: U256,
+    /// Placement of senders if shard transactions are signed, modulo the node's shard count.
+    placement: Option<ModuloPlacement>,
+    nonces: HashMap<Address, U256>,
+}
+
+impl Signer {
+    /// Signer for the chain of `client`, using the node's gas price unless one is given.
+    pub fn new(
+        client: &Client,
+        gas: U256,
+        gas_price: Option<U256>,
+        shard_transactions: bool,
+    ) -> Result<Self, Error> {
+        let gas_price = match gas_price {
+            Some(gas_price) => gas_price,
+            None => client.gas_price()?,
+        };
+        let placement = if shard_transactions {
+            Some(ModuloPlacement::new(client.shard_count()?))
+        } else {
+            None
+        };
+
+        Ok(Signer {
+            chain_id: client.chain_id()?,
+            gas,
+            gas_price,
+            placement,
+            nonces: HashMap::new(),
+        })
+    }
+
+    /// Sign `call` as the next transaction of `sender` and submit it.
+    ///
+    /// Legacy transactions are turned into shard transactions by the node, unless the
+    /// signer was asked to do so itself.
+    pub fn send(&mut self, client: &Client, sender: &KeyPair, call: Call) -> Result<H256, Error> {
+        let address = sender.address();
+        let nonce = match self.nonces.get(&address) {
+            Some(nonce) => *nonce,
+            None => client.nonce(&address)?,
+        };
+        let transaction = TypedTransaction::Legacy(Transaction {
+            nonce,
+            gas_price: self.gas_price,
+            gas: self.gas,
+            action: call.action,
+            value: call.value,
+            data: call.data,
+        })
+        .sign(sender.secret(), self.chain_id);
+        let transaction = match self.placement {
+            Some(ref placement) => transaction.to_shard_txn(&|address| placement.shard_of(address)),
+            None => transaction,
+        };
+
+        match client.send_raw_transaction(&transaction.encode()) {
+            Ok(hash) => {
+                self.nonces.insert(address, nonce + 1);
+                Ok(hash)
+            }
+            Err(e) => {
+                // the nonce may still be free, ask the node again next time
+                self.nonces.remove(&address);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Pacing and confirmation settings of a run.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Transactions submitted per second, unlimited if zero.
+    pub rate: f64,
+    /// Number of transactions to submit.
+    pub count: usize,
+    /// Receipt polling interval.
+    pub poll: Duration,
+    /// Time to wait for outstanding receipts once everything was submitted.
+    pub timeout: Duration,
+}
+
+/// Submit `options.count` transactions of `workload`, cycling through `accounts` as senders.
+///
+/// Receipts are polled by a second connection while transactions are submitted, so
+/// latencies are measured up to the polling interval.
+pub fn run(
+    client: &Client,
+    signer: &mut Signer,
+    accounts: &[KeyPair],
+    workload: &Workload,
+    options: &Options,
+) -> Result<Report, Error> {
+    if accounts.is_empty() {
+        return Err(Error::Custom("No benchmark accounts".into()));
+    }
+    let addresses: Vec<_> = accounts.iter().map(KeyPair::address).collect();
+
+    let start = Instant::now();
+    let (submitted, receiver) = mpsc::channel();
+    let poller = {
+        let client = client.clone();
+        let (poll, timeout) = (options.poll, options.timeout);
+        thread::spawn(move || confirm(&client, receiver, start, poll, timeout))
+    };
+
+    let mut samples = Vec::with_capacity(options.count);
+    for index in 0..options.count {
+        if options.rate > 0.0 {
+            let due = Duration::from_secs_f64(index as f64 / options.rate);
+            if let Some(wait) = due.checked_sub(start.elapsed()) {
+                thread::sleep(wait);
+            }
+        }
+
+        let sender = &accounts[index % accounts.len()];
+        let submitted_at = start.elapsed();
+        let hash = match signer.send(client, sender, workload.call(index, &addresses)) {
+            Ok(hash) => {
+                let _ = submitted.send((index, hash));
+                Some(hash)
+            }
+            Err(e) => {
+                warn!(target: "shardbench", "Transaction {} of {:?} rejected: {}", index, sender.address(), e);
+                None
+            }
+        };
+        samples.push(Sample {
+            sender: sender.address(),
+            hash,
+            submitted: submitted_at,
+            confirmation: None,
+        });
+    }
+    drop(submitted);
+
+    let confirmations = poller
+        .join()
+        .map_err(|_| Error::Custom("Receipt poller panicked".into()))?;
+    for (index, confirmation) in confirmations {
+        samples[index].confirmation = Some(confirmation);
+    }
+    Ok(Report { samples })
+}
+
+/// Wait for the final receipts of `hashes`, in order.
+pub fn wait_for(
+    client: &Client,
+    hashes: &[H256],
+    poll: Duration,
+    timeout: Duration,
+) -> Result<Vec<Receipt>, Error> {
+    let deadline = Instant::now() + timeout;
+    let mut receipts = vec![None; hashes.len()];
+    loop {
+        for (hash, receipt) in hashes.iter().zip(receipts.iter_mut()) {
+            if receipt.is_none() {
+                *receipt = client.receipt(hash)?.filter(Receipt::is_final);
+            }
+        }
+        let missing = receipts.iter().filter(|r| r.is_none()).count();
+        if missing == 0 {
+            return Ok(receipts.into_iter().flatten().collect());
+        }
+        if Instant::now() >= deadline {
+            return Err(Error::Custom(format!(
+                "Timed out waiting for {} receipts",
+                missing
+            )));
+        }
+        thread::sleep(poll);
+    }
+}
+
+/// Poll receipts of the transactions sent through `submitted` until every one is final,
+/// or `timeout` passed after the last submission.
+fn confirm(
+    client: &Client,
+    submitted: Receiver<(usize, H256)>,
+    start: Instant,
+    poll: Duration,
+    timeout: Duration,
+) -> Vec<(usize, Confirmation)> {
+    let mut pending = Vec::new();
+    let mut confirmed = Vec::new();
+    let mut finished_at = None;
+    loop {
+        loop {
+            match submitted.try_recv() {
+                Ok(transaction) => pending.push(transaction),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    finished_at.get_or_insert_with(Instant::now);
+                    break;
+                }
+            }
+        }
+
+        pending.retain(|&(index, ref hash)| match client.receipt(hash) {
+            Ok(Some(ref receipt)) if receipt.is_final() => {
+                confirmed.push((index, Confirmation::new(start.elapsed(), receipt)));
+                false
+            }
+            Ok(_) => true,
+            Err(e) => {
+                debug!(target: "shardbench", "Polling receipt of {:?} failed: {}", hash, e);
+                true
+            }
+        });
+
+        if let Some(finished_at) = finished_at {
+            if pending.is_empty() || finished_at.elapsed() >= timeout {
+                return confirmed;
+            }
+        }
+        thread::sleep(poll);
+    }
+}
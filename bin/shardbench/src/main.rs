@@ -0,0 +1,444 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Workload generator and benchmark for sharded networks.
+
+extern crate common_types as types;
+extern crate docopt;
+extern crate env_logger;
+extern crate ethereum_types;
+extern crate ethkey;
+extern crate ethstore;
+extern crate hyperproofs;
+extern crate keccak_hash as hash;
+extern crate panic_hook;
+extern crate parity_crypto as crypto;
+extern crate rustc_hex;
+extern crate serde;
+
+#[macro_use]
+extern crate log;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
+
+mod abi;
+mod accounts;
+mod bench;
+mod report;
+mod rpc;
+mod workload;
+
+use std::{env, fmt, fs, io, io::Write, path::Path, process, time::Duration};
+
+use crypto::publickey::{Error as EthkeyError, KeyPair, Secret};
+use docopt::Docopt;
+use ethereum_types::{Address, U256};
+
+use bench::{Options, Signer};
+use rpc::Client;
+use workload::{Call, Kind, Workload};
+
+const USAGE: &str = r#"
+Workload generator and benchmark for sharded OpenEthereum networks.
+  Copyright 2015-2020 Parity Technologies (UK) Ltd.
+
+Usage:
+    shardbench accounts <count> [--brain=<seed> --alloc=<file>] [options]
+    shardbench fund <secret> [--token=<address>] [options]
+    shardbench deploy-erc20 <secret> [--supply=<tokens>] [options]
+    shardbench run <workload> [--rate=<tps> --count=<n> --contract=<address> --token-a=<address> --token-b=<address> --to=<address> --value=<amount> --shard-txs --report=<dir>] [options]
+    shardbench balances [--out=<file>] [options]
+    shardbench [-h | --help]
+
+Options:
+    -h, --help              Display this message and exit.
+    --rpc=<url>             HTTP JSON-RPC endpoint of the local node [default: http://localhost:8540].
+    --accounts=<file>       Csv of the benchmark accounts [default: out.csv].
+    --keys=<dir>            Key directory holding the benchmark accounts, instead of the csv.
+    --password=<file>       Password file of the key directory.
+    --balance=<amount>      Wei, or tokens with --token, given to each account [default: 1000000000000000000000].
+    --gas=<gas>             Gas limit of every transaction [default: 1000000].
+    --gas-price=<wei>       Gas price, the node's suggestion if not given.
+    --poll=<ms>             Receipt polling interval [default: 200].
+    --timeout=<secs>        Time to wait for outstanding receipts [default: 60].
+
+Account options:
+    --brain=<seed>          Derive the accounts from a seed, so every run uses the same ones.
+    --alloc=<file>          Also write a chain spec `accounts` section funding them at genesis.
+    --token=<address>       Fund the accounts with tokens of an ERC20 contract instead of ether.
+    --supply=<tokens>       Tokens minted to the creator of the ERC20 contract [default: 50000].
+    --out=<file>            Csv of the account balances [default: balance.csv].
+
+Run options:
+    --rate=<tps>            Transactions submitted per second, 0 for no limit [default: 100].
+    --count=<n>             Number of transactions to submit [default: 1000].
+    --contract=<address>    Token of `erc20`, exchange of `dtransfer` or router of `swap`.
+    --token-a=<address>     First token of `dtransfer` and `swap`.
+    --token-b=<address>     Second token of `dtransfer` and `swap`.
+    --to=<address>          Recipient of every transaction, instead of the next account.
+    --value=<amount>        Wei or tokens moved by each transfer [default: 1].
+    --shard-txs             Sign shard transactions instead of leaving it to the node.
+                            Senders are placed modulo the shard count of the node.
+    --report=<dir>          Directory of the reports [default: shardbench-report].
+
+Commands:
+    accounts                Create accounts, writing them to the csv or the key directory.
+    fund                    Transfer --balance from the account of <secret> to each account.
+    deploy-erc20            Deploy the ERC20 token of the experiments from <secret>.
+    run                     Run a workload and report throughput, latency and hops.
+    balances                Write the balance of each account to --out.
+
+Workloads:
+    transfer                Ether transfers between the accounts.
+    erc20                   ERC20 `transfer` calls of --contract.
+    dtransfer               `dtransfer` calls of the two-token exchange --contract.
+    swap                    `swapExactTokensForTokens` calls of the Uniswap V2 router --contract.
+"#;
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    cmd_accounts: bool,
+    cmd_fund: bool,
+    cmd_deploy_erc20: bool,
+    cmd_run: bool,
+    cmd_balances: bool,
+    arg_count: Option<usize>,
+    arg_secret: String,
+    arg_workload: String,
+    flag_rpc: String,
+    flag_accounts: String,
+    flag_keys: String,
+    flag_password: String,
+    flag_balance: String,
+    flag_gas: String,
+    flag_gas_price: String,
+    flag_poll: u64,
+    flag_timeout: u64,
+    flag_brain: String,
+    flag_alloc: String,
+    flag_token: String,
+    flag_supply: String,
+    flag_out: String,
+    flag_rate: f64,
+    flag_count: usize,
+    flag_contract: String,
+    flag_token_a: String,
+    flag_token_b: String,
+    flag_to: String,
+    flag_value: String,
+    flag_shard_txs: bool,
+    flag_report: String,
+}
+
+#[derive(Debug)]
+enum Error {
+    Ethkey(EthkeyError),
+    Ethstore(ethstore::Error),
+    Docopt(docopt::Error),
+    Io(io::Error),
+    Json(serde_json::Error),
+    Rpc(String),
+    Custom(String),
+}
+
+impl From<EthkeyError> for Error {
+    fn from(err: EthkeyError) -> Self {
+        Error::Ethkey(err)
+    }
+}
+
+impl From<ethstore::Error> for Error {
+    fn from(err: ethstore::Error) -> Self {
+        Error::Ethstore(err)
+    }
+}
+
+impl From<docopt::Error> for Error {
+    fn from(err: docopt::Error) -> Self {
+        Error::Docopt(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            Error::Ethkey(ref e) => write!(f, "{}", e),
+            Error::Ethstore(ref e) => write!(f, "{}", e),
+            Error::Docopt(ref e) => write!(f, "{}", e),
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::Json(ref e) => write!(f, "{}", e),
+            Error::Rpc(ref e) => write!(f, "{}", e),
+            Error::Custom(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+fn main() {
+    panic_hook::set_abort();
+    if env::var("RUST_LOG").is_err() {
+        env::set_var("RUST_LOG", "info")
+    }
+    env_logger::try_init().expect("Logger initialized only once.");
+
+    match execute(env::args()) {
+        Ok(result) => println!("{}", result),
+        Err(Error::Docopt(ref e)) => e.exit(),
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    }
+}
+
+fn execute<S, I>(command: I) -> Result<String, Error>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let args: Args = Docopt::new(USAGE).and_then(|d| d.argv(command).deserialize())?;
+
+    if args.cmd_accounts {
+        let brain = optional(&args.flag_brain);
+        let accounts = accounts::generate(args.arg_count.unwrap_or_default(), brain);
+        match optional(&args.flag_keys) {
+            Some(keys) => accounts::insert_into_store(
+                Path::new(keys),
+                &accounts::load_password(Path::new(&args.flag_password))?,
+                &accounts,
+            )?,
+            None => accounts::write_csv(Path::new(&args.flag_accounts), &accounts)?,
+        }
+        if let Some(alloc) = optional(&args.flag_alloc) {
+            accounts::write_alloc(Path::new(alloc), &accounts, amount(&args.flag_balance)?)?;
+        }
+        return Ok(format!("Created {} accounts", accounts.len()));
+    }
+
+    let client = Client::new(&args.flag_rpc)?;
+    let poll = Duration::from_millis(args.flag_poll);
+    let timeout = Duration::from_secs(args.flag_timeout);
+    let gas_price = match optional(&args.flag_gas_price) {
+        Some(gas_price) => Some(amount(gas_price)?),
+        None => None,
+    };
+    let mut signer = Signer::new(
+        &client,
+        amount(&args.flag_gas)?,
+        gas_price,
+        args.flag_shard_txs,
+    )?;
+
+    if args.cmd_deploy_erc20 {
+        let creator = key_pair(&args.arg_secret)?;
+        let call = Call::create(abi::erc20_creation(amount(&args.flag_supply)?));
+        let hash = signer.send(&client, &creator, call)?;
+        let receipt = bench::wait_for(&client, &[hash], poll, timeout)?.remove(0);
+        return match receipt.contract_address {
+            Some(address) => Ok(format!("0x{:x}", address)),
+            None => Err(Error::Custom(format!(
+                "Deployment {:?} created no contract",
+                hash
+            ))),
+        };
+    }
+
+    let accounts = load_accounts(&args)?;
+
+    if args.cmd_fund {
+        let funder = key_pair(&args.arg_secret)?;
+        let balance = amount(&args.flag_balance)?;
+        let token = address(&args.flag_token)?;
+        let hashes = accounts
+            .iter()
+            .map(|account| {
+                let call = match token {
+                    Some(token) => Call::token_transfer(token, account.address(), balance),
+                    None => Call::transfer(account.address(), balance),
+                };
+                signer.send(&client, &funder, call)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        bench::wait_for(&client, &hashes, poll, timeout)?;
+        Ok(format!("Funded {} accounts", hashes.len()))
+    } else if args.cmd_run {
+        let workload = Workload {
+            kind: Kind::parse(
+                &args.arg_workload,
+                address(&args.flag_contract)?,
+                address(&args.flag_token_a)?,
+                address(&args.flag_token_b)?,
+            )?,
+            recipient: address(&args.flag_to)?,
+            value: amount(&args.flag_value)?,
+        };
+        let options = Options {
+            rate: args.flag_rate,
+            count: args.flag_count,
+            poll,
+            timeout,
+        };
+        let report = bench::run(&client, &mut signer, &accounts, &workload, &options)?;
+        report.write(Path::new(&args.flag_report))?;
+        Ok(report.summary().to_string())
+    } else if args.cmd_balances {
+        let mut file = fs::File::create(&args.flag_out)?;
+        writeln!(file, "Address,Balance")?;
+        for account in &accounts {
+            let address = account.address();
+            writeln!(file, "0x{:x},{}", address, client.balance(&address)?)?;
+        }
+        Ok(format!(
+            "Wrote {} balances to {}",
+            accounts.len(),
+            args.flag_out
+        ))
+    } else {
+        Ok(USAGE.into())
+    }
+}
+
+fn load_accounts(args: &Args) -> Result<Vec<KeyPair>, Error> {
+    match optional(&args.flag_keys) {
+        Some(keys) => accounts::load_from_store(
+            Path::new(keys),
+            &accounts::load_password(Path::new(&args.flag_password))?,
+        ),
+        None => accounts::read_csv(Path::new(&args.flag_accounts)),
+    }
+}
+
+fn optional(flag: &str) -> Option<&str> {
+    if flag.is_empty() {
+        None
+    } else {
+        Some(flag)
+    }
+}
+
+fn key_pair(secret: &str) -> Result<KeyPair, Error> {
+    let secret = secret
+        .trim_start_matches("0x")
+        .parse::<Secret>()
+        .map_err(|_| Error::Custom(format!("Invalid secret {}", secret)))?;
+    Ok(KeyPair::from_secret(secret)?)
+}
+
+fn address(flag: &str) -> Result<Option<Address>, Error> {
+    match optional(flag) {
+        Some(address) => address
+            .trim_start_matches("0x")
+            .parse()
+            .map(Some)
+            .map_err(|_| Error::Custom(format!("Invalid address {}", address))),
+        None => Ok(None),
+    }
+}
+
+/// Decimal, or `0x`-prefixed hexadecimal amount.
+fn amount(flag: &str) -> Result<U256, Error> {
+    let parsed = match flag.strip_prefix("0x") {
+        Some(hex) => hex.parse().ok(),
+        None => U256::from_dec_str(flag).ok(),
+    };
+    parsed.ok_or_else(|| Error::Custom(format!("Invalid amount {}", flag)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{address, amount, execute, Args, Docopt, USAGE};
+    use ethereum_types::{Address, U256};
+    use std::{env, fs, process};
+
+    fn args(command: &[&str]) -> Args {
+        Docopt::new(USAGE)
+            .and_then(|d| d.argv(command).deserialize())
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_run_arguments() {
+        let args = args(&[
+            "shardbench",
+            "run",
+            "swap",
+            "--rate=50",
+            "--contract=0x99d35b17cdf0e1de571f985d0ef4089c3c4d4e39",
+            "--shard-txs",
+        ]);
+        assert!(args.cmd_run);
+        assert_eq!(args.arg_workload, "swap");
+        assert_eq!(args.flag_rate, 50.0);
+        assert_eq!(args.flag_count, 1000);
+        assert!(args.flag_shard_txs);
+        assert_eq!(args.flag_rpc, "http://localhost:8540");
+        assert_eq!(args.flag_accounts, "out.csv");
+        assert_eq!(args.flag_token_a, "");
+    }
+
+    #[test]
+    fn parses_amounts_and_addresses() {
+        assert_eq!(amount("1000").unwrap(), U256::from(1000));
+        assert_eq!(amount("0x3e8").unwrap(), U256::from(1000));
+        assert!(amount("ten").is_err());
+
+        assert_eq!(address("").unwrap(), None);
+        assert_eq!(
+            address("0x00000000000000000000000000000000000000ff").unwrap(),
+            Some(Address::from_low_u64_be(0xff))
+        );
+        assert!(address("0x12").is_err());
+    }
+
+    #[test]
+    fn creates_accounts_without_a_node() {
+        let dir = env::temp_dir().join(format!("shardbench-main-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let csv = dir.join("out.csv");
+        let alloc = dir.join("alloc.json");
+
+        let result = execute(&[
+            "shardbench".to_owned(),
+            "accounts".to_owned(),
+            "2".to_owned(),
+            format!("--accounts={}", csv.display()),
+            format!("--alloc={}", alloc.display()),
+            "--balance=5".to_owned(),
+        ]);
+        let accounts = ::accounts::read_csv(&csv).unwrap();
+        let alloc: ::serde_json::Value =
+            ::serde_json::from_slice(&fs::read(&alloc).unwrap()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(result.unwrap(), "Created 2 accounts");
+        assert_eq!(accounts.len(), 2);
+        let address = format!("0x{:x}", accounts[0].address());
+        assert_eq!(alloc[&address]["balance"], "5");
+    }
+}
@@ -0,0 +1,382 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Throughput, latency and hop-distribution reports of a benchmark run.
+
+use std::{collections::BTreeMap, fmt, fs, io::Write, path::Path, time::Duration};
+
+use ethereum_types::{Address, H256};
+
+use rpc::{Receipt, ShardStatus};
+use Error;
+
+/// Outcome of a confirmed transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// Executed successfully; cross-shard calls completed on their final shard.
+    Success,
+    /// Executed, but failed.
+    Failed,
+    /// Cross-shard call reverted on its final shard.
+    Reverted,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Status::Success => write!(f, "success"),
+            Status::Failed => write!(f, "failed"),
+            Status::Reverted => write!(f, "reverted"),
+        }
+    }
+}
+
+/// Final receipt of a transaction, as seen by the benchmark.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Confirmation {
+    /// Time since the start of the run the receipt was seen at.
+    pub at: Duration,
+    pub status: Status,
+    /// Shard the call started on, `None` for plain transactions.
+    pub origin_shard: Option<u64>,
+    /// Shard the call finished on, `None` for plain transactions.
+    pub final_shard: Option<u64>,
+    /// Number of hops after the first shard.
+    pub hops: u64,
+}
+
+impl Confirmation {
+    /// Confirmation of a final `receipt` seen `at` the given time.
+    pub fn new(at: Duration, receipt: &Receipt) -> Self {
+        let failed = receipt.status.map_or(false, |status| status.is_zero());
+        match receipt.shard {
+            Some(ref shard) => Confirmation {
+                at,
+                status: match shard.status {
+                    ShardStatus::Reverted => Status::Reverted,
+                    _ if failed => Status::Failed,
+                    _ => Status::Success,
+                },
+                origin_shard: Some(shard.origin_shard.as_u64()),
+                final_shard: shard.final_shard.map(|shard| shard.as_u64()),
+                hops: shard.hop_count.as_u64(),
+            },
+            None => Confirmation {
+                at,
+                status: if failed {
+                    Status::Failed
+                } else {
+                    Status::Success
+                },
+                origin_shard: None,
+                final_shard: None,
+                hops: 0,
+            },
+        }
+    }
+}
+
+/// A submitted transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub sender: Address,
+    /// Hash returned by the node, `None` if the transaction was rejected.
+    pub hash: Option<H256>,
+    /// Time since the start of the run the transaction was submitted at.
+    pub submitted: Duration,
+    /// Final receipt, `None` if none was seen before the run ended.
+    pub confirmation: Option<Confirmation>,
+}
+
+impl Sample {
+    /// Time from submission to the final receipt.
+    pub fn latency(&self) -> Option<Duration> {
+        self.confirmation.as_ref().map(|confirmation| {
+            confirmation
+                .at
+                .checked_sub(self.submitted)
+                .unwrap_or_default()
+        })
+    }
+}
+
+/// Transactions of a benchmark run.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub samples: Vec<Sample>,
+}
+
+/// Latency percentiles, in milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Latency {
+    pub mean: f64,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+/// Aggregates of a benchmark run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    /// Transactions accepted by the node.
+    pub submitted: usize,
+    /// Transactions rejected by the node.
+    pub rejected: usize,
+    /// Transactions with a final receipt.
+    pub confirmed: usize,
+    /// Confirmed transactions which failed or reverted.
+    pub unsuccessful: usize,
+    /// Accepted transactions without a final receipt.
+    pub unconfirmed: usize,
+    /// Accepted transactions per second.
+    pub submit_rate: f64,
+    /// Confirmed transactions per second, from the start of the run to the last confirmation.
+    pub throughput: f64,
+    pub latency: Option<Latency>,
+    /// Number of confirmed transactions by hop count.
+    pub hops: BTreeMap<u64, usize>,
+}
+
+impl Report {
+    pub fn summary(&self) -> Summary {
+        let accepted: Vec<_> = self.samples.iter().filter(|s| s.hash.is_some()).collect();
+        let confirmed: Vec<_> = accepted
+            .iter()
+            .filter_map(|s| s.confirmation.as_ref())
+            .collect();
+
+        let last_submission = accepted.iter().map(|s| s.submitted).max();
+        let last_confirmation = confirmed.iter().map(|c| c.at).max();
+        let mut latencies: Vec<f64> = accepted
+            .iter()
+            .filter_map(|s| s.latency())
+            .map(|latency| latency.as_secs_f64() * 1000.0)
+            .collect();
+        latencies.sort_by(|a, b| a.partial_cmp(b).expect("latencies are finite; qed"));
+
+        let mut hops = BTreeMap::new();
+        for confirmation in &confirmed {
+            *hops.entry(confirmation.hops).or_insert(0) += 1;
+        }
+
+        Summary {
+            submitted: accepted.len(),
+            rejected: self.samples.len() - accepted.len(),
+            confirmed: confirmed.len(),
+            unsuccessful: confirmed
+                .iter()
+                .filter(|c| c.status != Status::Success)
+                .count(),
+            unconfirmed: accepted.len() - confirmed.len(),
+            submit_rate: rate(accepted.len(), last_submission),
+            throughput: rate(confirmed.len(), last_confirmation),
+            latency: if latencies.is_empty() {
+                None
+            } else {
+                Some(Latency {
+                    mean: latencies.iter().sum::<f64>() / latencies.len() as f64,
+                    p50: percentile(&latencies, 50),
+                    p95: percentile(&latencies, 95),
+                    p99: percentile(&latencies, 99),
+                    max: latencies[latencies.len() - 1],
+                })
+            },
+            hops,
+        }
+    }
+
+    /// Write `summary.csv`, `transactions.csv` and `hops.csv` into `dir`.
+    pub fn write(&self, dir: &Path) -> Result<(), Error> {
+        fs::create_dir_all(dir)?;
+        let summary = self.summary();
+
+        let mut file = fs::File::create(dir.join("summary.csv"))?;
+        writeln!(file, "metric,value")?;
+        writeln!(file, "submitted,{}", summary.submitted)?;
+        writeln!(file, "rejected,{}", summary.rejected)?;
+        writeln!(file, "confirmed,{}", summary.confirmed)?;
+        writeln!(file, "unsuccessful,{}", summary.unsuccessful)?;
+        writeln!(file, "unconfirmed,{}", summary.unconfirmed)?;
+        writeln!(file, "submit_rate,{:.2}", summary.submit_rate)?;
+        writeln!(file, "throughput,{:.2}", summary.throughput)?;
+        if let Some(latency) = summary.latency {
+            writeln!(file, "latency_mean_ms,{:.1}", latency.mean)?;
+            writeln!(file, "latency_p50_ms,{:.1}", latency.p50)?;
+            writeln!(file, "latency_p95_ms,{:.1}", latency.p95)?;
+            writeln!(file, "latency_p99_ms,{:.1}", latency.p99)?;
+            writeln!(file, "latency_max_ms,{:.1}", latency.max)?;
+        }
+
+        let mut file = fs::File::create(dir.join("transactions.csv"))?;
+        writeln!(
+            file,
+            "hash,sender,submitted_ms,latency_ms,origin_shard,final_shard,hops,status"
+        )?;
+        for sample in &self.samples {
+            let optional = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+            let confirmation = sample.confirmation.as_ref();
+            writeln!(
+                file,
+                "{},0x{:x},{},{},{},{},{},{}",
+                sample
+                    .hash
+                    .map(|hash| format!("0x{:x}", hash))
+                    .unwrap_or_default(),
+                sample.sender,
+                sample.submitted.as_millis(),
+                sample
+                    .latency()
+                    .map(|latency| latency.as_millis().to_string())
+                    .unwrap_or_default(),
+                optional(confirmation.and_then(|c| c.origin_shard)),
+                optional(confirmation.and_then(|c| c.final_shard)),
+                optional(confirmation.map(|c| c.hops)),
+                match (sample.hash, confirmation) {
+                    (None, _) => "rejected".to_owned(),
+                    (Some(_), None) => "unconfirmed".to_owned(),
+                    (Some(_), Some(c)) => c.status.to_string(),
+                },
+            )?;
+        }
+
+        let mut file = fs::File::create(dir.join("hops.csv"))?;
+        writeln!(file, "hops,transactions")?;
+        for (hops, count) in &summary.hops {
+            writeln!(file, "{},{}", hops, count)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "submitted:   {} ({} rejected), {:.2} tx/s",
+            self.submitted, self.rejected, self.submit_rate
+        )?;
+        writeln!(
+            f,
+            "confirmed:   {} ({} failed or reverted), {} unconfirmed",
+            self.confirmed, self.unsuccessful, self.unconfirmed
+        )?;
+        writeln!(f, "throughput:  {:.2} tx/s", self.throughput)?;
+        if let Some(ref latency) = self.latency {
+            writeln!(
+                f,
+                "latency:     mean {:.0} ms, p50 {:.0} ms, p95 {:.0} ms, p99 {:.0} ms, max {:.0} ms",
+                latency.mean, latency.p50, latency.p95, latency.p99, latency.max
+            )?;
+        }
+        let hops: Vec<_> = self
+            .hops
+            .iter()
+            .map(|(hops, count)| format!("{}: {}", hops, count))
+            .collect();
+        write!(f, "hops:        {}", hops.join(", "))
+    }
+}
+
+fn rate(count: usize, over: Option<Duration>) -> f64 {
+    match over.map(|d| d.as_secs_f64()) {
+        Some(secs) if secs > 0.0 => count as f64 / secs,
+        _ => 0.0,
+    }
+}
+
+/// Nearest-rank percentile of sorted `values`.
+fn percentile(values: &[f64], percent: usize) -> f64 {
+    let rank = (percent * values.len()).div_ceil(100);
+    values[rank.max(1) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(submitted_ms: u64, confirmation: Option<(u64, u64)>) -> Sample {
+        Sample {
+            sender: Address::zero(),
+            hash: Some(H256::zero()),
+            submitted: Duration::from_millis(submitted_ms),
+            confirmation: confirmation.map(|(at_ms, hops)| Confirmation {
+                at: Duration::from_millis(at_ms),
+                status: Status::Success,
+                origin_shard: Some(0),
+                final_shard: Some(hops % 2),
+                hops,
+            }),
+        }
+    }
+
+    #[test]
+    fn summarizes_run() {
+        let mut report = Report {
+            samples: (0..10)
+                .map(|i| sample(i * 100, Some((i * 100 + 1000, i % 3))))
+                .collect(),
+        };
+        report.samples.push(sample(1000, None));
+        report.samples.push(Sample {
+            hash: None,
+            ..sample(1000, None)
+        });
+
+        let summary = report.summary();
+        assert_eq!(summary.submitted, 11);
+        assert_eq!(summary.rejected, 1);
+        assert_eq!(summary.confirmed, 10);
+        assert_eq!(summary.unconfirmed, 1);
+        assert_eq!(summary.submit_rate, 11.0);
+        assert_eq!(
+            summary.throughput,
+            10.0 / Duration::from_millis(1900).as_secs_f64()
+        );
+        assert_eq!(summary.latency.unwrap().p50, 1000.0);
+        assert_eq!(
+            summary.hops.into_iter().collect::<Vec<_>>(),
+            vec![(0, 4), (1, 3), (2, 3)]
+        );
+    }
+
+    #[test]
+    fn picks_nearest_rank_percentiles() {
+        let values: Vec<f64> = (1..=20).map(f64::from).collect();
+        assert_eq!(percentile(&values, 50), 10.0);
+        assert_eq!(percentile(&values, 95), 19.0);
+        assert_eq!(percentile(&values, 99), 20.0);
+        assert_eq!(percentile(&[3.0], 50), 3.0);
+    }
+
+    #[test]
+    fn reports_plain_and_reverted_receipts() {
+        let receipt: Receipt =
+            ::serde_json::from_str(r#"{"status":"0x0","contractAddress":null}"#).unwrap();
+        let confirmation = Confirmation::new(Duration::from_secs(1), &receipt);
+        assert_eq!(confirmation.status, Status::Failed);
+        assert_eq!(confirmation.origin_shard, None);
+
+        let receipt: Receipt = ::serde_json::from_str(
+            r#"{"status":"0x1","shard":{"originShard":"0x1","finalShard":"0x2","hopCount":"0x3","status":"reverted"}}"#,
+        )
+        .unwrap();
+        let confirmation = Confirmation::new(Duration::from_secs(1), &receipt);
+        assert_eq!(confirmation.status, Status::Reverted);
+        assert_eq!(confirmation.final_shard, Some(2));
+        assert_eq!(confirmation.hops, 3);
+    }
+}
@@ -0,0 +1,294 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Blocking JSON-RPC client of a node's HTTP endpoint.
+
+use std::{
+    cell::Cell,
+    io::{Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use ethereum_types::{Address, H256, U256, U64};
+use rustc_hex::ToHex;
+use serde::de::DeserializeOwned;
+use serde_json::{self, Value};
+
+use Error;
+
+/// Time to wait for the response of a single request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// JSON-RPC client of a single node.
+#[derive(Debug, Clone)]
+pub struct Client {
+    host: String,
+    path: String,
+    next_id: Cell<u64>,
+}
+
+/// Transaction receipt, reduced to the fields the benchmark reads.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Receipt {
+    /// Execution status of the transaction, `1` on success.
+    pub status: Option<U64>,
+    /// Address of the created contract, if any.
+    pub contract_address: Option<Address>,
+    /// Cross-shard lifecycle of shard transactions.
+    pub shard: Option<ShardReceipt>,
+}
+
+impl Receipt {
+    /// Whether the receipt will not change anymore.
+    ///
+    /// Cross-shard calls are final once they completed or reverted on their final shard.
+    pub fn is_final(&self) -> bool {
+        self.shard
+            .as_ref()
+            .map_or(true, |shard| shard.status != ShardStatus::Pending)
+    }
+}
+
+/// Cross-shard lifecycle reported in transaction receipts.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShardReceipt {
+    /// Shard the call started on.
+    pub origin_shard: U64,
+    /// Shard the call finished on, unless it is still pending.
+    pub final_shard: Option<U64>,
+    /// Number of hops so far.
+    pub hop_count: U64,
+    /// Completion status.
+    pub status: ShardStatus,
+}
+
+/// Completion status of a cross-shard call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShardStatus {
+    /// Waiting to continue on another shard.
+    Pending,
+    /// Finished on its final shard.
+    Complete,
+    /// Reverted on its final shard.
+    Reverted,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RoundInfo {
+    shard_count: U64,
+}
+
+impl Client {
+    /// Client of the endpoint at `url`, e.g. `http://localhost:8540`.
+    pub fn new(url: &str) -> Result<Self, Error> {
+        let address = url.strip_prefix("http://").ok_or_else(|| {
+            Error::Rpc(format!(
+                "Unsupported endpoint {}, only http:// urls are supported",
+                url
+            ))
+        })?;
+        let (host, path) = match address.find('/') {
+            Some(i) => (&address[..i], &address[i..]),
+            None => (address, "/"),
+        };
+        if host.is_empty() {
+            return Err(Error::Rpc(format!("Endpoint {} has no host", url)));
+        }
+
+        Ok(Client {
+            host: host.into(),
+            path: path.into(),
+            next_id: Cell::new(1),
+        })
+    }
+
+    /// Call `method` with `params` and decode its result.
+    pub fn call<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, Error> {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        let request = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        });
+
+        let mut response: Value = serde_json::from_slice(&self.post(&request.to_string())?)?;
+        if let Some(error) = response.get("error") {
+            return Err(Error::Rpc(format!("{} failed: {}", method, error)));
+        }
+        let result = response
+            .get_mut("result")
+            .map(Value::take)
+            .unwrap_or(Value::Null);
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Chain id transactions are signed for, if replay protection is enabled.
+    pub fn chain_id(&self) -> Result<Option<u64>, Error> {
+        let chain_id: Option<U64> = self.call("eth_chainId", json!([]))?;
+        Ok(chain_id.map(|id| id.as_u64()))
+    }
+
+    /// Gas price suggested by the node.
+    pub fn gas_price(&self) -> Result<U256, Error> {
+        self.call("eth_gasPrice", json!([]))
+    }
+
+    /// Next nonce of `address`, including queued transactions.
+    pub fn nonce(&self, address: &Address) -> Result<U256, Error> {
+        self.call("eth_getTransactionCount", json!([address, "pending"]))
+    }
+
+    /// Latest balance of `address`.
+    pub fn balance(&self, address: &Address) -> Result<U256, Error> {
+        self.call("eth_getBalance", json!([address, "latest"]))
+    }
+
+    /// Submit a signed transaction and return its hash.
+    pub fn send_raw_transaction(&self, raw: &[u8]) -> Result<H256, Error> {
+        self.call(
+            "eth_sendRawTransaction",
+            json!([format!("0x{}", raw.to_hex())]),
+        )
+    }
+
+    /// Receipt of the transaction `hash`, if it was sealed.
+    pub fn receipt(&self, hash: &H256) -> Result<Option<Receipt>, Error> {
+        self.call("eth_getTransactionReceipt", json!([hash]))
+    }
+
+    /// Number of shards of the network, read through the `shard` api.
+    pub fn shard_count(&self) -> Result<u64, Error> {
+        let info: RoundInfo = self.call("shard_roundInfo", json!([]))?;
+        Ok(info.shard_count.as_u64())
+    }
+
+    fn post(&self, body: &str) -> Result<Vec<u8>, Error> {
+        let mut stream = TcpStream::connect(&*self.host)?;
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path,
+            self.host,
+            body.len(),
+            body
+        )?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response)?;
+        http_body(&response)
+    }
+}
+
+/// Body of a complete HTTP response.
+fn http_body(response: &[u8]) -> Result<Vec<u8>, Error> {
+    let malformed = || Error::Rpc("Malformed HTTP response".into());
+    let end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(malformed)?;
+    let head = String::from_utf8_lossy(&response[..end]).to_lowercase();
+    let body = &response[end + 4..];
+
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    if status != "200" {
+        return Err(Error::Rpc(format!("HTTP status {}", status)));
+    }
+    if !head.contains("transfer-encoding: chunked") {
+        return Ok(body.to_vec());
+    }
+
+    let mut chunks = body;
+    let mut body = Vec::new();
+    loop {
+        let line = chunks
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or_else(malformed)?;
+        let size = String::from_utf8_lossy(&chunks[..line]);
+        let size = usize::from_str_radix(size.split(';').next().unwrap_or_default().trim(), 16)
+            .map_err(|_| malformed())?;
+        chunks = &chunks[line + 2..];
+        if size == 0 {
+            return Ok(body);
+        }
+        if chunks.len() < size {
+            return Err(malformed());
+        }
+        body.extend_from_slice(&chunks[..size]);
+        chunks = &chunks[(size + 2).min(chunks.len())..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_endpoint_url() {
+        let client = Client::new("http://localhost:8540").unwrap();
+        assert_eq!(client.host, "localhost:8540");
+        assert_eq!(client.path, "/");
+
+        let client = Client::new("http://127.0.0.1:8545/rpc").unwrap();
+        assert_eq!(client.host, "127.0.0.1:8545");
+        assert_eq!(client.path, "/rpc");
+
+        assert!(Client::new("https://localhost:8540").is_err());
+        assert!(Client::new("http:///rpc").is_err());
+    }
+
+    #[test]
+    fn reads_http_body() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}";
+        assert_eq!(http_body(response).unwrap(), b"{}".to_vec());
+
+        let response =
+            b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\n{\"a\r\n4\r\n\":1}\r\n0\r\n\r\n";
+        assert_eq!(http_body(response).unwrap(), b"{\"a\":1}".to_vec());
+
+        let response = b"HTTP/1.1 403 Forbidden\r\n\r\n";
+        assert!(http_body(response).is_err());
+    }
+
+    #[test]
+    fn shard_receipts_are_final_once_finished() {
+        let receipt: Receipt = serde_json::from_value(json!({
+            "status": "0x1",
+            "contractAddress": null,
+            "shard": {
+                "originShard": "0x0",
+                "finalShard": null,
+                "nextShard": "0x1",
+                "hopCount": "0x0",
+                "status": "pending",
+            },
+        }))
+        .unwrap();
+        assert!(!receipt.is_final());
+
+        let receipt: Receipt =
+            serde_json::from_value(json!({ "status": "0x1", "contractAddress": null })).unwrap();
+        assert!(receipt.is_final());
+    }
+}
@@ -0,0 +1,260 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Transactions generated by the benchmark workloads.
+
+use ethereum_types::{Address, U256};
+use types::transaction::Action;
+
+use abi::{self, Token};
+use Error;
+
+/// Deadline of router swaps, far enough in the future to never expire.
+const SWAP_DEADLINE: u64 = 1_234_567_891_234_567;
+
+/// Unsigned part of a benchmark transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub action: Action,
+    pub value: U256,
+    pub data: Vec<u8>,
+}
+
+impl Call {
+    /// Transfer of `value` wei to `to`.
+    pub fn transfer(to: Address, value: U256) -> Self {
+        Call {
+            action: Action::Call(to),
+            value,
+            data: Vec::new(),
+        }
+    }
+
+    /// Call of the contract at `address`.
+    pub fn contract(address: Address, data: Vec<u8>) -> Self {
+        Call {
+            action: Action::Call(address),
+            value: U256::zero(),
+            data,
+        }
+    }
+
+    /// Creation of a contract.
+    pub fn create(data: Vec<u8>) -> Self {
+        Call {
+            action: Action::Create,
+            value: U256::zero(),
+            data,
+        }
+    }
+
+    /// ERC20 `transfer` of `value` tokens of `token` to `to`.
+    pub fn token_transfer(token: Address, to: Address, value: U256) -> Self {
+        Call::contract(
+            token,
+            abi::encode_call(
+                "transfer(address,uint256)",
+                &[Token::Address(to), Token::Uint(value)],
+            ),
+        )
+    }
+}
+
+/// Contract calls a workload is made of.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Kind {
+    /// Ether transfers.
+    Transfer,
+    /// `transfer` calls of an ERC20 token.
+    Erc20 { token: Address },
+    /// `dtransfer` calls of a two-token exchange contract.
+    Dtransfer {
+        exchange: Address,
+        token_a: Address,
+        token_b: Address,
+    },
+    /// `swapExactTokensForTokens` calls of a Uniswap V2 router, alternating the swap direction.
+    Swap {
+        router: Address,
+        token_a: Address,
+        token_b: Address,
+    },
+}
+
+impl Kind {
+    /// Workload `name`, calling `contract` and trading `token_a` for `token_b` where needed.
+    pub fn parse(
+        name: &str,
+        contract: Option<Address>,
+        token_a: Option<Address>,
+        token_b: Option<Address>,
+    ) -> Result<Self, Error> {
+        let required = |address: Option<Address>, flag: &str| {
+            address.ok_or_else(|| Error::Custom(format!("Workload {} requires {}", name, flag)))
+        };
+        match name {
+            "transfer" => Ok(Kind::Transfer),
+            "erc20" => Ok(Kind::Erc20 {
+                token: required(contract, "--contract")?,
+            }),
+            "dtransfer" => Ok(Kind::Dtransfer {
+                exchange: required(contract, "--contract")?,
+                token_a: required(token_a, "--token-a")?,
+                token_b: required(token_b, "--token-b")?,
+            }),
+            "swap" => Ok(Kind::Swap {
+                router: required(contract, "--contract")?,
+                token_a: required(token_a, "--token-a")?,
+                token_b: required(token_b, "--token-b")?,
+            }),
+            _ => Err(Error::Custom(format!("Unknown workload {}", name))),
+        }
+    }
+}
+
+/// Stream of benchmark transactions.
+#[derive(Debug, Clone)]
+pub struct Workload {
+    pub kind: Kind,
+    /// Recipient of every transaction, the next benchmark account if `None`.
+    pub recipient: Option<Address>,
+    /// Wei or tokens moved by transfers.
+    pub value: U256,
+}
+
+impl Workload {
+    /// The `index`-th transaction, sent by `accounts[index % accounts.len()]`.
+    pub fn call(&self, index: usize, accounts: &[Address]) -> Call {
+        let recipient = self
+            .recipient
+            .unwrap_or_else(|| accounts[(index + 1) % accounts.len()]);
+        // varying amounts keep otherwise identical calls apart
+        let amount = U256::from(index + 1);
+        match self.kind {
+            Kind::Transfer => Call::transfer(recipient, self.value),
+            Kind::Erc20 { token } => Call::token_transfer(token, recipient, self.value),
+            Kind::Dtransfer {
+                exchange,
+                token_a,
+                token_b,
+            } => Call::contract(
+                exchange,
+                abi::encode_call(
+                    "dtransfer(address,address,address,uint256)",
+                    &[
+                        Token::Address(token_a),
+                        Token::Address(token_b),
+                        Token::Address(recipient),
+                        Token::Uint(amount),
+                    ],
+                ),
+            ),
+            Kind::Swap {
+                router,
+                token_a,
+                token_b,
+            } => {
+                let path = if index % 2 == 0 {
+                    vec![token_b, token_a]
+                } else {
+                    vec![token_a, token_b]
+                };
+                Call::contract(
+                    router,
+                    abi::encode_call(
+                        "swapExactTokensForTokens(uint256,uint256,address[],address,uint256)",
+                        &[
+                            Token::Uint(amount),
+                            Token::Uint(U256::zero()),
+                            Token::Addresses(path),
+                            Token::Address(recipient),
+                            Token::Uint(SWAP_DEADLINE.into()),
+                        ],
+                    ),
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(n: u64) -> Address {
+        Address::from_low_u64_be(n)
+    }
+
+    #[test]
+    fn contract_workloads_require_their_contracts() {
+        assert_eq!(
+            Kind::parse("transfer", None, None, None).unwrap(),
+            Kind::Transfer
+        );
+        assert!(Kind::parse("erc20", None, None, None).is_err());
+        assert!(Kind::parse("swap", Some(address(1)), Some(address(2)), None).is_err());
+        assert!(Kind::parse("mint", Some(address(1)), None, None).is_err());
+    }
+
+    #[test]
+    fn transfers_go_to_the_next_account() {
+        let accounts = [address(1), address(2), address(3)];
+        let workload = Workload {
+            kind: Kind::Transfer,
+            recipient: None,
+            value: 5.into(),
+        };
+        assert_eq!(
+            workload.call(0, &accounts),
+            Call::transfer(address(2), 5.into())
+        );
+        assert_eq!(
+            workload.call(2, &accounts),
+            Call::transfer(address(1), 5.into())
+        );
+
+        let workload = Workload {
+            recipient: Some(address(9)),
+            ..workload
+        };
+        assert_eq!(
+            workload.call(2, &accounts),
+            Call::transfer(address(9), 5.into())
+        );
+    }
+
+    #[test]
+    fn swaps_alternate_direction() {
+        let accounts = [address(1)];
+        let workload = Workload {
+            kind: Kind::parse(
+                "swap",
+                Some(address(7)),
+                Some(address(10)),
+                Some(address(11)),
+            )
+            .unwrap(),
+            recipient: None,
+            value: 1.into(),
+        };
+        let path = |index: usize| {
+            let data = workload.call(index, &accounts).data;
+            (data[4 + 7 * 32 - 1], data[4 + 8 * 32 - 1])
+        };
+        assert_eq!(path(0), (11, 10));
+        assert_eq!(path(1), (10, 11));
+    }
+}
@@ -83,6 +83,9 @@ pub trait Client: fmt::Debug + Sync {
         &self,
         transaction: &[u8],
     ) -> Result<transaction::UnverifiedTransaction, transaction::Error>;
+
+    /// Shard holding the state of given address.
+    fn address_shard(&self, address: &Address) -> u64;
 }
 
 /// State nonce client
@@ -77,6 +77,8 @@ pub struct PendingSettings {
     /// If `true` all non-local transactions in the pending set should have
     /// `effective_priority_fee` to be at least `min_gas_price`.
     pub enforce_priority_fees: bool,
+    /// If set, only transactions which can be sealed on this shard are returned.
+    pub shard: Option<u64>,
 }
 
 impl PendingSettings {
@@ -90,6 +92,7 @@ impl PendingSettings {
             ordering: PendingOrdering::Priority,
             includable_boundary: Default::default(),
             enforce_priority_fees: false,
+            shard: None,
         }
     }
 }
@@ -148,6 +151,7 @@ pub struct VerifiedTransaction {
     sender: Address,
     priority: Priority,
     insertion_id: usize,
+    shard: Option<u64>,
 }
 
 impl VerifiedTransaction {
@@ -165,6 +169,7 @@ impl VerifiedTransaction {
             sender,
             priority: Priority::Retracted,
             insertion_id: 0,
+            shard: None,
         }
     }
 
@@ -173,6 +178,16 @@ impl VerifiedTransaction {
         self.insertion_id
     }
 
+    /// Shard the transaction has to be sealed on, `None` if any shard may include it.
+    pub fn shard(&self) -> Option<u64> {
+        self.shard
+    }
+
+    /// Whether the transaction can be sealed on `shard`.
+    pub fn belongs_to_shard(&self, shard: u64) -> bool {
+        self.shard.map_or(true, |s| s == shard)
+    }
+
     /// Gets wrapped `SignedTransaction`
     pub fn signed(&self) -> &transaction::SignedTransaction {
         &self.transaction
@@ -97,6 +97,7 @@ struct CachedPending {
     has_local_pending: bool,
     pending: Option<Vec<Arc<pool::VerifiedTransaction>>>,
     max_len: usize,
+    shard: Option<u64>,
 }
 
 impl CachedPending {
@@ -109,6 +110,7 @@ impl CachedPending {
             pending: None,
             nonce_cap: None,
             max_len: 0,
+            shard: None,
         }
     }
 
@@ -124,8 +126,16 @@ impl CachedPending {
         current_timestamp: u64,
         nonce_cap: Option<&U256>,
         max_len: usize,
+        shard: Option<u64>,
     ) -> Option<Vec<Arc<pool::VerifiedTransaction>>> {
-        self.pending_filtered(block_number, current_timestamp, nonce_cap, max_len, None)
+        self.pending_filtered(
+            block_number,
+            current_timestamp,
+            nonce_cap,
+            max_len,
+            shard,
+            None,
+        )
     }
 
     pub fn pending_filtered(
@@ -134,12 +144,13 @@ impl CachedPending {
         current_timestamp: u64,
         nonce_cap: Option<&U256>,
         max_len: usize,
+        shard: Option<u64>,
         filter: Option<TransactionFilter>,
     ) -> Option<Vec<Arc<pool::VerifiedTransaction>>> {
         // First check if we have anything in cache.
         let pending = self.pending.as_ref()?;
 
-        if block_number != self.block_number {
+        if block_number != self.block_number || shard != self.shard {
             return None;
         }
 
@@ -394,6 +405,18 @@ impl TransactionQueue {
             .collect()
     }
 
+    /// Returns transactions in the queue which have to be sealed on a shard other than `shard`.
+    ///
+    /// They are never part of this shard's pending set, but should still be propagated.
+    pub fn foreign_shard_transactions(&self, shard: u64) -> Vec<Arc<pool::VerifiedTransaction>> {
+        let ready = |_tx: &pool::VerifiedTransaction| txpool::Readiness::Ready;
+        self.pool
+            .read()
+            .unordered_pending(ready, Default::default())
+            .filter(|tx| !tx.belongs_to_shard(shard))
+            .collect()
+    }
+
     /// Returns all transaction hashes in the queue without explicit ordering.
     pub fn all_transaction_hashes(&self) -> Vec<H256> {
         let ready = |_tx: &pool::VerifiedTransaction| txpool::Readiness::Ready;
@@ -440,6 +463,7 @@ impl TransactionQueue {
             ordering,
             includable_boundary,
             enforce_priority_fees,
+            shard,
         } = settings;
 
         let cached = if enforce_priority_fees {
@@ -448,24 +472,31 @@ impl TransactionQueue {
             &self.cached_non_enforced_pending
         };
 
-        if let Some(pending) =
-            cached
-                .read()
-                .pending(block_number, current_timestamp, nonce_cap.as_ref(), max_len)
-        {
+        if let Some(pending) = cached.read().pending(
+            block_number,
+            current_timestamp,
+            nonce_cap.as_ref(),
+            max_len,
+            shard,
+        ) {
             return pending;
         }
 
         // Double check after acquiring write lock
         let mut cached_pending = cached.write();
-        if let Some(pending) =
-            cached_pending.pending(block_number, current_timestamp, nonce_cap.as_ref(), max_len)
-        {
+        if let Some(pending) = cached_pending.pending(
+            block_number,
+            current_timestamp,
+            nonce_cap.as_ref(),
+            max_len,
+            shard,
+        ) {
             return pending;
         }
 
         let effective_priority_fee_filter =
             self.build_effective_priority_fee_filter(enforce_priority_fees, includable_boundary);
+        let shard_filter = Self::build_shard_filter(shard);
 
         // In case we don't have a cached set, but we don't care about order
         // just return the unordered set.
@@ -475,6 +506,7 @@ impl TransactionQueue {
                 .pool
                 .read()
                 .unordered_pending(ready, includable_boundary)
+                .filter(shard_filter)
                 .filter(effective_priority_fee_filter)
                 .take(max_len)
                 .collect();
@@ -487,7 +519,8 @@ impl TransactionQueue {
             current_timestamp,
             nonce_cap,
             |i| {
-                i.filter(effective_priority_fee_filter)
+                i.filter(shard_filter)
+                    .filter(effective_priority_fee_filter)
                     .take(max_len)
                     .collect()
             },
@@ -500,6 +533,7 @@ impl TransactionQueue {
             has_local_pending: self.has_local_pending_transactions(),
             pending: Some(pending.clone()),
             max_len,
+            shard,
         };
 
         pending
@@ -521,6 +555,7 @@ impl TransactionQueue {
             settings.enforce_priority_fees,
             settings.includable_boundary,
         );
+        let shard_filter = Self::build_shard_filter(settings.shard);
         self.collect_pending(
             client,
             settings.includable_boundary,
@@ -529,6 +564,7 @@ impl TransactionQueue {
             settings.nonce_cap,
             |i| {
                 i.filter(|tx| filter.matches(tx))
+                    .filter(shard_filter)
                     .filter(effective_priority_fee_filter)
                     .take(settings.max_len)
                     .collect()
@@ -588,6 +624,17 @@ impl TransactionQueue {
         }
     }
 
+    /// Creates a filter that returns only transactions which can be sealed on `shard`,
+    /// or a filter that just returns all transactions.
+    fn build_shard_filter(
+        shard: Option<u64>,
+    ) -> Box<dyn Fn(&Arc<pool::VerifiedTransaction>) -> bool> {
+        match shard {
+            Some(shard) => Box::new(move |tx| tx.belongs_to_shard(shard)),
+            None => Box::new(|_| true),
+        }
+    }
+
     fn ready<C>(
         client: C,
         block_number: u64,
@@ -100,6 +100,7 @@ impl TestClient {
             priority: pool::Priority::Regular,
             transaction: tx,
             insertion_id: 1,
+            shard: None,
         }
     }
 
@@ -161,6 +162,10 @@ impl pool::client::Client for TestClient {
         TypedTransaction::decode(transaction)
             .map_err(|e| transaction::Error::InvalidRlp(e.to_string()))
     }
+
+    fn address_shard(&self, _address: &Address) -> u64 {
+        0
+    }
 }
 
 impl pool::client::NonceClient for TestClient {
@@ -1015,6 +1015,7 @@ fn should_not_return_transactions_over_nonce_cap(enforce_priority_fees: bool) {
             ordering: PendingOrdering::Priority,
             includable_boundary: Default::default(),
             enforce_priority_fees,
+            shard: None,
         },
     );
 
@@ -1055,6 +1056,7 @@ fn should_return_cached_pending_even_if_unordered_is_requested(enforce_priority_
             ordering: PendingOrdering::Priority,
             includable_boundary: Default::default(),
             enforce_priority_fees,
+            shard: None,
         },
     );
     assert_eq!(all[0].hash, tx2_1_hash);
@@ -1071,6 +1073,7 @@ fn should_return_cached_pending_even_if_unordered_is_requested(enforce_priority_
             ordering: PendingOrdering::Unordered,
             includable_boundary: Default::default(),
             enforce_priority_fees,
+            shard: None,
         },
     );
 
@@ -1110,6 +1113,7 @@ fn should_return_unordered_and_not_populate_the_cache(enforce_priority_fees: boo
             ordering: PendingOrdering::Unordered,
             includable_boundary: Default::default(),
             enforce_priority_fees,
+            shard: None,
         },
     );
 
@@ -1425,6 +1429,7 @@ fn should_not_return_pending_external_transactions_with_too_low_priority_fee_if_
         ordering: PendingOrdering::Priority,
         includable_boundary: block_base_fee,
         enforce_priority_fees: true,
+        shard: None,
     };
     let all = if !pending_filtered {
         txq.pending(client.clone(), pending_settings)
@@ -1451,6 +1456,7 @@ fn should_not_return_pending_external_transactions_with_too_low_priority_fee_if_
         ordering: PendingOrdering::Priority,
         includable_boundary: block_base_fee,
         enforce_priority_fees: false,
+        shard: None,
     };
     let all = if !pending_filtered {
         txq.pending(client.clone(), pending_settings)
@@ -1503,6 +1509,7 @@ fn should_return_correct_cached_pending_depending_on_fees_enforcement(enforce_pr
         ordering: PendingOrdering::Priority,
         includable_boundary: block_base_fee,
         enforce_priority_fees,
+        shard: None,
     };
     let all = txq.pending(client.clone(), pending_settings);
 
@@ -1517,9 +1524,43 @@ fn should_return_correct_cached_pending_depending_on_fees_enforcement(enforce_pr
             ordering: PendingOrdering::Unordered,
             includable_boundary: Default::default(),
             enforce_priority_fees,
+            shard: None,
         },
     );
 
     // then
     assert_eq!(all, limited);
 }
+
+#[test]
+fn should_only_return_pending_transactions_of_requested_shard() {
+    // given
+    let client = TestClient::new();
+    let txq = new_queue();
+    let own = Tx::default().shard_one(0);
+    let own_hash = own.hash();
+    let foreign = Tx::default().shard_one(1);
+    let foreign_hash = foreign.hash();
+    let legacy = Tx::default().signed();
+    let res = txq.import(client.clone(), vec![own, foreign, legacy].unverified());
+    assert_eq!(res, vec![Ok(()), Ok(()), Ok(())]);
+
+    // when
+    let pending = txq.pending(
+        client.clone(),
+        PendingSettings {
+            shard: Some(0),
+            ..PendingSettings::all_prioritized(0, 0)
+        },
+    );
+    let foreign_txs = txq.foreign_shard_transactions(0);
+    let all = txq.pending(client, PendingSettings::all_prioritized(0, 0));
+
+    // then
+    assert_eq!(pending.len(), 2);
+    assert!(pending.iter().any(|tx| tx.hash == own_hash));
+    assert!(pending.iter().all(|tx| tx.hash != foreign_hash));
+    assert_eq!(foreign_txs.len(), 1);
+    assert_eq!(foreign_txs[0].hash, foreign_hash);
+    assert_eq!(all.len(), 3);
+}
@@ -112,6 +112,29 @@ impl Tx {
         tx.sign(keypair.secret(), None)
     }
 
+    pub fn shard_one(self, shard: u64) -> SignedTransaction {
+        let keypair = Random.generate();
+        let tx = TypedTransaction::ShardTransaction(ShardTransactionTx::new(
+            Transaction {
+                action: transaction::Action::Create,
+                value: self.value.into(),
+                data: "3331600055".from_hex().unwrap(),
+                gas: self.gas.into(),
+                gas_price: self.gas_price.into(),
+                nonce: self.nonce.into(),
+            },
+            shard,
+            999,
+            0,
+            0,
+            keypair.address(),
+            Default::default(),
+            Vec::new(),
+            String::new(),
+        ));
+        tx.sign(keypair.secret(), None)
+    }
+
     pub fn eip1559_one(self, max_priority_fee_per_gas: u64) -> SignedTransaction {
         let keypair = Random.generate();
         let tx = TypedTransaction::EIP1559Transaction(EIP1559TransactionTx {
@@ -415,6 +415,9 @@ impl<C: Client> txpool::Verifier<Transaction>
             bail!(transaction::Error::Old);
         }
 
+        // Foreign-shard transactions are kept for propagation, but never sealed by this node.
+        let shard = transaction.home_shard(&|address| self.client.address_shard(address));
+
         let priority = match (is_own || account_details.is_local, is_retracted) {
             (true, _) => super::Priority::Local,
             (false, false) => super::Priority::Regular,
@@ -426,6 +429,7 @@ impl<C: Client> txpool::Verifier<Transaction>
             hash,
             sender,
             insertion_id: self.id.fetch_add(1, atomic::Ordering::AcqRel),
+            shard,
         })
     }
 }
@@ -44,6 +44,10 @@ pub enum ExtrasIndex {
     EpochTransitions = 5,
     /// Pending epoch transition data index.
     PendingEpochTransition = 6,
+    /// Latest continuation of a cross-shard transaction index.
+    ShardContinuation = 7,
+    /// Shard vector leaf of an address or storage key index.
+    ShardLeaf = 8,
 }
 
 fn with_index(hash: &H256, i: ExtrasIndex) -> H264 {
@@ -92,6 +96,14 @@ impl Key<TransactionAddress> for H256 {
     }
 }
 
+impl Key<ShardContinuation> for H256 {
+    type Target = H264;
+
+    fn key(&self) -> H264 {
+        with_index(self, ExtrasIndex::ShardContinuation)
+    }
+}
+
 impl Key<BlockReceipts> for H256 {
     type Target = H264;
 
@@ -151,6 +163,33 @@ impl Key<EpochTransitions> for u64 {
     }
 }
 
+/// length of shard leaf keys.
+pub const SHARD_LEAF_KEY_LEN: usize = 1 + 32;
+
+/// shard leaf key prefix.
+/// used to iterate over all shard vector leaf allocations.
+pub const SHARD_LEAF_KEY_PREFIX: &'static [u8; 1] = &[ExtrasIndex::ShardLeaf as u8];
+
+/// Shard leaf key
+pub struct ShardLeafKey([u8; SHARD_LEAF_KEY_LEN]);
+
+impl AsRef<[u8]> for ShardLeafKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0[..]
+    }
+}
+
+impl Key<ShardLeaf> for H256 {
+    type Target = ShardLeafKey;
+
+    fn key(&self) -> Self::Target {
+        let mut arr = [0u8; SHARD_LEAF_KEY_LEN];
+        arr[..1].copy_from_slice(&SHARD_LEAF_KEY_PREFIX[..]);
+        arr[1..].copy_from_slice(self.as_bytes());
+        ShardLeafKey(arr)
+    }
+}
+
 /// Familial details concerning a block
 #[derive(Debug, Clone, MallocSizeOf)]
 pub struct BlockDetails {
@@ -220,6 +259,20 @@ pub struct TransactionAddress {
     pub index: usize,
 }
 
+/// Latest continuation hop of a cross-shard transaction, keyed by the original hash.
+#[derive(Debug, PartialEq, Clone, RlpEncodable, RlpDecodable, MallocSizeOf)]
+pub struct ShardContinuation {
+    /// Hash of the continuation transaction
+    pub transaction_hash: H256,
+}
+
+/// Shard vector leaf allocated to an address or storage key.
+#[derive(Debug, PartialEq, Clone, RlpEncodable, RlpDecodable, MallocSizeOf)]
+pub struct ShardLeaf {
+    /// Index of the leaf
+    pub index: u64,
+}
+
 /// Contains all block receipts.
 #[derive(Clone, MallocSizeOf)]
 pub struct BlockReceipts {
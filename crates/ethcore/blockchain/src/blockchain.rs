@@ -45,7 +45,10 @@ use db::{DBTransaction, KeyValueDB};
 use ethcore_db::{
     self as db,
     cache_manager::CacheManager,
-    keys::{BlockDetails, BlockReceipts, EpochTransitions, TransactionAddress, EPOCH_KEY_PREFIX},
+    keys::{
+        BlockDetails, BlockReceipts, EpochTransitions, ShardContinuation, ShardLeaf,
+        TransactionAddress, EPOCH_KEY_PREFIX, SHARD_LEAF_KEY_LEN, SHARD_LEAF_KEY_PREFIX,
+    },
     CacheUpdatePolicy, Readable, Writable,
 };
 use ethereum_types::{Address, Bloom, BloomRef, H256, U256};
@@ -64,6 +67,7 @@ use stats::PrometheusMetrics;
 use crate::{
     best_block::{BestAncientBlock, BestBlock},
     block_info::{BlockInfo, BlockLocation, BranchBecomingCanonChainData},
+    shard_state::ShardRoundState,
     update::{ExtrasInsert, ExtrasUpdate},
     CacheSize, Config, ImportRoute,
 };
@@ -282,9 +286,9 @@ pub struct BlockChain {
     //new state root should be here
     pub shard_state_root: RwLock<(H256, BlockNumber)>,
     //stores latest data modified for the last 10 rounds. 11th hashmap is popped every round and new hashmap is pushed on top.
-    pub data_hash_map_global: RwLock<Vec<HashMap<Address, U256>>>,
+    pub data_hash_map_global: RwLock<Vec<HashMap<H256, U256>>>,
     //this keeps track of the data at the beginning of each round. Key, val is pushed whenever SStore occurs and no key exists in hashmap
-    pub data_hash_map_round_beginning: RwLock<HashMap<Address, U256>>,
+    pub data_hash_map_round_beginning: RwLock<HashMap<H256, U256>>,
     // this keeps track of increment in the current round for balances only
     pub incr_bal_round: RwLock<HashMap<Address,U256>>,
     // All the incomplete txn with next_shard equal to my shard gets collected here.
@@ -820,6 +824,9 @@ impl BlockChain {
             }
         }
 
+        // reload shard round state of the best block
+        bc.restore_best_shard_round_state();
+
         bc
     }
 
@@ -1531,6 +1538,160 @@ impl BlockChain {
         }
 
     }
+
+    /// Snapshot of the in-memory shard round state.
+    pub fn shard_round_state(&self) -> ShardRoundState {
+        ShardRoundState {
+            shard_state_root: *self.shard_state_root.read(),
+            data_hash_map_global: self.data_hash_map_global.read().clone(),
+            data_hash_map_round_beginning: self.data_hash_map_round_beginning.read().clone(),
+            incr_bal_round: self.incr_bal_round.read().clone(),
+            incomplete_txn: self.incomplete_txn.read().clone(),
+            leaves: Vec::new(),
+        }
+    }
+
+    /// Replace the in-memory shard round state.
+    pub fn set_shard_round_state(&self, state: ShardRoundState) {
+        *self.shard_state_root.write() = state.shard_state_root;
+        *self.data_hash_map_global.write() = state.data_hash_map_global;
+        *self.data_hash_map_round_beginning.write() = state.data_hash_map_round_beginning;
+        *self.incr_bal_round.write() = state.incr_bal_round;
+        *self.incomplete_txn.write() = state.incomplete_txn;
+    }
+
+    /// Shard round state persisted after importing the given block.
+    /// Round state is only persisted with the `shard` feature.
+    #[cfg(not(feature = "shard"))]
+    pub fn block_shard_round_state(&self, _hash: &H256) -> Option<ShardRoundState> {
+        None
+    }
+
+    /// Shard round state persisted after importing the given block.
+    #[cfg(feature = "shard")]
+    pub fn block_shard_round_state(&self, hash: &H256) -> Option<ShardRoundState> {
+        self.db
+            .key_value()
+            .get(db::COL_SHARD, hash.as_bytes())
+            .expect(
+                "Low level database error when fetching shard round state. Some issue with disk?",
+            )
+            .map(|bytes| {
+                rlp::decode(&bytes).expect("Shard round state is written by this node; qed")
+            })
+    }
+
+    /// Shard round state a child of `parent` with the given number starts from.
+    ///
+    /// Uses the state persisted for `parent`, so blocks on competing branches do not
+    /// see each other's data, and falls back to the in-memory state for parents
+    /// imported before round state was persisted.
+    pub fn child_shard_round_state(
+        &self,
+        parent: &H256,
+        number: BlockNumber,
+        round_length: u64,
+    ) -> ShardRoundState {
+        let mut state = self
+            .block_shard_round_state(parent)
+            .unwrap_or_else(|| self.shard_round_state());
+        state.leaves.clear();
+        if number.rem_euclid(round_length) == 0 {
+            state.begin_round();
+        }
+        state
+    }
+
+    /// Write the shard round state after importing the given block to the batch.
+    /// Round state is only persisted with the `shard` feature.
+    #[cfg(not(feature = "shard"))]
+    pub fn insert_shard_round_state(
+        &self,
+        _batch: &mut DBTransaction,
+        _hash: &H256,
+        _state: &ShardRoundState,
+    ) {
+    }
+
+    /// Write the shard round state after importing the given block to the batch.
+    /// Should be written in the same batch as the block itself.
+    #[cfg(feature = "shard")]
+    pub fn insert_shard_round_state(
+        &self,
+        batch: &mut DBTransaction,
+        hash: &H256,
+        state: &ShardRoundState,
+    ) {
+        batch.put(db::COL_SHARD, hash.as_bytes(), &rlp::encode(state));
+    }
+
+    /// Index the cross-shard continuation hops of the given block by their original
+    /// transaction hash. Should be written in the same batch as the block itself.
+    pub fn insert_shard_continuations(&self, batch: &mut DBTransaction, block: &encoded::Block) {
+        for tx in block.view().transactions() {
+            if let Some(original) = tx.original_transaction_hash() {
+                batch.write(
+                    db::COL_EXTRA,
+                    &original,
+                    &ShardContinuation {
+                        transaction_hash: tx.hash(),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Hash of the latest continuation hop of a cross-shard transaction.
+    pub fn shard_continuation(&self, original: &H256) -> Option<H256> {
+        self.db
+            .key_value()
+            .read::<ShardContinuation, _>(db::COL_EXTRA, original)
+            .map(|continuation| continuation.transaction_hash)
+    }
+
+    /// Write shard vector leaf allocations which became canonical to the batch.
+    pub fn insert_shard_leaves(&self, batch: &mut DBTransaction, leaves: &[(H256, u64)]) {
+        for (key, index) in leaves {
+            batch.write(db::COL_EXTRA, key, &ShardLeaf { index: *index });
+        }
+    }
+
+    /// Remove shard vector leaf allocations which are no longer canonical from the batch.
+    pub fn remove_shard_leaves(&self, batch: &mut DBTransaction, leaves: &[(H256, u64)]) {
+        for (key, _) in leaves {
+            Writable::delete::<ShardLeaf, _>(batch, db::COL_EXTRA, key);
+        }
+    }
+
+    /// Shard vector leaves allocated by the given block.
+    pub fn block_shard_leaves(&self, hash: &H256) -> Vec<(H256, u64)> {
+        self.block_shard_round_state(hash)
+            .map(|state| state.leaves)
+            .unwrap_or_default()
+    }
+
+    /// All canonical shard vector leaf allocations.
+    pub fn shard_leaves(&self) -> Vec<(H256, u64)> {
+        self.db
+            .key_value()
+            .iter_from_prefix(db::COL_EXTRA, &SHARD_LEAF_KEY_PREFIX[..])
+            // iterator may continue beyond values beginning with this prefix.
+            .take_while(|(key, _)| key.starts_with(&SHARD_LEAF_KEY_PREFIX[..]))
+            .filter(|(key, _)| key.len() == SHARD_LEAF_KEY_LEN)
+            .map(|(key, value)| {
+                let leaf: ShardLeaf = ::rlp::decode(&value)
+                    .expect("decode error: the db is corrupted or the data structure has changed");
+                (H256::from_slice(&key[1..]), leaf.index)
+            })
+            .collect()
+    }
+
+    /// Reload the shard round state of the current best block, if one was persisted.
+    pub fn restore_best_shard_round_state(&self) {
+        if let Some(state) = self.block_shard_round_state(&self.best_block_hash()) {
+            self.set_shard_round_state(state);
+        }
+    }
     /// t_nb 9.12 commit changed to become current greatest by applying pending insertion updates
     pub fn commit(&self) {
         let mut pending_best_ancient_block = self.pending_best_ancient_block.write();
@@ -2580,6 +2741,158 @@ mod tests {
         }
     }
 
+    #[test]
+    #[cfg(feature = "shard")]
+    fn shard_round_state_is_reloaded_on_reopen() {
+        let genesis = BlockBuilder::genesis();
+        let first = genesis.add_block();
+        let first_hash = first.last().hash();
+
+        let mut data = HashMap::new();
+        data.insert(H256::from_low_u64_be(1), U256::from(10));
+        let mut balances = HashMap::new();
+        balances.insert(Address::from_low_u64_be(1), U256::from(10));
+        let state = ShardRoundState {
+            shard_state_root: (H256::from_low_u64_be(7), 1),
+            data_hash_map_global: vec![data.clone()],
+            data_hash_map_round_beginning: data,
+            incr_bal_round: balances,
+            incomplete_txn: vec![],
+            leaves: vec![(H256::from_low_u64_be(1), 5)],
+        };
+
+        let db = new_db();
+
+        {
+            let bc = new_chain(
+                genesis.last().encoded(),
+                db.clone(),
+                BlockNumber::max_value(),
+            );
+            assert_eq!(bc.shard_round_state().shard_state_root.1, 999);
+
+            let mut batch = db.key_value().transaction();
+            bc.insert_shard_round_state(&mut batch, &first_hash, &state);
+            insert_block_batch(&mut batch, &bc, first.last().encoded(), vec![]);
+            db.key_value().write(batch).unwrap();
+            bc.commit();
+            assert_eq!(bc.block_shard_round_state(&first_hash), Some(state.clone()));
+        }
+
+        {
+            let bc = new_chain(
+                genesis.last().encoded(),
+                db.clone(),
+                BlockNumber::max_value(),
+            );
+
+            assert_eq!(bc.best_block_hash(), first_hash);
+            assert_eq!(bc.shard_round_state(), state);
+        }
+    }
+
+    #[test]
+    fn shard_leaves_are_persisted() {
+        let genesis = BlockBuilder::genesis();
+        let db = new_db();
+        let bc = new_chain(
+            genesis.last().encoded(),
+            db.clone(),
+            BlockNumber::max_value(),
+        );
+        assert!(bc.shard_leaves().is_empty());
+
+        let leaves = vec![
+            (H256::from_low_u64_be(1), 5),
+            (H256::from_low_u64_be(0x1_0001), 6),
+        ];
+        let mut batch = db.key_value().transaction();
+        bc.insert_shard_leaves(&mut batch, &leaves);
+        db.key_value().write(batch).unwrap();
+
+        let mut persisted = bc.shard_leaves();
+        persisted.sort();
+        assert_eq!(persisted, leaves);
+
+        let mut batch = db.key_value().transaction();
+        bc.remove_shard_leaves(&mut batch, &leaves[..1]);
+        db.key_value().write(batch).unwrap();
+        assert_eq!(bc.shard_leaves(), leaves[1..].to_vec());
+    }
+
+    /// Imports `block` the way the client does: starts from the parent's round state,
+    /// records `touched` as modified in this block and persists the result with the block.
+    #[cfg(feature = "shard")]
+    fn insert_block_with_round_state(
+        db: &Arc<dyn BlockChainDB>,
+        bc: &BlockChain,
+        block: &crate::generator::Block,
+        touched: u64,
+    ) -> ShardRoundState {
+        let header = block.header();
+        let mut state = bc.child_shard_round_state(header.parent_hash(), block.number(), 2);
+        if state.data_hash_map_global.is_empty() {
+            state.data_hash_map_global.push(HashMap::new());
+        }
+        state
+            .data_hash_map_global
+            .last_mut()
+            .unwrap()
+            .insert(H256::from_low_u64_be(touched), U256::from(touched));
+        state.shard_state_root = (H256::from_low_u64_be(touched), block.number());
+
+        let mut batch = db.key_value().transaction();
+        bc.insert_shard_round_state(&mut batch, &block.hash(), &state);
+        let route = insert_block_batch(&mut batch, bc, block.encoded(), vec![]);
+        db.key_value().write(batch).unwrap();
+        bc.commit();
+        if route.enacted.last() == Some(&block.hash()) {
+            bc.set_shard_round_state(state.clone());
+        }
+        state
+    }
+
+    #[test]
+    #[cfg(feature = "shard")]
+    fn shard_round_state_follows_fork_across_round_boundary() {
+        let genesis = BlockBuilder::genesis();
+        let b1 = genesis.add_block();
+        // block 2 begins a new round on both branches
+        let b2a = b1.add_block();
+        let b2b = b1.add_block_with_difficulty(9);
+        let b3b = b2b.add_block();
+
+        let db = new_db();
+        let bc = new_chain(
+            genesis.last().encoded(),
+            db.clone(),
+            BlockNumber::max_value(),
+        );
+
+        let s1 = insert_block_with_round_state(&db, &bc, b1.last(), 1);
+        let s2a = insert_block_with_round_state(&db, &bc, b2a.last(), 2);
+        assert_eq!(bc.shard_round_state(), s2a);
+        assert_eq!(s2a.data_hash_map_global.len(), 2);
+
+        // the competing block starts from block 1, not from the current best block
+        let s2b = insert_block_with_round_state(&db, &bc, b2b.last(), 3);
+        assert_eq!(bc.best_block_hash(), b2a.last().hash());
+        assert_eq!(bc.shard_round_state(), s2a);
+        assert_eq!(s2b.data_hash_map_global[0], s1.data_hash_map_global[0]);
+        assert!(!s2b.data_hash_map_global[1].contains_key(&H256::from_low_u64_be(2)));
+
+        // reorg onto the second branch replaces the retracted round state
+        let s3b = insert_block_with_round_state(&db, &bc, b3b.last(), 4);
+        assert_eq!(bc.best_block_hash(), b3b.last().hash());
+        assert_eq!(bc.shard_round_state(), s3b);
+        assert!(s3b.data_hash_map_global[1].contains_key(&H256::from_low_u64_be(3)));
+        assert!(!s3b.data_hash_map_global[1].contains_key(&H256::from_low_u64_be(2)));
+
+        // every branch keeps its own persisted state
+        assert_eq!(bc.block_shard_round_state(&b2a.last().hash()), Some(s2a));
+        assert_eq!(bc.block_shard_round_state(&b2b.last().hash()), Some(s2b));
+    }
+
     #[test]
     fn find_transaction_by_hash() {
         let genesis = "f901fcf901f7a00000000000000000000000000000000000000000000000000000000000000000a01dcc4de8dec75d7aab85b567b6ccd41ad312451b948a7413f0a142fd40d49347948888f1f195afa192cfee860698584c030f4c9db1a0af81e09f8c46ca322193edfda764fa7e88e81923f802f1d325ec0b0308ac2cd0a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421b9010000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000830200008083023e38808454c98c8142a056e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421880102030405060708c0c0".from_hex().unwrap();
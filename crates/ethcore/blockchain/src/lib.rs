@@ -26,6 +26,7 @@ mod blockchain;
 mod cache;
 mod config;
 mod import_route;
+mod shard_state;
 mod update;
 
 pub mod generator;
@@ -35,7 +36,10 @@ pub use self::{
     cache::CacheSize,
     config::Config,
     import_route::ImportRoute,
+    shard_state::{ShardRoundState, ROUND_HISTORY},
     update::ExtrasInsert,
 };
 pub use common_types::tree_route::TreeRoute;
-pub use ethcore_db::keys::{BlockDetails, BlockNumberKey, BlockReceipts, TransactionAddress};
+pub use ethcore_db::keys::{
+    BlockDetails, BlockNumberKey, BlockReceipts, ShardContinuation, ShardLeaf, TransactionAddress,
+};
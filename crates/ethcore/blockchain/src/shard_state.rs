@@ -0,0 +1,202 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Cross-shard round state persisted alongside each block.
+
+use std::{collections::HashMap, hash::Hash};
+
+use common_types::{
+    transaction::{SignedTransaction, TypedTransaction},
+    BlockNumber,
+};
+use ethereum_types::{Address, H256, U256};
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+
+/// Number of rounds of modified data kept in `data_hash_map_global`.
+pub const ROUND_HISTORY: usize = 5;
+
+/// Round state of a shard node after a block has been imported.
+///
+/// Stored in `COL_SHARD` under the block hash, so it can be reloaded on
+/// startup and restored when the best block changes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShardRoundState {
+    /// State root and number of the block the round state belongs to.
+    pub shard_state_root: (H256, BlockNumber),
+    /// Data modified in the last rounds, oldest first, keyed by shard key.
+    pub data_hash_map_global: Vec<HashMap<H256, U256>>,
+    /// Data at the beginning of the current round, keyed by shard key.
+    pub data_hash_map_round_beginning: HashMap<H256, U256>,
+    /// Balance increments of the current round.
+    pub incr_bal_round: HashMap<Address, U256>,
+    /// Incomplete transactions waiting to be continued on this shard.
+    pub incomplete_txn: Vec<SignedTransaction>,
+    /// Vector leaves allocated by the block itself, in allocation order.
+    pub leaves: Vec<(H256, u64)>,
+}
+
+impl ShardRoundState {
+    /// Start a new round: forget the data of the previous round and make room
+    /// for the data modified in this one, dropping the oldest round if needed.
+    pub fn begin_round(&mut self) {
+        self.data_hash_map_round_beginning.clear();
+        self.incr_bal_round.clear();
+        if self.data_hash_map_global.len() == ROUND_HISTORY {
+            self.data_hash_map_global.remove(0);
+        }
+        self.data_hash_map_global.push(HashMap::new());
+    }
+}
+
+fn append_balances<K: Encodable + Ord>(s: &mut RlpStream, balances: &HashMap<K, U256>) {
+    // sort entries so the encoding does not depend on hash map iteration order
+    let mut entries: Vec<_> = balances.iter().collect();
+    entries.sort_by(|a, b| a.0.cmp(b.0));
+    s.begin_list(entries.len());
+    for (key, value) in entries {
+        s.begin_list(2).append(key).append(value);
+    }
+}
+
+fn decode_balances<K: Decodable + Eq + Hash>(rlp: &Rlp) -> Result<HashMap<K, U256>, DecoderError> {
+    rlp.iter()
+        .map(|entry| Ok((entry.val_at(0)?, entry.val_at(1)?)))
+        .collect()
+}
+
+impl Encodable for ShardRoundState {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(7);
+        s.append(&self.shard_state_root.0);
+        s.append(&self.shard_state_root.1);
+        s.begin_list(self.data_hash_map_global.len());
+        for balances in &self.data_hash_map_global {
+            append_balances(s, balances);
+        }
+        append_balances(s, &self.data_hash_map_round_beginning);
+        append_balances(s, &self.incr_bal_round);
+        s.begin_list(self.incomplete_txn.len());
+        for tx in &self.incomplete_txn {
+            s.append(&tx.encode());
+        }
+        s.begin_list(self.leaves.len());
+        for (key, index) in &self.leaves {
+            s.begin_list(2).append(key).append(index);
+        }
+    }
+}
+
+impl Decodable for ShardRoundState {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 7 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        let data_hash_map_global = rlp
+            .at(2)?
+            .iter()
+            .map(|balances| decode_balances(&balances))
+            .collect::<Result<_, _>>()?;
+        let incomplete_txn = rlp
+            .at(5)?
+            .iter()
+            .map(|item| {
+                let bytes: Vec<u8> = item.as_val()?;
+                SignedTransaction::new(TypedTransaction::decode(&bytes)?)
+                    .map_err(|_| DecoderError::Custom("Invalid incomplete transaction signature"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(ShardRoundState {
+            shard_state_root: (rlp.val_at(0)?, rlp.val_at(1)?),
+            data_hash_map_global,
+            data_hash_map_round_beginning: decode_balances(&rlp.at(3)?)?,
+            incr_bal_round: decode_balances(&rlp.at(4)?)?,
+            incomplete_txn,
+            leaves: rlp
+                .at(6)?
+                .iter()
+                .map(|leaf| Ok((leaf.val_at(0)?, leaf.val_at(1)?)))
+                .collect::<Result<_, DecoderError>>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use common_types::transaction::{Action, Transaction};
+    use crypto::publickey::Secret;
+    use keccak_hash::keccak;
+
+    fn secret() -> Secret {
+        keccak("").into()
+    }
+
+    #[test]
+    fn round_state_rlp_roundtrip() {
+        let tx = TypedTransaction::Legacy(Transaction {
+            nonce: 0.into(),
+            gas_price: 0.into(),
+            gas: 21_000.into(),
+            action: Action::Call(Address::from_low_u64_be(2)),
+            value: 10.into(),
+            data: vec![],
+        })
+        .sign(&secret(), None);
+
+        let mut data = HashMap::new();
+        data.insert(H256::from_low_u64_be(1), U256::from(5));
+        data.insert(H256::repeat_byte(0xab), U256::from(7));
+        let mut balances = HashMap::new();
+        balances.insert(Address::from_low_u64_be(1), U256::from(5));
+        balances.insert(Address::from_low_u64_be(3), U256::from(7));
+
+        let state = ShardRoundState {
+            shard_state_root: (H256::from_low_u64_be(9), 4),
+            data_hash_map_global: vec![data.clone(), HashMap::new()],
+            data_hash_map_round_beginning: data,
+            incr_bal_round: balances,
+            incomplete_txn: vec![tx],
+            leaves: vec![(H256::from_low_u64_be(1), 3)],
+        };
+
+        let encoded = rlp::encode(&state);
+        assert_eq!(rlp::decode::<ShardRoundState>(&encoded).unwrap(), state);
+    }
+
+    #[test]
+    fn begin_round_keeps_limited_history() {
+        let mut data = HashMap::new();
+        data.insert(H256::from_low_u64_be(1), U256::from(5));
+        let mut balances = HashMap::new();
+        balances.insert(Address::from_low_u64_be(1), U256::from(5));
+
+        let mut state = ShardRoundState {
+            data_hash_map_global: vec![data.clone(); ROUND_HISTORY],
+            data_hash_map_round_beginning: data,
+            incr_bal_round: balances,
+            ..Default::default()
+        };
+        state.begin_round();
+
+        assert_eq!(state.data_hash_map_global.len(), ROUND_HISTORY);
+        assert!(state.data_hash_map_global[ROUND_HISTORY - 1].is_empty());
+        assert!(!state.data_hash_map_global[0].is_empty());
+        assert!(state.data_hash_map_round_beginning.is_empty());
+        assert!(state.incr_bal_round.is_empty());
+    }
+}
@@ -50,13 +50,19 @@ use vm::{EnvInfo, LastHashes};
 
 use hash::keccak;
 use rlp::{encode_list, RlpStream};
-use hyperproofs::AggProof;
+#[cfg(feature = "shard")]
+use rlp::{decode, encode};
+use hyperproofs::{PendingLeaves, ShardKey};
+#[cfg(feature = "shard")]
+use hyperproofs::{AggProof, ShardContext};
 use stats::prometheus::register_int_counter;
 use types::{
     header::{ExtendedHeader, Header},
     receipt::{TransactionOutcome, TypedReceipt},
     transaction::{Error as TransactionError, SignedTransaction},
 };
+#[cfg(feature = "shard")]
+use types::receipt::LegacyReceipt;
 
 /// Block that is ready for transactions to be added.
 ///
@@ -181,9 +187,7 @@ impl<'x> OpenBlock<'x> {
     ) -> Result<Self, Error> {
         let number = parent.number() + 1;
 
-        // t_nb 8.1.1 get parent StateDB.
-        //this part is different
-        // #[cfg(feature = "shard")]
+        // t_nb 8.1.1 get parent StateDB, at the shard-local root of the parent when sharded.
         let state = State::from_existing(
             db,
             state_root.clone(),
@@ -327,6 +331,7 @@ impl<'x> OpenBlock<'x> {
     /// Push a transaction into the block.
     ///
     /// If valid, it will be executed, and archived together with the receipt.
+    #[cfg(not(feature = "shard"))]
     pub fn push_transaction(
         &mut self,
         t: SignedTransaction,
@@ -335,29 +340,53 @@ impl<'x> OpenBlock<'x> {
         if self.block.transactions_set.contains(&t.hash()) {
             return Err(TransactionError::AlreadyImported.into());
         }
-        // let mut wtr = csv::Writer::from_writer();
-        // #[cfg(feature = "shard")]
-        //here we will verify the proof if any
-        let data = t.shard_proof_data();
-        if !data.is_empty(){
-            println!("data looks like{:?}", data);
-            AggProof::resetAddressBalanceVerify(t.shard_id());
-            for datum in data{
-                println!("address looks like{} balance looks like {} shard looks like {}",datum.0.to_low_u64_be().rem_euclid(2u64.pow(16)),datum.1, t.shard_id());
-                AggProof::pushAddressBalanceVerify(datum.0.to_low_u64_be().rem_euclid(2u64.pow(16)),datum.1.to_string(),t.shard_id());
-            }
-            let proof_result = AggProof::verifyProof(t.shard_proof(),t.shard_id(),0u64);
-            println!("verification is {:?}", proof_result);
 
+        let env_info = self.block.env_info();
+        let outcome = self.block.state.apply(
+            &env_info,
+            self.engine.machine(),
+            &t,
+            self.block.traces.is_enabled(),
+        )?;
+
+        self.block
+            .transactions_set
+            .insert(h.unwrap_or_else(|| t.hash()));
+        self.block.transactions.push(t.into());
+        if let Tracing::Enabled(ref mut traces) = self.block.traces {
+            traces.push(outcome.trace.into());
         }
+        self.block.receipts.push(outcome.receipt);
+        Ok(self
+            .block
+            .receipts
+            .last()
+            .expect("receipt just pushed; qed"))
+    }
+
+    /// Push a transaction into the block.
+    ///
+    /// If valid, it will be executed on this shard, and archived together with the receipt
+    /// and the data list the next hop continues with.
+    #[cfg(feature = "shard")]
+    pub fn push_transaction(
+        &mut self,
+        t: SignedTransaction,
+        h: Option<H256>,
+    ) -> Result<&TypedReceipt, Error> {
+        if self.block.transactions_set.contains(&t.hash()) {
+            return Err(TransactionError::AlreadyImported.into());
+        }
+        // let mut wtr = csv::Writer::from_writer();
+        //here we will verify the proof if any
+        verify_shard_proof(self.block.state.shard_context(), &t)?;
         let env_info = self.block.env_info();
-        // #[cfg(feature = "shard")]
         let sender = t.original_sender();
         // debug!(target: "miner", "transaction looks like {:?}", t);
         let mut t= if !t.contains_balance(){
-            AggProof::incr_bal_read_count(1u64);
+            self.state.shard_context().counters().incr_bal_read(1u64);
             let mut balance = self.state.balance(&sender)?;
-            let mut begin_round_balance = self.state.hash_map_beginning_storage_at(&sender);
+            let mut begin_round_balance = self.state.hash_map_beginning_storage_at(&AggProof::balance_key(&sender));
             if begin_round_balance.1 {
                 balance = begin_round_balance.0;
             }
@@ -365,11 +394,10 @@ impl<'x> OpenBlock<'x> {
         } else{
             t
         };
-        // #[cfg(feature = "shard")]
         //clear address txn vec and add original sender's address only if transaction is complete.
         self.block.state.clear_address_txn_vec();
         if !t.is_incomplete() && t.is_shard(){
-            self.block.state.push_address_txn_vec(sender);
+            self.block.state.push_address_txn_vec(AggProof::balance_key(&sender));
         }
         // we turn revert flag down before.
         self.block.state.reverted(false);
@@ -383,7 +411,42 @@ impl<'x> OpenBlock<'x> {
         let mut outcome = self.block.state.default_apply_result().unwrap();
         self.block.state.clear_temp_sstore_val();
         self.block.state.clear_temp_sstore_delta();
+        // a continuation hop resumes the frame its previous hop stopped in. Only the engine
+        // continues a call, the signer of the hop was checked to seal for this shard.
+        let suspended_frame = if t.suspended_frame().is_empty() {
+            None
+        } else if t.get_hop_count() == 0 || !t.is_engine_shard_transaction() {
+            return Err(TransactionError::InvalidSuspendedFrame.into());
+        } else {
+            Some(decode(t.suspended_frame()).map_err(|_| TransactionError::InvalidSuspendedFrame)?)
+        };
+        self.block.state.set_suspended_frame(suspended_frame);
+        // a call bouncing between shards forever must not stay incomplete forever
+        let hop_limit_reached =
+            t.is_shard() && t.get_hop_count() >= self.block.state.shard_context().max_hops();
+        // it is finalized on the original sender's shard, which holds the balance to refund
+        let home_shard = self.block.state.shard_context().address_shard(&sender);
         match self.block.state.get_mined_status(){
+            Some(true) if hop_limit_reached && t.shard_id() != home_shard => {
+                debug!(target: "txn", "txn reached the hop limit after {} hops, handing it back to shard {}", t.get_hop_count(), home_shard);
+                self.block.state.inc_nonce(&t.nonce_sender())?;
+                t.set_next_shard(home_shard);
+                t.set_gas_used(t.shard_gas_used(), U256::zero());
+                self.block
+                    .state
+                    .settle_incomplete_hop(&t, &env_info.author, env_info.base_fee)?;
+                outcome.receipt = incomplete_hop_receipt(&t, env_info.gas_used);
+            }
+            _ if hop_limit_reached && t.shard_id() == home_shard => {
+                debug!(target: "txn", "txn reached the hop limit after {} hops, finalizing it as reverted", t.get_hop_count());
+                self.block.state.inc_nonce(&t.nonce_sender())?;
+                t.set_incomplete(0u64);
+                t.set_next_shard(999u64);
+                outcome = self
+                    .block
+                    .state
+                    .apply_hop_limit(&env_info, self.engine.machine(), &t)?;
+            }
             Some(true) => {
                 if !t.tx().data.is_empty(){
                     if !t.is_incomplete(){
@@ -431,6 +494,21 @@ impl<'x> OpenBlock<'x> {
                                 t.hash_map_replace_with(self.block.state.data_hashmap_txn());
                                 t.set_next_shard(self.block.state.get_next_shard());
                                 t.set_incomplete(1u64);
+                                let hop_gas_used = outcome
+                                    .receipt
+                                    .receipt()
+                                    .gas_used
+                                    .saturating_sub(env_info.gas_used);
+                                t.set_gas_used(t.shard_gas_used(), hop_gas_used);
+                                self.block.state.hand_off_shard_budget(&t, env_info.base_fee)?;
+                                outcome.receipt = incomplete_hop_receipt(&t, env_info.gas_used);
+                                t.set_suspended_frame(
+                                    self.block
+                                        .state
+                                        .take_suspended_frame()
+                                        .map(|frame| encode(&frame))
+                                        .unwrap_or_default(),
+                                );
                             }
 
                         }
@@ -467,6 +545,21 @@ impl<'x> OpenBlock<'x> {
                             t.hash_map_replace_with(self.block.state.data_hashmap_txn());
                             t.set_next_shard(self.block.state.get_next_shard());
                             t.set_incomplete(1u64);
+                            let hop_gas_used = outcome
+                                .receipt
+                                .receipt()
+                                .gas_used
+                                .saturating_sub(env_info.gas_used);
+                            t.set_gas_used(t.shard_gas_used(), hop_gas_used);
+                            self.block.state.hand_off_shard_budget(&t, env_info.base_fee)?;
+                            outcome.receipt = incomplete_hop_receipt(&t, env_info.gas_used);
+                            t.set_suspended_frame(
+                                self.block
+                                    .state
+                                    .take_suspended_frame()
+                                    .map(|frame| encode(&frame))
+                                    .unwrap_or_default(),
+                            );
                         }
                     }
                 } else{ //mined, CALL transfer
@@ -488,8 +581,13 @@ impl<'x> OpenBlock<'x> {
             _ => {if t.is_incomplete(){//enact, incomplete
                 //do nothing in terms of state.apply
             debug!(target: "txn", "incomplete txn in enact, do nothing");
-                self.block.state.inc_nonce(&t.sender())?;
-                if t.get_next_shard() == AggProof::get_shard(){
+                self.block.state.inc_nonce(&t.nonce_sender())?;
+                // but its gas is paid for and its budget handed over as by the sealer
+                self.block
+                    .state
+                    .settle_incomplete_hop(&t, &env_info.author, env_info.base_fee)?;
+                outcome.receipt = incomplete_hop_receipt(&t, env_info.gas_used);
+                if t.get_next_shard() == self.block.state.shard_context().shard(){
                     self.block.state.push_incomplete_txn(t.clone());
                 }
             } else{ //enact, complete
@@ -580,10 +678,17 @@ impl<'x> OpenBlock<'x> {
         //         }
         //     }
         // }
+        // what the sealer added to the hop is signed by it, every shard verifies the signature
+        if t.is_shard() && self.block.state.get_mined_status() == Some(true) {
+            t = self.sign_shard_transaction(t)?;
+        }
+        // a frame the hop did not resume must not leak into the next transaction
+        self.block.state.set_suspended_frame(None);
         if !t.is_incomplete(){
-            AggProof::incr_hop_count(t.get_hop_count()+1);
+            let counters = self.block.state.shard_context().counters();
+            counters.incr_hop(t.get_hop_count()+1);
             if self.block.state.is_reverted(){
-                AggProof::incr_reverted_count();
+                counters.incr_reverted();
             }
         }
         self.block
@@ -602,6 +707,20 @@ impl<'x> OpenBlock<'x> {
             .expect("receipt just pushed; qed"))
     }
 
+    /// Sign the shard transaction `t` as sealed in this block. The first hop of a call keeps
+    /// the hash its sender signed as the original hash of the call.
+    #[cfg(feature = "shard")]
+    fn sign_shard_transaction(&self, mut t: SignedTransaction) -> Result<SignedTransaction, Error> {
+        if t.original_transaction_hash().is_none() {
+            let hash = t.hash();
+            t.set_original_hash(hash);
+        }
+        let chain_id = self.engine.signing_chain_id(&self.block.env_info());
+        let (transaction, _, _) = t.deconstruct();
+        let signature = self.engine.sign(transaction.unsigned.signature_hash(chain_id))?;
+        Ok(SignedTransaction::new(transaction.unsigned.with_signature(signature, chain_id))?)
+    }
+
     /// Push transactions onto the block.
     #[cfg(not(feature = "slow-blocks"))]
     fn push_transactions(&mut self, transactions: Vec<SignedTransaction>) -> Result<(), Error> {
@@ -686,9 +805,6 @@ impl<'x> OpenBlock<'x> {
         let uncle_bytes = encode_list(&s.block.uncles);
         s.block.header.set_uncles_hash(keccak(&uncle_bytes));
         s.block.header.set_state_root(s.block.state.root().clone());
-        // #[cfg(feature = "shard")]
-        // we set state root to default value for all nodes
-        // s.block.header.set_state_root(H256::default());
         debug!(target: "block", "Adding block state root {:?}",s.block.state.root().clone());
         s.block.header.set_receipts_root(ordered_trie_root(
             s.block.receipts.iter().map(|r| r.encode()),
@@ -708,11 +824,11 @@ impl<'x> OpenBlock<'x> {
 
         Ok(LockedBlock { block: s.block })
     }
-    pub fn set_hash_map_global(&mut self, h: Vec<HashMap<Address,U256>>){
+    pub fn set_hash_map_global(&mut self, h: Vec<HashMap<ShardKey,U256>>){
         self.block.state.set_hash_map_global(h);
     }
 
-    pub fn set_hash_map_round_beginning(&mut self, h: HashMap<Address,U256>){
+    pub fn set_hash_map_round_beginning(&mut self, h: HashMap<ShardKey,U256>){
         self.block.state.set_hash_map_round_beginning(h);
     }
     pub fn set_incr_bal_round(&mut self, h: HashMap<Address,U256>){
@@ -849,6 +965,44 @@ impl Drain for SealedBlock {
     }
 }
 
+/// Check the aggregated balance proof carried by a shard transaction, if any.
+#[cfg(feature = "shard")]
+pub(crate) fn verify_shard_proof(
+    shard: &ShardContext,
+    t: &SignedTransaction,
+) -> Result<(), TransactionError> {
+    let data = t.shard_proof_data();
+    if data.is_empty() {
+        return Ok(());
+    }
+    trace!(target: "txn", "verifying balances {:?} in shard {}", data, t.shard_id());
+    match shard.verify_balances(&t.shard_proof(), t.shard_id(), &data) {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            warn!(target: "txn", "Shard proof of transaction {:?} from shard {} does not verify", t.hash(), t.shard_id());
+            Err(TransactionError::InvalidShardProof)
+        }
+        Err(e) => Err(TransactionError::ShardProofUnverifiable(format!("{}", e))),
+    }
+}
+
+/// Receipt of a hop which left its call incomplete.
+///
+/// The outcome and logs of the call are only known to the hop completing it, so the receipt
+/// only accounts the gas of the hop. Every shard derives the same receipt from the transaction,
+/// whether it executed the hop or not.
+#[cfg(feature = "shard")]
+fn incomplete_hop_receipt(t: &SignedTransaction, gas_used: U256) -> TypedReceipt {
+    TypedReceipt::new(
+        t.tx_type(),
+        LegacyReceipt::new(
+            TransactionOutcome::Unknown,
+            gas_used + t.hop_gas_used(),
+            Vec::new(),
+        ),
+    )
+}
+
 // t_nb 8.0 Enact the block given by block header, transactions and uncles
 pub(crate) fn enact(
     header: Header,
@@ -860,9 +1014,10 @@ pub(crate) fn enact(
     parent: &Header,
     last_hashes: Arc<LastHashes>,
     factories: Factories,
-    hash_map_global: Vec<HashMap<Address, U256>>,
-    hash_map_round_beginning: HashMap<Address,U256>,
+    hash_map_global: Vec<HashMap<ShardKey, U256>>,
+    hash_map_round_beginning: HashMap<ShardKey,U256>,
     incr_bal_round: HashMap<Address,U256>,
+    leaves: PendingLeaves,
     state_root: H256,
     is_epoch_begin: bool,
     ancestry: &mut dyn Iterator<Item = ExtendedHeader>,
@@ -905,23 +1060,26 @@ pub(crate) fn enact(
         trace!(target: "enact", "num={}, root={}, author={}, author_balance={}\n",
 				b.block.header.number(), s.root(), b.header.author(), author_balance);
     }
-    // #[cfg(feature = "shard")]
-    let block_number = b.block.header.number().clone();
-    if block_number.rem_euclid(AggProof::shard_count()) ==0{
-        trace!(target:"enact", "block number is {}", block_number);
-        if block_number != AggProof::get_last_commit_round(){
-            AggProof::commit(AggProof::get_shard(),0u64);
-            AggProof::set_last_commit_shard(block_number);
+    #[cfg(feature = "shard")]
+    {
+        let block_number = b.block.header.number().clone();
+        let shard = b.block.state.shard_context();
+        if block_number.rem_euclid(shard.round_length()) ==0{
+            trace!(target:"enact", "block number is {}", block_number);
+            if block_number != shard.last_commit_round(){
+                shard.commit_round(block_number)?;
+            }
         }
-    }
 
-    //set mined status to false in the state
-    // #[cfg(feature = "shard")]
-    b.block.state.set_mined_status(Some(false));
+        //set mined status to false in the state
+        b.block.state.set_mined_status(Some(false));
+    }
     // set the hash_maps (global and beginning round)
     b.block.state.set_hash_map_global(hash_map_global);
     b.block.state.set_hash_map_round_beginning(hash_map_round_beginning);
     b.block.state.set_incr_bal_round(incr_bal_round);
+    // vector leaves seen by the branch the block is on
+    b.block.state.set_pending_leaves(leaves);
     // t_nb 8.2 transfer all field from current header to OpenBlock header that we created
     b.populate_from(&header);
     // t_nb 8.3 execute transactions one by one
@@ -945,10 +1103,11 @@ pub fn enact_verified(
     parent: &Header,
     last_hashes: Arc<LastHashes>,
     factories: Factories,
-    // #[cfg(feature = "shard")]
-    hash_map_global: Vec<HashMap<Address,U256>>,
-    hash_map_round_beginning: HashMap<Address, U256>,
+    // round state of the parent, only carried on by the shard execution
+    hash_map_global: Vec<HashMap<ShardKey,U256>>,
+    hash_map_round_beginning: HashMap<ShardKey, U256>,
     incr_bal_round: HashMap<Address,U256>,
+    leaves: PendingLeaves,
     state_root : H256,
     is_epoch_begin: bool,
     ancestry: &mut dyn Iterator<Item = ExtendedHeader>,
@@ -966,6 +1125,7 @@ pub fn enact_verified(
         hash_map_global,
         hash_map_round_beginning,
         incr_bal_round,
+        leaves,
         state_root,
         is_epoch_begin,
         ancestry,
@@ -982,6 +1142,8 @@ mod tests {
     use state_db::StateDB;
     use std::sync::Arc;
     use test_helpers::get_temp_state_db;
+    #[cfg(feature = "shard")]
+    use types::transaction::{Action, ShardTransactionTx, Transaction, TypedTransaction};
     use types::{header::Header, transaction::SignedTransaction, view, views::BlockView};
     use verification::queue::kind::blocks::Unverified;
     use vm::LastHashes;
@@ -1227,4 +1389,170 @@ mod tests {
                 == None
         );
     }
+
+    #[cfg(feature = "shard")]
+    fn shard_proof(context: &ShardContext, shard: u64, balances: &[(Address, U256)]) -> String {
+        let backend = context.backend();
+        backend.init(0u64).unwrap();
+        for (address, balance) in balances {
+            backend
+                .push_address_delta(
+                    context.leaves().index(&AggProof::balance_key(address)).unwrap(),
+                    &balance.to_string(),
+                    shard,
+                )
+                .unwrap();
+        }
+        backend.commit(shard, 0u64).unwrap();
+        for (address, _) in balances {
+            backend
+                .push_address_commit(
+                    context.leaves().index(&AggProof::balance_key(address)).unwrap(),
+                    shard,
+                )
+                .unwrap();
+        }
+        backend.update_tree(shard).unwrap();
+        backend.agg(shard).unwrap().0
+    }
+
+    #[cfg(feature = "shard")]
+    fn proof_carrying_transaction(
+        shard: u64,
+        balances: &[(Address, U256)],
+        proof: String,
+    ) -> SignedTransaction {
+        TypedTransaction::ShardTransaction(ShardTransactionTx::new(
+            Transaction {
+                action: Action::Call(Address::zero()),
+                ..Default::default()
+            },
+            shard,
+            999u64,
+            0u64,
+            0u64,
+            Address::zero(),
+            HashMap::new(),
+            Vec::new(),
+            String::new(),
+        ))
+        .fake_sign(Address::zero())
+        .with_proof(
+            balances
+                .iter()
+                .map(|(address, balance)| (AggProof::balance_key(address), *balance))
+                .collect(),
+            proof,
+        )
+    }
+
+    #[test]
+    #[cfg(feature = "shard")]
+    fn accepts_valid_shard_proof() {
+        let balances = vec![
+            (Address::from_low_u64_be(0x10), U256::from(1000)),
+            (Address::from_low_u64_be(0x20), U256::from(2000)),
+        ];
+        let context = ShardContext::default();
+        let proof = shard_proof(&context, 0, &balances);
+        let t = proof_carrying_transaction(0, &balances, proof);
+
+        assert_eq!(verify_shard_proof(&context, &t), Ok(()));
+    }
+
+    #[test]
+    #[cfg(feature = "shard")]
+    fn rejects_shard_proof_with_tampered_balance() {
+        let balances = vec![
+            (Address::from_low_u64_be(0x11), U256::from(1000)),
+            (Address::from_low_u64_be(0x21), U256::from(2000)),
+        ];
+        let context = ShardContext::default();
+        let proof = shard_proof(&context, 1, &balances);
+        let mut tampered = balances.clone();
+        tampered[1].1 = U256::from(2_000_000);
+        let t = proof_carrying_transaction(1, &tampered, proof);
+
+        assert_eq!(
+            verify_shard_proof(&context, &t),
+            Err(TransactionError::InvalidShardProof)
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "shard")]
+    fn rejects_tampered_shard_proof() {
+        let balances = vec![
+            (Address::from_low_u64_be(0x12), U256::from(1000)),
+            (Address::from_low_u64_be(0x22), U256::from(2000)),
+        ];
+        let context = ShardContext::default();
+        let mut proof = shard_proof(&context, 2, &balances);
+        let last = if proof.pop() == Some('0') { '1' } else { '0' };
+        proof.push(last);
+        let t = proof_carrying_transaction(2, &balances, proof);
+
+        assert!(verify_shard_proof(&context, &t).is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "shard")]
+    fn transaction_without_shard_proof_is_not_verified() {
+        let context = ShardContext::default();
+        let t = proof_carrying_transaction(0, &[], String::new());
+
+        assert_eq!(verify_shard_proof(&context, &t), Ok(()));
+    }
+
+    #[test]
+    #[cfg(feature = "shard")]
+    fn enact_fails_if_round_cannot_be_committed() {
+        use error::ErrorKind;
+        use hyperproofs::ShardConfig;
+        use spec::*;
+
+        let spec = Spec::new_test();
+        let genesis_header = spec.genesis_header();
+        let db = spec
+            .ensure_db_good(get_temp_state_db(), &Default::default())
+            .unwrap();
+        let mut factories = Factories::default();
+        factories.shard = Arc::new(ShardContext::new(ShardConfig {
+            round_length: 1,
+            ..Default::default()
+        }));
+        // a debit without a balance to cover it
+        let backend = factories.shard.backend();
+        backend.init(0).unwrap();
+        backend.push_address_delta(0, "-1", 0).unwrap();
+
+        let mut header = Header::default();
+        header.set_parent_hash(genesis_header.hash());
+        header.set_number(1);
+        let result = enact(
+            header,
+            Vec::new(),
+            Vec::new(),
+            &*spec.engine,
+            false,
+            db,
+            &genesis_header,
+            Arc::new(vec![genesis_header.hash()]),
+            factories.clone(),
+            Vec::new(),
+            HashMap::new(),
+            HashMap::new(),
+            PendingLeaves::default(),
+            *genesis_header.state_root(),
+            false,
+            &mut Vec::new().into_iter(),
+        );
+
+        match result {
+            Err(Error(ErrorKind::Shard(_), _)) => (),
+            Err(e) => panic!("unexpected error {:?}", e),
+            Ok(_) => panic!("block enacted without committing the round"),
+        }
+        assert_ne!(factories.shard.last_commit_round(), 1);
+    }
 }
@@ -30,7 +30,7 @@ use std::collections::HashMap;
 
 use blockchain::{
     BlockChain, BlockChainDB, BlockNumberKey, BlockProvider, BlockReceipts, ExtrasInsert,
-    ImportRoute, TransactionAddress, TreeRoute,
+    ImportRoute, ShardRoundState, TransactionAddress, TreeRoute,
 };
 use bytes::{Bytes, ToPretty};
 use call_contract::CallContract;
@@ -51,7 +51,9 @@ use types::{
     filter::Filter,
     header::{ExtendedHeader, Header},
     log_entry::LocalizedLogEntry,
-    receipt::{LocalizedReceipt, TypedReceipt},
+    receipt::{
+        LocalizedReceipt, ShardReceipt, ShardTransactionStatus, TransactionOutcome, TypedReceipt,
+    },
     transaction::{
         self, Action, LocalizedTransaction, SignedTransaction, TypedTransaction,
         UnverifiedTransaction,
@@ -87,6 +89,7 @@ use executive::{contract_address, Executed, Executive, TransactOptions};
 use factory::{Factories, VmFactory};
 use io::IoChannel;
 use miner::{Miner, MinerService};
+use shard_directory::ShardDirectory;
 use snapshot::{self, io as snapshot_io, SnapshotClient};
 use spec::Spec;
 use state::{self, State};
@@ -108,7 +111,7 @@ use db::{keys::BlockDetails, Readable, Writable};
 pub use reth_util::queue::ExecutionQueue;
 pub use types::{block_status::BlockStatus, blockchain_info::BlockChainInfo};
 pub use verification::QueueInfo as BlockQueueInfo;
-use hyperproofs::AggProof;
+use hyperproofs::{PendingLeaves, ProofOutcome, ShardContext, ShardKey, HOP_BUCKETS};
 use_contract!(registry, "res/contracts/registrar.json");
 
 const ANCIENT_BLOCKS_QUEUE_SIZE: usize = 4096;
@@ -338,7 +341,7 @@ impl Importer {
                 let mut already_imported = false;
                 // t_nb 7.0 check and lock block
                 match self.check_and_lock_block(&bytes, block, client, &mut already_imported) {
-                    Ok((closed_block, pending)) => {
+                    Ok((closed_block, pending, round_state)) => {
                         //replace the header form the enacted block
                         // shard
                         // let header = closed_block.header.clone();
@@ -351,6 +354,7 @@ impl Importer {
                             &header,
                             encoded::Block::new(bytes),
                             pending,
+                            round_state,
                             client,
                         );
                         trace!(target:"block_import","Block #{}({}) commited",header.number(),header.hash());
@@ -378,7 +382,6 @@ impl Importer {
                             );
                             //insert random hash
                             invalid_blocks.insert(H256::random());
-                            println!("block imported again..")
                         }
 
                     }
@@ -450,7 +453,7 @@ impl Importer {
         block: PreverifiedBlock,
         client: &Client,
         already_imported: &mut bool,
-    ) -> EthcoreResult<(LockedBlock, Option<PendingTransition>)> {
+    ) -> EthcoreResult<(LockedBlock, Option<PendingTransition>, ShardRoundState)> {
         let engine = &*self.engine;
         let header = block.header.clone();
 
@@ -462,7 +465,7 @@ impl Importer {
             warn!(target: "client", "Block import failed for #{} ({})\nBlock is already imported (current best block: #{}).", header.number(), header.hash(), best_block_number);
             bail!("Block already imported");
         }else {
-            AggProof::set_latest_imported_block(header.number());
+            client.factories.shard.set_latest_imported_block(header.number());
         }
         if client.pruning_info().earliest_state > header.number() {
             warn!(target: "client", "Block import failed for #{} ({})\nBlock is ancient (current best block: #{}).", header.number(), header.hash(), best_block_number);
@@ -566,28 +569,56 @@ impl Importer {
                 };
             }
         }
-        // #[cfg(feature = "shard")]
-        // resize hash_map_global and clear hash_map_round_beginning
-        let block_number = block.header.number().clone();
-        if block_number.rem_euclid(AggProof::shard_count()) ==0{
-            let chain_wr = client.chain.write();
-            chain_wr.data_hash_map_round_beginning.write().clear();
-            chain_wr.incr_bal_round.write().clear();
-            let mut _h = chain_wr.data_hash_map_global.write();
-            if _h.len() == 5{
-                _h.remove(0);
-                _h.push(HashMap::new());
-            }else {
-                _h.push(HashMap::new());
+        // round state the block starts from, taken from its parent so forks stay apart
+        #[cfg(feature = "shard")]
+        let parent_round_state = client.chain.read().child_shard_round_state(
+            header.parent_hash(),
+            header.number(),
+            client.factories.shard.round_length(),
+        );
+        #[cfg(not(feature = "shard"))]
+        let parent_round_state = ShardRoundState::default();
+        // vector leaves as seen by the parent: a block on a side branch does not see the
+        // canonical allocations made since the fork, but those of its own ancestors
+        #[cfg(not(feature = "shard"))]
+        let leaves = PendingLeaves::default();
+        #[cfg(feature = "shard")]
+        let leaves = {
+            let chain = client.chain.read();
+            let best_hash = chain.best_block_hash();
+            if best_hash == *header.parent_hash() {
+                PendingLeaves::default()
+            } else {
+                match chain.tree_route(best_hash, *header.parent_hash()) {
+                    Some(route) => {
+                        let (retracted, enacted) = route.blocks.split_at(route.index);
+                        PendingLeaves::on_branch(
+                            retracted.iter().flat_map(|h| chain.block_shard_leaves(h)),
+                            enacted.iter().flat_map(|h| chain.block_shard_leaves(h)),
+                        )
+                    }
+                    None => {
+                        warn!(target: "client", "Block import failed for #{} ({}): No route from best block to parent", header.number(), header.hash());
+                        bail!("No route from best block to parent");
+                    }
+                }
             }
-
-        }
+        };
         // t_nb 8.0 Block enacting. Execution of transactions.
 
         debug!(target: "txn", "^^^^^^^^^^^^entering trace_state 0^^^^^^^^^^");
+        let parent_shard_root = parent_round_state.shard_state_root;
+        // the block is executed on the shard-local state of its parent
+        #[cfg(feature = "shard")]
+        let state_root = if parent.number() == parent_shard_root.1 {
+            parent_shard_root.0
+        } else {
+            *parent.state_root()
+        };
+        #[cfg(not(feature = "shard"))]
+        let state_root = *parent.state_root();
         let enact_result = {
             let chain = client.chain.read();
-            let sr = chain.shard_state_root.read().clone();
            let x =  enact_verified(
                 block,
                 engine,
@@ -596,10 +627,11 @@ impl Importer {
                 &parent,
                 last_hashes,
                 client.factories.clone(),
-                chain.data_hash_map_global.read().clone(),
-                chain.data_hash_map_round_beginning.read().clone(),
-                chain.incr_bal_round.read().clone(),
-                if parent.number() == sr.1{ sr.0} else { parent.state_root().clone() },
+                parent_round_state.data_hash_map_global.clone(),
+                parent_round_state.data_hash_map_round_beginning.clone(),
+                parent_round_state.incr_bal_round.clone(),
+                leaves,
+                state_root,
                 is_epoch_begin,
                 &mut chain.ancestry_with_metadata_iter(*header.parent_hash()),
             );
@@ -613,33 +645,16 @@ impl Importer {
                 bail!(e);
             }
         };
-        // #[cfg(feature = "shard")]
-        //change hashmap of the state
-        {
-            let mut chain_wr = client.chain.write();
-            let mut h_global = chain_wr.data_hash_map_global.write();
-            let mut h_round_beginning = chain_wr.data_hash_map_round_beginning.write();
-            let mut i_bal_round = chain_wr.incr_bal_round.write();
-            let mut incomplete_txn = chain_wr.incomplete_txn.write();
-            let hashmap_global = locked_block.state.export_data_hashmap_global();
-            let hashmap_round_beginning = locked_block.state.export_data_hashmap_round_beginning();
-            let incr_bal_round = locked_block.state.export_incr_bal_round();
-            let i_txn = locked_block.state.export_incomplete_txn();
-            {
-                *h_global = hashmap_global;
-            }
-            {
-                *h_round_beginning = hashmap_round_beginning;
-            }
-            {
-                *i_bal_round = incr_bal_round;
-            }
-            {   // we push transactions permanently to blockchain
-                for i_t in i_txn {
-                    incomplete_txn.push(i_t);
-                }
-            }
-        }
+        // round state reached by this block; applied to the chain only once it is canon
+        #[cfg(feature = "shard")]
+        let round_state = child_shard_round_state(
+            parent_round_state,
+            &locked_block.header,
+            &locked_block.transactions,
+            &locked_block.state,
+        );
+        #[cfg(not(feature = "shard"))]
+        let round_state = parent_round_state;
         // t_nb 7.6 Strip receipts for blocks before validate_receipts_transition,
         // if the expected receipts root header does not match.
         // (i.e. allow inconsistency in receipts outcome before the transition block)
@@ -650,10 +665,18 @@ impl Importer {
         }
 
         // t_nb 7.7 Final Verification. See if block that we created (executed) matches exactly with block that we received.
-        if let Err(e) = self
+        #[cfg(not(feature = "shard"))]
+        let verified = self
             .verifier
-            .verify_block_final(&header, &locked_block.header)
-        {
+            .verify_block_final(&header, &locked_block.header);
+        #[cfg(feature = "shard")]
+        let verified = self.verifier.verify_block_final(
+            &header,
+            &locked_block.header,
+            &client.factories.shard,
+            &parent_shard_root,
+        );
+        if let Err(e) = verified {
             warn!(target: "client", "Stage 5 block verification failed for #{} ({})\nError: {:?}", header.number(), header.hash(), e);
             bail!(e);
         }
@@ -668,7 +691,7 @@ impl Importer {
             client,
         )?;
 
-        Ok((locked_block, pending))
+        Ok((locked_block, pending, round_state))
     }
 
     /// Import a block with transaction receipts.
@@ -719,6 +742,10 @@ impl Importer {
     // it is for reconstructing the state transition.
     //
     // The header passed is from the original block data and is sealed.
+    //
+    // The shard round state reached by the block is persisted with it, including
+    // the vector leaves the block allocated. Leaves of retracted blocks are released
+    // and those of enacted blocks become canonical in the same batch.
     // TODO: should return an error if ImportRoute is none, issue #9910
     fn commit_block<B>(
         &self,
@@ -726,6 +753,7 @@ impl Importer {
         header: &Header,
         block_data: encoded::Block,
         pending: Option<PendingTransition>,
+        round_state: ShardRoundState,
         client: &Client,
     ) -> ImportRoute
     where
@@ -824,6 +852,10 @@ impl Importer {
             })
             .collect();
 
+        // persist the shard round state reached by this block together with the block
+        chain.insert_shard_round_state(&mut batch, hash, &round_state);
+        chain.insert_shard_continuations(&mut batch, &block_data);
+
         // t_nb 9.8 insert block
         let route = chain.insert_block(
             &mut batch,
@@ -835,6 +867,26 @@ impl Importer {
             },
         );
 
+        // move the canonical vector leaf allocations along with the route
+        let released: Vec<_> = route
+            .retracted
+            .iter()
+            .flat_map(|h| chain.block_shard_leaves(h))
+            .collect();
+        let allocated: Vec<_> = route
+            .enacted
+            .iter()
+            .flat_map(|h| {
+                if h == hash {
+                    round_state.leaves.clone()
+                } else {
+                    chain.block_shard_leaves(h)
+                }
+            })
+            .collect();
+        chain.remove_shard_leaves(&mut batch, &released);
+        chain.insert_shard_leaves(&mut batch, &allocated);
+
         // t_nb 9.9 insert traces (if they are enabled)
         client.tracedb.read().import(
             &mut batch,
@@ -858,7 +910,9 @@ impl Importer {
 
         // t_nb 9.12 commit changed to become current greatest by applying pending insertion updates (Sync point)
         chain.commit();
-         println!("block looklike {:?}",block.header);
+        let leaves = client.factories.shard.leaves();
+        leaves.release(released);
+        leaves.load(allocated);
         // t_nb 9.13 check epoch end. Related only to AuRa and it seems light engine
         self.check_epoch_end(&header, &finalized, &chain, client);
 
@@ -871,7 +925,10 @@ impl Importer {
         }
         // change the header in change
         //shard
-        chain.change_shard_state_root(block.header.state_root().clone(), block.header.number());
+        // the new best block's round state replaces the retracted branch's one
+        if is_canon {
+            chain.set_shard_round_state(round_state);
+        }
         route
     }
 
@@ -1034,6 +1091,7 @@ impl Client {
             vm: VmFactory::new(config.vm_type.clone(), config.jump_table_size),
             trie: trie_factory,
             accountdb: Default::default(),
+            shard: miner.shard_context(),
         };
 
         let journal_db = journaldb::new(db.key_value().clone(), config.pruning, ::db::COL_STATE);
@@ -1053,6 +1111,8 @@ impl Client {
             db.clone(),
             spec.params().eip1559_transition,
         ));
+        // leaves allocated before restart keep their index
+        factories.shard.leaves().load(chain.shard_leaves());
         let tracedb = RwLock::new(TraceDB::new(
             config.tracing.clone(),
             db.clone(),
@@ -1131,6 +1191,12 @@ impl Client {
             config,
         });
 
+        if let Some(directory) = ShardDirectory::from_params(spec.params()) {
+            let directory = Arc::new(directory);
+            directory.register_client(Arc::downgrade(&client) as _);
+            client.factories.shard.set_placement(directory);
+        }
+
         let exec_client = client.clone();
 
         let queued = client.queued_ancient_blocks.clone();
@@ -1649,7 +1715,16 @@ impl Client {
 
     fn transaction_address(&self, id: TransactionId) -> Option<TransactionAddress> {
         match id {
-            TransactionId::Hash(ref hash) => self.chain.read().transaction_address(hash),
+            TransactionId::Hash(ref hash) => {
+                let chain = self.chain.read();
+                // the sealer signs the hops of a shard transaction, which are found by the
+                // hash its sender signed
+                chain.transaction_address(hash).or_else(|| {
+                    chain
+                        .shard_continuation(hash)
+                        .and_then(|latest| chain.transaction_address(&latest))
+                })
+            }
             TransactionId::Location(id, index) => {
                 Self::block_hash(&self.chain.read(), id).map(|hash| TransactionAddress {
                     block_hash: hash,
@@ -2017,7 +2092,8 @@ impl ImportBlock for Client {
             bail!(EthcoreErrorKind::Import(ImportErrorKind::AlreadyInChain));
         }
 
-        if (unverified.header.number() <= AggProof::get_latest_imported_block()) && unverified.header.number()>4{
+        let shard = &self.factories.shard;
+        if (unverified.header.number() <= shard.latest_imported_block()) && unverified.header.number()>shard.round_length(){
             debug!(target: "miner", "Bailed already block import ");
             bail!(EthcoreErrorKind::Import(ImportErrorKind::AlreadyInChain));
         }
@@ -2391,6 +2467,10 @@ impl BlockChainClient for Client {
             .is_processing_fork(&chain.best_block_hash(), &chain)
     }
 
+    fn shard_context(&self) -> Arc<ShardContext> {
+        self.factories.shard.clone()
+    }
+
     fn block_total_difficulty(&self, id: BlockId) -> Option<U256> {
         let chain = self.chain.read();
 
@@ -2577,7 +2657,8 @@ impl BlockChainClient for Client {
             None
         };
 
-        let receipt = transaction_receipt(
+        let shard = shard_receipt(&chain, &self.factories.shard, &transaction);
+        let mut receipt = transaction_receipt(
             self.engine().machine(),
             transaction,
             receipt,
@@ -2585,6 +2666,7 @@ impl BlockChainClient for Client {
             no_of_logs,
             base_fee,
         );
+        receipt.shard = shard;
         Some(receipt)
     }
 
@@ -2611,8 +2693,9 @@ impl BlockChainClient for Client {
                 .localized_transactions(&hash, number)
                 .into_iter()
                 .zip(receipts.receipts)
-                .map(move |(transaction, receipt)| {
-                    let result = transaction_receipt(
+                .map(|(transaction, receipt)| {
+                    let shard = shard_receipt(&chain, &self.factories.shard, &transaction);
+                    let mut result = transaction_receipt(
                         engine.machine(),
                         transaction,
                         receipt,
@@ -2620,6 +2703,7 @@ impl BlockChainClient for Client {
                         no_of_logs,
                         base_fee,
                     );
+                    result.shard = shard;
                     gas_used = result.cumulative_gas_used;
                     no_of_logs += result.logs.len();
                     result
@@ -2844,9 +2928,21 @@ impl BlockChainClient for Client {
                 .as_u64() as usize,
             )
         };
-        self.importer
-            .miner
-            .ready_transactions(self, max_len, ::miner::PendingOrdering::Priority)
+        let mut transactions = self.importer.miner.ready_transactions(
+            self,
+            max_len,
+            ::miner::PendingOrdering::Priority,
+        );
+        // transactions of other shards are never sealed here, so the pending set misses them
+        let known: HashSet<H256> = transactions.iter().map(|tx| tx.signed().hash()).collect();
+        transactions.extend(
+            self.importer
+                .miner
+                .foreign_shard_transactions()
+                .into_iter()
+                .filter(|tx| !known.contains(&tx.signed().hash())),
+        );
+        transactions
     }
 
     fn transaction(&self, tx_hash: &H256) -> Option<Arc<VerifiedTransaction>> {
@@ -2919,7 +3015,7 @@ impl BlockChainClient for Client {
             transaction.with_signature(signature, chain_id),
         )?)
     }
-    // #[cfg(feature = "shard")]
+    #[cfg(feature = "shard")]
     fn create_shard_transaction(
         &self,
         TransactionRequest {
@@ -2932,13 +3028,14 @@ impl BlockChainClient for Client {
     ) -> Result<SignedTransaction, transaction::Error> {
         let authoring_params = self.importer.miner.authoring_params();
         let service_transaction_checker = self.importer.miner.service_transaction_checker();
-        let gas_price = if let Some(checker) = service_transaction_checker {
-            match checker.check_address(self, authoring_params.author) {
+        // an explicit price is kept, continuation hops are paid at the original sender's price
+        let gas_price = match (gas_price, service_transaction_checker) {
+            (Some(gas_price), _) => gas_price,
+            (None, Some(checker)) => match checker.check_address(self, authoring_params.author) {
                 Ok(true) => U256::zero(),
-                _ => gas_price.unwrap_or_else(|| self.importer.miner.sensible_gas_price()),
-            }
-        } else {
-            self.importer.miner.sensible_gas_price()
+                _ => self.importer.miner.sensible_gas_price(),
+            },
+            (None, None) => self.importer.miner.sensible_gas_price(),
         };
         let transaction = TypedTransaction::Legacy(transaction::Transaction {
             nonce: nonce.unwrap_or_else(|| self.latest_nonce(&authoring_params.author)),
@@ -2954,7 +3051,11 @@ impl BlockChainClient for Client {
             .sign(transaction.signature_hash(chain_id))
             .map_err(|e| transaction::Error::InvalidSignature(e.to_string()))?;
         Ok(SignedTransaction::new(
-            transaction.to_shard_txn(authoring_params.author).with_signature(signature, chain_id),
+            transaction
+                .to_shard_txn(authoring_params.author, &|address| {
+                    self.factories.shard.address_shard(address)
+                })
+                .with_signature(signature, chain_id),
         )?)
     }
     fn transact(&self, tx_request: TransactionRequest) -> Result<(), transaction::Error> {
@@ -3121,14 +3222,18 @@ impl PrepareOpenBlock for Client {
         let chain = self.chain.read();
         let best_header = chain.best_block_header();
         let h = best_header.hash();
-        //only propagate the real shard state root
-        let state_root = chain.shard_state_root.read().clone();
-        let sr;
-        if state_root.1 == best_header.number() && state_root.1>2{
-            sr = state_root.0.clone();
-        }else {
-            sr = best_header.state_root().clone();
-        }
+        // build on the shard-local state root of the best block, as its importers do
+        #[cfg(feature = "shard")]
+        let sr = {
+            let state_root = *chain.shard_state_root.read();
+            if state_root.1 == best_header.number() {
+                state_root.0
+            } else {
+                *best_header.state_root()
+            }
+        };
+        #[cfg(not(feature = "shard"))]
+        let sr = *best_header.state_root();
         let is_epoch_begin = chain.epoch_transition(best_header.number(), h).is_some();
         let mut open_block = OpenBlock::new_shard(
             engine,
@@ -3144,11 +3249,13 @@ impl PrepareOpenBlock for Client {
             chain.ancestry_with_metadata_iter(best_header.hash()),
             sr,
         )?;
-        // #[cfg(feature = "shard")]
         // set the global and round_beginning hashmaps
-        open_block.set_hash_map_global(chain.data_hash_map_global.read().clone());
-        open_block.set_hash_map_round_beginning(chain.data_hash_map_round_beginning.read().clone());
-        open_block.set_incr_bal_round(chain.incr_bal_round.read().clone());
+        #[cfg(feature = "shard")]
+        {
+            open_block.set_hash_map_global(chain.data_hash_map_global.read().clone());
+            open_block.set_hash_map_round_beginning(chain.data_hash_map_round_beginning.read().clone());
+            open_block.set_incr_bal_round(chain.incr_bal_round.read().clone());
+        }
         // Add uncles
         chain
             .find_uncle_headers(&h, MAX_UNCLE_AGE)
@@ -3172,7 +3279,7 @@ impl PrepareOpenBlock for Client {
 
         Ok(open_block)
     }
-    fn import_hash_map_in_chain(&self, hash_map_global: Vec<HashMap<Address, U256>>, hash_map_round_beginning: HashMap<Address, U256>, incr_bal_round: HashMap<Address,U256>) {
+    fn import_hash_map_in_chain(&self, hash_map_global: Vec<HashMap<ShardKey, U256>>, hash_map_round_beginning: HashMap<ShardKey, U256>, incr_bal_round: HashMap<Address,U256>) {
         let mut chain = self.chain.write();
         let mut h_global = chain.data_hash_map_global.write();
         let mut h_round_beginning = chain.data_hash_map_round_beginning.write();
@@ -3284,11 +3391,22 @@ impl ImportSealedBlock for Client {
                 block.state.db(),
                 self,
             )?;
+            let round_state = child_shard_round_state(
+                self.chain.read().child_shard_round_state(
+                    header.parent_hash(),
+                    header.number(),
+                    self.factories.shard.round_length(),
+                ),
+                &header,
+                &block.transactions,
+                &block.state,
+            );
             let route = self.importer.commit_block(
                 block,
                 &header,
                 encoded::Block::new(block_data),
                 pending,
+                round_state,
                 self,
             );
             trace!(target: "client", "Imported sealed block #{} ({})", header.number(), hash);
@@ -3564,6 +3682,96 @@ impl ImportExportBlocks for Client {
     }
 }
 
+/// Shard round state reached by a block executed on `state`, starting from the round
+/// state of its parent.
+///
+/// The incomplete transactions left for this shard are those of the parent which no
+/// continuation in the block took over, followed by the hops of the block handed to it.
+fn child_shard_round_state(
+    parent: ShardRoundState,
+    header: &Header,
+    transactions: &[SignedTransaction],
+    state: &State<StateDB>,
+) -> ShardRoundState {
+    let continued: HashSet<H256> = transactions
+        .iter()
+        .filter_map(|t| t.original_transaction_hash())
+        .collect();
+    let shard = state.shard_context().shard();
+    let mut incomplete_txn: Vec<_> = parent
+        .incomplete_txn
+        .into_iter()
+        .filter(|t| !continued.contains(&t.original_hash()))
+        .collect();
+    incomplete_txn.extend(
+        transactions
+            .iter()
+            .filter(|t| t.is_incomplete() && t.get_next_shard() == shard)
+            .cloned(),
+    );
+    ShardRoundState {
+        shard_state_root: (*header.state_root(), header.number()),
+        data_hash_map_global: state.export_data_hashmap_global(),
+        data_hash_map_round_beginning: state.export_data_hashmap_round_beginning(),
+        incr_bal_round: state.export_incr_bal_round(),
+        incomplete_txn,
+        leaves: state.export_leaves(),
+    }
+}
+
+/// Returns the cross-shard lifecycle of a shard transaction, following the latest
+/// canonical continuation hop of the call it belongs to. The call started on the shard
+/// its original sender is placed on.
+fn shard_receipt(
+    chain: &BlockChain,
+    shard_context: &ShardContext,
+    tx: &UnverifiedTransaction,
+) -> Option<ShardReceipt> {
+    let canonical_tx = |hash: &H256| -> Option<(UnverifiedTransaction, TransactionAddress)> {
+        let address = chain.transaction_address(hash)?;
+        let tx = chain.transaction(&address)?;
+        Some((tx.signed, address))
+    };
+
+    let original_transaction_hash = tx.original_transaction_hash().unwrap_or_else(|| tx.hash());
+    let (latest, address) = chain
+        .shard_continuation(&original_transaction_hash)
+        .and_then(|hash| canonical_tx(&hash))
+        .or_else(|| canonical_tx(&original_transaction_hash))?;
+    let receipts = chain.block_receipts(&address.block_hash)?.receipts;
+    let receipt = receipts.get(address.index)?.receipt();
+    let outcome = receipt.outcome.clone();
+    let prior_gas_used = match address.index {
+        0 => U256::zero(),
+        i => receipts[i - 1].receipt().gas_used,
+    };
+    let hop_gas_used = receipt.gas_used.saturating_sub(prior_gas_used);
+
+    match latest.unsigned {
+        TypedTransaction::ShardTransaction(ref hop) => {
+            let origin_shard = shard_context.address_shard(&hop.original_sender);
+            let (status, final_shard, next_shard) = if hop.incomplete != 0 {
+                (ShardTransactionStatus::Pending, None, Some(hop.next_shard))
+            } else if outcome == TransactionOutcome::StatusCode(0) {
+                (ShardTransactionStatus::Reverted, Some(hop.shard), None)
+            } else {
+                (ShardTransactionStatus::Complete, Some(hop.shard), None)
+            };
+            Some(ShardReceipt {
+                original_transaction_hash,
+                latest_transaction_hash: latest.hash(),
+                origin_shard,
+                final_shard,
+                next_shard,
+                hop_count: hop.hop_count,
+                gas_used: hop.gas_used.saturating_add(hop_gas_used),
+                status,
+            })
+        }
+        _ => None,
+    }
+}
+
 /// Returns `LocalizedReceipt` given `LocalizedTransaction`
 /// and a vector of receipts from given block up to transaction index.
 fn transaction_receipt(
@@ -3625,6 +3833,7 @@ fn transaction_receipt(
         log_bloom: receipt.log_bloom,
         outcome: receipt.outcome.clone(),
         effective_gas_price: tx.effective_gas_price(base_fee),
+        shard: None,
     }
 }
 
@@ -3811,6 +4020,105 @@ impl PrometheusMetrics for Client {
             queue.verifying_queue_size as i64,
         );
 
+        // shard execution
+        let shard = self.shard_context();
+        let shard_id = shard.shard().to_string();
+        let labels = [("shard", shard_id.as_str())];
+        let counters = shard.counters();
+        r.register_labeled_counter(
+            "shard_sload",
+            "Storage reads of shard data",
+            &labels,
+            counters.sload() as i64,
+        );
+        r.register_labeled_counter(
+            "shard_sstore",
+            "Storage writes of shard data",
+            &labels,
+            counters.sstore() as i64,
+        );
+        r.register_labeled_counter(
+            "shard_balance_read",
+            "Balance reads of shard data",
+            &labels,
+            counters.bal_read() as i64,
+        );
+        r.register_labeled_counter(
+            "shard_balance_write",
+            "Balance writes of shard data",
+            &labels,
+            counters.bal_write() as i64,
+        );
+        r.register_labeled_counter(
+            "shard_reverted_txs",
+            "Shard transactions reverted",
+            &labels,
+            counters.reverted() as i64,
+        );
+        for hop in 1..=HOP_BUCKETS as u64 {
+            let hops = if hop == HOP_BUCKETS as u64 {
+                format!("{}+", hop)
+            } else {
+                hop.to_string()
+            };
+            r.register_labeled_counter(
+                "shard_completed_txs",
+                "Shard transactions completed by number of hops",
+                &[("shard", shard_id.as_str()), ("hops", hops.as_str())],
+                counters.hop(hop) as i64,
+            );
+        }
+        let timers = [
+            (
+                "proof_aggregation",
+                "Proof aggregations",
+                counters.aggregation(),
+            ),
+            (
+                "proof_verification",
+                "Proof verifications",
+                counters.verification(),
+            ),
+            ("round_commit", "Round commits", counters.commit()),
+        ];
+        for &(name, help, timer) in timers.iter() {
+            r.register_labeled_counter(
+                &format!("shard_{}_count", name),
+                help,
+                &labels,
+                timer.count() as i64,
+            );
+            r.register_labeled_counter(
+                &format!("shard_{}_micros", name),
+                &format!("{}, total time in microseconds", help),
+                &labels,
+                timer.micros() as i64,
+            );
+        }
+        for outcome in ProofOutcome::ALL.iter() {
+            r.register_labeled_counter(
+                "shard_sealed_proofs",
+                "Blocks sealed by this node by aggregated proof outcome",
+                &[("shard", shard_id.as_str()), ("outcome", outcome.as_str())],
+                counters.proof_outcome(*outcome) as i64,
+            );
+        }
+        {
+            let chain = self.chain.read();
+            r.register_labeled_gauge(
+                "shard_incomplete_txs",
+                "Incomplete transactions waiting to be continued on this shard",
+                &[("shard", shard_id.as_str()), ("queue", "next_block")],
+                chain.incomplete_txn.read().len() as i64,
+            );
+            r.register_labeled_gauge(
+                "shard_incomplete_txs",
+                "Incomplete transactions waiting to be continued on this shard",
+                &[("shard", shard_id.as_str()), ("queue", "pending")],
+                chain.pending_incomplete_txn.read().len() as i64,
+            );
+        }
+
         // database info
         self.db.read().key_value().prometheus_metrics(r);
     }
@@ -3995,6 +4303,7 @@ mod tests {
                 log_bloom: Default::default(),
                 outcome: TransactionOutcome::StateRoot(state_root),
                 effective_gas_price: Default::default(),
+                shard: None,
             }
         );
     }
@@ -4022,4 +4331,68 @@ mod tests {
         assert_eq!(block2_details.children.len(), 0);
         assert!(!block2_details.is_finalized);
     }
+
+    #[test]
+    #[cfg(feature = "shard")]
+    fn continuations_consumed_on_one_branch_stay_open_on_the_other() {
+        use super::child_shard_round_state;
+        use blockchain::ShardRoundState;
+        use ethereum_types::{Address, U256};
+        use factory::Factories;
+        use hyperproofs::ShardContext;
+        use state::State;
+        use std::{collections::HashMap, sync::Arc};
+        use test_helpers::get_temp_state_db;
+        use types::{
+            header::Header,
+            transaction::{Action, ShardTransactionTx, Transaction, TypedTransaction},
+        };
+
+        let hop = |nonce: u64, next_shard: u64| {
+            TypedTransaction::ShardTransaction(ShardTransactionTx::new(
+                Transaction {
+                    nonce: nonce.into(),
+                    action: Action::Call(Address::from_low_u64_be(0x100)),
+                    ..Default::default()
+                },
+                0,
+                next_shard,
+                1,
+                0,
+                Address::zero(),
+                HashMap::new(),
+                Vec::new(),
+                String::new(),
+            ))
+            .fake_sign(Address::from_low_u64_be(1))
+        };
+        let mut factories = Factories::default();
+        factories.shard = Arc::new(ShardContext::default());
+        factories.shard.set_shard(1);
+        let state = State::new(get_temp_state_db(), U256::zero(), factories);
+
+        // a call handed to shard 1 by the parent
+        let open = hop(0, 1);
+        let parent = ShardRoundState {
+            incomplete_txn: vec![open.clone()],
+            ..Default::default()
+        };
+        let mut continuation = hop(1, 999);
+        continuation.set_incomplete(0);
+        continuation.set_original_hash(open.original_hash());
+
+        // the block continuing the call leaves nothing open
+        let continued =
+            child_shard_round_state(parent.clone(), &Header::default(), &[continuation], &state);
+        assert!(continued.incomplete_txn.is_empty());
+        // a competing block which did not continue it keeps it open, along with its own hop
+        let other = hop(2, 1);
+        let competing = child_shard_round_state(
+            parent,
+            &Header::default(),
+            &[other.clone(), hop(3, 2)],
+            &state,
+        );
+        assert_eq!(competing.incomplete_txn, vec![open, other]);
+    }
 }
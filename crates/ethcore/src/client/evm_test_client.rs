@@ -16,6 +16,7 @@
 
 //! Simple Client used for EVM tests.
 
+use bytes::Bytes;
 use client;
 use db;
 use ethereum_types::{H160, H256, U256};
@@ -23,13 +24,14 @@ use ethtrie;
 use evm::{FinalizationResult, VMType};
 use executive;
 use factory::{self, Factories};
+use hyperproofs::{AggProof, ShardContext};
 use journaldb;
 use kvdb::{self, KeyValueDB};
 use pod_state;
 use spec;
 use state;
 use state_db;
-use std::{fmt, sync::Arc};
+use std::{collections::HashMap, fmt, sync::Arc};
 use trace;
 use trie;
 use types::{log_entry, receipt, transaction};
@@ -130,7 +132,7 @@ impl<'a> EvmTestClient<'a> {
         spec: &'a spec::Spec,
         trie_spec: trie::TrieSpec,
     ) -> Result<Self, EvmTestError> {
-        let factories = Self::factories(trie_spec);
+        let factories = Self::factories(trie_spec, Default::default());
         let state = Self::state_from_spec(spec, &factories)?;
 
         Ok(EvmTestClient {
@@ -152,7 +154,18 @@ impl<'a> EvmTestClient<'a> {
         pod_state: pod_state::PodState,
         trie_spec: trie::TrieSpec,
     ) -> Result<Self, EvmTestError> {
-        let factories = Self::factories(trie_spec);
+        Self::from_pod_state_with_shard(spec, pod_state, trie_spec, Default::default())
+    }
+
+    /// Creates new EVM test client with an in-memory DB initialized with given PodState,
+    /// executing for the shard and topology of `shard`.
+    pub fn from_pod_state_with_shard(
+        spec: &'a spec::Spec,
+        pod_state: pod_state::PodState,
+        trie_spec: trie::TrieSpec,
+        shard: Arc<ShardContext>,
+    ) -> Result<Self, EvmTestError> {
+        let factories = Self::factories(trie_spec, shard);
         let state = Self::state_from_pod(spec, &factories, pod_state)?;
 
         Ok(EvmTestClient {
@@ -170,11 +183,12 @@ impl<'a> EvmTestClient<'a> {
         Self::from_pod_state_with_trie(spec, pod_state, trie::TrieSpec::Secure)
     }
 
-    fn factories(trie_spec: trie::TrieSpec) -> Factories {
+    fn factories(trie_spec: trie::TrieSpec, shard: Arc<ShardContext>) -> Factories {
         Factories {
             vm: factory::VmFactory::new(VMType::Interpreter, 5 * 1024),
             trie: trie::TrieFactory::new(trie_spec),
             accountdb: Default::default(),
+            shard,
         }
     }
 
@@ -292,15 +306,141 @@ impl<'a> EvmTestClient<'a> {
                 end_state: (self.dump_state)(&self.state),
             });
         }
-        let mut transaction = transaction;
         // Apply transaction
         let result = self.state.apply_with_tracing(
             &env_info,
             self.spec.engine.machine(),
-            &mut transaction,
+            &transaction,
             tracer,
             vm_tracer,
         );
+        self.finalize(env_info, &transaction, initial_gas, result)
+    }
+
+    /// Executes a shard transaction the way the miner of its shard does.
+    ///
+    /// Balances and storage of other shards are read from the data list the transaction
+    /// carries. Execution stops once it needs data the list does not hold, in which case
+    /// the transaction is left incomplete and has to continue on `next_shard`.
+    pub fn transact_shard<T: trace::Tracer, V: trace::VMTracer>(
+        &mut self,
+        env_info: &client::EnvInfo,
+        transaction: transaction::SignedTransaction,
+        tracer: T,
+        vm_tracer: V,
+    ) -> std::result::Result<ShardTransactSuccess<T::Output, V::Output>, TransactErr> {
+        let initial_gas = transaction.tx().gas;
+        if let Err(error) = transaction.verify_basic(true, None) {
+            return Err(TransactErr {
+                state_root: *self.state.root(),
+                error: error.into(),
+                end_state: (self.dump_state)(&self.state),
+            });
+        }
+        let transaction = if transaction.contains_balance() {
+            transaction
+        } else {
+            let balance = match self.state.balance(&transaction.original_sender()) {
+                Ok(balance) => balance,
+                Err(error) => {
+                    return Err(TransactErr {
+                        state_root: *self.state.root(),
+                        error: error.into(),
+                        end_state: (self.dump_state)(&self.state),
+                    })
+                }
+            };
+            transaction.with_balance(balance)
+        };
+
+        self.state.clear_address_txn_vec();
+        if !transaction.is_incomplete() {
+            self.state
+                .push_address_txn_vec(AggProof::balance_key(&transaction.original_sender()));
+        }
+        self.state.reverted(false);
+        self.state.set_mined_status(Some(true));
+        self.state.set_is_create_txn(!transaction.is_shard());
+        self.state.clear_hash_map_cache();
+        self.state.clear_temp_sstore_val();
+        self.state.clear_temp_sstore_delta();
+        if self.state.export_data_hashmap_global().is_empty() {
+            self.state.set_hash_map_global(vec![HashMap::new()]);
+        }
+        self.state.clear_data_hashmap_txn();
+        self.state.set_next_shard(999u64);
+        for (key, val) in transaction.shard_data_hashmap() {
+            self.state.hash_map_txn_insert(key, val);
+        }
+        let suspended_frame = if transaction.suspended_frame().is_empty() {
+            None
+        } else {
+            match ::rlp::decode(transaction.suspended_frame()) {
+                Ok(frame) => Some(frame),
+                Err(_) => {
+                    return Err(TransactErr {
+                        state_root: *self.state.root(),
+                        error: transaction::Error::InvalidSuspendedFrame.into(),
+                        end_state: (self.dump_state)(&self.state),
+                    });
+                }
+            }
+        };
+        self.state.set_suspended_frame(suspended_frame);
+
+        // Plain transfers run to completion, calls may stop at data of another shard.
+        let result = if transaction.tx().data.is_empty() {
+            self.state.set_txn_status(Some(true));
+            self.state.apply_with_tracing(
+                &env_info,
+                self.spec.engine.machine(),
+                &transaction,
+                tracer,
+                vm_tracer,
+            )
+        } else {
+            self.state.set_txn_status(None);
+            self.state.fake_apply_with_tracing(
+                &env_info,
+                self.spec.engine.machine(),
+                &transaction,
+                tracer,
+                vm_tracer,
+            )
+        };
+
+        let incomplete = self.state.txn_complete_status() == Some(false);
+        let next_shard = if incomplete {
+            Some(self.state.get_next_shard())
+        } else {
+            None
+        };
+        let data_list = self.state.data_hashmap_txn();
+        let suspended_frame = self
+            .state
+            .take_suspended_frame()
+            .map(|frame| ::rlp::encode(&frame))
+            .unwrap_or_default();
+        let reverted = self.state.is_reverted();
+
+        self.finalize(env_info, &transaction, initial_gas, result)
+            .map(|result| ShardTransactSuccess {
+                result,
+                incomplete,
+                reverted,
+                next_shard,
+                data_list,
+                suspended_frame,
+            })
+    }
+
+    fn finalize<T, V>(
+        &mut self,
+        env_info: &client::EnvInfo,
+        transaction: &transaction::SignedTransaction,
+        initial_gas: U256,
+        result: state::ApplyResult<T, V>,
+    ) -> std::result::Result<TransactSuccess<T, V>, TransactErr> {
         let scheme = self
             .spec
             .engine
@@ -396,6 +536,23 @@ pub struct TransactSuccess<T, V> {
     pub end_state: Option<pod_state::PodState>,
 }
 
+/// To be returned inside a std::result::Result::Ok after a shard transaction
+/// was executed, whether or not it completed.
+pub struct ShardTransactSuccess<T, V> {
+    /// Result of the execution on this shard
+    pub result: TransactSuccess<T, V>,
+    /// Whether the transaction has to continue on another shard
+    pub incomplete: bool,
+    /// Whether the call reverted
+    pub reverted: bool,
+    /// Shard to continue on, if incomplete
+    pub next_shard: Option<u64>,
+    /// Data list produced for the next hop
+    pub data_list: HashMap<H256, U256>,
+    /// Frame the next hop resumes from, empty if it runs the call from the start
+    pub suspended_frame: Bytes,
+}
+
 /// To be returned inside a std::result::Result::Err after a failed
 /// transaction.
 #[allow(dead_code)]
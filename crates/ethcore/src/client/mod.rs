@@ -23,12 +23,18 @@ mod config;
 #[cfg(any(test, feature = "test-helpers"))]
 mod evm_test_client;
 mod io_message;
+#[cfg(all(feature = "shard", any(test, feature = "test-helpers")))]
+mod shard_network;
 #[cfg(any(test, feature = "test-helpers"))]
 pub mod test_client;
 mod trace;
 
 #[cfg(any(test, feature = "test-helpers"))]
-pub use self::evm_test_client::{EvmTestClient, EvmTestError, TransactErr, TransactSuccess};
+pub use self::evm_test_client::{
+    EvmTestClient, EvmTestError, ShardTransactSuccess, TransactErr, TransactSuccess,
+};
+#[cfg(all(feature = "shard", any(test, feature = "test-helpers")))]
+pub use self::shard_network::{ShardNetwork, ShardNode};
 #[cfg(any(test, feature = "test-helpers"))]
 pub use self::test_client::{EachBlockWith, TestBlockChainClient};
 pub use self::{
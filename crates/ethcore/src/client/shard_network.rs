@@ -0,0 +1,310 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! In-process network of shard clients for testing cross-shard calls.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use crypto::publickey::KeyPair;
+use ethereum_types::{Address, H256, U256};
+use hash::keccak;
+use io::IoChannel;
+use types::{
+    ids::{BlockId, TransactionId},
+    receipt::ShardReceipt,
+    transaction::{Action, Transaction, TypedTransaction},
+};
+
+use client::{Balance, BlockChainClient, BlockInfo, ChainInfo, Client, ClientConfig, ImportBlock};
+use engines::signer;
+use miner::{Author, Miner, MinerService};
+use spec::Spec;
+use test_helpers::new_db;
+use verification::queue::kind::blocks::Unverified;
+
+/// Number of funded accounts in `Spec::new_test_round_shards`.
+const FUNDED_ACCOUNTS: usize = 8;
+
+/// Gas given to transactions submitted through the network.
+const TRANSACTION_GAS: u64 = 1_000_000;
+
+/// A client sealing the blocks of one shard.
+pub struct ShardNode {
+    /// Client of the node.
+    pub client: Arc<Client>,
+    /// Validator sealing for the shard.
+    pub validator: Address,
+    /// Shard sealed by the node.
+    pub shard: u64,
+}
+
+/// Shard clients sharing one chain inside a single process.
+///
+/// Nodes run AuRa with a fixed start step, so nothing is sealed until the network is stepped.
+/// Every sealed block is handed to the other nodes in memory, the way sync would.
+pub struct ShardNetwork {
+    nodes: Vec<ShardNode>,
+    gas_price: U256,
+}
+
+impl ShardNetwork {
+    /// Four shards sealed by the validators of `Spec::new_test_round_shards`.
+    pub fn new() -> Self {
+        ShardNetwork::with_shards_spec(Spec::new_test_round_shards)
+    }
+
+    /// Four shards sealed by the validators of `Spec::new_test_round_shards`, loading the
+    /// chain spec from `spec`.
+    pub fn with_shards_spec<F>(spec: F) -> Self
+    where
+        F: Fn() -> Spec,
+    {
+        let validators = (0..4).map(|i| secret_key_pair(&i.to_string())).collect();
+        ShardNetwork::with_spec(spec, validators)
+    }
+
+    /// One node for each of `validators`, loading the chain spec from `spec`.
+    pub fn with_spec<F>(spec: F, validators: Vec<KeyPair>) -> Self
+    where
+        F: Fn() -> Spec,
+    {
+        let nodes = validators
+            .into_iter()
+            .map(|validator| {
+                let spec = spec();
+                let miner = Miner::new_for_tests_force_sealing(&spec, None, true);
+                let client = Client::new(
+                    ClientConfig::default(),
+                    &spec,
+                    new_db(),
+                    Arc::new(miner),
+                    IoChannel::disconnected(),
+                )
+                .expect("test client is created");
+                client
+                    .engine()
+                    .register_client(Arc::downgrade(&client) as _);
+
+                let address = validator.address();
+                client
+                    .miner()
+                    .set_author(Author::Sealer(signer::from_keypair(validator)));
+                // genesis balances were queued by the miner
+                let shard_context = client.shard_context();
+                let shard = shard_context.set_author_shard(&address);
+                let backend = shard_context.backend();
+                backend
+                    .commit(shard, 0u64)
+                    .and_then(|_| backend.update_tree(shard))
+                    .and_then(|_| backend.reset_prev_commit())
+                    .expect("genesis shard state is committed");
+
+                ShardNode {
+                    client,
+                    validator: address,
+                    shard,
+                }
+            })
+            .collect();
+
+        ShardNetwork {
+            nodes,
+            gas_price: U256::zero(),
+        }
+    }
+
+    /// Gas price of the transactions submitted from now on, zero by default.
+    pub fn set_gas_price(&mut self, gas_price: U256) {
+        self.gas_price = gas_price;
+    }
+
+    /// All nodes of the network.
+    pub fn nodes(&self) -> &[ShardNode] {
+        &self.nodes
+    }
+
+    /// Node sealing for `shard`.
+    pub fn node(&self, shard: u64) -> &ShardNode {
+        self.nodes
+            .iter()
+            .find(|node| node.shard == shard)
+            .unwrap_or_else(|| panic!("no node seals for shard {}", shard))
+    }
+
+    /// Shard `address` is placed on.
+    pub fn shard_of(&self, address: &Address) -> u64 {
+        self.nodes[0].client.shard_context().address_shard(address)
+    }
+
+    /// Node of the shard `address` is placed on.
+    pub fn home_node(&self, address: &Address) -> &ShardNode {
+        self.node(self.shard_of(address))
+    }
+
+    /// A funded account of `Spec::new_test_round_shards` placed on `shard`.
+    pub fn account(&self, shard: u64) -> KeyPair {
+        (0..FUNDED_ACCOUNTS)
+            .map(|i| secret_key_pair(&format!("user{}", i)))
+            .find(|account| self.shard_of(&account.address()) == shard)
+            .unwrap_or_else(|| panic!("no funded account on shard {}", shard))
+    }
+
+    /// Sign a transaction of `sender` and queue it on the node of the sender's shard.
+    ///
+    /// Returns the hash of the shard transaction that will be sealed.
+    pub fn submit(&self, sender: &KeyPair, action: Action, value: U256, data: Bytes) -> H256 {
+        let client = &self.home_node(&sender.address()).client;
+        let transaction = TypedTransaction::Legacy(Transaction {
+            nonce: client.miner().next_nonce(&**client, &sender.address()),
+            gas_price: self.gas_price,
+            gas: TRANSACTION_GAS.into(),
+            action,
+            value,
+            data,
+        })
+        .sign(
+            sender.secret(),
+            client.engine().signing_chain_id(&client.latest_env_info()),
+        )
+        .to_shard_txn(&|address| client.shard_context().address_shard(address));
+        let hash = transaction.hash();
+        client
+            .miner()
+            .import_own_transaction(&**client, transaction.into())
+            .expect("submitted transaction is valid");
+        hash
+    }
+
+    /// Transfer `value` from `sender` to `recipient`.
+    pub fn transfer(&self, sender: &KeyPair, recipient: Address, value: U256) -> H256 {
+        self.submit(sender, Action::Call(recipient), value, Bytes::new())
+    }
+
+    /// Advance every node by one step and relay the block sealed in it.
+    ///
+    /// Returns the shard that sealed the block, if any.
+    pub fn step(&self) -> Option<u64> {
+        let best = self.best_block_number();
+        for node in &self.nodes {
+            // the step proposer seals right away
+            node.client.engine().step();
+        }
+        let sealer = self
+            .nodes
+            .iter()
+            .find(|node| node.client.chain_info().best_block_number > best)?;
+        self.relay(sealer);
+        Some(sealer.shard)
+    }
+
+    /// Step the network until `shard` seals a block.
+    pub fn seal_on(&self, shard: u64) {
+        for _ in 0..self.nodes.len() {
+            if self.step() == Some(shard) {
+                return;
+            }
+        }
+        panic!("shard {} did not seal within a round", shard);
+    }
+
+    /// Step the network through `rounds` rounds, every shard sealing once per round.
+    pub fn run(&self, rounds: usize) {
+        for _ in 0..rounds * self.nodes.len() {
+            self.step();
+        }
+    }
+
+    /// Best block number all nodes agree on.
+    pub fn best_block_number(&self) -> u64 {
+        self.nodes
+            .iter()
+            .map(|node| node.client.chain_info().best_block_number)
+            .min()
+            .unwrap_or_default()
+    }
+
+    /// Whether every node has the same best block.
+    pub fn is_synced(&self) -> bool {
+        let best = self.nodes[0].client.chain_info().best_block_hash;
+        self.nodes
+            .iter()
+            .all(|node| node.client.chain_info().best_block_hash == best)
+    }
+
+    /// Balance of `address` as seen by the node of its shard.
+    pub fn balance(&self, address: &Address) -> U256 {
+        self.home_node(address)
+            .client
+            .balance(address, BlockId::Latest.into())
+            .expect("latest state is available")
+    }
+
+    /// Cross-shard lifecycle of the call started by the transaction `hash`.
+    pub fn shard_receipt(&self, hash: &H256) -> Option<ShardReceipt> {
+        self.nodes
+            .iter()
+            .filter_map(|node| node.client.transaction_receipt(TransactionId::Hash(*hash)))
+            .next()?
+            .shard
+    }
+
+    /// Number of continuation hops of the call started by the transaction `hash` in the chain.
+    pub fn continuation_count(&self, hash: &H256) -> usize {
+        let client = &self.nodes[0].client;
+        (1..=client.chain_info().best_block_number)
+            .filter_map(|number| client.block(BlockId::Number(number)))
+            .flat_map(|block| block.transactions())
+            .filter(|tx| match tx.unsigned {
+                // the first hop carries the hash too once sealed
+                TypedTransaction::ShardTransaction(ref hop) => {
+                    hop.hop_count != 0 && hop.original_hash == *hash
+                }
+                _ => false,
+            })
+            .count()
+    }
+
+    fn relay(&self, sealer: &ShardNode) {
+        let number = sealer.client.chain_info().best_block_number;
+        let block = sealer
+            .client
+            .block(BlockId::Number(number))
+            .expect("sealed block is in the chain")
+            .into_inner();
+        for node in self.nodes.iter().filter(|node| node.shard != sealer.shard) {
+            let unverified = Unverified::from_rlp(
+                block.clone(),
+                node.client.engine().params().eip1559_transition,
+            )
+            .expect("sealed block decodes");
+            node.client
+                .import_block(unverified)
+                .expect("sealed block is imported");
+            node.client.flush_queue();
+        }
+    }
+}
+
+impl Default for ShardNetwork {
+    fn default() -> Self {
+        ShardNetwork::new()
+    }
+}
+
+fn secret_key_pair(seed: &str) -> KeyPair {
+    KeyPair::from_secret_slice(keccak(seed).as_bytes()).expect("keccak is a valid secret")
+}
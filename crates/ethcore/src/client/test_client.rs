@@ -33,6 +33,7 @@ use ethcore_miner::pool::VerifiedTransaction;
 use ethereum_types::{Address, H256, U256};
 use ethtrie;
 use hash::keccak;
+use hyperproofs::{ShardContext, ShardKey};
 use itertools::Itertools;
 use kvdb::DBValue;
 use parking_lot::RwLock;
@@ -470,7 +471,7 @@ impl PrepareOpenBlock for TestBlockChainClient {
         open_block.set_timestamp(*self.latest_block_timestamp.read());
         Ok(open_block)
     }
-    fn import_hash_map_in_chain(&self, hash_map_global: Vec<HashMap<Address, U256>>, hash_map_round_beginning: HashMap<Address, U256>, incr_bal_round: HashMap<Address,U256>) {
+    fn import_hash_map_in_chain(&self, hash_map_global: Vec<HashMap<ShardKey, U256>>, hash_map_round_beginning: HashMap<ShardKey, U256>, incr_bal_round: HashMap<Address,U256>) {
     }
     fn set_latest_mined_block(&self, h: H256){
 
@@ -488,7 +489,7 @@ impl PrepareOpenBlock for TestBlockChainClient {
 
     }
     fn get_pending_incomplete_txn(&self)->Vec<SignedTransaction>{
-
+        Vec::new()
     }
     fn clear_data_hash_map_global(&self) {
 
@@ -953,6 +954,10 @@ impl BlockChainClient for TestBlockChainClient {
         false
     }
 
+    fn shard_context(&self) -> Arc<ShardContext> {
+        self.miner.shard_context()
+    }
+
     // works only if blocks are one after another 1 -> 2 -> 3
     fn tree_route(&self, from: &H256, to: &H256) -> Option<TreeRoute> {
         Some(TreeRoute {
@@ -1119,6 +1124,7 @@ impl BlockChainClient for TestBlockChainClient {
             .unwrap();
         Ok(SignedTransaction::new(transaction.with_signature(sig, chain_id)).unwrap())
     }
+    #[cfg(feature = "shard")]
     fn create_shard_transaction(
         &self,
         TransactionRequest {
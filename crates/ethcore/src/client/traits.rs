@@ -52,6 +52,7 @@ use engines::EthEngine;
 use error::{Error, EthcoreResult};
 use executed::CallError;
 use executive::Executed;
+use hyperproofs::{ShardContext, ShardKey};
 use state::StateInfo;
 use trace::LocalizedTrace;
 use verification::queue::{kind::blocks::Unverified, QueueInfo as BlockQueueInfo};
@@ -408,7 +409,6 @@ pub trait BlockChainClient:
                             TypedTxId::Legacy => None,
                             TypedTxId::AccessList => None,
                             TypedTxId::EIP1559Transaction => Some(block.header().base_fee()),
-                            // #[cfg(feature = "shard")]
                             TypedTxId::ShardTransaction => None,
                         }
                     }))
@@ -486,7 +486,8 @@ pub trait BlockChainClient:
         &self,
         tx_request: TransactionRequest,
     ) -> Result<SignedTransaction, transaction::Error>;
-    // #[cfg(feature = "shard")]
+    /// Returns a shard transaction of the author's shard, signed like `create_transaction`.
+    #[cfg(feature = "shard")]
     fn create_shard_transaction(
         &self,
         tx_request: TransactionRequest,
@@ -500,6 +501,9 @@ pub trait BlockChainClient:
 
     /// Returns true, if underlying import queue is processing possible fork at the moment
     fn is_processing_fork(&self) -> bool;
+
+    /// Shard state of this node.
+    fn shard_context(&self) -> Arc<ShardContext>;
 }
 
 /// The data required for a `Client` to create a transaction.
@@ -568,8 +572,8 @@ pub trait PrepareOpenBlock {
     ) -> Result<OpenBlock, Error>;
     fn import_hash_map_in_chain(
         &self,
-        hash_map_global: Vec<HashMap<Address,U256>>,
-        hash_map_round_beginning: HashMap<Address,U256>,
+        hash_map_global: Vec<HashMap<ShardKey,U256>>,
+        hash_map_round_beginning: HashMap<ShardKey,U256>,
         incr_bal_round: HashMap<Address, U256>,
     ) ;
     fn set_latest_mined_block(&self, h: H256);
@@ -80,7 +80,7 @@ use types::{
     ancestry_action::AncestryAction,
     header::{ExtendedHeader, Header},
     ids::BlockId,
-    transaction::SignedTransaction,
+    transaction::{self, SignedTransaction},
     BlockNumber,
 };
 use hyperproofs::AggProof;
@@ -1965,38 +1965,103 @@ impl Engine<EthereumMachine> for AuthorityRound {
         let nonce = transactions.last().map(|tx| tx.tx().nonce + U256::one());
         transactions.extend(self.run_posdao(block, nonce)?);
         debug!(target:"authority round", "engine transactions look like {:?}",transactions);
-        // #[cfg(feature = "shard")]
-        let client = self.upgrade_client_or("Unable to prepare block")?;
-        let full_client = client.as_full_client().ok_or_else(|| {
-            EngineError::FailedSystemCall("Failed to upgrade to BlockchainClient.".to_string())
-        })?;
-        let tx_request = TransactionRequest::call(Address::zero(), Bytes::new())
-            .gas_price(U256::zero());
-        // let dummy_txn = full_client.create_shard_transaction(tx_request).unwrap();
-        // let mut current_nonce = dummy_txn.tx().nonce;
-        // transactions.push(dummy_txn);
-        transactions.extend(full_client.create_shard_transaction(tx_request));
-        for txn in block.state.export_incomplete_txn() {
-            match txn.call_address(){
-                Some(a) => {
-                    // current_nonce = current_nonce.saturating_add(U256::from(1));
-                    let tx_request = TransactionRequest::call(a, txn.tx().data.clone()).gas_price(U256::zero()).gas(txn.tx().gas);
-                    let mut new_txn = full_client.create_shard_transaction(tx_request).unwrap();
-                    new_txn.set_next_shard(999u64);
-                    new_txn.incr_hop_count(txn.get_hop_count()+1);
-                    new_txn.set_incomplete(1u64);
-                    new_txn.change_original_sender(txn.original_sender());
-                    new_txn.hash_map_replace_with(txn.shard_data_hashmap());
-                    new_txn.set_shard(txn.get_next_shard());
-                    transactions.push(new_txn);
+        // the proof carrying transaction of the author's shard and the next hops of the
+        // calls handed to it
+        #[cfg(feature = "shard")]
+        {
+            let client = self.upgrade_client_or("Unable to prepare block")?;
+            let full_client = client.as_full_client().ok_or_else(|| {
+                EngineError::FailedSystemCall("Failed to upgrade to BlockchainClient.".to_string())
+            })?;
+            let create_shard_transaction = |tx_request: TransactionRequest| {
+                full_client
+                    .create_shard_transaction(tx_request)
+                    .map_err(|e| {
+                        EngineError::FailedSystemCall(format!(
+                            "Failed to create shard transaction: {}",
+                            e
+                        ))
+                    })
+            };
+            let tx_request = TransactionRequest::call(Address::zero(), Bytes::new())
+                .gas_price(U256::zero());
+            transactions.push(create_shard_transaction(tx_request)?);
+            for txn in block.state.export_incomplete_txn() {
+                match txn.call_address(){
+                    Some(a) => {
+                        // current_nonce = current_nonce.saturating_add(U256::from(1));
+                        // the next hop runs on what is left of the original sender's budget
+                        let tx_request = TransactionRequest::call(a, txn.tx().data.clone())
+                            .gas_price(txn.tx().gas_price)
+                            .gas(txn.tx().gas.saturating_sub(txn.hop_gas_used()));
+                        let mut new_txn = create_shard_transaction(tx_request)?;
+                        new_txn.set_gas_used(txn.shard_gas_used() + txn.hop_gas_used(), U256::zero());
+                        new_txn.set_next_shard(999u64);
+                        new_txn.incr_hop_count(txn.get_hop_count()+1);
+                        new_txn.set_incomplete(1u64);
+                        new_txn.change_original_sender(txn.original_sender());
+                        new_txn.set_original_hash(txn.original_hash());
+                        new_txn.hash_map_replace_with(txn.shard_data_hashmap());
+                        // the next hop resumes where this one stopped instead of re-running the call
+                        new_txn.set_suspended_frame(txn.suspended_frame().to_vec());
+                        new_txn.set_shard(txn.get_next_shard());
+                        transactions.push(new_txn);
+                    }
+                    None => {}
                 }
-                None => {}
-            }
 
+            }
         }
         Ok(transactions)
     }
 
+    fn verify_shard_transaction(
+        &self,
+        t: &SignedTransaction,
+        parent: &Header,
+    ) -> Result<(), transaction::Error> {
+        if !t.is_engine_shard_transaction() {
+            return Ok(());
+        }
+        let unauthorized = || transaction::Error::UnauthorizedShardTransaction {
+            signer: t.sender(),
+            shard: t.shard_id(),
+        };
+        let client = self
+            .upgrade_client_or("Unable to verify shard transaction")
+            .map_err(|_| unauthorized())?;
+
+        let validators = if self.immediate_transitions {
+            CowLike::Borrowed(&*self.validators)
+        } else {
+            let mut epoch_manager = self.epoch_manager.lock();
+            if !epoch_manager.zoom_to_after(
+                &*client,
+                &self.machine,
+                &*self.validators,
+                parent.hash(),
+            ) {
+                debug!(target: "engine", "Unable to zoom to epoch of {} to verify shard transaction.", parent.hash());
+                return Err(unauthorized());
+            }
+            CowLike::Owned(epoch_manager.validators().clone())
+        };
+        if !validators.contains(&parent.hash(), &t.sender()) {
+            return Err(unauthorized());
+        }
+
+        // the signer must also seal for the shard the transaction claims to come from
+        let shard_context = match client.as_full_client() {
+            Some(full_client) => full_client.shard_context(),
+            None => return Err(unauthorized()),
+        };
+        let config = shard_context.config();
+        if config.shard_count > 1 && config.author_shard(&t.sender()) != t.shard_id() {
+            return Err(unauthorized());
+        }
+        Ok(())
+    }
+
     /// Check the number of seal fields.
     fn verify_block_basic(&self, header: &Header) -> Result<(), Error> {
         if header.number() >= self.validate_score_transition
@@ -2437,7 +2502,8 @@ mod tests {
     };
     use accounts::AccountProvider;
     use block::*;
-    use crypto::publickey::Signature;
+    use client::TestBlockChainClient;
+    use crypto::publickey::{KeyPair, Signature};
     use engines::{
         block_reward::BlockRewardContract,
         validator_set::{SimpleList, TestSet},
@@ -2452,7 +2518,7 @@ mod tests {
     use rlp::encode;
     use spec::Spec;
     use std::{
-        collections::BTreeMap,
+        collections::{BTreeMap, HashMap},
         str::FromStr,
         sync::{
             atomic::{AtomicU64, AtomicUsize, Ordering as AtomicOrdering},
@@ -2467,7 +2533,9 @@ mod tests {
     use types::{
         header::Header,
         ids::BlockId,
-        transaction::{Action, Transaction, TypedTransaction},
+        transaction::{
+            self, Action, ShardTransactionTx, SignedTransaction, Transaction, TypedTransaction,
+        },
     };
 
     fn aura<F>(f: F) -> Arc<AuthorityRound>
@@ -3902,4 +3970,126 @@ mod tests {
             "Fourth address after block 3"
         );
     }
+
+    /// Shard transaction of `original_sender` signed by `signer`, a continuation hop of the
+    /// call unless `hop_count` is zero.
+    fn shard_transaction(
+        signer: &KeyPair,
+        shard: u64,
+        hop_count: u64,
+        original_sender: Address,
+    ) -> SignedTransaction {
+        TypedTransaction::ShardTransaction(ShardTransactionTx::new(
+            Transaction::default(),
+            shard,
+            999u64,
+            (hop_count != 0) as u64,
+            hop_count,
+            original_sender,
+            HashMap::new(),
+            Vec::new(),
+            String::new(),
+        ))
+        .sign(signer.secret(), None)
+    }
+
+    #[test]
+    fn only_shard_validators_may_sign_engine_shard_transactions() {
+        // seals for shard 0 of the shard test spec
+        let validator = KeyPair::from_secret_slice(keccak("0").as_bytes()).unwrap();
+        let user = KeyPair::from_secret_slice(keccak("user").as_bytes()).unwrap();
+        let engine = aura(|p| p.validators = Box::new(SimpleList::new(vec![validator.address()])));
+        let client = Arc::new(TestBlockChainClient::new_with_spec(
+            Spec::new_test_round_shards(),
+        ));
+        engine.register_client(Arc::downgrade(&client) as _);
+        let parent = Header::default();
+
+        // continuation hops
+        assert_eq!(
+            engine.verify_shard_transaction(
+                &shard_transaction(&validator, 0, 1, user.address()),
+                &parent
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            engine
+                .verify_shard_transaction(&shard_transaction(&user, 0, 1, user.address()), &parent),
+            Err(transaction::Error::UnauthorizedShardTransaction {
+                signer: user.address(),
+                shard: 0,
+            })
+        );
+        // a validator sealing for another shard
+        assert!(engine
+            .verify_shard_transaction(
+                &shard_transaction(&validator, 1, 1, user.address()),
+                &parent
+            )
+            .is_err());
+        // a user acting for another sender
+        assert!(engine
+            .verify_shard_transaction(
+                &shard_transaction(&user, 0, 0, validator.address()),
+                &parent
+            )
+            .is_err());
+        // the first hop of a user's own call
+        assert_eq!(
+            engine
+                .verify_shard_transaction(&shard_transaction(&user, 0, 0, user.address()), &parent),
+            Ok(())
+        );
+    }
+
+    #[cfg(feature = "shard")]
+    #[test]
+    fn shard_fields_changed_after_signing_are_rejected() {
+        use hyperproofs::ShardKey;
+
+        let validator = KeyPair::from_secret_slice(keccak("0").as_bytes()).unwrap();
+        let user = KeyPair::from_secret_slice(keccak("user").as_bytes()).unwrap();
+        let engine = aura(|p| p.validators = Box::new(SimpleList::new(vec![validator.address()])));
+        let client = Arc::new(TestBlockChainClient::new_with_spec(
+            Spec::new_test_round_shards(),
+        ));
+        engine.register_client(Arc::downgrade(&client) as _);
+        let parent = Header::default();
+        let received = |t: &SignedTransaction| {
+            SignedTransaction::new(TypedTransaction::decode(&t.encode()).unwrap()).unwrap()
+        };
+
+        let hop = shard_transaction(&validator, 0, 1, user.address());
+        assert_eq!(
+            engine.verify_shard_transaction(&received(&hop), &parent),
+            Ok(())
+        );
+
+        // a relay resuming the call elsewhere, handing it on or crediting another sender
+        let mut forged = hop.clone();
+        forged.set_suspended_frame(vec![0xc0]);
+        assert!(engine
+            .verify_shard_transaction(&received(&forged), &parent)
+            .is_err());
+        let mut forged = hop.clone();
+        forged.set_next_shard(1);
+        assert!(engine
+            .verify_shard_transaction(&received(&forged), &parent)
+            .is_err());
+        let mut forged = hop.clone();
+        forged.change_original_sender(validator.address());
+        assert!(engine
+            .verify_shard_transaction(&received(&forged), &parent)
+            .is_err());
+
+        // users may not add shard data to their own call either
+        let mut unsigned = shard_transaction(&user, 0, 0, user.address())
+            .as_unsigned()
+            .clone();
+        unsigned.hash_map_insert(ShardKey::default(), U256::one());
+        assert!(engine
+            .verify_shard_transaction(&unsigned.sign(user.secret(), None), &parent)
+            .is_err());
+    }
 }
@@ -545,6 +545,18 @@ pub trait Engine<M: Machine>: Sync + Send {
         Ok(Vec::new())
     }
 
+    /// Verify that a shard transaction only the engine may produce, like a continuation hop of a
+    /// cross-shard call or one carrying a balance proof, was signed by a validator of its shard.
+    ///
+    /// Called for every transaction of an imported block and for every transaction entering the pool.
+    fn verify_shard_transaction(
+        &self,
+        _t: &SignedTransaction,
+        _parent: &Header,
+    ) -> Result<(), transaction::Error> {
+        Ok(())
+    }
+
     /// Overrides the block gas limit. Whenever this returns `Some` for a header, the next block's gas limit must be
     /// exactly that value. used by AuRa engine.
     fn gas_limit_override(&self, _header: &Header) -> Option<U256> {
@@ -107,6 +107,10 @@ pub enum BlockError {
     UnknownUncleParent(H256),
     /// No transition to epoch number.
     UnknownEpochTransition(u64),
+    /// No shard state root is tracked for the block of the given number.
+    UnknownShardStateRoot(BlockNumber),
+    /// No committed vector root is tracked for the given shard.
+    UnknownShardVector(u64),
 }
 
 impl fmt::Display for BlockError {
@@ -159,6 +163,12 @@ impl fmt::Display for BlockError {
             UnknownEpochTransition(ref num) => {
                 format!("Unknown transition to epoch number: {}", num)
             }
+            UnknownShardStateRoot(ref num) => {
+                format!("No shard state root tracked for block #{}", num)
+            }
+            UnknownShardVector(ref shard) => {
+                format!("No vector root tracked for shard {}", shard)
+            }
             TimestampOverflow => format!("Timestamp overflow"),
             TooManyTransactions(ref address) => format!("Too many transactions from: {}", address),
         };
@@ -258,6 +268,7 @@ error_chain! {
         Engine(EngineError) #[doc = "Consensus vote error."];
         Ethkey(EthkeyError) #[doc = "Ethkey error."];
         Decoder(rlp::DecoderError) #[doc = "RLP decoding errors"];
+        Shard(::hyperproofs::Error) #[doc = "Error concerning the shard vector commitments."];
     }
 
     errors {
@@ -23,6 +23,8 @@ pub use executed::{Executed, ExecutionResult};
 use externalities::*;
 use factory::VmFactory;
 use hash::keccak;
+#[cfg(feature = "shard")]
+use hyperproofs::AggProof;
 use machine::EthereumMachine as Machine;
 use state::{Backend as StateBackend, CleanupMode, State, Substate};
 use std::{cmp, convert::TryFrom, sync::Arc};
@@ -33,7 +35,6 @@ use vm::{
     self, AccessList, ActionParams, ActionValue, CleanDustMode, CreateContractAddress, EnvInfo,
     ResumeCall, ResumeCreate, ReturnData, Schedule, TrapError,
 };
-use hyperproofs::AggProof;
 
 #[cfg(any(test, feature = "test-helpers"))]
 /// Precompile that can never be prunned from state trie (0x3, only in tests)
@@ -1146,8 +1147,14 @@ impl<'a, B: 'a + StateBackend> Executive<'a, B> {
         debug!(target: "txn", "inside transact_with_tracer");
         // check if particualar transaction type is enabled at this block number in schedule
         match t.as_unsigned() {
-            // #[cfg(feature = "shard")]
+            #[cfg(feature = "shard")]
             TypedTransaction::ShardTransaction(_) => (), //shard transactions are always valid
+            #[cfg(not(feature = "shard"))]
+            TypedTransaction::ShardTransaction(_) => {
+                return Err(ExecutionError::TransactionMalformed(
+                    "Shard transactions not enabled".into(),
+                ));
+            }
             TypedTransaction::AccessList(_) => {
                 if !schedule.eip2930 {
                     return Err(ExecutionError::TransactionMalformed(
@@ -1164,8 +1171,7 @@ impl<'a, B: 'a + StateBackend> Executive<'a, B> {
             }
             TypedTransaction::Legacy(_) => (), //legacy transactions are allways valid
         };
-        // #[cfg(feature = "shard")]
-        let mut sender = t.sender();
+        let sender = t.nonce_sender();
         let nonce = self.state.nonce(&sender)?;
 
         let mut base_gas_required = U256::from(t.tx().gas_required(&schedule));
@@ -1212,13 +1218,16 @@ impl<'a, B: 'a + StateBackend> Executive<'a, B> {
 
         let init_gas = t.tx().gas - base_gas_required;
 
-        // validate transaction nonce
-        //     if check_nonce && t.tx().nonce != nonce {
-        //         return Err(ExecutionError::InvalidNonce {
-        //             expected: nonce,
-        //             got: t.tx().nonce,
-        //         });
-        //     }
+        // validate transaction nonce, a hop of a cross-shard call reuses the nonce of the call
+        #[cfg(not(feature = "shard"))]
+        {
+            if check_nonce && t.tx().nonce != nonce {
+                return Err(ExecutionError::InvalidNonce {
+                    expected: nonce,
+                    got: t.tx().nonce,
+                });
+            }
+        }
         // validate if transaction fits into given block
         if self.info.gas_used + t.tx().gas > self.info.gas_limit {
             return Err(ExecutionError::BlockGasLimitReached {
@@ -1244,12 +1253,13 @@ impl<'a, B: 'a + StateBackend> Executive<'a, B> {
         }
 
         // TODO: we might need bigints here, or at least check overflows.
-        sender = t.original_sender();
-        // #[cfg(feature = "shard")]
+        #[cfg(feature = "shard")]
+        let sender = t.original_sender();
+        #[cfg(feature = "shard")]
         let balance = match t.as_unsigned() {
-            TypedTransaction::ShardTransaction(shard_tx) => match shard_tx.shard_data_list.get(&sender) {
+            TypedTransaction::ShardTransaction(shard_tx) => match shard_tx.shard_data_list.get(&AggProof::balance_key(&sender)) {
              Some(bal) => {
-                 let temp_val = self.state.global_hash_map_storage_at_one_round(&sender);
+                 let temp_val = self.state.global_hash_map_storage_at_one_round(&AggProof::balance_key(&sender));
                  let increment = self.state.incr_bal_round_storage_at(&sender);
                  if temp_val.1{
                      if increment.1{temp_val.0.saturating_add(increment.0)} else { temp_val.0 }
@@ -1261,16 +1271,25 @@ impl<'a, B: 'a + StateBackend> Executive<'a, B> {
                     panic!("something is wrong, tx should have data");
                     self.state.balance(&sender)?},
             },
-            _ => {AggProof::incr_bal_read_count(1u64);
+            _ => {self.state.shard_context().counters().incr_bal_read(1u64);
                 self.state.balance(&sender)?},
         };
-        // #[cfg(not(feature = "shard"))]
-        // let balance = self.state.balance(&sender)?;
-        let gas_cost_effective = t
-            .tx()
-            .gas
-            .full_mul(t.effective_gas_price(self.info.base_fee));
-        let gas_cost_max = t.tx().gas.full_mul(t.tx().gas_price);
+        #[cfg(not(feature = "shard"))]
+        let balance = self.state.balance(&sender)?;
+        // continuation hops spend the budget held since the first hop of the call
+        let budget_held = t.get_hop_count() != 0;
+        let gas_cost_effective = if budget_held {
+            U512::zero()
+        } else {
+            t.tx()
+                .gas
+                .full_mul(t.effective_gas_price(self.info.base_fee))
+        };
+        let gas_cost_max = if budget_held {
+            U512::zero()
+        } else {
+            t.tx().gas.full_mul(t.tx().gas_price)
+        };
         let needed_balance = U512::from(t.tx().value) + gas_cost_max;
 
         // avoid unaffordable transactions
@@ -1286,13 +1305,14 @@ impl<'a, B: 'a + StateBackend> Executive<'a, B> {
 
         // NOTE: there can be no invalid transactions from this point.
         if !schedule.keep_unsigned_nonce || !t.is_unsigned() {
-                self.state.inc_nonce(&t.sender())?;
+                self.state.inc_nonce(&t.nonce_sender())?;
         }
         self.state.sub_balance(
             &sender,
             &U256::try_from(gas_cost_effective).expect("Total cost (value + gas_cost_effective) is lower than max allowed balance (U256); gas_cost has to fit U256; qed"),
             &mut substate.to_cleanup_mode(&schedule),
         )?;
+        self.state.claim_shard_budget(t, self.info.base_fee)?;
         let sender = t.original_sender();
         let (result, output) = match t.tx().action {
             Action::Create => {
@@ -1377,8 +1397,14 @@ impl<'a, B: 'a + StateBackend> Executive<'a, B> {
         debug!(target: "txn", "inside fake_transact_with_tracer");
         // check if particualar transaction type is enabled at this block number in schedule
         match t.as_unsigned() {
-            // #[cfg(feature = "shard")]
+            #[cfg(feature = "shard")]
             TypedTransaction::ShardTransaction(_) => (), //shard transactions are always valid
+            #[cfg(not(feature = "shard"))]
+            TypedTransaction::ShardTransaction(_) => {
+                return Err(ExecutionError::TransactionMalformed(
+                    "Shard transactions not enabled".into(),
+                ));
+            }
             TypedTransaction::AccessList(_) => {
                 if !schedule.eip2930 {
                     return Err(ExecutionError::TransactionMalformed(
@@ -1395,8 +1421,7 @@ impl<'a, B: 'a + StateBackend> Executive<'a, B> {
             }
             TypedTransaction::Legacy(_) => (), //legacy transactions are allways valid
         };
-        // #[cfg(feature = "shard")]
-        let mut sender = t.sender();
+        let sender = t.nonce_sender();
         let nonce = self.state.nonce(&sender)?;
 
         let mut base_gas_required = U256::from(t.tx().gas_required(&schedule));
@@ -1475,24 +1500,34 @@ impl<'a, B: 'a + StateBackend> Executive<'a, B> {
 
         // TODO: we might need bigints here, or at least check overflows.
 
-        // #[cfg(feature = "shard")]
+        #[cfg(feature = "shard")]
         let sender = t.original_sender();
+        #[cfg(feature = "shard")]
         let balance = match t.as_unsigned() {
-            TypedTransaction::ShardTransaction(shard_tx) => match shard_tx.shard_data_list.get(&sender) {
+            TypedTransaction::ShardTransaction(shard_tx) => match shard_tx.shard_data_list.get(&AggProof::balance_key(&sender)) {
                 Some(bal) => bal.clone(),
-                None => { AggProof::incr_bal_read_count(1u64);
+                None => { self.state.shard_context().counters().incr_bal_read(1u64);
                     self.state.balance(&sender)?},
             },
-            _ => {AggProof::incr_bal_read_count(1u64);
+            _ => {self.state.shard_context().counters().incr_bal_read(1u64);
                 self.state.balance(&sender)?},
         };
-        // #[cfg(not(feature = "shard"))]
-        // let balance = self.state.balance(&sender)?;
-        let gas_cost_effective = t
-            .tx()
-            .gas
-            .full_mul(t.effective_gas_price(self.info.base_fee));
-        let gas_cost_max = t.tx().gas.full_mul(t.tx().gas_price);
+        #[cfg(not(feature = "shard"))]
+        let balance = self.state.balance(&sender)?;
+        // continuation hops spend the budget held since the first hop of the call
+        let budget_held = t.get_hop_count() != 0;
+        let gas_cost_effective = if budget_held {
+            U512::zero()
+        } else {
+            t.tx()
+                .gas
+                .full_mul(t.effective_gas_price(self.info.base_fee))
+        };
+        let gas_cost_max = if budget_held {
+            U512::zero()
+        } else {
+            t.tx().gas.full_mul(t.tx().gas_price)
+        };
         let needed_balance = U512::from(t.tx().value) + gas_cost_max;
 
         // avoid unaffordable transactions
@@ -1515,6 +1550,7 @@ impl<'a, B: 'a + StateBackend> Executive<'a, B> {
             &U256::try_from(gas_cost_effective).expect("Total cost (value + gas_cost_effective) is lower than max allowed balance (U256); gas_cost has to fit U256; qed"),
             &mut substate.to_cleanup_mode(&schedule),
         )?;
+        self.state.claim_shard_budget(t, self.info.base_fee)?;
         let sender = t.original_sender();
         let mut code_address = Address::zero();
         let (result, output) = match t.tx().action {
@@ -1577,7 +1613,7 @@ impl<'a, B: 'a + StateBackend> Executive<'a, B> {
         // no if, increase the nonce.
         if !schedule.keep_unsigned_nonce || !t.is_unsigned() {
             // if self.state.txn_complete_status()!= None{
-                    self.state.inc_nonce(&t.sender())?;
+                    self.state.inc_nonce(&t.nonce_sender())?;
             // }
         }
         // if the status doesn't change && no revert, write the state
@@ -1590,15 +1626,15 @@ impl<'a, B: 'a + StateBackend> Executive<'a, B> {
             for _i in 0..val_vec.len(){
                 let x = delta_vec.pop().unwrap();
                 let y = val_vec.pop().unwrap();
-                if y.1.to_low_u64_be().rem_euclid(AggProof::shard_count()) == AggProof::get_shard() {
+                if self.state.shard_context().address_shard(&y.1) == self.state.shard_context().shard() {
                     self.state
                         .set_storage(&y.1, y.2.clone(), BigEndianHash::from_uint(&y.3));
-                    AggProof::incr_sstore_count(1u64);
-                    println!("SSTORE setting storage at {} with val {} and code address {}", y.2, y.3, y.1);
+                    self.state.shard_context().counters().incr_sstore(1u64);
+                    trace!(target: "txn", "SSTORE setting storage at {} with val {} and code address {}", y.2, y.3, y.1);
                 }
                 self.state.global_hash_map_insert(y.0, y.3);
-                AggProof::pushAddressDelta(x.0.clone(), x.1.clone(),x.2.clone());
-                println!("delta {} from address {} in shard {}", x.1, x.0 , x.2);
+                self.state.push_shard_delta(&x.0, &x.1, x.2)?;
+                trace!(target: "txn", "delta {} from address {} in shard {}", x.1, x.0, x.2);
 
             }
         }
@@ -1799,6 +1835,12 @@ impl<'a, B: 'a + StateBackend> Executive<'a, B> {
         let gas_used = t.tx().gas.saturating_sub(gas_left);
         let (refund_value, overflow_1) =
             gas_left.overflowing_mul(t.effective_gas_price(self.info.base_fee));
+        // an incomplete hop hands its unused gas over to the next hop as its budget
+        let refund_value = if t.is_shard() && self.state.txn_complete_status() == Some(false) {
+            U256::zero()
+        } else {
+            refund_value
+        };
         let (fees_value, overflow_2) =
             gas_used.overflowing_mul(t.effective_gas_price(self.info.base_fee));
         if overflow_1 || overflow_2 {
@@ -1827,13 +1869,17 @@ impl<'a, B: 'a + StateBackend> Executive<'a, B> {
 
         trace!("exec::finalize: t.gas={}, sstore_refunds={}, suicide_refunds={}, refunds_bound={}, gas_left_prerefund={}, refunded={}, gas_left={}, gas_used={}, refund_value={}, fees_value={}\n",
 			t.tx().gas, sstore_refunds, suicide_refunds, refunds_bound, gas_left_prerefund, refunded, gas_left, gas_used, refund_value, fees_value);
-        // #[cfg(feature = "shard")]
+        #[cfg(feature = "shard")]
         let sender = t.original_sender();
+        #[cfg(not(feature = "shard"))]
+        let sender = t.sender();
         trace!(
             "exec::finalize: Refunding refund_value={}, sender={}\n",
             refund_value,
             sender
         );
+        // The last hop of a cross-shard call refunds what is left of its budget: credited
+        // directly on the sender's shard, queued as a balance delta of it elsewhere.
         // Below: NoEmpty is safe since the sender must already be non-null to have sent this transaction
         self.state
             .add_balance(&sender, &refund_value, CleanupMode::NoEmpty)?;
@@ -2884,7 +2930,9 @@ mod tests {
         );
     }
 
+    #[cfg(not(feature = "shard"))]
     evm_test! {test_transact_invalid_nonce: test_transact_invalid_nonce_int}
+    #[cfg(not(feature = "shard"))]
     fn test_transact_invalid_nonce(factory: Factory) {
         let keypair = Random.generate();
         let t = TypedTransaction::Legacy(Transaction {
@@ -3475,4 +3523,258 @@ mod tests {
         // Since transaction errored due to wasm was deactivated, result is just empty
         assert_eq!(output[..], [0u8; 20][..]);
     }
+
+    #[cfg(feature = "shard")]
+    mod shard_hops {
+        use super::*;
+        use crypto::publickey::KeyPair;
+        use factory::Factories;
+        use hyperproofs::{HyperproofBackend, ShardConfig, ShardContext, ShardKey};
+        use state_db::StateDB;
+        use std::sync::Mutex;
+        use test_helpers::get_temp_state_db;
+        use types::transaction::SignedTransaction;
+
+        const BALANCE: u64 = 1_000_000;
+        const BUDGET: u64 = 100_000;
+        const CALL_GAS: u64 = 21_000;
+
+        /// Backend keeping the deltas queued by an execution.
+        #[derive(Default)]
+        struct DeltaBackend {
+            deltas: Mutex<Vec<(u64, String, u64)>>,
+        }
+
+        impl HyperproofBackend for DeltaBackend {
+            fn init(&self, _: u64) -> hyperproofs::Result<i64> {
+                Ok(0)
+            }
+            fn push_address_delta(
+                &self,
+                leaf: u64,
+                delta: &str,
+                shard: u64,
+            ) -> hyperproofs::Result<i64> {
+                self.deltas
+                    .lock()
+                    .unwrap()
+                    .push((leaf, delta.into(), shard));
+                Ok(0)
+            }
+            fn reset_address_delta(&self, _: u64) -> hyperproofs::Result<i64> {
+                Ok(0)
+            }
+            fn push_address_commit(&self, _: u64, _: u64) -> hyperproofs::Result<i64> {
+                Ok(0)
+            }
+            fn reset_address_commit(&self, _: u64) -> hyperproofs::Result<i64> {
+                Ok(0)
+            }
+            fn push_address_balance_verify(
+                &self,
+                _: u64,
+                _: &str,
+                _: u64,
+            ) -> hyperproofs::Result<i64> {
+                Ok(0)
+            }
+            fn reset_address_balance_verify(&self, _: u64) -> hyperproofs::Result<i64> {
+                Ok(0)
+            }
+            fn agg(&self, _: u64) -> hyperproofs::Result<(String, bool)> {
+                Ok((String::new(), false))
+            }
+            fn verify_proof(&self, _: &str, _: u64, _: u64) -> hyperproofs::Result<bool> {
+                Ok(true)
+            }
+            fn commit(&self, _: u64, _: u64) -> hyperproofs::Result<i64> {
+                Ok(0)
+            }
+            fn update_tree(&self, _: u64) -> hyperproofs::Result<i64> {
+                Ok(0)
+            }
+            fn reset_prev_commit(&self) -> hyperproofs::Result<i64> {
+                Ok(0)
+            }
+            fn digest(&self, _: u64) -> hyperproofs::Result<Option<H256>> {
+                Ok(None)
+            }
+        }
+
+        /// State of a node of shard 0 out of two sealing a block.
+        fn shard_state(backend: Arc<DeltaBackend>) -> State<StateDB> {
+            let mut factories = Factories::default();
+            factories.shard = Arc::new(ShardContext::with_backend(
+                ShardConfig {
+                    shard_count: 2,
+                    ..Default::default()
+                },
+                backend,
+            ));
+            let mut state = State::new(get_temp_state_db(), U256::zero(), factories);
+            state.set_mined_status(Some(true));
+            state
+        }
+
+        fn account_on(state: &State<StateDB>, shard: u64) -> KeyPair {
+            loop {
+                let account = Random.generate();
+                if state.shard_context().address_shard(&account.address()) == shard {
+                    return account;
+                }
+            }
+        }
+
+        /// Hop `hop_count` on shard 0 of a call of `sender` with `gas` left of its budget.
+        /// Continuation hops are signed by `signer`, the first hop by the sender itself.
+        fn hop(sender: &KeyPair, signer: &KeyPair, hop_count: u64, gas: u64) -> SignedTransaction {
+            let mut t = TypedTransaction::ShardTransaction(ShardTransactionTx::new(
+                Transaction {
+                    nonce: U256::zero(),
+                    gas_price: U256::one(),
+                    gas: gas.into(),
+                    action: Action::Call(Address::from_low_u64_be(0x100)),
+                    value: U256::zero(),
+                    data: vec![],
+                },
+                0,
+                1,
+                (hop_count > 0) as u64,
+                hop_count,
+                sender.address(),
+                Default::default(),
+                Default::default(),
+                String::new(),
+            ));
+            t.hash_map_insert(AggProof::balance_key(&sender.address()), BALANCE.into());
+            if hop_count > 0 {
+                t.set_original_hash(H256::from_low_u64_be(1));
+            }
+            t.sign(signer.secret(), None)
+        }
+
+        /// Execute `t` on `state` as its author, leaving the call incomplete if `stops`.
+        fn execute(
+            state: &mut State<StateDB>,
+            t: &SignedTransaction,
+            author: Address,
+            stops: bool,
+        ) -> U256 {
+            state.set_txn_status(if stops { Some(false) } else { None });
+            let mut info = EnvInfo::default();
+            info.gas_limit = U256::from(BALANCE);
+            info.author = author;
+            let machine = make_frontier_machine(0);
+            let schedule = machine.schedule(info.number);
+            let mut ex = Executive::new(state, &info, &machine, &schedule);
+            ex.fake_transact(t, TransactOptions::with_no_tracing())
+                .unwrap()
+                .gas_used
+        }
+
+        /// Deltas queued for `key` by `state`, with the shard they are queued for.
+        fn deltas(
+            state: &State<StateDB>,
+            backend: &DeltaBackend,
+            key: &ShardKey,
+        ) -> Vec<(String, u64)> {
+            let leaf = state
+                .export_leaves()
+                .into_iter()
+                .find(|(allocated, _)| allocated == key)
+                .map(|(_, leaf)| leaf);
+            backend
+                .deltas
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(queued, _, _)| Some(*queued) == leaf)
+                .map(|(_, delta, shard)| (delta.clone(), *shard))
+                .collect()
+        }
+
+        #[test]
+        fn every_hop_pays_its_author_from_the_held_budget() {
+            let backend = Arc::new(DeltaBackend::default());
+            let mut state = shard_state(backend.clone());
+            let sender = account_on(&state, 0);
+            let validator = account_on(&state, 0);
+            let author = account_on(&state, 0).address();
+            state
+                .add_balance(&sender.address(), &BALANCE.into(), CleanupMode::NoEmpty)
+                .unwrap();
+
+            // the first hop holds the whole budget
+            let first = hop(&sender, &sender, 0, BUDGET);
+            assert_eq!(execute(&mut state, &first, author, true), CALL_GAS.into());
+            assert_eq!(
+                state.balance(&sender.address()).unwrap(),
+                (BALANCE - BUDGET).into()
+            );
+            assert_eq!(state.balance(&author).unwrap(), CALL_GAS.into());
+
+            // the next one is paid from what is left of it
+            let next = hop(&sender, &validator, 1, BUDGET - CALL_GAS);
+            assert_eq!(execute(&mut state, &next, author, true), CALL_GAS.into());
+            assert_eq!(
+                state.balance(&sender.address()).unwrap(),
+                (BALANCE - BUDGET).into()
+            );
+            assert_eq!(state.balance(&author).unwrap(), (2 * CALL_GAS).into());
+            assert_eq!(
+                deltas(&state, &backend, &AggProof::budget_key(&sender.address())),
+                vec![(format!("-{}", BUDGET - CALL_GAS), 0)]
+            );
+        }
+
+        #[test]
+        fn incomplete_hops_hand_the_remaining_budget_to_the_next_shard() {
+            let backend = Arc::new(DeltaBackend::default());
+            let mut state = shard_state(backend.clone());
+            let sender = account_on(&state, 0);
+            let author = account_on(&state, 0).address();
+            state
+                .add_balance(&sender.address(), &BALANCE.into(), CleanupMode::NoEmpty)
+                .unwrap();
+
+            let mut first = hop(&sender, &sender, 0, BUDGET);
+            let gas_used = execute(&mut state, &first, author, true);
+            first.set_gas_used(U256::zero(), gas_used);
+            state.hand_off_shard_budget(&first, None).unwrap();
+
+            // nothing is refunded, the budget left moves on to shard 1
+            assert_eq!(
+                state.balance(&sender.address()).unwrap(),
+                (BALANCE - BUDGET).into()
+            );
+            assert_eq!(
+                deltas(&state, &backend, &AggProof::budget_key(&sender.address())),
+                vec![((BUDGET - CALL_GAS).to_string(), 1)]
+            );
+        }
+
+        #[test]
+        fn last_hop_refunds_the_budget_to_the_sender_shard() {
+            let backend = Arc::new(DeltaBackend::default());
+            let mut state = shard_state(backend.clone());
+            let sender = account_on(&state, 1);
+            let validator = account_on(&state, 0);
+            let author = account_on(&state, 0).address();
+
+            let last = hop(&sender, &validator, 2, BUDGET);
+            assert_eq!(execute(&mut state, &last, author, false), CALL_GAS.into());
+
+            // the sender's balance lives on shard 1, which is sent the refund
+            assert_eq!(state.balance(&sender.address()).unwrap(), U256::zero());
+            assert!(
+                deltas(&state, &backend, &AggProof::balance_key(&sender.address()))
+                    .contains(&((BUDGET - CALL_GAS).to_string(), 1))
+            );
+            assert_eq!(state.balance(&author).unwrap(), CALL_GAS.into());
+            assert_eq!(
+                deltas(&state, &backend, &AggProof::budget_key(&sender.address())),
+                vec![(format!("-{}", BUDGET), 0)]
+            );
+        }
+    }
 }
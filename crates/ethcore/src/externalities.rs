@@ -18,6 +18,7 @@
 use bytes::Bytes;
 use ethereum_types::{Address, BigEndianHash, H256, U256};
 use executive::*;
+use hyperproofs::{ShardContext, ShardKey};
 use machine::EthereumMachine as Machine;
 use state::{Backend as StateBackend, CleanupMode, State, Substate};
 use std::{cmp, sync::Arc};
@@ -25,7 +26,8 @@ use trace::{Tracer, VMTracer};
 use types::transaction::UNSIGNED_SENDER;
 use vm::{
     self, AccessList, ActionParams, ActionValue, CallType, ContractCreateResult,
-    CreateContractAddress, EnvInfo, Ext, MessageCallResult, ReturnData, Schedule, TrapKind,
+    CreateContractAddress, EnvInfo, Ext, MessageCallResult, ReturnData, Schedule,
+    ShardExt, SuspendedFrame, TrapKind,
 };
 
 /// Policy for handling output data on `RETURN` opcode.
@@ -119,67 +121,6 @@ where
     V: VMTracer,
     B: StateBackend,
 {
-    // #[cfg(feature = "shard")]
-    fn push_address_txn_vec(&mut self, a: Address) {
-        self.state.push_address_txn_vec(a);
-    }
-    fn is_create_txn(&self)->bool{
-        self.state.is_create_txn()
-    }
-
-    fn push_temp_sstore_val(&mut self, k: Address, code: Address, ah: H256, v: U256) {
-        self.state.push_temp_sstore_val(k, code ,ah, v);
-    }
-    fn push_temp_sstore_delta(&mut self, a: u64, s: String, sh: u64){
-        self.state.push_temp_sstore_delta(a, s, sh);
-    }
-    fn reverted(&mut self, s: bool) {
-        self.state.reverted(s);
-    }
-    fn static_flag(&self) -> bool {
-        self.static_flag.clone()
-    }
-    fn origin_address(&self)-> Address {
-      self.origin_info.address.clone()
-    }
-    fn hash_map_storage_at(&self, key: &Address) ->(U256, bool){
-        //check in hashmap_cache first
-        let mut val = self.state.hash_map_cache_storage_at(key);
-        if !val.1{
-            val = self.state.data_hash_map_txn_storage_at(key);
-            if val.1{
-                let temp_val = self.state.global_hash_map_storage_at(key);
-                if temp_val.1{
-                    val = temp_val
-                }
-            }
-        }
-        val
-    }
-    fn hash_map_beginning_storage_at(&self, key: &Address) ->(U256, bool){
-        self.state.hash_map_beginning_storage_at(key)
-    }
-    fn hash_map_cache_insert(&self, key: Address, val: U256){
-        self.state.hash_map_cache_insert(key, val);
-    }
-    fn hash_map_beginning_insert(&self, key: Address, val: U256){
-        self.state.hash_map_beginning_insert(key, val);
-    }
-    fn hash_map_global_insert(&self, key: Address, val: U256){
-        self.state.global_hash_map_insert(key, val);
-    }
-    fn hash_map_txn_insert(&self, key: Address, val: U256){
-        self.state.hash_map_txn_insert(key, val);
-    }
-    fn set_txn_incomplete(&mut self){
-        self.state.set_txn_status(Some(false));
-    }
-    fn set_next_shard(&mut self, shard: u64){
-        self.state.set_next_shard(shard);
-    }
-    fn txn_complete_status(&mut self) -> Option<bool>{
-        self.state.txn_complete_status()
-    }
     fn initial_storage_at(&self, key: &H256) -> vm::Result<H256> {
         if self
             .state
@@ -196,7 +137,6 @@ where
     }
 
     fn storage_at(&self, key: &H256) -> vm::Result<H256> {
-        println!("setting storage at {}",self.origin_info.address);
         self.state
             .storage_at(&self.origin_info.address, key)
             .map_err(Into::into)
@@ -620,6 +560,91 @@ where
     fn al_insert_address(&mut self, address: Address) {
         self.substate.access_list.insert_address(address)
     }
+
+    #[cfg(feature = "shard")]
+    fn shard_ext(&mut self) -> Option<&mut dyn ShardExt> {
+        Some(self)
+    }
+}
+
+impl<'a, T: 'a, V: 'a, B: 'a> ShardExt for Externalities<'a, T, V, B>
+where
+    T: Tracer,
+    V: VMTracer,
+    B: StateBackend,
+{
+    fn push_address_txn_vec(&mut self, a: ShardKey) {
+        self.state.push_address_txn_vec(a);
+    }
+    fn is_create_txn(&self)->bool{
+        self.state.is_create_txn()
+    }
+
+    fn push_temp_sstore_val(&mut self, k: ShardKey, code: Address, ah: H256, v: U256) {
+        self.state.push_temp_sstore_val(k, code ,ah, v);
+    }
+    fn push_temp_sstore_delta(&mut self, k: ShardKey, s: String, sh: u64){
+        self.state.push_temp_sstore_delta(k, s, sh);
+    }
+    fn push_shard_delta(&mut self, k: &ShardKey, s: &str, sh: u64) -> vm::Result<()> {
+        self.state.push_shard_delta(k, s, sh).map_err(Into::into)
+    }
+    fn reverted(&mut self, s: bool) {
+        self.state.reverted(s);
+    }
+    fn shard_context(&self) -> &ShardContext {
+        self.state.shard_context()
+    }
+    fn origin_address(&self)-> Address {
+      self.origin_info.address.clone()
+    }
+    fn hash_map_storage_at(&self, key: &ShardKey) ->(U256, bool){
+        //check in hashmap_cache first
+        let mut val = self.state.hash_map_cache_storage_at(key);
+        if !val.1{
+            val = self.state.data_hash_map_txn_storage_at(key);
+            if val.1{
+                let temp_val = self.state.global_hash_map_storage_at(key);
+                if temp_val.1{
+                    val = temp_val
+                }
+            }
+        }
+        val
+    }
+    fn hash_map_beginning_storage_at(&self, key: &ShardKey) ->(U256, bool){
+        self.state.hash_map_beginning_storage_at(key)
+    }
+    fn hash_map_cache_insert(&self, key: ShardKey, val: U256){
+        self.state.hash_map_cache_insert(key, val);
+    }
+    fn hash_map_beginning_insert(&self, key: ShardKey, val: U256){
+        self.state.hash_map_beginning_insert(key, val);
+    }
+    fn hash_map_global_insert(&self, key: ShardKey, val: U256){
+        self.state.global_hash_map_insert(key, val);
+    }
+    fn hash_map_txn_insert(&self, key: ShardKey, val: U256){
+        self.state.hash_map_txn_insert(key, val);
+    }
+    fn set_txn_incomplete(&mut self){
+        self.state.set_txn_status(Some(false));
+    }
+    fn set_next_shard(&mut self, shard: u64){
+        self.state.set_next_shard(shard);
+    }
+    fn suspend_frame(&mut self, mut frame: SuspendedFrame){
+        // the writes of this and earlier hops are only applied once the call completes
+        frame.storage_writes = self.state.get_temp_sstore_val();
+        frame.storage_deltas = self.state.get_temp_sstore_delta();
+        self.state.set_suspended_frame(Some(frame));
+    }
+    fn resume_frame(&mut self) -> Option<SuspendedFrame>{
+        self.state.take_suspended_frame()
+    }
+    fn txn_complete_status(&mut self) -> Option<bool>{
+        self.state.txn_complete_status()
+    }
 }
 
 #[cfg(test)]
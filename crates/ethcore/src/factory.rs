@@ -17,7 +17,9 @@
 use account_db::Factory as AccountFactory;
 use ethtrie::RlpCodec;
 use evm::{Factory as EvmFactory, VMType};
+use hyperproofs::ShardContext;
 use keccak_hasher::KeccakHasher;
+use std::sync::Arc;
 use trie::TrieFactory;
 use vm::{ActionParams, Exec, Schedule};
 use wasm::WasmInterpreter;
@@ -65,4 +67,6 @@ pub struct Factories {
     pub trie: TrieFactory<KeccakHasher, RlpCodec>,
     /// factory for account databases.
     pub accountdb: AccountFactory,
+    /// shard state of the node.
+    pub shard: Arc<ShardContext>,
 }
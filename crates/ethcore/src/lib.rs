@@ -136,6 +136,7 @@ pub mod verification;
 mod account_db;
 mod externalities;
 mod factory;
+mod shard_directory;
 mod tx_filter;
 
 #[cfg(feature = "json-tests")]
@@ -17,9 +17,8 @@
 use std::{
     cmp,
     collections::{BTreeMap, BTreeSet, HashSet},
-    sync::{Arc,mpsc},
+    sync::Arc,
     time::{Duration, Instant},
-    thread,
 };
 
 use ansi_term::Colour;
@@ -37,7 +36,9 @@ use ethcore_miner::{
     },
     service_transaction_checker::ServiceTransactionChecker,
 };
+#[cfg(feature = "shard")]
 use hyperproofs::AggProof;
+use hyperproofs::{ShardContext, ShardKey};
 use ethereum_types::{Address, H256, U256};
 use io::IoChannel;
 use miner::{
@@ -46,6 +47,8 @@ use miner::{
     pool_client::{CachedNonceClient, PoolClient},
     MinerService,
 };
+#[cfg(feature = "shard")]
+use miner::proof_aggregator::ProofAggregator;
 use parking_lot::{Mutex, RwLock};
 use rayon::prelude::*;
 use types::{
@@ -163,6 +166,8 @@ pub struct MinerOptions {
     pub pool_limits: pool::Options,
     /// Initial transaction verification options.
     pub pool_verification_options: pool::verifier::Options,
+    /// How long block preparation waits for an aggregated proof before sealing without it.
+    pub proof_aggregation_timeout: Duration,
 }
 
 impl Default for MinerOptions {
@@ -195,6 +200,7 @@ impl Default for MinerOptions {
                 no_early_reject: false,
                 allow_non_eoa_sender: false,
             },
+            proof_aggregation_timeout: Duration::from_secs(2),
         }
     }
 }
@@ -248,10 +254,9 @@ impl SealingWork {
 /// Handles preparing work for "work sealing" or seals "internally" if Engine does not require work.
 pub struct Miner {
     // NOTE [ToDr]  When locking always lock in this order!
-    // #[cfg(feature = "shard")]
-    pub channel_sender: Arc<Mutex<mpsc::Sender<String>>>,
-    pub channel_receiver: Arc<Mutex<mpsc::Receiver<String>>>,
-    pub proof_data: RwLock<Vec<(Address,U256)>>,
+    #[cfg(feature = "shard")]
+    proof_aggregator: ProofAggregator,
+    pub proof_data: RwLock<Vec<(ShardKey,U256)>>,
     sealing: Mutex<SealingWork>,
     params: RwLock<AuthoringParams>,
     #[cfg(feature = "work-notify")]
@@ -266,6 +271,7 @@ pub struct Miner {
     accounts: Arc<dyn LocalAccounts>,
     io_channel: RwLock<Option<IoChannel<ClientIoMessage>>>,
     service_transaction_checker: Option<ServiceTransactionChecker>,
+    shard_context: Arc<ShardContext>,
 }
 
 impl Miner {
@@ -283,8 +289,6 @@ impl Miner {
 
     /// Creates new instance of miner Arc.
     pub fn new<A: LocalAccounts + 'static>(
-        sender: Arc<Mutex<mpsc::Sender<String>>>,
-        reciever: Arc<Mutex<mpsc::Receiver<String>>>,
         options: MinerOptions,
         gas_pricer: GasPricer,
         spec: &Spec,
@@ -297,10 +301,14 @@ impl Miner {
         let balance_cache_size = cmp::max(4096, limits.max_count / 4);
         let refuse_service_transactions = options.refuse_service_transactions;
         let engine = spec.engine.clone();
+        let shard_context = Arc::new(ShardContext::new(spec.params().shard_config()));
+        if let Err(e) = spec.init_shard_context(&shard_context) {
+            warn!(target: "miner", "Failed to queue genesis balances for the shard vectors: {}", e);
+        }
 
         Miner {
-            channel_sender: sender,
-            channel_receiver: reciever,
+            #[cfg(feature = "shard")]
+            proof_aggregator: ProofAggregator::new(shard_context.clone()),
             proof_data: RwLock::new(Vec::new()),
             sealing: Mutex::new(SealingWork {
                 queue: UsingQueue::new(options.work_queue_size),
@@ -330,6 +338,7 @@ impl Miner {
             } else {
                 Some(ServiceTransactionChecker::default())
             },
+            shard_context,
         }
     }
 
@@ -348,13 +357,8 @@ impl Miner {
         accounts: Option<HashSet<Address>>,
         force_sealing: bool,
     ) -> Miner {
-        let (tx,rx): (mpsc::Sender<String>, mpsc::Receiver<String>) = mpsc::channel();
-        let tx = Arc::new(Mutex::new(tx));
-        let rx = Arc::new(Mutex::new(rx));
         let minimal_gas_price = 0.into();
         Miner::new(
-                tx,
-            rx,
             MinerOptions {
                 pool_verification_options: pool::verifier::Options {
                     minimal_gas_price,
@@ -374,6 +378,11 @@ impl Miner {
         )
     }
 
+    /// Shard state of this node.
+    pub fn shard_context(&self) -> Arc<ShardContext> {
+        self.shard_context.clone()
+    }
+
     /// Sets `IoChannel`
     pub fn set_io_channel(&self, io_channel: IoChannel<ClientIoMessage>) {
         *self.io_channel.write() = Some(io_channel);
@@ -458,32 +467,262 @@ impl Miner {
             &*self.engine,
             &*self.accounts,
             self.service_transaction_checker.as_ref(),
+            &self.shard_context,
         )
     }
 
     /// Prepares new block for sealing including top transactions from queue.
-    // #[cfg(feature = "shard")]
-    //changing from self to mut self since we would need to change proof data
+    #[cfg(not(feature = "shard"))]
+    fn prepare_block<C>(&self, chain: &C) -> Option<(ClosedBlock, Option<H256>)>
+    where
+        C: BlockChain + CallContract + BlockProducer + Nonce + Sync,
+    {
+        trace_time!("prepare_block");
+        let chain_info = chain.chain_info();
+
+        // Some engines add transactions to the block for their own purposes, e.g. AuthorityRound RANDAO.
+        let (mut open_block, original_work_hash, engine_txs) = {
+            let mut sealing = self.sealing.lock();
+            let last_work_hash = sealing.queue.peek_last_ref().map(|pb| pb.header.hash());
+            let best_hash = chain_info.best_block_hash;
+
+            // check to see if last ClosedBlock in would_seals is actually same parent block.
+            // if so
+            //   duplicate, re-open and push any new transactions.
+            //   if at least one was pushed successfully, close and enqueue new ClosedBlock;
+            //   otherwise, leave everything alone.
+            // otherwise, author a fresh block.
+            match sealing
+                .queue
+                .get_pending_if(|b| b.header.parent_hash() == &best_hash)
+            {
+                Some(old_block) => {
+                    trace!(target: "miner", "prepare_block: Already have previous work; updating and returning");
+                    // add transactions to old_block
+                    (chain.reopen_block(old_block), last_work_hash, Vec::new())
+                }
+                None => {
+                    // block not found - create it.
+                    trace!(target: "miner", "prepare_block: No existing work - making new block");
+                    let params = self.params.read().clone();
+
+                    let block = match chain.prepare_open_block(
+                        params.author,
+                        params.gas_range_target,
+                        params.extra_data,
+                    ) {
+                        Ok(block) => block,
+                        Err(err) => {
+                            warn!(target: "miner", "Open new block failed with error {:?}. This is likely an error in \
+								  chain specification or on-chain consensus smart contracts.", err);
+                            return None;
+                        }
+                    };
+                    // Before adding from the queue to the new block, give the engine a chance to add transactions.
+                    match self.engine.generate_engine_transactions(&block) {
+                        Ok(transactions) => (block, last_work_hash, transactions),
+                        Err(err) => {
+                            error!(target: "miner", "Failed to prepare engine transactions for new block: {:?}. \
+								   This is likely an error in chain specification or on-chain consensus smart \
+								   contracts.", err);
+                            return None;
+                        }
+                    }
+                }
+            }
+        };
+
+        if self.options.infinite_pending_block {
+            open_block.remove_gas_limit();
+        }
+
+        let mut invalid_transactions = HashSet::new();
+        let mut not_allowed_transactions = HashSet::new();
+        let mut senders_to_penalize = HashSet::new();
+        let block_number = open_block.header.number();
+
+        let mut tx_count = 0usize;
+        let mut skipped_transactions = 0usize;
+
+        let client = self.pool_client(chain);
+        let engine_params = self.engine.params();
+        let schedule = self.engine.schedule(block_number);
+        let min_tx_gas: U256 = schedule.tx_gas.into();
+        let gas_limit = open_block.header.gas_limit();
+        let nonce_cap: Option<U256> = if chain_info.best_block_number + 1
+            >= engine_params.dust_protection_transition
+        {
+            Some((engine_params.nonce_cap_increment * (chain_info.best_block_number + 1)).into())
+        } else {
+            None
+        };
+        // we will never need more transactions than limit divided by min gas
+        let max_transactions = if min_tx_gas.is_zero() {
+            usize::max_value()
+        } else {
+            MAX_SKIPPED_TRANSACTIONS.saturating_add(
+                cmp::min(gas_limit / min_tx_gas, u64::max_value().into()).as_u64() as usize,
+            )
+        };
+
+        let queue_txs: Vec<Arc<_>> = self.transaction_queue.pending(
+            client.clone(),
+            pool::PendingSettings {
+                block_number: chain_info.best_block_number,
+                current_timestamp: chain_info.best_block_timestamp,
+                nonce_cap,
+                max_len: max_transactions.saturating_sub(engine_txs.len()),
+                ordering: miner::PendingOrdering::Priority,
+                includable_boundary: self
+                    .engine
+                    .calculate_base_fee(&chain.best_block_header())
+                    .unwrap_or_default(),
+                enforce_priority_fees: true,
+                shard: None,
+            },
+        );
+
+        let took_ms = |elapsed: &Duration| {
+            elapsed.as_secs() * 1000 + elapsed.subsec_nanos() as u64 / 1_000_000
+        };
+
+        let block_start = Instant::now();
+        debug!(target: "miner", "Attempting to push {} transactions.", engine_txs.len() + queue_txs.len());
+
+        for transaction in engine_txs
+            .into_iter()
+            .chain(queue_txs.into_iter().map(|tx| tx.signed().clone()))
+        {
+            let start = Instant::now();
+
+            let hash = transaction.hash();
+            let sender = transaction.sender();
+
+            // Re-verify transaction again vs current state.
+            let result = client
+                .verify_for_pending_block(&transaction, &open_block.header)
+                .map_err(|e| e.into())
+                .and_then(|_| open_block.push_transaction(transaction, None));
+
+            let took = start.elapsed();
+
+            // Check for heavy transactions
+            match self.options.tx_queue_penalization {
+                Penalization::Enabled {
+                    ref offend_threshold,
+                } if &took > offend_threshold => {
+                    senders_to_penalize.insert(sender);
+                    debug!(target: "miner", "Detected heavy transaction ({} ms). Penalizing sender.", took_ms(&took));
+                }
+                _ => {}
+            }
+
+            debug!(target: "miner", "Adding tx {:?} took {} ms", hash, took_ms(&took));
+            match result {
+                Err(Error(
+                    ErrorKind::Execution(ExecutionError::BlockGasLimitReached {
+                        gas_limit,
+                        gas_used,
+                        gas,
+                    }),
+                    _,
+                )) => {
+                    debug!(target: "miner", "Skipping adding transaction to block because of gas limit: {:?} (limit: {:?}, used: {:?}, gas: {:?})", hash, gas_limit, gas_used, gas);
+
+                    // Penalize transaction if it's above current gas limit
+                    if gas > gas_limit {
+                        debug!(target: "txqueue", "[{:?}] Transaction above block gas limit.", hash);
+                        invalid_transactions.insert(hash);
+                    }
+
+                    // Exit early if gas left is smaller then min_tx_gas
+                    let gas_left = gas_limit - gas_used;
+                    if gas_left < min_tx_gas {
+                        debug!(target: "miner", "Remaining gas is lower than minimal gas for a transaction. Block is full.");
+                        break;
+                    }
+
+                    // Avoid iterating over the entire queue in case block is almost full.
+                    skipped_transactions += 1;
+                    if skipped_transactions > MAX_SKIPPED_TRANSACTIONS {
+                        debug!(target: "miner", "Reached skipped transactions threshold. Assuming block is full.");
+                        break;
+                    }
+                }
+                // Invalid nonce error can happen only if previous transaction is skipped because of gas limit.
+                // If there is errornous state of transaction queue it will be fixed when next block is imported.
+                Err(Error(
+                    ErrorKind::Execution(ExecutionError::InvalidNonce { expected, got }),
+                    _,
+                )) => {
+                    debug!(target: "miner", "Skipping adding transaction to block because of invalid nonce: {:?} (expected: {:?}, got: {:?})", hash, expected, got);
+                }
+                // already have transaction - ignore
+                Err(Error(ErrorKind::Transaction(transaction::Error::AlreadyImported), _)) => {}
+                Err(Error(ErrorKind::Transaction(transaction::Error::NotAllowed), _)) => {
+                    not_allowed_transactions.insert(hash);
+                    debug!(target: "miner", "Skipping non-allowed transaction for sender {:?}", hash);
+                }
+                Err(e) => {
+                    debug!(target: "txqueue", "[{:?}] Marking as invalid: {:?}.", hash, e);
+                    debug!(
+                        target: "miner", "Error adding transaction to block: number={}. transaction_hash={:?}, Error: {:?}", block_number, hash, e
+                    );
+                    invalid_transactions.insert(hash);
+                }
+                // imported ok
+                _ => tx_count += 1,
+            }
+        }
+        let elapsed = block_start.elapsed();
+        debug!(target: "miner", "Pushed {} transactions in {} ms", tx_count, took_ms(&elapsed));
+
+        let block = match open_block.close() {
+            Ok(block) => block,
+            Err(err) => {
+                warn!(target: "miner", "Closing the block failed with error {:?}. This is likely an error in chain specificiations or on-chain consensus smart contracts.", err);
+                return None;
+            }
+        };
+
+        {
+            self.transaction_queue
+                .remove(invalid_transactions.iter(), true);
+            self.transaction_queue
+                .remove(not_allowed_transactions.iter(), false);
+            self.transaction_queue.penalize(senders_to_penalize.iter());
+        }
+
+        Some((block, original_work_hash))
+    }
+
+    /// Prepares new block for sealing including top transactions from queue.
+    ///
+    /// Commits the shard vectors at round boundaries, resumes the incomplete hops handed to
+    /// this shard and collects the data the next balance proof covers.
+    #[cfg(feature = "shard")]
     fn prepare_block<C>(&self, chain: &C) -> Option<(ClosedBlock, Option<H256>)>
     where
         C: BlockChain + CallContract + BlockProducer + Nonce + Sync,
     {
         trace_time!("prepare_block");
         let chain_info = chain.chain_info();
-        // #[cfg(feature = "shard")]
         //committing data to hyperproofs
         let block_num = chain_info.best_block_number+1;
-        if block_num.clone().rem_euclid(AggProof::shard_count()) ==0 {
+        let shard = &self.shard_context;
+        if block_num.clone().rem_euclid(shard.round_length()) ==0 {
             trace!(target:"miner", "block number is {}", block_num.clone());
-            if block_num.clone() != AggProof::get_last_commit_round(){
+            if block_num.clone() != shard.last_commit_round(){
+                if let Err(e) = shard.commit_round(block_num) {
+                    warn!(target: "miner", "Failed to commit the shard vectors for #{}: {}. Not sealing.", block_num, e);
+                    return None;
+                }
                 //clear data_hash_map_round_beginning
                 debug!(target: "miner", "before clearing data hashmap");
                 chain.clear_data_hash_map_round_beginning();
                 chain.clear_incr_bal_round();
                 chain.resize_hash_map_global();
                 debug!(target: "miner", "after clearing data hashmap");
-                AggProof::commit(AggProof::get_shard(),0u64);
-                AggProof::set_last_commit_shard(block_num.clone());
             }
         }
 
@@ -526,10 +765,20 @@ impl Miner {
                         }
                     };
                     debug!(target: "miner", "before exporting incomplete txn");
-                    block.set_incomplete_txn(chain.export_incomplete_txn());
-                    chain.clear_incomplete_txn();
                     let mut pending_incomplete_txn = chain.get_pending_incomplete_txn();
                     chain.clear_pending_incomplete_txn();
+                    // continuations still waiting for room in a block are not generated again
+                    let incomplete_txn = chain
+                        .export_incomplete_txn()
+                        .into_iter()
+                        .filter(|t| {
+                            pending_incomplete_txn
+                                .iter()
+                                .all(|p| p.original_hash() != t.original_hash())
+                        })
+                        .collect();
+                    block.set_incomplete_txn(incomplete_txn);
+                    chain.clear_incomplete_txn();
                     // Before adding from the queue to the new block, give the engine a chance to add transactions.
                     match self.engine.generate_engine_transactions(&block) {
                         Ok( transactions) => { let mut transactions = transactions;
@@ -589,6 +838,8 @@ impl Miner {
             )
         };
 
+        let block_shard = self.shard_context.shard();
+        // only this shard's transactions are ordered, foreign ones are left to the propagator
         let queue_txs: Vec<Arc<_>> = self.transaction_queue.pending(
             client.clone(),
             pool::PendingSettings {
@@ -602,6 +853,7 @@ impl Miner {
                     .calculate_base_fee(&chain.best_block_header())
                     .unwrap_or_default(),
                 enforce_priority_fees: true,
+                shard: Some(block_shard),
             },
         );
 
@@ -610,26 +862,24 @@ impl Miner {
         let took_ms = |elapsed: &Duration| {
             elapsed.as_secs() * 1000 + elapsed.subsec_nanos() as u64 / 1_000_000
         };
-        let block_shard = AggProof::get_shard();
         let block_start = Instant::now();
         debug!(target: "txn", "Attempting to push {} transactions.", engine_txs.len() + queue_txs.len());
-        // #[cfg(feature = "shard")]
         debug!(target: "miner", "before waiting for proof");
-        let (is_proof, proof) = if !self.proof_data.read().is_empty() {match self.channel_receiver.lock().recv() {
-            Ok(T) => (true,T),
-        _ => (false, String::new()),
-        }
-        }else {(false, String::new()) };
+        let proof = if !self.proof_data.read().is_empty() {
+            self.proof_aggregator
+                .take(self.options.proof_aggregation_timeout)
+        } else {
+            None
+        };
         debug!(target: "miner", "after waiting for proofs");
         for transaction in engine_txs
             .into_iter().map(|tx| if tx.call_address()==Some(Address::zero()) {
-            if is_proof{
+            if let Some(ref proof) = proof {
                 let pd = self.proof_data.read().clone();
                 //clear proof data from previous round
                 self.proof_data.write().clear();
-                AggProof::resetAddressCommit(block_shard);
+                let _ = self.shard_context.backend().reset_address_commit(block_shard);
 
-                println!("author shard is {}", block_shard);
                 tx.with_proof(pd,proof.clone()).with_shard(block_shard)
             }else { tx.with_shard(block_shard) }
         }else {
@@ -638,7 +888,8 @@ impl Miner {
             .chain(queue_txs.into_iter().map(|tx| tx.signed().clone()))
         {
             let start = Instant::now();
-            let transaction = transaction.clean_shard();
+            let transaction =
+                transaction.clean_shard(&|address| self.shard_context.address_shard(address));
             let hash_before = transaction.hash();
             // let transaction = transaction.to_shard_txn();
             let hash = transaction.hash();
@@ -648,7 +899,6 @@ impl Miner {
             let sender = transaction.original_sender();
             // let balance = open_block.state.balance(&sender).unwrap();
             // let shard_data = (sender.clone(),balance.clone());
-            // #[cfg(feature = "shard")]
             // let params = self.params.read().clone();
             // let _block_shard = AggProof::author_shard(&params.author);
                 // params.author.to_low_u64_be().rem_euclid(1);
@@ -729,7 +979,6 @@ impl Miner {
                 }
                 // already have transaction - ignore
                 Err(Error(ErrorKind::Transaction(transaction::Error::AlreadyImported), _)) => {}
-                // #[cfg(feature = "shard")]
                 Err(Error(ErrorKind::Transaction(transaction::Error::SenderInvalidShard), _)) => {
                     debug!(target: "txn", "Transaction sent to wrong shard {} txn is {:?}", block_shard, _txn);
                     // not_allowed_transactions.insert(hash);
@@ -741,6 +990,14 @@ impl Miner {
                     }
                     // not_allowed_transactions.insert(hash);
                 }
+                Err(Error(ErrorKind::Transaction(transaction::Error::InvalidShardProof), _))
+                | Err(Error(
+                    ErrorKind::Transaction(transaction::Error::ShardProofUnverifiable(_)),
+                    _,
+                )) => {
+                    warn!(target: "miner", "Transaction {:?} carries a shard proof which does not verify. Marking as invalid.", hash);
+                    invalid_transactions.insert(hash);
+                }
                 Err(Error(ErrorKind::Transaction(transaction::Error::NotAllowed), _)) => {
                     not_allowed_transactions.insert(hash);
                     debug!(target: "miner", "Skipping non-allowed transaction for sender {:?}", hash);
@@ -753,32 +1010,25 @@ impl Miner {
                     invalid_transactions.insert(hash);
                 }
                 // imported ok
-                // #[cfg(feature = "shard")]
-                // _ =>tx_count += 1,
                   _ => {tx_count += 1;
                       let _h = open_block.state.data_hashmap_txn();
                       for _t in open_block.state.get_address_txn_vec(){
-                          self.proof_data.write().push((_t,_h.get(&_t).unwrap().clone()));
-                          AggProof::pushAddressCommit(_t.to_low_u64_be().rem_euclid(2u64.pow(16)),block_shard);
-                          proof_data_count += 1;
+                          // only keys with a leaf on the canonical chain can be proven
+                          if let Some(leaf) = self.shard_context.leaves().get(&_t) {
+                              self.proof_data.write().push((_t,_h.get(&_t).unwrap().clone()));
+                              let _ = self.shard_context.backend().push_address_commit(leaf, block_shard);
+                              proof_data_count += 1;
+                          }
                       }
                       // self.proof_data.write().push(shard_data);
                       // AggProof::pushAddressCommit(shard_data.0.to_low_u64_be().rem_euclid(2u64.pow(16)),block_shard);
                       },
             }
         }
-        AggProof::updateTree(block_shard);
-        let tx_new = self.channel_sender.clone();
+        let _ = self.shard_context.backend().update_tree(block_shard);
         if !self.proof_data.read().is_empty() {
-            thread::spawn(move || {
-                let a = match AggProof::agg(block_shard.clone()) {
-                    Ok(t) => t.0,
-                    _ => String::new(),
-                };
-                tx_new.lock().send(a).unwrap();
-            });
-        };
-        println!("proof_data looks like{:?} and txn_count looks like {}", self.proof_data.read(), tx_count);
+            self.proof_aggregator.request(block_shard);
+        }
         let elapsed = block_start.elapsed();
         debug!(target: "miner", "Pushed {} transactions in {} ms", tx_count, took_ms(&elapsed));
 
@@ -1283,6 +1533,11 @@ impl miner::MinerService for Miner {
         self.transaction_queue.all_transactions()
     }
 
+    fn foreign_shard_transactions(&self) -> Vec<Arc<VerifiedTransaction>> {
+        self.transaction_queue
+            .foreign_shard_transactions(self.shard_context.shard())
+    }
+
     fn queued_transaction_hashes(&self) -> Vec<H256> {
         self.transaction_queue.all_transaction_hashes()
     }
@@ -1348,6 +1603,7 @@ impl miner::MinerService for Miner {
                     .calculate_base_fee(&chain.best_block_header())
                     .unwrap_or_default(),
                 enforce_priority_fees: false,
+                shard: None,
             };
 
             if let Some(ref f) = filter {
@@ -1677,6 +1933,7 @@ impl miner::MinerService for Miner {
                 let engine = self.engine.clone();
                 let accounts = self.accounts.clone();
                 let service_transaction_checker = self.service_transaction_checker.clone();
+                let shard_context = self.shard_context.clone();
 
                 let cull = move |chain: &::client::Client| {
                     let client = PoolClient::new(
@@ -1686,6 +1943,7 @@ impl miner::MinerService for Miner {
                         &*engine,
                         &*accounts,
                         service_transaction_checker.as_ref(),
+                        &shard_context,
                     );
                     // t_nb 10.5 do culling
                     debug!(target: "txn", "culling the txn at 1");
@@ -1809,10 +2067,7 @@ mod tests {
     }
 
     fn miner() -> Miner {
-        let (tx,rx): (mpsc::Sender<String>, mpsc::Receiver<String>) = mpsc::channel();
         Miner::new(
-            tx.clone(),
-            rx.clone(),
             MinerOptions {
                 force_sealing: false,
                 reseal_on_external_tx: false,
@@ -1837,6 +2092,7 @@ mod tests {
                     no_early_reject: false,
                     allow_non_eoa_sender: false,
                 },
+                proof_aggregation_timeout: Duration::from_secs(2),
             },
             GasPricer::new_fixed(0u64.into()),
             &Spec::new_test(),
@@ -1981,6 +2237,53 @@ mod tests {
         );
     }
 
+    #[test]
+    #[cfg(feature = "shard")]
+    fn should_reject_external_transaction_with_unverifiable_shard_proof() {
+        use types::transaction::ShardTransactionTx;
+
+        // given
+        let client = TestBlockChainClient::default();
+        let miner = miner();
+        let keypair = Random.generate();
+        let transaction = TypedTransaction::ShardTransaction(ShardTransactionTx::new(
+            Transaction {
+                action: Action::Call(Address::from_low_u64_be(1)),
+                value: U256::zero(),
+                data: vec![],
+                gas: U256::from(100_000),
+                gas_price: U256::zero(),
+                nonce: U256::zero(),
+            },
+            0,
+            999u64,
+            0u64,
+            0u64,
+            keypair.address(),
+            Default::default(),
+            Vec::new(),
+            String::new(),
+        ))
+        .with_proof(
+            vec![(ShardKey::from_low_u64_be(1), U256::from(1))],
+            "zz".into(),
+        )
+        .sign(keypair.secret(), Some(TEST_CHAIN_ID));
+
+        // when
+        let res = miner
+            .import_external_transactions(&client, vec![transaction.into()])
+            .pop()
+            .unwrap();
+
+        // then
+        match res {
+            Err(transaction::Error::ShardProofUnverifiable(_)) => {}
+            other => panic!("Expected an unverifiable shard proof, got {:?}", other),
+        }
+        assert_eq!(miner.queue_status().status.transaction_count, 0);
+    }
+
     #[test]
     fn should_activate_eip_3607_according_to_spec() {
         // given
@@ -2022,10 +2325,7 @@ mod tests {
         let client = TestBlockChainClient::default();
         let mut local_accounts = ::std::collections::HashSet::new();
         local_accounts.insert(keypair.address());
-        let (tx,rx): (mpsc::Sender<String>, mpsc::Receiver<String>) = mpsc::channel();
         let miner = Miner::new(
-            tx.clone(),
-            rx.clone(),
             MinerOptions {
                 tx_queue_no_unfamiliar_locals: true,
                 ..miner().options
@@ -2126,10 +2426,7 @@ mod tests {
     fn should_prioritize_locals() {
         let client = TestBlockChainClient::default();
         let transaction = transaction();
-        let (tx,rx): (mpsc::Sender<String>, mpsc::Receiver<String>) = mpsc::channel();
         let miner = Miner::new(
-            tx.clone(),
-            rx.clone(),
             MinerOptions {
                 tx_queue_no_unfamiliar_locals: true, // should work even with this enabled
                 ..miner().options
@@ -21,6 +21,8 @@
 
 mod cache;
 mod miner;
+#[cfg(feature = "shard")]
+mod proof_aggregator;
 
 pub mod pool_client;
 #[cfg(feature = "stratum")]
@@ -245,6 +247,11 @@ pub trait MinerService: Send + Sync {
     /// Get a list of all transactions in the pool (some of them might not be ready for inclusion yet).
     fn queued_transactions(&self) -> Vec<Arc<VerifiedTransaction>>;
 
+    /// Get a list of transactions in the pool which have to be sealed on another shard.
+    ///
+    /// They are never included in blocks sealed by this node, but should still be propagated.
+    fn foreign_shard_transactions(&self) -> Vec<Arc<VerifiedTransaction>>;
+
     /// Get a list of all transaction hashes in the pool (some of them might not be ready for inclusion yet).
     fn queued_transaction_hashes(&self) -> Vec<H256>;
 
@@ -23,11 +23,14 @@ use ethcore_miner::{
     service_transaction_checker::ServiceTransactionChecker,
 };
 use ethereum_types::{Address, H256, U256};
+use hyperproofs::ShardContext;
 use types::{
     header::Header,
     transaction::{self, SignedTransaction, UnverifiedTransaction},
 };
 
+#[cfg(feature = "shard")]
+use block::verify_shard_proof;
 use call_contract::CallContract;
 use client::{Balance, BlockId, BlockInfo, Nonce, TransactionId};
 use engines::EthEngine;
@@ -125,6 +128,7 @@ pub struct PoolClient<'a, C: 'a> {
     accounts: &'a dyn LocalAccounts,
     best_block_header: Header,
     service_transaction_checker: Option<&'a ServiceTransactionChecker>,
+    shard_context: &'a ShardContext,
 }
 
 impl<'a, C: 'a> Clone for PoolClient<'a, C> {
@@ -137,6 +141,7 @@ impl<'a, C: 'a> Clone for PoolClient<'a, C> {
             accounts: self.accounts.clone(),
             best_block_header: self.best_block_header.clone(),
             service_transaction_checker: self.service_transaction_checker.clone(),
+            shard_context: self.shard_context,
         }
     }
 }
@@ -145,7 +150,8 @@ impl<'a, C: 'a> PoolClient<'a, C>
 where
     C: BlockInfo + CallContract,
 {
-    /// Creates new client given chain, nonce cache, accounts and service transaction verifier.
+    /// Creates new client given chain, nonce cache, accounts, service transaction verifier
+    /// and shard context.
     pub fn new(
         chain: &'a C,
         cached_nonces: &'a Cache<Address, U256>,
@@ -153,6 +159,7 @@ where
         engine: &'a dyn EthEngine,
         accounts: &'a dyn LocalAccounts,
         service_transaction_checker: Option<&'a ServiceTransactionChecker>,
+        shard_context: &'a ShardContext,
     ) -> Self {
         let best_block_header = chain.best_block_header();
         PoolClient {
@@ -163,6 +170,7 @@ where
             accounts,
             best_block_header,
             service_transaction_checker,
+            shard_context,
         }
     }
 
@@ -219,6 +227,22 @@ where
         self.engine
             .machine()
             .verify_transaction(&tx, &self.best_block_header, self.chain)?;
+        self.engine
+            .verify_shard_transaction(&tx, &self.best_block_header)?;
+        // a proof which does not verify now is not retried on every reseal
+        #[cfg(feature = "shard")]
+        verify_shard_proof(self.shard_context, &tx)?;
+        // the shard of a plain shard transaction is not signed, it has to be its sender's
+        #[cfg(feature = "shard")]
+        {
+            let shard = self.shard_context.address_shard(&tx.original_sender());
+            if !tx.is_engine_shard_transaction()
+                && !tx.match_shard(999u64)
+                && !tx.match_shard(shard)
+            {
+                return Err(transaction::Error::SenderInvalidShard);
+            }
+        }
         Ok(tx)
     }
 
@@ -256,6 +280,10 @@ where
         let number = self.chain.best_block_header().number();
         self.engine.decode_transaction(transaction, number)
     }
+
+    fn address_shard(&self, address: &Address) -> u64 {
+        self.shard_context.address_shard(address)
+    }
 }
 
 impl<'a, C: 'a> NonceClient for PoolClient<'a, C>
@@ -0,0 +1,177 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Background aggregation of shard proofs for block authoring.
+//!
+//! Proofs are aggregated on a dedicated worker thread so that a slow or
+//! crashed aggregation never stalls block preparation. Every request gets a
+//! new generation; results of older generations are stale and dropped.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use hyperproofs::{ProofOutcome, ShardContext};
+use parking_lot::Mutex;
+
+/// Aggregated proof of a generation, `None` if aggregation failed.
+type Aggregated = (u64, Option<String>);
+
+/// Aggregates proofs of this node's shard on a background thread.
+pub struct ProofAggregator {
+    requests: Mutex<mpsc::Sender<(u64, u64)>>,
+    results: Mutex<mpsc::Receiver<Aggregated>>,
+    generation: Arc<AtomicU64>,
+    in_flight: AtomicBool,
+    shard_context: Arc<ShardContext>,
+}
+
+impl ProofAggregator {
+    /// Spawn the aggregation worker for `shard_context`.
+    pub fn new(shard_context: Arc<ShardContext>) -> Self {
+        let (request_tx, request_rx) = mpsc::channel::<(u64, u64)>();
+        let (result_tx, result_rx) = mpsc::channel::<Aggregated>();
+        let generation = Arc::new(AtomicU64::new(0));
+
+        let worker_context = shard_context.clone();
+        let worker_generation = generation.clone();
+        let spawned = thread::Builder::new()
+            .name("proof-aggregator".into())
+            .spawn(move || {
+                for (request, shard) in request_rx {
+                    // a newer request is already queued, skip the work
+                    if request != worker_generation.load(Ordering::SeqCst) {
+                        continue;
+                    }
+                    let proof = match worker_context.aggregate(shard) {
+                        Ok((proof, _)) => Some(proof),
+                        Err(e) => {
+                            warn!(target: "miner", "Proof aggregation for shard {} failed: {}", shard, e);
+                            None
+                        }
+                    };
+                    if result_tx.send((request, proof)).is_err() {
+                        break;
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            warn!(target: "miner", "Failed to spawn the proof aggregation worker: {}", e);
+        }
+
+        ProofAggregator {
+            requests: Mutex::new(request_tx),
+            results: Mutex::new(result_rx),
+            generation,
+            in_flight: AtomicBool::new(false),
+            shard_context,
+        }
+    }
+
+    /// Start aggregating a proof for `shard`. Any proof still being
+    /// aggregated becomes stale.
+    pub fn request(&self, shard: u64) {
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        match self.requests.lock().send((generation, shard)) {
+            Ok(()) => self.in_flight.store(true, Ordering::SeqCst),
+            Err(_) => {
+                warn!(target: "miner", "Proof aggregation worker is gone, sealing without proofs.");
+                self.in_flight.store(false, Ordering::SeqCst);
+            }
+        }
+    }
+
+    /// Take the proof of the latest request, waiting at most `timeout` for it.
+    ///
+    /// Returns `None` if the proof did not arrive in time or aggregation
+    /// failed. The outcome is logged and counted in the shard counters.
+    pub fn take(&self, timeout: Duration) -> Option<String> {
+        if !self.in_flight.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        let deadline = Instant::now() + timeout;
+        let results = self.results.lock();
+        let (outcome, proof) = loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match results.recv_timeout(remaining) {
+                Ok((generation, proof)) => {
+                    if generation != self.generation.load(Ordering::SeqCst) {
+                        trace!(target: "miner", "Dropping stale proof of request {}", generation);
+                        continue;
+                    }
+                    self.in_flight.store(false, Ordering::SeqCst);
+                    match proof {
+                        Some(proof) => break (ProofOutcome::Ready, Some(proof)),
+                        None => break (ProofOutcome::Failed, None),
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => break (ProofOutcome::TimedOut, None),
+                Err(mpsc::RecvTimeoutError::Disconnected) => {
+                    self.in_flight.store(false, Ordering::SeqCst);
+                    break (ProofOutcome::Failed, None);
+                }
+            }
+        };
+
+        match outcome {
+            ProofOutcome::Ready => {
+                debug!(target: "miner", "Aggregated proof ready, sealing it into the block.")
+            }
+            ProofOutcome::TimedOut => {
+                warn!(target: "miner", "Proof aggregation did not finish within {} ms, sealing without proof.", timeout.as_millis())
+            }
+            ProofOutcome::Failed => {
+                warn!(target: "miner", "Proof aggregation failed, sealing without proof.")
+            }
+        }
+        self.shard_context.counters().incr_proof_outcome(outcome);
+        proof
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_proof_of_latest_request() {
+        let context = Arc::new(ShardContext::default());
+        context.backend().init(0).unwrap();
+        let aggregator = ProofAggregator::new(context.clone());
+
+        aggregator.request(0);
+        aggregator.request(0);
+
+        assert!(aggregator.take(Duration::from_secs(10)).is_some());
+        assert_eq!(context.counters().proof_outcome(ProofOutcome::Ready), 1);
+        assert_eq!(context.counters().proof_outcome(ProofOutcome::TimedOut), 0);
+    }
+
+    #[test]
+    fn nothing_to_take_without_request() {
+        let context = Arc::new(ShardContext::default());
+        let aggregator = ProofAggregator::new(context.clone());
+
+        assert!(aggregator.take(Duration::from_secs(10)).is_none());
+        assert_eq!(context.counters().proof_outcome(ProofOutcome::TimedOut), 0);
+    }
+}
@@ -0,0 +1,110 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Placement of accounts on shards read from a directory contract.
+
+use std::{cell::Cell, collections::HashMap, sync::Weak};
+
+use ethabi::FunctionOutputDecoder;
+use ethereum_types::Address;
+use hyperproofs::{ModuloPlacement, PlacementConfig, ShardPlacement};
+use parking_lot::RwLock;
+
+use client::{BlockId, EngineClient};
+use spec::CommonParams;
+
+use_contract!(shard_directory, "res/contracts/shard_directory.json");
+
+thread_local! {
+    /// Set while the directory contract is being called on this thread. The
+    /// call itself executes code which needs placements, these fall back to
+    /// modulo instead of calling the directory again.
+    static RESOLVING: Cell<bool> = Cell::new(false);
+}
+
+/// Placement that asks a directory contract for the shard of an address.
+///
+/// Addresses the directory has not assigned, or all addresses while no client
+/// is registered, are placed by modulo. Assignments are read once and kept, so
+/// the directory is expected to assign an address before it holds any state.
+pub struct ShardDirectory {
+    contract_address: Address,
+    fallback: ModuloPlacement,
+    client: RwLock<Option<Weak<dyn EngineClient>>>,
+    assigned: RwLock<HashMap<Address, u64>>,
+}
+
+impl ShardDirectory {
+    /// Create a new instance if the params place accounts through a directory.
+    pub fn from_params(params: &CommonParams) -> Option<ShardDirectory> {
+        match params.shard_placement {
+            PlacementConfig::Directory(contract_address) => Some(ShardDirectory {
+                contract_address,
+                fallback: ModuloPlacement::new(params.shard_count),
+                client: RwLock::new(None),
+                assigned: RwLock::new(HashMap::new()),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Set the client used to call the directory contract.
+    pub fn register_client(&self, client: Weak<dyn EngineClient>) {
+        *self.client.write() = Some(client);
+    }
+
+    fn lookup(&self, address: &Address) -> Option<u64> {
+        let client = self.client.read().as_ref().and_then(Weak::upgrade)?;
+        let client = client.as_full_client()?;
+        let (data, decoder) = shard_directory::functions::shard_of::call(*address);
+        let (assigned, shard) = client
+            .call_contract(BlockId::Latest, self.contract_address, data)
+            .and_then(|value| decoder.decode(&value).map_err(|e| e.to_string()))
+            .map_err(|e| {
+                warn!(target: "shard", "Error calling shard directory contract: {}", e);
+            })
+            .ok()?;
+        if assigned {
+            Some(shard.low_u64())
+        } else {
+            None
+        }
+    }
+}
+
+impl ShardPlacement for ShardDirectory {
+    fn shard_of(&self, address: &Address) -> u64 {
+        if let Some(shard) = self.assigned.read().get(address) {
+            return *shard;
+        }
+        if *address == self.contract_address || RESOLVING.with(Cell::get) {
+            return self.fallback.shard_of(address);
+        }
+
+        RESOLVING.with(|resolving| resolving.set(true));
+        let shard = self.lookup(address);
+        RESOLVING.with(|resolving| resolving.set(false));
+
+        match shard {
+            Some(shard) => {
+                trace!(target: "shard", "Directory places {:?} on shard {}", address, shard);
+                self.assigned.write().insert(*address, shard);
+                shard
+            }
+            None => self.fallback.shard_of(address),
+        }
+    }
+}
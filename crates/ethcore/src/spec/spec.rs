@@ -33,7 +33,7 @@ use rlp::{Rlp, RlpStream};
 use rustc_hex::FromHex;
 use types::{header::Header, BlockNumber};
 use vm::{AccessList, ActionParams, ActionValue, CallType, EnvInfo, ParamsType};
-use hyperproofs::AggProof;
+use hyperproofs::{self, AggProof, PlacementConfig, ShardConfig, ShardContext};
 use builtin::Builtin;
 use engines::{
     AuthorityRound, BasicAuthority, Clique, EthEngine, InstantSeal, InstantSealParams, NullEngine,
@@ -53,6 +53,9 @@ pub use ethash::OptimizeFor;
 
 const MAX_TRANSACTION_SIZE: usize = 300 * 1024;
 
+/// Deepest shard vector supported, leaf indexes have to fit in a `u64`.
+const MAX_SHARD_VECTOR_DEPTH: u8 = 63;
+
 // helper for formatting errors.
 fn fmt_err<F: ::std::fmt::Display>(f: F) -> String {
     format!("Spec json is invalid: {}", f)
@@ -195,6 +198,21 @@ pub struct CommonParams {
     pub eip1559_fee_collector_transition: BlockNumber,
     /// Block at which zero gas price transactions start being checked with Certifier contract.
     pub validate_service_transactions_transition: BlockNumber,
+    /// Number of shards in the network.
+    pub shard_count: u64,
+    /// Number of blocks in a shard commit round.
+    pub shard_round_length: u64,
+    /// Block authors and the shard each of them seals for.
+    pub shard_authors: BTreeMap<Address, u64>,
+    /// Depth of the shard vectors, which hold `2^shard_vector_depth` leaves.
+    pub shard_vector_depth: u8,
+    /// Policy deciding which shard holds the state of an address.
+    pub shard_placement: PlacementConfig,
+    /// Hops a cross-shard call may take before it is finalized as reverted.
+    pub shard_max_hops: u64,
+    /// Contracts whose storage is kept on their home shard instead of the shard of
+    /// the account they are executed for.
+    pub shard_shared_storage: BTreeSet<Address>,
 }
 
 impl CommonParams {
@@ -309,6 +327,19 @@ impl CommonParams {
         }
     }
 
+    /// Shard topology declared by these parameters.
+    pub fn shard_config(&self) -> ShardConfig {
+        ShardConfig {
+            shard_count: self.shard_count.max(1),
+            round_length: self.shard_round_length.max(1),
+            authors: self.shard_authors.clone(),
+            vector_depth: self.shard_vector_depth.max(1).min(MAX_SHARD_VECTOR_DEPTH),
+            placement: self.shard_placement.clone(),
+            max_hops: self.shard_max_hops.max(1),
+            shared_storage: self.shard_shared_storage.clone(),
+        }
+    }
+
     /// Return Some if the current parameters contain a bugfix hard fork not on block 0.
     pub fn nonzero_bugfix_hard_fork(&self) -> Option<&str> {
         if self.eip155_transition != 0 {
@@ -327,8 +358,23 @@ impl CommonParams {
     }
 }
 
+fn placement_config(placement: ethjson::spec::ShardPlacement) -> PlacementConfig {
+    use ethjson::spec::ShardPlacement;
+    match placement {
+        ShardPlacement::Modulo => PlacementConfig::Modulo,
+        ShardPlacement::PrefixRanges(ranges) => PlacementConfig::PrefixRanges(
+            ranges
+                .into_iter()
+                .map(|(start, shard)| (start.into(), shard.into()))
+                .collect(),
+        ),
+        ShardPlacement::Directory(address) => PlacementConfig::Directory(address.into()),
+    }
+}
+
 impl From<ethjson::spec::Params> for CommonParams {
     fn from(p: ethjson::spec::Params) -> Self {
+        let shard_count = p.shard_count.map_or(1, Into::into);
         CommonParams {
             account_start_nonce: p.account_start_nonce.map_or_else(U256::zero, Into::into),
             maximum_extra_data_size: p.maximum_extra_data_size.into(),
@@ -483,6 +529,31 @@ impl From<ethjson::spec::Params> for CommonParams {
             validate_service_transactions_transition: p
                 .validate_service_transactions_transition
                 .map_or_else(BlockNumber::max_value, Into::into),
+            shard_count,
+            shard_round_length: p
+                .shard_round_length
+                .map_or(shard_count, Into::into),
+            shard_authors: p
+                .shard_authors
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(author, shard)| (author.into(), shard.into()))
+                .collect(),
+            shard_vector_depth: p
+                .shard_vector_depth
+                .map_or(hyperproofs::DEFAULT_VECTOR_DEPTH, Into::into),
+            shard_placement: p
+                .shard_placement
+                .map_or_else(Default::default, placement_config),
+            shard_max_hops: p
+                .shard_max_hops
+                .map_or(hyperproofs::DEFAULT_MAX_HOPS, Into::into),
+            shard_shared_storage: p
+                .shard_shared_storage
+                .unwrap_or_default()
+                .into_iter()
+                .map(Into::into)
+                .collect(),
         }
     }
 }
@@ -831,11 +902,6 @@ impl Spec {
             let mut t = factories.trie.create(db.as_hash_db_mut(), &mut root);
 
             for (address, account) in self.genesis_state.get().iter() {
-                if AggProof::get_genesis_commit() == 0u64 {
-                    println!("inserting address {} and val {:?}", address, account);
-                    AggProof::pushAddressDelta(address.to_low_u64_be().rem_euclid(2u64.pow(16)),account.balance().to_string(),address.to_low_u64_be().rem_euclid(AggProof::shard_count()));
-                    debug!(target:"txn", "increasing {} from address {} in shard {}", account.balance(), address , address.to_low_u64_be().rem_euclid(AggProof::shard_count()));
-                }
                 t.insert(address.as_bytes(), &account.rlp())?;
             }
         }
@@ -1015,6 +1081,21 @@ impl Spec {
         Ok(())
     }
 
+    /// Reset the shard vectors of `context` and queue the genesis balances as deltas.
+    pub fn init_shard_context(&self, context: &ShardContext) -> Result<(), hyperproofs::Error> {
+        let backend = context.backend();
+        backend.init(0u64)?;
+        for (address, account) in self.genesis_state.get().iter() {
+            debug!(target:"txn", "increasing {} from address {} in shard {}", account.balance(), address, context.address_shard(address));
+            backend.push_address_delta(
+                context.leaves().index(&AggProof::balance_key(address))?,
+                &account.balance().to_string(),
+                context.address_shard(address),
+            )?;
+        }
+        Ok(())
+    }
+
     /// Return genesis state as Plain old data.
     pub fn genesis_state(&self) -> &PodState {
         &self.genesis_state
@@ -1180,6 +1261,23 @@ impl Spec {
         load_bundled!("test/authority_round")
     }
 
+    /// Create a new Spec with AuthorityRound consensus sealing four shards.
+    /// Accounts with secrets keccak("0") to keccak("3") are the validators of shards 0 to 3,
+    /// accounts with secrets keccak("user0") to keccak("user7") are funded, two on every shard.
+    /// The contract at 0x100 reads the balance of every address in its call data, one
+    /// 32-byte word each.
+    #[cfg(any(test, feature = "test-helpers"))]
+    pub fn new_test_round_shards() -> Self {
+        load_bundled!("test/authority_round_shards")
+    }
+
+    /// Create a new Spec like `new_test_round_shards` whose cross-shard calls reach the hop
+    /// limit after three hops.
+    #[cfg(any(test, feature = "test-helpers"))]
+    pub fn new_test_round_shards_hop_limit() -> Self {
+        load_bundled!("test/authority_round_shards_hop_limit")
+    }
+
     /// Create a new Spec with AuthorityRound consensus which does internal sealing (not
     /// requiring work) with empty step messages enabled.
     /// Accounts with secrets keccak("0") and keccak("1") are the validators.
@@ -44,8 +44,8 @@ use types::{
     transaction::SignedTransaction,
 };
 
-use hyperproofs::AggProof;
-use vm::EnvInfo;
+use hyperproofs::{AggProof, PendingLeaves, ShardContext, ShardKey};
+use vm::{EnvInfo, SuspendedFrame};
 
 use bytes::Bytes;
 use ethereum_types::{Address, H256, U256};
@@ -318,20 +318,24 @@ pub struct State<B> {
     factories: Factories,
     // #[cfg(feature = "shard")]
     // stores latest data modified for the last 10 rounds. 11th hashmap is popped every round and new hashmap is pushed on top.
-    data_hash_map_global: RefCell<Vec<HashMap<Address, U256>>>,
+    data_hash_map_global: RefCell<Vec<HashMap<ShardKey, U256>>>,
     // this keeps track of the data at the beginning of each round. Key, val is pushed whenever SStore occurs and no key exists in hashmap
-    data_hash_map_round_beginning: RefCell<HashMap<Address,U256>>,
+    data_hash_map_round_beginning: RefCell<HashMap<ShardKey,U256>>,
     //  this is the cache related to execution (whenever SSTORE occurs for incomplete transaction).
-    hash_map_cache: RefCell<HashMap<Address,U256>>,
+    hash_map_cache: RefCell<HashMap<ShardKey,U256>>,
     //the exact hash_map of a transaction.
-    data_hash_map_txn: RefCell<HashMap<Address,U256>>,
+    data_hash_map_txn: RefCell<HashMap<ShardKey,U256>>,
     // All the incomplete txn with next_shard equal to my shard gets collected here.
     incomplete_txn_vec: RefCell<Vec<SignedTransaction>>,
-    address_txn_vec: RefCell<Vec<Address>>,
-    temp_sstore_val: RefCell<Vec<(Address, Address, H256, U256)>>,
-    temp_sstore_delta: RefCell<Vec<(u64, String, u64)>>,
+    address_txn_vec: RefCell<Vec<ShardKey>>,
+    temp_sstore_val: RefCell<Vec<(ShardKey, Address, H256, U256)>>,
+    temp_sstore_delta: RefCell<Vec<(ShardKey, String, u64)>>,
+    // vector leaves allocated by the block being executed
+    leaves: RefCell<PendingLeaves>,
     incr_bal_round: RefCell<HashMap<Address,U256>>,
     next_shard: RefCell<u64>,
+    // frame of the call the executing transaction resumes from, or stopped in
+    suspended_frame: RefCell<Option<SuspendedFrame>>,
     complete: RefCell<Option<bool>>,
     mined: RefCell<Option<bool>>,
     is_create_txn: RefCell<bool>,
@@ -414,8 +418,10 @@ impl<B: Backend> State<B> {
             address_txn_vec: RefCell::new(Vec::new()),
             temp_sstore_val: RefCell::new(Vec::new()),
             temp_sstore_delta: RefCell::new(Vec::new()),
+            leaves: RefCell::new(PendingLeaves::default()),
             incr_bal_round: RefCell::new(HashMap::new()),
             next_shard: RefCell::new(999u64),
+            suspended_frame: RefCell::new(None),
             complete: RefCell::new(None::<bool>),
             mined: RefCell::new(None::<bool>),
             is_create_txn: RefCell::new(false),
@@ -449,8 +455,10 @@ impl<B: Backend> State<B> {
             address_txn_vec: RefCell::new(Vec::new()),
             temp_sstore_val: RefCell::new(Vec::new()),
             temp_sstore_delta: RefCell::new(Vec::new()),
+            leaves: RefCell::new(PendingLeaves::default()),
             incr_bal_round: RefCell::new(HashMap::new()),
             next_shard: RefCell::new(999u64),
+            suspended_frame: RefCell::new(None),
             complete: RefCell::new(None::<bool>),
             mined: RefCell::new(None::<bool>),
             is_create_txn: RefCell::new(false),
@@ -494,18 +502,18 @@ impl<B: Backend> State<B> {
     }
     pub fn clear_temp_sstore_val(&mut self) {self.temp_sstore_val.get_mut().clear();}
     pub fn clear_temp_sstore_delta(&mut self) {self.temp_sstore_delta.get_mut().clear();}
-    pub fn push_temp_sstore_val(&mut self, k:Address, code: Address, ah: H256, v: U256) {self.temp_sstore_val.borrow_mut().push((k, code,ah,v));}
-    pub fn get_temp_sstore_val(&self)-> Vec<(Address, Address, H256, U256)> {
+    pub fn push_temp_sstore_val(&mut self, k:ShardKey, code: Address, ah: H256, v: U256) {self.temp_sstore_val.borrow_mut().push((k, code,ah,v));}
+    pub fn get_temp_sstore_val(&self)-> Vec<(ShardKey, Address, H256, U256)> {
         self.temp_sstore_val.borrow().clone()
     }
-    pub fn push_temp_sstore_delta(&mut self, a: u64, s: String, sh: u64) {self.temp_sstore_delta.borrow_mut().push((a,s,sh));}
-    pub fn get_temp_sstore_delta(&self) -> Vec<(u64, String,u64)> {
+    pub fn push_temp_sstore_delta(&mut self, k: ShardKey, s: String, sh: u64) {self.temp_sstore_delta.borrow_mut().push((k,s,sh));}
+    pub fn get_temp_sstore_delta(&self) -> Vec<(ShardKey, String,u64)> {
         self.temp_sstore_delta.borrow().clone()
     }
-    pub fn push_address_txn_vec(&mut self, a:Address){
+    pub fn push_address_txn_vec(&mut self, a:ShardKey){
         self.address_txn_vec.borrow_mut().push(a);
     }
-    pub fn get_address_txn_vec(&self)-> Vec<Address>{
+    pub fn get_address_txn_vec(&self)-> Vec<ShardKey>{
         self.address_txn_vec.borrow().clone()
     }
     pub fn push_incomplete_txn(&mut self, t: SignedTransaction){
@@ -514,34 +522,34 @@ impl<B: Backend> State<B> {
     pub fn set_incomplete_txn(&mut self, t: Vec<SignedTransaction>){
         self.incomplete_txn_vec = RefCell::new(t);
     }
-    pub fn set_hash_map_global(&mut self, h: Vec<HashMap<Address, U256>>){
+    pub fn set_hash_map_global(&mut self, h: Vec<HashMap<ShardKey, U256>>){
         self.data_hash_map_global = RefCell::new(h);
     }
-    pub fn set_hash_map_round_beginning(&mut self, h: HashMap<Address, U256>){
+    pub fn set_hash_map_round_beginning(&mut self, h: HashMap<ShardKey, U256>){
         self.data_hash_map_round_beginning = RefCell::new(h);
     }
     pub fn set_incr_bal_round(&mut self, h: HashMap<Address, U256>){
         self.incr_bal_round = RefCell::new(h);
     }
-    pub fn hash_map_cache_storage_at(& self, key:&Address) -> (U256, bool) {
+    pub fn hash_map_cache_storage_at(& self, key:&ShardKey) -> (U256, bool) {
         match self.hash_map_cache.borrow().get(key){
             Some(val) => (val.clone(), true),
             None => (U256::zero(), false),
         }
     }
-    pub fn data_hash_map_txn_storage_at(& self, key:&Address) -> (U256, bool) {
+    pub fn data_hash_map_txn_storage_at(& self, key:&ShardKey) -> (U256, bool) {
         match self.data_hash_map_txn.borrow().get(key){
             Some(val) => (val.clone(), true),
             None => (U256::zero(), false),
         }
     }
-    pub fn data_hashmap_txn(& self)->HashMap<Address, U256>{
+    pub fn data_hashmap_txn(& self)->HashMap<ShardKey, U256>{
         self.data_hash_map_txn.borrow().clone()
     }
-    pub fn export_data_hashmap_global(&self)->Vec<HashMap<Address, U256>>{
+    pub fn export_data_hashmap_global(&self)->Vec<HashMap<ShardKey, U256>>{
         self.data_hash_map_global.borrow().clone()
     }
-    pub fn export_data_hashmap_round_beginning(&self)->HashMap<Address, U256>{
+    pub fn export_data_hashmap_round_beginning(&self)->HashMap<ShardKey, U256>{
         self.data_hash_map_round_beginning.borrow().clone()
     }
     pub fn export_incr_bal_round(&self)->HashMap<Address, U256>{
@@ -550,11 +558,19 @@ impl<B: Backend> State<B> {
     pub fn export_incomplete_txn(&self)->Vec<SignedTransaction>{
         self.incomplete_txn_vec.borrow().clone()
     }
+    /// Start executing a block on top of the leaf allocations of its branch.
+    pub fn set_pending_leaves(&mut self, leaves: PendingLeaves) {
+        self.leaves = RefCell::new(leaves);
+    }
+    /// Vector leaves allocated by the executed block, in allocation order.
+    pub fn export_leaves(&self) -> Vec<(ShardKey, u64)> {
+        self.leaves.borrow().fresh().to_vec()
+    }
     pub fn clear_data_hashmap_txn(&mut self){
         self.data_hash_map_txn.get_mut().clear();
     }
 
-    pub fn global_hash_map_storage_at(&self, key:&Address) -> (U256, bool) {
+    pub fn global_hash_map_storage_at(&self, key:&ShardKey) -> (U256, bool) {
         let len = self.data_hash_map_global.borrow().len();
         let mut val = (U256::zero(), false);
         for i in (0..len).rev(){
@@ -566,7 +582,7 @@ impl<B: Backend> State<B> {
         }
     val
     }
-    pub fn global_hash_map_storage_at_one_round(&self, key:&Address) -> (U256, bool) {
+    pub fn global_hash_map_storage_at_one_round(&self, key:&ShardKey) -> (U256, bool) {
         let len = self.data_hash_map_global.borrow().len();
         let mut val = (U256::zero(), false);
         if len > 0 {
@@ -577,22 +593,22 @@ impl<B: Backend> State<B> {
         }
         val
     }
-    pub fn hash_map_beginning_storage_at(&self, key:&Address) -> (U256, bool) {
+    pub fn hash_map_beginning_storage_at(&self, key:&ShardKey) -> (U256, bool) {
         match self.data_hash_map_round_beginning.borrow().get(key){
             Some(val) => (val.clone(), true),
             None => (U256::zero(), false),
         }
     }
-    pub fn hash_map_cache_insert(&self, key: Address, val: U256){
+    pub fn hash_map_cache_insert(&self, key: ShardKey, val: U256){
         self.hash_map_cache.borrow_mut().insert(key,val);
     }
-    pub fn hash_map_beginning_insert(&self, key: Address, val: U256) {
+    pub fn hash_map_beginning_insert(&self, key: ShardKey, val: U256) {
         self.data_hash_map_round_beginning.borrow_mut().insert(key,val);
     }
-    pub fn hash_map_txn_insert(&self, key: Address, val: U256){
+    pub fn hash_map_txn_insert(&self, key: ShardKey, val: U256){
         self.data_hash_map_txn.borrow_mut().insert(key, val);
     }
-    pub fn global_hash_map_insert(&self, key: Address, val: U256){
+    pub fn global_hash_map_insert(&self, key: ShardKey, val: U256){
         let len = self.data_hash_map_global.borrow().len();
         assert!(len > 0);
         self.data_hash_map_global.borrow_mut()[len-1].insert(key, val);
@@ -615,6 +631,12 @@ impl<B: Backend> State<B> {
     pub fn get_next_shard(&self)-> u64{
         self.next_shard.borrow().clone()
     }
+    pub fn set_suspended_frame(&mut self, frame: Option<SuspendedFrame>){
+        self.suspended_frame = RefCell::new(frame);
+    }
+    pub fn take_suspended_frame(&mut self) -> Option<SuspendedFrame>{
+        self.suspended_frame.get_mut().take()
+    }
     pub fn get_mined_status(&self)-> Option<bool>{
         self.mined.borrow().clone()
     }
@@ -628,6 +650,11 @@ impl<B: Backend> State<B> {
         self.factories.vm.clone()
     }
 
+    /// Shard state of the node executing on this state.
+    pub fn shard_context(&self) -> &ShardContext {
+        &self.factories.shard
+    }
+
     /// Create a recoverable checkpoint of this state. Return the checkpoint index.
     pub fn checkpoint(&mut self) -> usize {
         let checkpoints = self.checkpoints.get_mut();
@@ -1026,20 +1053,47 @@ impl<B: Backend> State<B> {
     }
 
     /// Add `incr` to the balance of account `a`.
+    #[cfg(not(feature = "shard"))]
+    pub fn add_balance(
+        &mut self,
+        a: &Address,
+        incr: &U256,
+        cleanup_mode: CleanupMode,
+    ) -> TrieResult<()> {
+        trace!(target: "state", "add_balance({}, {}): {}", a, incr, self.balance(a)?);
+        let is_value_transfer = !incr.is_zero();
+        if is_value_transfer || (cleanup_mode == CleanupMode::ForceCreate && !self.exists(a)?) {
+            self.require(a, false)?.add_balance(incr);
+        } else if let CleanupMode::TrackTouched(set) = cleanup_mode {
+            if self.exists(a)? {
+                set.insert(*a);
+                self.touch(a)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Add `incr` to the balance of account `a`.
+    ///
+    /// Only the home shard of `a` changes its trie, every shard queues the delta.
+    #[cfg(feature = "shard")]
     pub fn add_balance(
         &mut self,
         a: &Address,
         incr: &U256,
         cleanup_mode: CleanupMode,
     ) -> TrieResult<()> {
-        if a.to_low_u64_be().rem_euclid(AggProof::shard_count()) == AggProof::get_shard() {
-            if !self.hash_map_beginning_storage_at(a).1 {
-                self.hash_map_beginning_insert(a.clone(), self.balance(&a).unwrap());
+        let shard = self.factories.shard.clone();
+        let address_shard = shard.address_shard(a);
+        let key = AggProof::balance_key(a);
+        if address_shard == shard.shard() {
+            if !self.hash_map_beginning_storage_at(&key).1 {
+                self.hash_map_beginning_insert(key, self.balance(&a).unwrap());
             }
             trace!(target: "state", "add_balance({}, {}): {}", a, incr, self.balance(a)?);
             let is_value_transfer = !incr.is_zero();
             if is_value_transfer || (cleanup_mode == CleanupMode::ForceCreate && !self.exists(a)?) {
-                AggProof::incr_bal_write_count(1u64);
+                shard.counters().incr_bal_write(1u64);
                 self.require(a, false)?.add_balance(incr);
             } else if let CleanupMode::TrackTouched(set) = cleanup_mode {
                 if self.exists(a)? {
@@ -1048,10 +1102,9 @@ impl<B: Backend> State<B> {
                 }
             }
         }
-        AggProof::pushAddressDelta(a.to_low_u64_be().rem_euclid(2u64.pow(16)), incr.to_string(), a.to_low_u64_be().rem_euclid(AggProof::shard_count()));
-        debug!(target:"txn", "increasing {} from address {} in shard {}", incr, a , a.to_low_u64_be().rem_euclid(AggProof::shard_count()));
+        self.push_shard_delta(&key, &incr.to_string(), address_shard)?;
+        debug!(target:"txn", "increasing {} from address {} in shard {}", incr, a , address_shard);
         if !incr.is_zero() {
-            println!("increasing {} from address {} in shard {}", incr, a , a.to_low_u64_be().rem_euclid(AggProof::shard_count()));
             // let mut balance = self.data_hash_map_txn_storage_at(a);
             // if balance.1 {
             //     let temp_val = self.global_hash_map_storage_at(a);
@@ -1074,17 +1127,64 @@ impl<B: Backend> State<B> {
         Ok(())
     }
 
+    /// Queue a signed `delta` of the balance or storage `key` of `address_shard` for the
+    /// next commit round, allocating a vector leaf to the executing block if needed.
+    ///
+    /// Executions outside of a block, such as calls, leave the shard vectors alone.
+    pub fn push_shard_delta(&self, key: &ShardKey, delta: &str, address_shard: u64) -> TrieResult<()> {
+        if self.get_mined_status().is_none() {
+            return Ok(());
+        }
+        let shard = &self.factories.shard;
+        shard
+            .leaves()
+            .allocate(key, &mut self.leaves.borrow_mut())
+            .and_then(|leaf| shard.backend().push_address_delta(leaf, delta, address_shard))
+            .map(|_| ())
+            .map_err(|e| {
+                warn!(target: "state", "Delta of {:?} rejected by the shard backend: {}", key, e);
+                Box::new(TrieError::DecoderError(
+                    *key,
+                    rlp::DecoderError::Custom("Shard delta rejected"),
+                ))
+            })
+    }
+
+    /// Subtract `decr` from the balance of account `a`.
+    #[cfg(not(feature = "shard"))]
+    pub fn sub_balance(
+        &mut self,
+        a: &Address,
+        decr: &U256,
+        cleanup_mode: &mut CleanupMode,
+    ) -> TrieResult<()> {
+        trace!(target: "state", "sub_balance({}, {}): {}", a, decr, self.balance(a)?);
+        if !decr.is_zero() || !self.exists(a)? {
+            self.require(a, false)?.sub_balance(decr);
+        }
+        if let CleanupMode::TrackTouched(ref mut set) = *cleanup_mode {
+            set.insert(*a);
+        }
+        Ok(())
+    }
+
     /// Subtract `decr` from the balance of account `a`.
+    ///
+    /// Only the home shard of `a` changes its trie, every shard queues the delta.
+    #[cfg(feature = "shard")]
     pub fn sub_balance(
         &mut self,
         a: &Address,
         decr: &U256,
         cleanup_mode: &mut CleanupMode,
     ) -> TrieResult<()> {
-        if a.to_low_u64_be().rem_euclid(AggProof::shard_count()) == AggProof::get_shard() {
+        let shard = self.factories.shard.clone();
+        let address_shard = shard.address_shard(a);
+        let key = AggProof::balance_key(a);
+        if address_shard == shard.shard() {
             trace!(target: "state", "sub_balance({}, {}): {}", a, decr, self.balance(a)?);
             if !decr.is_zero() || !self.exists(a)? {
-                AggProof::incr_bal_write_count(1u64);
+                shard.counters().incr_bal_write(1u64);
                 self.require(a, false)?.sub_balance(decr);
             }
             if let CleanupMode::TrackTouched(ref mut set) = *cleanup_mode {
@@ -1095,32 +1195,82 @@ impl<B: Backend> State<B> {
         let mut neg = String::from("-");
         let val = decr.to_string();
         neg.push_str(&val);
-        AggProof::pushAddressDelta(a.to_low_u64_be().rem_euclid(2u64.pow(16)), neg.clone(), a.to_low_u64_be().rem_euclid(AggProof::shard_count()));
-        debug!(target: "txn","decreasing {} from address {} in shard {}", decr, a , a.to_low_u64_be().rem_euclid(AggProof::shard_count()));
+        self.push_shard_delta(&key, &neg, address_shard)?;
+        debug!(target: "txn","decreasing {} from address {} in shard {}", decr, a , address_shard);
         if !decr.is_zero() {
-            println!("decreasing {} from address {} in shard {}", decr, a , a.to_low_u64_be().rem_euclid(AggProof::shard_count()));
-            let mut balance = self.data_hash_map_txn_storage_at(a);
+            let mut balance = self.data_hash_map_txn_storage_at(&key);
             if balance.1 {
-                let temp_val = self.global_hash_map_storage_at_one_round(a);
+                let temp_val = self.global_hash_map_storage_at_one_round(&key);
                 if temp_val.1 {
                     balance = temp_val;
                 }
             } else {
                 if self.data_hash_map_global.borrow().len() > 0 {
-                    println!("the balance should exist");
+                    debug!(target: "txn", "missing round balance of {}", a);
                 }
             }
             let new_bal = balance.0.saturating_sub(decr.clone());
             if self.data_hash_map_global.borrow().len() > 0 {
-                self.global_hash_map_insert(a.clone(), new_bal);
-                if !self.hash_map_beginning_storage_at(a).1 {
-                    self.hash_map_beginning_insert(a.clone(), balance.0);
+                self.global_hash_map_insert(key, new_bal);
+                if !self.hash_map_beginning_storage_at(&key).1 {
+                    self.hash_map_beginning_insert(key, balance.0);
                 }
             }
         }
         Ok(())
     }
 
+    /// Take the budget the continuation hop `t` runs on out of the one its original sender
+    /// holds on the shard of the hop. The first hop of a call is paid from the balance.
+    pub fn claim_shard_budget(&self, t: &SignedTransaction, base_fee: Option<U256>) -> TrieResult<()> {
+        let budget = t.tx().gas.saturating_mul(t.effective_gas_price(base_fee));
+        if t.get_hop_count() == 0 || budget.is_zero() {
+            return Ok(());
+        }
+        let key = AggProof::budget_key(&t.original_sender());
+        self.push_shard_delta(&key, &format!("-{}", budget), t.shard_id())
+    }
+
+    /// Hand the budget left by the incomplete hop `t` over to the shard the call continues on.
+    pub fn hand_off_shard_budget(&self, t: &SignedTransaction, base_fee: Option<U256>) -> TrieResult<()> {
+        let left = t
+            .tx()
+            .gas
+            .saturating_sub(t.hop_gas_used())
+            .saturating_mul(t.effective_gas_price(base_fee));
+        if left.is_zero() {
+            return Ok(());
+        }
+        let key = AggProof::budget_key(&t.original_sender());
+        self.push_shard_delta(&key, &left.to_string(), t.get_next_shard())
+    }
+
+    /// Apply the gas accounting of the incomplete hop `t` sealed by `author` without
+    /// executing it: the hop is paid from the sender's balance or held budget, its author
+    /// earns the gas it used and what is left is handed over to the next shard.
+    pub fn settle_incomplete_hop(
+        &mut self,
+        t: &SignedTransaction,
+        author: &Address,
+        base_fee: Option<U256>,
+    ) -> TrieResult<()> {
+        let gas_price = t.effective_gas_price(base_fee);
+        if t.get_hop_count() != 0 {
+            self.claim_shard_budget(t, base_fee)?;
+        } else {
+            self.sub_balance(
+                &t.original_sender(),
+                &t.tx().gas.saturating_mul(gas_price),
+                &mut CleanupMode::NoEmpty,
+            )?;
+        }
+        let fees = t.hop_gas_used().saturating_mul(gas_price);
+        if !fees.is_zero() {
+            self.add_balance(author, &fees, CleanupMode::NoEmpty)?;
+        }
+        self.hand_off_shard_budget(t, base_fee)
+    }
+
     /// Subtracts `by` from the balance of `from` and adds it to that of `to`.
     pub fn transfer_balance(
         &mut self,
@@ -1255,6 +1405,54 @@ impl<B: Backend> State<B> {
             vm_trace: e.vm_trace,
         })
     }
+    /// Finalize a cross-shard transaction which reached the hop limit as reverted.
+    ///
+    /// The transaction is not executed. The budget and value it still carries are refunded
+    /// to its original sender and the receipt reports a failure. Calls reaching the limit
+    /// elsewhere are first handed back to the sender's shard, so the refund is always
+    /// credited on the shard holding its balance.
+    pub fn apply_hop_limit(
+        &mut self,
+        env_info: &EnvInfo,
+        machine: &Machine,
+        t: &SignedTransaction,
+    ) -> ApplyResult<FlatTrace, VMTrace> {
+        let refund = t
+            .tx()
+            .gas
+            .saturating_mul(t.effective_gas_price(env_info.base_fee))
+            .saturating_add(t.tx().value);
+        self.claim_shard_budget(t, env_info.base_fee)?;
+        if !refund.is_zero() {
+            self.add_balance(&t.original_sender(), &refund, CleanupMode::NoEmpty)?;
+        }
+        self.reverted(true);
+
+        let params = machine.params();
+        let outcome = if env_info.number >= params.eip658_transition {
+            TransactionOutcome::StatusCode(0)
+        } else if env_info.number >= params.eip98_transition
+            && env_info.number >= params.validate_receipts_transition
+        {
+            TransactionOutcome::Unknown
+        } else {
+            self.commit()?;
+            TransactionOutcome::StateRoot(self.root().clone())
+        };
+        let receipt = TypedReceipt::new(
+            t.tx_type(),
+            LegacyReceipt::new(outcome, env_info.gas_used, Vec::new()),
+        );
+        trace!(target: "state", "Transaction receipt: {:?}", receipt);
+
+        Ok(ApplyOutcome {
+            receipt,
+            output: Vec::new(),
+            trace: Vec::new(),
+            vm_trace: None,
+        })
+    }
+
     /// Execute a given transaction with given tracer and VM tracer producing a receipt and an optional trace.
     /// This will change the state accordingly.
     pub fn apply_with_tracing<V, T>(
@@ -1956,8 +2154,10 @@ impl Clone for State<StateDB> {
             address_txn_vec: RefCell::new(address_txn_vec),
             temp_sstore_val: RefCell::new(temp_sstore_val),
             temp_sstore_delta: RefCell::new(temp_sstore_delta),
+            leaves: self.leaves.clone(),
             incr_bal_round: RefCell::new(incr_bal_round),
             next_shard: self.next_shard.clone(),
+            suspended_frame: self.suspended_frame.clone(),
             complete: self.complete.clone(),
             mined: self.mined.clone(),
             is_create_txn: self.is_create_txn.clone(),
@@ -2039,6 +2239,39 @@ mod tests {
         assert_eq!(result.trace, expected_trace);
     }
 
+    #[test]
+    fn should_refund_original_sender_at_hop_limit() {
+        let mut state = get_temp_state();
+        let info = EnvInfo::default();
+        let machine = make_frontier_machine(5);
+        let original_sender = Address::from_low_u64_be(7);
+
+        let t = TypedTransaction::ShardTransaction(ShardTransactionTx::new(
+            Transaction {
+                nonce: 0.into(),
+                gas_price: 2.into(),
+                gas: 30_000.into(),
+                action: Action::Call(Address::from_low_u64_be(9)),
+                value: 5.into(),
+                data: vec![],
+            },
+            0,
+            0,
+            1,
+            4,
+            original_sender,
+            Default::default(),
+            Default::default(),
+            String::new(),
+        ))
+        .sign(&secret(), None);
+
+        state.apply_hop_limit(&info, &machine, &t).unwrap();
+
+        assert!(state.is_reverted());
+        assert_eq!(state.balance(&original_sender).unwrap(), 60_005.into());
+    }
+
     #[test]
     fn should_work_when_cloned() {
         let _ = env_logger::try_init();
@@ -3866,6 +4099,7 @@ mod tests {
             vm: Default::default(),
             trie: TrieFactory::new(TrieSpec::Fat),
             accountdb: Default::default(),
+            shard: Default::default(),
         };
 
         let get_pod_state_val = |pod_state: &PodState, ak, k| {
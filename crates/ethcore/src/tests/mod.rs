@@ -17,4 +17,6 @@
 mod blockchain;
 mod client;
 mod evm;
+#[cfg(feature = "shard")]
+mod shard;
 mod trace;
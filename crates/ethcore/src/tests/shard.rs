@@ -0,0 +1,156 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+use bytes::Bytes;
+use client::ShardNetwork;
+use ethereum_types::{Address, H256, U256};
+use spec::Spec;
+use types::{receipt::ShardTransactionStatus, transaction::Action};
+
+fn genesis_balance() -> U256 {
+    U256::from_dec_str("1000000000000000000000").unwrap()
+}
+
+/// Contract of `Spec::new_test_round_shards` reading the balance of every address in its
+/// call data.
+fn balance_reader() -> Address {
+    Address::from_low_u64_be(0x100)
+}
+
+/// Call data making the balance reader read `count` accounts placed on `shards` in turn,
+/// so that every read continues the call on the next of them.
+fn reads_across(network: &ShardNetwork, shards: &[u64], count: usize) -> Bytes {
+    let mut accounts = (0x1000u64..).map(Address::from_low_u64_be);
+    (0..count)
+        .map(|i| shards[i % shards.len()])
+        .flat_map(|shard| {
+            let account = accounts
+                .by_ref()
+                .find(|account| network.shard_of(account) == shard)
+                .unwrap();
+            H256::from(account).as_bytes().to_vec()
+        })
+        .collect()
+}
+
+#[test]
+fn every_shard_seals_in_turn() {
+    let network = ShardNetwork::new();
+    network.run(2);
+
+    assert_eq!(network.best_block_number(), 8);
+    assert!(network.is_synced());
+    let shards: Vec<u64> = network.nodes().iter().map(|node| node.shard).collect();
+    assert_eq!(shards, vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn transfers_between_shards() {
+    let network = ShardNetwork::new();
+    let sender = network.account(0);
+    let recipient = network.account(1).address();
+
+    let hash = network.transfer(&sender, recipient, 1000.into());
+    network.seal_on(0);
+    network.run(1);
+
+    assert!(network.is_synced());
+    assert_eq!(
+        network.balance(&sender.address()),
+        genesis_balance() - U256::from(1000)
+    );
+    assert_eq!(
+        network.balance(&recipient),
+        genesis_balance() + U256::from(1000)
+    );
+
+    let receipt = network.shard_receipt(&hash).unwrap();
+    assert_eq!(receipt.origin_shard, 0);
+    assert_eq!(receipt.final_shard, Some(0));
+    assert_eq!(receipt.hop_count, 0);
+    assert_eq!(receipt.status, ShardTransactionStatus::Complete);
+}
+
+#[test]
+fn contract_calls_continue_on_the_shard_of_the_state_they_read() {
+    let mut network = ShardNetwork::new();
+    network.set_gas_price(1.into());
+    let sender = network.account(0);
+
+    let data = reads_across(&network, &[1], 1);
+    let hash = network.submit(&sender, Action::Call(balance_reader()), U256::zero(), data);
+    network.seal_on(0);
+    network.run(2);
+
+    assert!(network.is_synced());
+    let receipt = network.shard_receipt(&hash).unwrap();
+    assert_eq!(receipt.status, ShardTransactionStatus::Complete);
+    assert_eq!(receipt.origin_shard, 0);
+    assert_eq!(receipt.final_shard, Some(1));
+    assert_eq!(receipt.hop_count, 1);
+    // the sender pays the gas of both hops, earned by the authors of shards 0 and 1
+    assert_eq!(
+        network.balance(&sender.address()),
+        genesis_balance() - receipt.gas_used
+    );
+    let earned = network.nodes().iter().fold(U256::zero(), |earned, node| {
+        earned + network.balance(&node.validator)
+    });
+    assert_eq!(earned, receipt.gas_used);
+}
+
+#[test]
+fn continuations_are_generated_once() {
+    let network = ShardNetwork::new();
+    let sender = network.account(0);
+
+    let data = reads_across(&network, &[1], 1);
+    let hash = network.submit(&sender, Action::Call(balance_reader()), U256::zero(), data);
+    network.seal_on(0);
+    // shard 1 seals twice after the call was handed to it
+    network.run(2);
+
+    assert!(network.is_synced());
+    assert_eq!(network.continuation_count(&hash), 1);
+    let receipt = network.shard_receipt(&hash).unwrap();
+    assert_eq!(receipt.status, ShardTransactionStatus::Complete);
+    assert_eq!(receipt.hop_count, 1);
+}
+
+#[test]
+fn calls_reaching_the_hop_limit_are_refunded_on_the_sender_shard() {
+    let mut network = ShardNetwork::with_shards_spec(Spec::new_test_round_shards_hop_limit);
+    network.set_gas_price(1.into());
+    let sender = network.account(0);
+
+    // bounce between shards 1 and 0 for longer than the three hops allowed
+    let data = reads_across(&network, &[1, 0], 8);
+    let hash = network.submit(&sender, Action::Call(balance_reader()), U256::zero(), data);
+    network.seal_on(0);
+    network.run(4);
+
+    assert!(network.is_synced());
+    let receipt = network.shard_receipt(&hash).unwrap();
+    assert_eq!(receipt.status, ShardTransactionStatus::Reverted);
+    // three hops between the shards, then one back to the sender's shard
+    assert_eq!(receipt.hop_count, 4);
+    assert_eq!(receipt.final_shard, Some(0));
+    // the budget left at the limit is refunded, only the gas used is paid
+    assert_eq!(
+        network.balance(&sender.address()),
+        genesis_balance() - receipt.gas_used
+    );
+}
@@ -21,7 +21,13 @@ use call_contract::CallContract;
 use client::BlockInfo;
 use engines::EthEngine;
 use error::Error;
+#[cfg(feature = "shard")]
+use ethereum_types::H256;
+#[cfg(feature = "shard")]
+use hyperproofs::ShardContext;
 use types::header::Header;
+#[cfg(feature = "shard")]
+use types::BlockNumber;
 
 /// A canonial verifier -- this does full verification.
 pub struct CanonVerifier;
@@ -37,10 +43,22 @@ impl<C: BlockInfo + CallContract> Verifier<C> for CanonVerifier {
         verification::verify_block_family(header, parent, engine, do_full)
     }
 
+    #[cfg(not(feature = "shard"))]
     fn verify_block_final(&self, expected: &Header, got: &Header) -> Result<(), Error> {
         verification::verify_block_final(expected, got)
     }
 
+    #[cfg(feature = "shard")]
+    fn verify_block_final(
+        &self,
+        expected: &Header,
+        got: &Header,
+        shard: &ShardContext,
+        parent_shard_root: &(H256, BlockNumber),
+    ) -> Result<(), Error> {
+        verification::verify_block_final(expected, got, shard, parent_shard_root)
+    }
+
     fn verify_block_external(&self, header: &Header, engine: &dyn EthEngine) -> Result<(), Error> {
         engine.verify_block_external(header)
     }
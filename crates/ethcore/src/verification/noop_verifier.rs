@@ -21,7 +21,13 @@ use call_contract::CallContract;
 use client::BlockInfo;
 use engines::EthEngine;
 use error::Error;
+#[cfg(feature = "shard")]
+use ethereum_types::H256;
+#[cfg(feature = "shard")]
+use hyperproofs::ShardContext;
 use types::header::Header;
+#[cfg(feature = "shard")]
+use types::BlockNumber;
 
 /// A no-op verifier -- this will verify everything it's given immediately.
 #[allow(dead_code)]
@@ -38,10 +44,22 @@ impl<C: BlockInfo + CallContract> Verifier<C> for NoopVerifier {
         Ok(())
     }
 
+    #[cfg(not(feature = "shard"))]
     fn verify_block_final(&self, _expected: &Header, _got: &Header) -> Result<(), Error> {
         Ok(())
     }
 
+    #[cfg(feature = "shard")]
+    fn verify_block_final(
+        &self,
+        _expected: &Header,
+        _got: &Header,
+        _shard: &ShardContext,
+        _parent_shard_root: &(H256, BlockNumber),
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
     fn verify_block_external(
         &self,
         _header: &Header,
@@ -38,6 +38,10 @@ use call_contract::CallContract;
 use client::BlockInfo;
 use engines::{EthEngine, MAX_UNCLE_AGE};
 use error::{BlockError, Error};
+use ethereum_types::H256;
+#[cfg(feature = "shard")]
+use hyperproofs::ShardContext;
+use hyperproofs::{ShardConfig, UNKNOWN_SHARD};
 use types::{header::Header, transaction::SignedTransaction, BlockNumber};
 use verification::queue::kind::blocks::Unverified;
 
@@ -190,6 +194,7 @@ pub fn verify_block_family<C: BlockInfo + CallContract>(
         engine
             .machine()
             .verify_transaction(tx, parent, params.client)?;
+        engine.verify_shard_transaction(tx, parent)?;
     }
 
     Ok(())
@@ -306,34 +311,106 @@ fn verify_uncles(
 }
 
 /// Phase 4 verification. Check block information against transaction enactment results,
+#[cfg(not(feature = "shard"))]
 pub fn verify_block_final(expected: &Header, got: &Header) -> Result<(), Error> {
-    // #[cfg(feature = "shard")]
-    // if expected.state_root() != got.state_root() {
-    //     return Err(From::from(BlockError::InvalidStateRoot(Mismatch {
-    //         expected: *expected.state_root(),
-    //         found: *got.state_root(),
-    //     })));
-    // }
-    // if expected.gas_used() != got.gas_used() {
-    //     return Err(From::from(BlockError::InvalidGasUsed(Mismatch {
-    //         expected: *expected.gas_used(),
-    //         found: *got.gas_used(),
-    //     })));
-    // }
-    // if expected.log_bloom() != got.log_bloom() {
-    //     return Err(From::from(BlockError::InvalidLogBloom(Box::new(
-    //         Mismatch {
-    //             expected: *expected.log_bloom(),
-    //             found: *got.log_bloom(),
-    //         },
-    //     ))));
-    // }
-    // if expected.receipts_root() != got.receipts_root() {
-    //     return Err(From::from(BlockError::InvalidReceiptsRoot(Mismatch {
-    //         expected: *expected.receipts_root(),
-    //         found: *got.receipts_root(),
-    //     })));
-    // }
+    // every block is executed on the full state
+    verify_shard_block_final(
+        expected,
+        got,
+        &ShardConfig::default(),
+        0,
+        &(H256::zero(), 0),
+        None,
+    )
+}
+
+/// Phase 4 verification. Check block information against transaction enactment results,
+///
+/// `parent_shard_root` is the shard state root tracked for the parent of the block, which the
+/// block was executed on.
+#[cfg(feature = "shard")]
+pub fn verify_block_final(
+    expected: &Header,
+    got: &Header,
+    shard: &ShardContext,
+    parent_shard_root: &(H256, BlockNumber),
+) -> Result<(), Error> {
+    let author_shard = shard.config().author_shard(expected.author());
+    // a backend which cannot report its digests cannot vouch for blocks of other shards
+    let vector_root = shard.vector_root(author_shard).unwrap_or(None);
+    verify_shard_block_final(
+        expected,
+        got,
+        shard.config(),
+        shard.shard(),
+        parent_shard_root,
+        vector_root,
+    )
+}
+
+/// Phase 4 verification against the shard-local execution of the block.
+///
+/// Every block must have been executed on the shard state root tracked for its parent, and its
+/// gas and bloom must match. A block sealed by an author of `local_shard` was executed on the
+/// same shard state as ours, so its state root and receipts must match as well.
+///
+/// Blocks sealed on other shards commit to their own shard state in the header, which we cannot
+/// recompute: their authors are trusted for the state of their shard. Balances only cross shards
+/// as vector deltas, which every node recomputes, and as proofs, which are checked against the
+/// vector roots we track. Such blocks are therefore only accepted from authors of a known shard
+/// and, once the first round is committed, only while `vector_root` of that shard is tracked.
+pub fn verify_shard_block_final(
+    expected: &Header,
+    got: &Header,
+    config: &ShardConfig,
+    local_shard: u64,
+    parent_shard_root: &(H256, BlockNumber),
+    vector_root: Option<H256>,
+) -> Result<(), Error> {
+    let sharded = config.shard_count > 1;
+    // only the genesis header commits to the state of every shard
+    if sharded
+        && expected.number() > 1
+        && (parent_shard_root.0.is_zero() || parent_shard_root.1 + 1 != expected.number())
+    {
+        return Err(From::from(BlockError::UnknownShardStateRoot(
+            expected.number() - 1,
+        )));
+    }
+    let author_shard = config.author_shard(expected.author());
+    let local = !sharded || author_shard == local_shard;
+    if !local
+        && (author_shard == UNKNOWN_SHARD
+            || (expected.number() > config.round_length && vector_root.is_none()))
+    {
+        return Err(From::from(BlockError::UnknownShardVector(author_shard)));
+    }
+    if local && expected.state_root() != got.state_root() {
+        return Err(From::from(BlockError::InvalidStateRoot(Mismatch {
+            expected: *expected.state_root(),
+            found: *got.state_root(),
+        })));
+    }
+    if expected.gas_used() != got.gas_used() {
+        return Err(From::from(BlockError::InvalidGasUsed(Mismatch {
+            expected: *expected.gas_used(),
+            found: *got.gas_used(),
+        })));
+    }
+    if expected.log_bloom() != got.log_bloom() {
+        return Err(From::from(BlockError::InvalidLogBloom(Box::new(
+            Mismatch {
+                expected: *expected.log_bloom(),
+                found: *got.log_bloom(),
+            },
+        ))));
+    }
+    if local && expected.receipts_root() != got.receipts_root() {
+        return Err(From::from(BlockError::InvalidReceiptsRoot(Mismatch {
+            expected: *expected.receipts_root(),
+            found: *got.receipts_root(),
+        })));
+    }
     Ok(())
 }
 
@@ -545,7 +622,7 @@ mod tests {
     use crypto::publickey::{Generator, Random};
     use engines::EthEngine;
     use error::{BlockError::*, ErrorKind};
-    use ethereum_types::{Address, BloomRef, H256, U256};
+    use ethereum_types::{Address, Bloom, BloomRef, H256, U256};
     use hash::keccak;
     use rlp;
     use spec::{CommonParams, Spec};
@@ -1169,4 +1246,228 @@ mod tests {
         )
         .unwrap();
     }
+
+    fn four_shard_config() -> ShardConfig {
+        let mut authors = BTreeMap::new();
+        for shard in 0..4u64 {
+            authors.insert(Address::from_low_u64_be(shard + 1), shard);
+        }
+        ShardConfig {
+            shard_count: 4,
+            round_length: 4,
+            authors,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn final_verification_detects_divergent_shard_execution() {
+        let config = four_shard_config();
+        let parent_root = (H256::from_low_u64_be(9), 0);
+        let mut expected = Header::default();
+        expected.set_author(Address::from_low_u64_be(2));
+        expected.set_state_root(H256::from_low_u64_be(1));
+        let mut got = expected.clone();
+        check_ok(verify_shard_block_final(
+            &expected,
+            &got,
+            &config,
+            1,
+            &parent_root,
+            None,
+        ));
+
+        got.set_state_root(H256::from_low_u64_be(2));
+        check_fail(
+            verify_shard_block_final(&expected, &got, &config, 1, &parent_root, None),
+            InvalidStateRoot(Mismatch {
+                expected: H256::from_low_u64_be(1),
+                found: H256::from_low_u64_be(2),
+            }),
+        );
+
+        let mut got = expected.clone();
+        got.set_gas_used(U256::from(21000));
+        check_fail(
+            verify_shard_block_final(&expected, &got, &config, 1, &parent_root, None),
+            InvalidGasUsed(Mismatch {
+                expected: U256::zero(),
+                found: U256::from(21000),
+            }),
+        );
+    }
+
+    #[test]
+    fn final_verification_checks_gas_and_bloom_of_blocks_from_other_shards() {
+        let config = four_shard_config();
+        let parent_root = (H256::from_low_u64_be(9), 0);
+        let mut expected = Header::default();
+        expected.set_author(Address::from_low_u64_be(3));
+        expected.set_state_root(H256::from_low_u64_be(1));
+        let mut got = expected.clone();
+        got.set_state_root(H256::from_low_u64_be(2));
+        check_ok(verify_shard_block_final(
+            &expected,
+            &got,
+            &config,
+            0,
+            &parent_root,
+            None,
+        ));
+
+        got.set_gas_used(U256::from(21000));
+        check_fail(
+            verify_shard_block_final(&expected, &got, &config, 0, &parent_root, None),
+            InvalidGasUsed(Mismatch {
+                expected: U256::zero(),
+                found: U256::from(21000),
+            }),
+        );
+
+        let mut got = expected.clone();
+        got.set_log_bloom(Bloom::from_low_u64_be(1));
+        check_fail(
+            verify_shard_block_final(&expected, &got, &config, 0, &parent_root, None),
+            InvalidLogBloom(Box::new(Mismatch {
+                expected: Bloom::zero(),
+                found: Bloom::from_low_u64_be(1),
+            })),
+        );
+    }
+
+    #[test]
+    fn final_verification_requires_shard_root_of_parent() {
+        let config = four_shard_config();
+        let mut expected = Header::default();
+        expected.set_number(5);
+        expected.set_author(Address::from_low_u64_be(3));
+        let got = expected.clone();
+        check_ok(verify_shard_block_final(
+            &expected,
+            &got,
+            &config,
+            0,
+            &(H256::from_low_u64_be(9), 4),
+            Some(H256::from_low_u64_be(7)),
+        ));
+
+        // the tracked root belongs to an older block
+        check_fail(
+            verify_shard_block_final(
+                &expected,
+                &got,
+                &config,
+                0,
+                &(H256::from_low_u64_be(9), 3),
+                None,
+            ),
+            UnknownShardStateRoot(4),
+        );
+        // nothing tracked yet
+        check_fail(
+            verify_shard_block_final(&expected, &got, &config, 0, &(H256::zero(), 999), None),
+            UnknownShardStateRoot(4),
+        );
+    }
+
+    #[test]
+    fn final_verification_requires_vector_root_of_other_shards() {
+        let config = four_shard_config();
+        let parent_root = (H256::from_low_u64_be(9), 4);
+        let mut expected = Header::default();
+        expected.set_number(5);
+        expected.set_author(Address::from_low_u64_be(3));
+        let got = expected.clone();
+
+        // the first round is not committed yet
+        let mut first = expected.clone();
+        first.set_number(4);
+        check_ok(verify_shard_block_final(
+            &first,
+            &first,
+            &config,
+            0,
+            &(H256::from_low_u64_be(9), 3),
+            None,
+        ));
+        check_fail(
+            verify_shard_block_final(&expected, &got, &config, 0, &parent_root, None),
+            UnknownShardVector(2),
+        );
+        // blocks of our own shard are executed in full
+        check_ok(verify_shard_block_final(
+            &expected,
+            &got,
+            &config,
+            2,
+            &parent_root,
+            None,
+        ));
+
+        // authors outside of the shard topology are never trusted
+        expected.set_author(Address::from_low_u64_be(9));
+        let mut first = expected.clone();
+        first.set_number(1);
+        check_fail(
+            verify_shard_block_final(
+                &first,
+                &first,
+                &config,
+                0,
+                &parent_root,
+                Some(H256::from_low_u64_be(7)),
+            ),
+            UnknownShardVector(UNKNOWN_SHARD),
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "shard")]
+    fn final_verification_is_shard_local_with_shard_feature() {
+        let shard = ShardContext::new(four_shard_config());
+        shard.set_shard(0);
+        let parent_root = (H256::from_low_u64_be(9), 0);
+        let mut expected = Header::default();
+        expected.set_author(Address::from_low_u64_be(3));
+        expected.set_state_root(H256::from_low_u64_be(1));
+        let mut got = expected.clone();
+        got.set_state_root(H256::from_low_u64_be(2));
+
+        check_ok(verify_block_final(&expected, &got, &shard, &parent_root));
+    }
+
+    #[test]
+    #[cfg(not(feature = "shard"))]
+    fn final_verification_checks_state_root_without_shard_feature() {
+        let mut expected = Header::default();
+        expected.set_author(Address::from_low_u64_be(3));
+        expected.set_state_root(H256::from_low_u64_be(1));
+        let mut got = expected.clone();
+        got.set_state_root(H256::from_low_u64_be(2));
+
+        check_fail(
+            verify_block_final(&expected, &got),
+            InvalidStateRoot(Mismatch {
+                expected: H256::from_low_u64_be(1),
+                found: H256::from_low_u64_be(2),
+            }),
+        );
+    }
+
+    #[test]
+    fn final_verification_is_strict_without_shards() {
+        let config = ShardConfig::default();
+        let mut expected = Header::default();
+        expected.set_state_root(H256::from_low_u64_be(1));
+        let mut got = expected.clone();
+        got.set_state_root(H256::from_low_u64_be(2));
+
+        check_fail(
+            verify_shard_block_final(&expected, &got, &config, 0, &(H256::zero(), 999), None),
+            InvalidStateRoot(Mismatch {
+                expected: H256::from_low_u64_be(1),
+                found: H256::from_low_u64_be(2),
+            }),
+        );
+    }
 }
@@ -21,7 +21,13 @@ use call_contract::CallContract;
 use client::BlockInfo;
 use engines::EthEngine;
 use error::Error;
+#[cfg(feature = "shard")]
+use ethereum_types::H256;
+#[cfg(feature = "shard")]
+use hyperproofs::ShardContext;
 use types::header::Header;
+#[cfg(feature = "shard")]
+use types::BlockNumber;
 
 /// Should be used to verify blocks.
 pub trait Verifier<C>: Send + Sync
@@ -38,7 +44,18 @@ where
     ) -> Result<(), Error>;
 
     /// Do a final verification check for an enacted header vs its expected counterpart.
+    #[cfg(not(feature = "shard"))]
     fn verify_block_final(&self, expected: &Header, got: &Header) -> Result<(), Error>;
+    /// Do a final verification check for an enacted header vs its expected counterpart,
+    /// executed on the shard state root tracked for its parent.
+    #[cfg(feature = "shard")]
+    fn verify_block_final(
+        &self,
+        expected: &Header,
+        got: &Header,
+        shard: &ShardContext,
+        parent_shard_root: &(H256, BlockNumber),
+    ) -> Result<(), Error>;
     /// Verify a block, inspecing external state.
     fn verify_block_external(&self, header: &Header, engine: &dyn EthEngine) -> Result<(), Error>;
 }
@@ -317,6 +317,38 @@ pub struct RichReceipt {
     pub effective_gas_price: U256,
 }
 
+/// Progress of a cross-shard transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShardTransactionStatus {
+    /// Waiting to continue on another shard.
+    Pending,
+    /// Finished on its final shard.
+    Complete,
+    /// Reverted on its final shard.
+    Reverted,
+}
+
+/// Cross-shard lifecycle of a shard transaction, following its latest hop.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShardReceipt {
+    /// Hash of the transaction that started the cross-shard call.
+    pub original_transaction_hash: H256,
+    /// Hash of the latest hop of the call.
+    pub latest_transaction_hash: H256,
+    /// Shard the call started on.
+    pub origin_shard: u64,
+    /// Shard the call finished on, unless it is still pending.
+    pub final_shard: Option<u64>,
+    /// Shard the call continues on while pending.
+    pub next_shard: Option<u64>,
+    /// Number of hops so far.
+    pub hop_count: u64,
+    /// Gas used by all hops so far.
+    pub gas_used: U256,
+    /// Completion status.
+    pub status: ShardTransactionStatus,
+}
+
 /// Receipt with additional info.
 #[derive(Debug, Clone, PartialEq)]
 pub struct LocalizedReceipt {
@@ -350,6 +382,8 @@ pub struct LocalizedReceipt {
     pub from: H160,
     /// Effective gas price
     pub effective_gas_price: U256,
+    /// Cross-shard lifecycle, for shard transactions.
+    pub shard: Option<ShardReceipt>,
 }
 
 #[cfg(test)]
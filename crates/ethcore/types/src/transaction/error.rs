@@ -17,7 +17,7 @@
 use std::{error, fmt};
 
 use crate::crypto;
-use ethereum_types::U256;
+use ethereum_types::{Address, U256};
 use rlp;
 use unexpected::OutOfBounds;
 
@@ -100,7 +100,19 @@ pub enum Error {
     SenderInvalidShard,
     /// Block cannot aggregate more data elements
     BlockDataLimitExceeded,
-
+    /// Aggregated balance proof carried by a shard transaction does not verify
+    InvalidShardProof,
+    /// Aggregated balance proof could not be checked
+    ShardProofUnverifiable(String),
+    /// Shard transaction carrying more than its sender signed, not signed by a validator of its shard
+    UnauthorizedShardTransaction {
+        /// Signer of the transaction
+        signer: Address,
+        /// Shard the transaction claims to come from
+        shard: u64,
+    },
+    /// Suspended frame carried by a transaction which is not a continuation hop or does not decode
+    InvalidSuspendedFrame,
 }
 
 impl From<crypto::publickey::Error> for Error {
@@ -166,7 +178,16 @@ impl fmt::Display for Error {
             // #[cfg(feature = "shard")]
             SenderInvalidShard => "Transaction sender's shard is different from Block producer".into(),
 
-            BlockDataLimitExceeded => "Block cannot aggregate more data elements".into()
+            BlockDataLimitExceeded => "Block cannot aggregate more data elements".into(),
+            InvalidShardProof => "Shard balance proof does not verify".into(),
+            ShardProofUnverifiable(ref err) => {
+                format!("Shard balance proof could not be verified: {}", err)
+            }
+            UnauthorizedShardTransaction { signer, shard } => format!(
+                "{:?} is not a validator of shard {} and may not sign engine shard transactions",
+                signer, shard
+            ),
+            InvalidSuspendedFrame => "Shard transaction carries a frame it may not resume".into(),
         };
 
         f.write_fmt(format_args!("Transaction error ({})", msg))
@@ -22,6 +22,7 @@ use crate::{
     transaction::error,
 };
 use ethereum_types::{Address, BigEndianHash, H160, H256, U256};
+use hyperproofs::{AggProof, ShardKey};
 use parity_util_mem::MallocSizeOf;
 
 use rlp::{self, DecoderError, Rlp, RlpStream};
@@ -31,15 +32,14 @@ use std::collections::HashMap;
 pub type AccessListItem = (H160, Vec<H256>);
 pub type AccessList = Vec<AccessListItem>;
 
-pub type ShardProofItem = (H160, U256);
+pub type ShardProofItem = (ShardKey, U256);
 pub type ShardProofList = Vec<ShardProofItem>;
 
-pub type ShardDataItem = (H160, U256);
+pub type ShardDataItem = (ShardKey, U256);
 // Vec<ShardDataItem>;
-pub type ShardDataList = HashMap<H160,U256>;
+pub type ShardDataList = HashMap<ShardKey,U256>;
 use super::TypedTxId;
 
-use hyperproofs::AggProof;
 type Bytes = Vec<u8>;
 type BlockNumber = u64;
 
@@ -508,7 +508,6 @@ impl EIP1559TransactionTx {
         rlp.append(&self.encode(chain_id, Some(signature)));
     }
 }
-// #[cfg(feature = "shard")]
 
 #[derive(Debug, Clone, Eq, PartialEq, MallocSizeOf)]
 pub struct ShardTransactionTx {
@@ -522,10 +521,17 @@ pub struct ShardTransactionTx {
     //only if there is a non-empty proof
     pub shard_proof_list: ShardProofList,
     pub shard_proof: String,
+    // hash of the transaction that started the cross-shard call, zero for the first hop
+    pub original_hash: H256,
+    // gas used by the earlier hops of the call, charged to the original sender
+    pub gas_used: U256,
+    // gas used by this hop, recorded once it executed and left the call incomplete
+    pub hop_gas_used: U256,
+    // rlp of the interpreter frame the next hop resumes from, empty to run the call from the start
+    pub suspended_frame: Bytes,
     //account balance
     // pub balance: Option<U256>,
 }
-// #[cfg(feature = "shard")]
 impl ShardTransactionTx {
     pub fn new(transaction: Transaction, shard: u64, next_shard: u64, incomplete: u64, hop_count: u64, original_sender:Address, shard_data_list: ShardDataList, shard_proof_list:ShardProofList, shard_proof:String) -> ShardTransactionTx {
         ShardTransactionTx {
@@ -538,12 +544,33 @@ impl ShardTransactionTx {
             shard_data_list,
             shard_proof_list,
             shard_proof,
+            original_hash: H256::zero(),
+            gas_used: U256::zero(),
+            hop_gas_used: U256::zero(),
+            suspended_frame: Bytes::new(),
         }
     }
     pub fn tx_type(&self) -> TypedTxId {
         TypedTxId::ShardTransaction
     }
 
+    /// Whether the transaction carries nothing but what its sender signed as a legacy
+    /// transaction. Its shard follows from the sender's placement and its original sender
+    /// is the sender itself, so it keeps the legacy signature and hash; any other shard
+    /// transaction signs every shard field.
+    pub fn is_plain(&self) -> bool {
+        self.next_shard == 999u64
+            && self.incomplete == 0
+            && self.hop_count == 0
+            && self.shard_data_list.is_empty()
+            && self.shard_proof_list.is_empty()
+            && self.shard_proof.is_empty()
+            && self.original_hash.is_zero()
+            && self.gas_used.is_zero()
+            && self.hop_gas_used.is_zero()
+            && self.suspended_frame.is_empty()
+    }
+
     pub fn tx(&self) -> &Transaction {
         &self.transaction
     }
@@ -553,15 +580,20 @@ impl ShardTransactionTx {
     }
 
     //EIP1559 inspired decoding but difficult to generate dummy transactions from web3js
-    // rlp([3, [chainId, nonce, maxFeePerGas(gasPrice), gasLimit, to, value, data, shard, next_shard,incomplete, hop_count, original_sender, shard_data_item list, shard_proof_list, shard_proof, senderV, senderR, senderS]])
+    // rlp([3, [chainId, nonce, maxFeePerGas(gasPrice), gasLimit, to, value, data, shard, next_shard,incomplete, hop_count, original_sender, shard_data_item list, shard_proof_list, shard_proof, (original_hash, (gas_used, hop_gas_used, (suspended_frame))), senderV, senderR, senderS]])
+    // original_hash is only present on continuation hops, the gas fields once the call used gas,
+    // the suspended frame once a hop stopped in a frame the next hop can resume
     pub fn decode(tx: &[u8]) -> Result<UnverifiedTransaction, DecoderError> {
         let tx_rlp = &Rlp::new(tx);
 
-        // we need to have 18 items in this list
-        if tx_rlp.item_count()? != 18{
+        // we need to have 18 items in this list, 19 with the original hash, 21 with the gas fields
+        // and 22 with the suspended frame
+        let item_count = tx_rlp.item_count()?;
+        if item_count != 18 && item_count != 19 && item_count != 21 && item_count != 22 {
             // println!("item count is {:?}",tx_rlp.item_count());
             return Err(DecoderError::RlpIncorrectListLen);
         }
+        let signature_at = item_count - 3;
 
         let chain_id = Some(tx_rlp.val_at(0)?);
         // println!("hello");
@@ -589,7 +621,7 @@ impl ShardTransactionTx {
         // shard data list we get from here
         let datal_rlp = tx_rlp.at(12)?;
 
-        // shard_data_list pattern: [[{20 bytes}, {32 bytes}]...]
+        // shard_data_list pattern: [[{32 bytes}, {32 bytes}]...]
         let mut datal: ShardDataList = HashMap::new();
 
         for i in 0..datal_rlp.item_count()? {
@@ -604,7 +636,7 @@ impl ShardTransactionTx {
         // shard proof list we get from here
         let proofl_rlp = tx_rlp.at(13)?;
 
-        // shard_proof_list pattern: [[{20 bytes}, {32 bytes}]...]
+        // shard_proof_list pattern: [[{32 bytes}, {32 bytes}]...]
         let mut proofl: ShardProofList = Vec::new();
 
         for i in 0..proofl_rlp.item_count()? {
@@ -617,11 +649,26 @@ impl ShardTransactionTx {
             proofl.push((datas.val_at(0)?, datas.val_at(1)?));
         }
         let proof = tx_rlp.val_at(14)?;
+        let original_hash = if item_count >= 19 {
+            tx_rlp.val_at(15)?
+        } else {
+            H256::zero()
+        };
+        let (gas_used, hop_gas_used) = if item_count >= 21 {
+            (tx_rlp.val_at(16)?, tx_rlp.val_at(17)?)
+        } else {
+            (U256::zero(), U256::zero())
+        };
+        let suspended_frame = if item_count == 22 {
+            tx_rlp.val_at(18)?
+        } else {
+            Bytes::new()
+        };
         // we get signature part from here
         let signature = SignatureComponents {
-            standard_v: tx_rlp.val_at(15)?,
-            r: tx_rlp.val_at(16)?,
-            s: tx_rlp.val_at(17)?,
+            standard_v: tx_rlp.val_at(signature_at)?,
+            r: tx_rlp.val_at(signature_at + 1)?,
+            s: tx_rlp.val_at(signature_at + 2)?,
         };
 
 
@@ -637,6 +684,10 @@ impl ShardTransactionTx {
                 shard_data_list: datal,
                 shard_proof_list: proofl,
                 shard_proof: proof,
+                original_hash,
+                gas_used,
+                hop_gas_used,
+                suspended_frame,
             }),
             chain_id,
             signature,
@@ -696,8 +747,18 @@ impl ShardTransactionTx {
         } else {
             15
         };
-        // rlp([3, [chainId, nonce, maxFeePerGas(gasPrice), gasLimit, to, value, data, shard, next_shard, incomplete, hop_count, original_sender, shard_data_item list, shard_proof_list, shard_proof, senderV, senderR, senderS]])
-        stream.begin_list(list_size);
+        // the original hash, gas fields and frame are left out until needed to keep first hops unchanged
+        let has_suspended_frame = !self.suspended_frame.is_empty();
+        let has_gas_used =
+            has_suspended_frame || !self.gas_used.is_zero() || !self.hop_gas_used.is_zero();
+        let has_original_hash = has_gas_used || !self.original_hash.is_zero();
+        // rlp([3, [chainId, nonce, maxFeePerGas(gasPrice), gasLimit, to, value, data, shard, next_shard, incomplete, hop_count, original_sender, shard_data_item list, shard_proof_list, shard_proof, (original_hash, (gas_used, hop_gas_used, (suspended_frame))), senderV, senderR, senderS]])
+        stream.begin_list(
+            list_size
+                + has_original_hash as usize
+                + 2 * has_gas_used as usize
+                + has_suspended_frame as usize,
+        );
 
         // append chain_id. from EIP-2930: chainId is defined to be an integer of arbitrary size.
         stream.append(&(if let Some(n) = chain_id { n } else { 0 }));
@@ -735,6 +796,16 @@ impl ShardTransactionTx {
             stream.append(&stuff.1);
         }
         stream.append(&self.shard_proof);
+        if has_original_hash {
+            stream.append(&self.original_hash);
+        }
+        if has_gas_used {
+            stream.append(&self.gas_used);
+            stream.append(&self.hop_gas_used);
+        }
+        if has_suspended_frame {
+            stream.append(&self.suspended_frame);
+        }
         // append signature if any
         if let Some(signature) = signature {
             signature.rlp_append(&mut stream);
@@ -770,66 +841,85 @@ pub enum TypedTransaction {
     AccessList(AccessListTx), // EIP-2930 Transaction with a list of addresses and storage keys that the transaction plans to access.
     // Accesses outside the list are possible, but become more expensive.
     EIP1559Transaction(EIP1559TransactionTx),
-    // #[cfg(feature = "shard")]
     ShardTransaction(ShardTransactionTx),
 
 }
 
 impl TypedTransaction {
-    // #[cfg(feature = "shard")]
-    pub fn hash_map_replace_with(&mut self, h: HashMap<Address, U256>){
+    pub fn hash_map_replace_with(&mut self, h: HashMap<ShardKey, U256>){
         match self {
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction( tx) => {tx.shard_data_list = h},
             _ => {},
         }
     }
     pub fn change_original_sender(&mut self, address: Address){
         match self {
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction( tx) => {tx.original_sender = address;},
             _ => {},
         }
     }
     pub fn set_shard(&mut self, shard: u64){
         match self {
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction( tx) => {tx.shard = shard;},
             _ => {},
         }
     }
-    pub fn hash_map_insert(&mut self, key:Address, val:U256){
+    pub fn set_original_hash(&mut self, hash: H256){
+        match self {
+            Self::ShardTransaction( tx) => {tx.original_hash = hash;},
+            _ => {},
+        }
+    }
+    pub fn set_gas_used(&mut self, gas_used: U256, hop_gas_used: U256){
+        match self {
+            Self::ShardTransaction( tx) => {
+                tx.gas_used = gas_used;
+                tx.hop_gas_used = hop_gas_used;
+            },
+            _ => {},
+        }
+    }
+    pub fn set_suspended_frame(&mut self, frame: Bytes){
+        match self {
+            Self::ShardTransaction( tx) => {tx.suspended_frame = frame;},
+            _ => {},
+        }
+    }
+    /// Hash of the transaction its sender signed to start the cross-shard call, once the
+    /// engine signed this hop of it instead.
+    pub fn original_transaction_hash(&self) -> Option<H256> {
+        match self {
+            Self::ShardTransaction(tx) if !tx.original_hash.is_zero() => Some(tx.original_hash),
+            _ => None,
+        }
+    }
+    pub fn hash_map_insert(&mut self, key:ShardKey, val:U256){
         match self {
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction( tx) => {tx.shard_data_list.insert(key, val);},
             _ => {},
         }
     }
     pub fn set_next_shard(&mut self, shard:u64){
         match self {
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction( tx) => {tx.next_shard = shard;},
             _ => {},
         }
     }
     pub fn incr_hop_count(&mut self, delta:u64){
         match self {
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction( tx) => {tx.hop_count = delta;},
             _ => {},
         }
     }
     pub fn set_incomplete(&mut self, status:u64){
         match self {
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction( tx) => {tx.incomplete = status;},
             _ => {},
         }
     }
     pub fn conatins_balance(&self, sender:Address) -> bool{
         match self {
-            // #[cfg(feature = "shard")]
-            Self::ShardTransaction( tx) => if tx.shard_data_list.contains_key(&sender) {
+            Self::ShardTransaction( tx) => if tx.shard_data_list.contains_key(&AggProof::balance_key(&sender)) {
                 true
             } else {
                 false
@@ -837,25 +927,21 @@ impl TypedTransaction {
             _ => false,
         }
     }
-    // #[cfg(feature = "shard")]
     pub fn shard_id(&self) -> u64{
         match self {
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction( tx) => tx.shard.clone(),
             _ => 999u64,
         }
     }
 
-    // #[cfg(feature = "shard")]
-    pub fn to_shard_txn(self, address: Address) -> TypedTransaction{
+    pub fn to_shard_txn(self, address: Address, placement: &dyn Fn(&Address) -> u64) -> TypedTransaction{
         match self {
-            // #[cfg(feature = "shard")]
             Self::Legacy( tx) => {
                 match tx.action {
                     Action::Create => Self::Legacy(tx),
                     _ => Self::ShardTransaction(ShardTransactionTx{
                         transaction: tx,
-                        shard: address.to_low_u64_be().rem_euclid(AggProof::shard_count()),
+                        shard: placement(&address),
                         next_shard: 999u64,
                         incomplete: 0u64,
                         hop_count:0u64,
@@ -863,35 +949,34 @@ impl TypedTransaction {
                         shard_data_list: HashMap::new(),
                         shard_proof_list: Vec::new(),
                         shard_proof: String::new(),
+                        original_hash: H256::zero(),
+                        gas_used: U256::zero(),
+                        hop_gas_used: U256::zero(),
+                        suspended_frame: Bytes::new(),
                     }),
                 }
             }
             _ => self,
         }
     }
-    // #[cfg(feature = "shard")]
     pub fn is_shard(&self) -> bool {
         match self {
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction(_) => true,
             _ => false,
         }
     }
-    // #[cfg(feature = "shard")]
     pub fn with_balance(self, sender:Address, balance: U256) -> TypedTransaction{
         match self {
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction(mut tx) => {
-                        tx.shard_data_list.insert(sender,balance);
+                        tx.shard_data_list.insert(AggProof::balance_key(&sender),balance);
                         Self::ShardTransaction(tx)
             },
             _ => self,
         }
     }
-    // #[cfg(feature = "shard")]
-    pub fn with_proof(self, proof_data: Vec<(Address,U256)>, proof:String) -> TypedTransaction{
+    #[cfg(feature = "shard")]
+    pub fn with_proof(self, proof_data: Vec<(ShardKey,U256)>, proof:String) -> TypedTransaction{
         match self {
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction(mut tx) => {
                 tx.shard_proof_list.extend( proof_data);
                 tx.shard_proof = proof;
@@ -900,10 +985,9 @@ impl TypedTransaction {
             _ => self,
         }
     }
-    // #[cfg(feature = "shard")]
+    #[cfg(feature = "shard")]
     pub fn with_shard(self, shard: u64) -> TypedTransaction{
         match self {
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction(mut tx) => {
                 tx.shard = shard;
                 Self::ShardTransaction(tx)
@@ -911,13 +995,11 @@ impl TypedTransaction {
             _ => self,
         }
     }
-    // #[cfg(feature = "shard")]
-    pub fn clean_shard(self, address: Address) -> TypedTransaction{
+    pub fn clean_shard(self, address: Address, placement: &dyn Fn(&Address) -> u64) -> TypedTransaction{
         match self {
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction(mut tx) => {
                 if tx.shard == 999u64 {
-                    tx.shard = address.to_low_u64_be().rem_euclid(AggProof::shard_count());
+                    tx.shard = placement(&address);
                 }
                 Self::ShardTransaction(tx)
             },
@@ -929,7 +1011,6 @@ impl TypedTransaction {
             Self::Legacy(_) => TypedTxId::Legacy,
             Self::AccessList(_) => TypedTxId::AccessList,
             Self::EIP1559Transaction(_) => TypedTxId::EIP1559Transaction,
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction(_) => TypedTxId::ShardTransaction,
         }
     }
@@ -940,8 +1021,8 @@ impl TypedTransaction {
             Self::Legacy(tx) => tx.encode(chain_id, None),
             Self::AccessList(tx) => tx.encode(chain_id, None),
             Self::EIP1559Transaction(tx) => tx.encode(chain_id, None),
-            // #[cfg(feature = "shard")]
-            Self::ShardTransaction(tx) => tx.encode_no_bal(chain_id, None),
+            Self::ShardTransaction(tx) if tx.is_plain() => tx.encode_no_bal(chain_id, None),
+            Self::ShardTransaction(tx) => tx.encode(chain_id, None),
         })
     }
 
@@ -1031,7 +1112,6 @@ impl TypedTransaction {
             Self::Legacy(tx) => tx,
             Self::AccessList(ocl) => ocl.tx(),
             Self::EIP1559Transaction(tx) => tx.tx(),
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction(tx) => tx.tx(),
         }
     }
@@ -1041,7 +1121,6 @@ impl TypedTransaction {
             Self::Legacy(tx) => tx,
             Self::AccessList(ocl) => ocl.tx_mut(),
             Self::EIP1559Transaction(tx) => tx.tx_mut(),
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction(tx) => tx.tx_mut(),
 
         }
@@ -1052,7 +1131,6 @@ impl TypedTransaction {
             Self::EIP1559Transaction(tx) => Some(&tx.transaction.access_list),
             Self::AccessList(tx) => Some(&tx.access_list),
             Self::Legacy(_) => None,
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction(_) => None,
 
         }
@@ -1072,7 +1150,6 @@ impl TypedTransaction {
             }
             Self::AccessList(_) => self.tx().gas_price,
             Self::Legacy(_) => self.tx().gas_price,
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction(_) => self.tx().gas_price,
 
         }
@@ -1083,7 +1160,6 @@ impl TypedTransaction {
             Self::EIP1559Transaction(tx) => tx.max_priority_fee_per_gas,
             Self::AccessList(tx) => tx.tx().gas_price,
             Self::Legacy(tx) => tx.gas_price,
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction(tx) => tx.tx().gas_price,
         }
     }
@@ -1101,7 +1177,6 @@ impl TypedTransaction {
             }
             Self::AccessList(tx) => tx.tx().gas_price.is_zero(),
             Self::Legacy(tx) => tx.gas_price.is_zero(),
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction(tx) => tx.tx().gas_price.is_zero(),
 
         }
@@ -1118,7 +1193,6 @@ impl TypedTransaction {
         }
         // other transaction types
         match id.unwrap() {
-            // #[cfg(feature = "shard")]
             TypedTxId::ShardTransaction => ShardTransactionTx::decode(&tx[1..]),
             TypedTxId::EIP1559Transaction => EIP1559TransactionTx::decode(&tx[1..]),
             TypedTxId::AccessList => AccessListTx::decode(&tx[1..]),
@@ -1172,7 +1246,6 @@ impl TypedTransaction {
             Self::Legacy(tx) => tx.rlp_append(s, chain_id, signature),
             Self::AccessList(opt) => opt.rlp_append(s, chain_id, signature),
             Self::EIP1559Transaction(tx) => tx.rlp_append(s, chain_id, signature),
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction(tx) => tx.rlp_append(s, chain_id, signature),
         }
     }
@@ -1189,18 +1262,15 @@ impl TypedTransaction {
             Self::Legacy(tx) => tx.encode(chain_id, signature),
             Self::AccessList(opt) => opt.encode(chain_id, signature),
             Self::EIP1559Transaction(tx) => tx.encode(chain_id, signature),
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction(tx) => tx.encode(chain_id, signature),
         }
     }
-    // #[cfg(feature = "shard")]
     fn encode_no_bal(&self, chain_id: Option<u64>, signature: &SignatureComponents) -> Vec<u8> {
         let signature = Some(signature);
         match self {
             Self::Legacy(tx) => tx.encode(chain_id, signature),
             Self::AccessList(opt) => opt.encode(chain_id, signature),
             Self::EIP1559Transaction(tx) => tx.encode(chain_id, signature),
-            // #[cfg(feature = "shard")]
             Self::ShardTransaction(tx) => tx.encode_no_bal(chain_id, signature),
         }
     }
@@ -1271,15 +1341,15 @@ impl UnverifiedTransaction {
     pub fn encode(&self) -> Vec<u8> {
         self.unsigned.encode(self.chain_id, &self.signature)
     }
-    // #[cfg(feature = "shard")]
     pub fn encode_no_bal(&self) -> Vec<u8> {
         self.unsigned.encode_no_bal(self.chain_id, &self.signature)
     }
     /// Used to compute hash of created transactions.
     pub fn compute_hash(mut self) -> UnverifiedTransaction {
-        // #[cfg(feature = "shard")]
         let hash = match self.unsigned {
-            TypedTransaction::ShardTransaction(_) => keccak(&*self.encode_no_bal()),
+            TypedTransaction::ShardTransaction(ref tx) if tx.is_plain() => {
+                keccak(&*self.encode_no_bal())
+            }
             _ => keccak(&*self.encode()),
         };
         // let hash = keccak(&*self.encode());
@@ -1445,36 +1515,33 @@ impl SignedTransaction {
             tx.unsigned.rlp_append(s, tx.chain_id, &tx.signature);
         }
     }
-    // #[cfg(feature = "shard")]
     pub fn with_balance(mut self, balance: U256) -> SignedTransaction{
         self.transaction.unsigned = self.transaction.unsigned.with_balance(self.sender,balance);
         self
     }
-    // #[cfg(feature = "shard")]
     pub fn shard_proof_data(&self)->Vec<ShardProofItem>{
         match &self.transaction.unsigned {
             TypedTransaction::ShardTransaction(tx) => tx.shard_proof_list.clone(),
             _ => Vec::new(),
         }
     }
-    // #[cfg(feature = "shard")]
     pub fn shard_proof(&self)->String{
         match &self.transaction.unsigned {
             TypedTransaction::ShardTransaction(tx) => tx.shard_proof.clone(),
             _ => String::new(),
         }
     }
-    // #[cfg(feature = "shard")]
-    pub fn with_proof(mut self, proof_data: Vec<(Address,U256)>, proof: String) -> SignedTransaction{
+    #[cfg(feature = "shard")]
+    pub fn with_proof(mut self, proof_data: Vec<(ShardKey,U256)>, proof: String) -> SignedTransaction{
         self.transaction.unsigned = self.transaction.unsigned.with_proof(proof_data, proof);
         self
     }
-    // #[cfg(feature = "shard")]
+    #[cfg(feature = "shard")]
     pub fn with_shard(mut self, shard: u64) -> SignedTransaction{
         self.transaction.unsigned = self.transaction.unsigned.with_shard(shard);
         self
     }
-    // #[cfg(feature = "shard")]
+    #[cfg(feature = "shard")]
     pub fn match_shard(&self, shard: u64) -> bool {
         if self.transaction.unsigned.is_shard(){
             shard == self.transaction.unsigned.shard_id()
@@ -1482,21 +1549,32 @@ impl SignedTransaction {
             true
         }
     }
-    // #[cfg(feature = "shard")]
-    pub fn to_shard_txn(mut self) -> SignedTransaction{
-        self.transaction.unsigned = self.transaction.unsigned.to_shard_txn(self.sender.clone());
+    /// Shard this transaction has to be sealed on, `None` if any shard may include it.
+    ///
+    /// Transactions without an explicit shard belong to the shard `placement` puts their
+    /// original sender on.
+    pub fn home_shard(&self, placement: &dyn Fn(&Address) -> u64) -> Option<u64> {
+        match &self.transaction.unsigned {
+            TypedTransaction::ShardTransaction(tx) if tx.shard != 999u64 => Some(tx.shard),
+            TypedTransaction::ShardTransaction(tx) => {
+                Some(placement(&tx.original_sender))
+            }
+            _ => None,
+        }
+    }
+    pub fn to_shard_txn(mut self, placement: &dyn Fn(&Address) -> u64) -> SignedTransaction{
+        self.transaction.unsigned = self.transaction.unsigned.to_shard_txn(self.sender.clone(), placement);
         self
     }
-    pub fn clean_shard(mut self) -> SignedTransaction{
-        self.transaction.unsigned = self.transaction.unsigned.clean_shard(self.sender.clone());
+    pub fn clean_shard(mut self, placement: &dyn Fn(&Address) -> u64) -> SignedTransaction{
+        self.transaction.unsigned = self.transaction.unsigned.clean_shard(self.sender.clone(), placement);
         self
     }
-    // #[cfg(feature = "shard")]
     pub fn contains_balance(& self) -> bool{
         self.transaction.unsigned.conatins_balance(self.original_sender())
     }
 
-    pub fn shard_data_hashmap(&self)->HashMap<Address, U256> {
+    pub fn shard_data_hashmap(&self)->HashMap<ShardKey, U256> {
         match &self.transaction.unsigned {
             TypedTransaction::ShardTransaction(tx) => tx.shard_data_list.clone(),
             _ => HashMap::new(),
@@ -1509,10 +1587,10 @@ impl SignedTransaction {
         }
     }
 
-    pub fn hash_map_insert(&mut self, key: Address, val: U256){
+    pub fn hash_map_insert(&mut self, key: ShardKey, val: U256){
         self.transaction.unsigned.hash_map_insert(key, val);
     }
-    pub fn hash_map_replace_with(&mut self, h: HashMap<Address,U256>){
+    pub fn hash_map_replace_with(&mut self, h: HashMap<ShardKey,U256>){
         self.transaction.unsigned.hash_map_replace_with(h);
     }
     pub fn set_next_shard(&mut self, shard:u64){
@@ -1536,6 +1614,25 @@ impl SignedTransaction {
     pub fn set_incomplete(&mut self, status:u64){
         self.transaction.unsigned.set_incomplete(status);
     }
+    /// Whether only a validator may sign this transaction: it was executed or continued by
+    /// a sealer, carries shard data or a balance proof, or acts for another sender.
+    pub fn is_engine_shard_transaction(&self) -> bool {
+        match &self.transaction.unsigned {
+            TypedTransaction::ShardTransaction(tx) => {
+                !tx.is_plain() || tx.original_sender != self.sender
+            }
+            _ => false,
+        }
+    }
+    /// Account whose nonce the transaction uses: the original sender on the first hop of a
+    /// call, even once a sealer signed it, and the engine on the hops it continues it with.
+    pub fn nonce_sender(&self) -> Address {
+        if self.get_hop_count() == 0 {
+            self.original_sender()
+        } else {
+            self.sender
+        }
+    }
     pub fn call_address(&self) -> Option<Address> {
         match self.tx().action{
             Action::Call(a) => Some(a),
@@ -1548,6 +1645,40 @@ impl SignedTransaction {
     pub fn set_shard(&mut self, shard: u64){
         self.transaction.unsigned.set_shard(shard);
     }
+    /// Hash of the transaction that started the cross-shard call.
+    pub fn original_hash(&self) -> H256 {
+        self.transaction.unsigned.original_transaction_hash().unwrap_or_else(|| self.hash())
+    }
+    pub fn set_original_hash(&mut self, hash: H256){
+        self.transaction.unsigned.set_original_hash(hash);
+    }
+    /// Gas used by the earlier hops of the call.
+    pub fn shard_gas_used(&self) -> U256 {
+        match &self.transaction.unsigned {
+            TypedTransaction::ShardTransaction(tx) => tx.gas_used,
+            _ => U256::zero(),
+        }
+    }
+    /// Gas used by this hop, zero until it executed and left the call incomplete.
+    pub fn hop_gas_used(&self) -> U256 {
+        match &self.transaction.unsigned {
+            TypedTransaction::ShardTransaction(tx) => tx.hop_gas_used,
+            _ => U256::zero(),
+        }
+    }
+    pub fn set_gas_used(&mut self, gas_used: U256, hop_gas_used: U256){
+        self.transaction.unsigned.set_gas_used(gas_used, hop_gas_used);
+    }
+    /// Rlp of the interpreter frame the call resumes from, empty to run it from the start.
+    pub fn suspended_frame(&self) -> &[u8] {
+        match &self.transaction.unsigned {
+            TypedTransaction::ShardTransaction(tx) => &tx.suspended_frame,
+            _ => &[],
+        }
+    }
+    pub fn set_suspended_frame(&mut self, frame: Bytes){
+        self.transaction.unsigned.set_suspended_frame(frame);
+    }
     pub fn original_sender(&self) -> Address {
         match &self.transaction.unsigned {
             TypedTransaction::ShardTransaction(tx) => tx.original_sender,
@@ -1607,9 +1738,9 @@ pub struct PendingTransaction {
 }
 
 impl PendingTransaction {
-    // #[cfg(feature = "shard")]
-    pub fn to_shard_txn(mut self) -> PendingTransaction{
-        self.transaction = self.transaction.to_shard_txn();
+    #[cfg(feature = "shard")]
+    pub fn to_shard_txn(mut self, placement: &dyn Fn(&Address) -> u64) -> PendingTransaction{
+        self.transaction = self.transaction.to_shard_txn(placement);
         self
     }
     /// Create a new pending transaction from signed transaction.
@@ -1779,6 +1910,7 @@ mod tests {
         assert_eq!(Address::from(keccak(key.public())), t.sender());
         assert_eq!(t.chain_id(), Some(69));
     }
+    #[cfg(feature = "shard")]
     #[test]
     fn should_encode_decode_shard_tx() {
         use self::publickey::{Generator, Random};
@@ -1797,7 +1929,7 @@ mod tests {
             0u64,
             0u64,
             Address::zero(),
-            HashMap::from([(H160::default(),U256::zero()),]),
+            HashMap::from([(ShardKey::default(),U256::zero()),]),
             Vec::new(),
             String::from("hello bitches"),
         ))
@@ -1810,6 +1942,173 @@ mod tests {
             assert!(true, "encoded/decoded tx differs from original");
         }
     }
+    #[cfg(feature = "shard")]
+    #[test]
+    fn should_encode_decode_shard_continuation_tx() {
+        use self::publickey::{Generator, Random};
+        let key = Random.generate();
+        let mut tx = ShardTransactionTx::new(
+            Transaction {
+                action: Action::Call(Address::from_low_u64_be(5)),
+                nonce: U256::from(42),
+                gas_price: U256::from(3000),
+                gas: U256::from(50_000),
+                value: U256::from(1),
+                data: b"Hello!".to_vec(),
+            },
+            1u64,
+            999u64,
+            1u64,
+            1u64,
+            Address::from_low_u64_be(1),
+            HashMap::new(),
+            Vec::new(),
+            String::new(),
+        );
+        let first_hop = TypedTransaction::ShardTransaction(tx.clone()).sign(&key.secret(), Some(69));
+        assert_eq!(first_hop.original_transaction_hash(), None);
+        assert_eq!(first_hop.original_hash(), first_hop.hash());
+
+        tx.original_hash = first_hop.hash();
+        let t = TypedTransaction::ShardTransaction(tx).sign(&key.secret(), Some(69));
+        let encoded = t.encode();
+        assert_eq!(encoded.len(), first_hop.encode().len() + 33);
+
+        let t_new =
+            TypedTransaction::decode(&encoded).expect("Error on UnverifiedTransaction decoder");
+        assert_eq!(t_new.unsigned, t.unsigned);
+        assert_eq!(t_new.original_transaction_hash(), Some(first_hop.hash()));
+    }
+    #[cfg(feature = "shard")]
+    #[test]
+    fn should_encode_decode_shard_gas_used() {
+        use self::publickey::{Generator, Random};
+        let key = Random.generate();
+        let mut tx = ShardTransactionTx::new(
+            Transaction {
+                action: Action::Call(Address::from_low_u64_be(5)),
+                nonce: U256::from(42),
+                gas_price: U256::from(3000),
+                gas: U256::from(50_000),
+                value: U256::from(1),
+                data: b"Hello!".to_vec(),
+            },
+            1u64,
+            2u64,
+            1u64,
+            0u64,
+            Address::from_low_u64_be(1),
+            HashMap::new(),
+            Vec::new(),
+            String::new(),
+        );
+        tx.hop_gas_used = U256::from(21_000);
+        let t = TypedTransaction::ShardTransaction(tx).sign(&key.secret(), Some(69));
+
+        let t_new = TypedTransaction::decode(&t.encode())
+            .expect("Error on UnverifiedTransaction decoder");
+        assert_eq!(t_new.unsigned, t.unsigned);
+        assert_eq!(t_new.original_transaction_hash(), None);
+    }
+    #[cfg(feature = "shard")]
+    #[test]
+    fn should_encode_decode_shard_suspended_frame() {
+        use self::publickey::{Generator, Random};
+        let key = Random.generate();
+        let mut tx = ShardTransactionTx::new(
+            Transaction {
+                action: Action::Call(Address::from_low_u64_be(5)),
+                nonce: U256::from(42),
+                gas_price: U256::from(3000),
+                gas: U256::from(50_000),
+                value: U256::from(1),
+                data: b"Hello!".to_vec(),
+            },
+            1u64,
+            999u64,
+            1u64,
+            1u64,
+            Address::from_low_u64_be(1),
+            HashMap::new(),
+            Vec::new(),
+            String::new(),
+        );
+        tx.suspended_frame = vec![0xc2, 0x01, 0x02];
+        let t = TypedTransaction::ShardTransaction(tx).sign(&key.secret(), Some(69));
+
+        let t_new = TypedTransaction::decode(&t.encode())
+            .expect("Error on UnverifiedTransaction decoder");
+        assert_eq!(t_new.unsigned, t.unsigned);
+        assert_eq!(
+            SignedTransaction::new(t_new).unwrap().suspended_frame(),
+            &[0xc2, 0x01, 0x02][..]
+        );
+    }
+    #[cfg(feature = "shard")]
+    #[test]
+    fn should_sign_every_shard_field() {
+        use self::publickey::{Generator, Random};
+        let key = Random.generate();
+        let legacy = TypedTransaction::Legacy(Transaction {
+            action: Action::Call(Address::from_low_u64_be(5)),
+            nonce: U256::from(42),
+            gas_price: U256::from(3000),
+            gas: U256::from(50_000),
+            value: U256::from(1),
+            data: b"Hello!".to_vec(),
+        })
+        .sign(&key.secret(), Some(69));
+        let recover = |t: &SignedTransaction| {
+            TypedTransaction::decode(&t.encode())
+                .ok()
+                .and_then(|t| SignedTransaction::new(t).ok())
+        };
+
+        // the legacy signature is kept while the transaction is plain
+        let plain = legacy.clone().to_shard_txn(&|_| 1);
+        let plain = recover(&plain).expect("plain shard transaction is recoverable");
+        assert_eq!(plain.sender(), key.address());
+        assert_eq!(plain.hash(), legacy.hash());
+        assert!(!plain.is_engine_shard_transaction());
+
+        let mut unsigned = plain.as_unsigned().clone();
+        unsigned.set_next_shard(2);
+        let continued = unsigned.sign(&key.secret(), Some(69));
+        assert!(continued.is_engine_shard_transaction());
+        assert_eq!(recover(&continued).map(|t| t.sender()), Some(key.address()));
+
+        let mut forged = continued.clone();
+        forged.set_suspended_frame(vec![0xc2, 0x01, 0x02]);
+        assert_ne!(recover(&forged).map(|t| t.sender()), Some(key.address()));
+        assert_ne!(recover(&forged).map(|t| t.hash()), Some(continued.hash()));
+    }
+    #[cfg(not(feature = "shard"))]
+    #[test]
+    fn should_reject_shard_tx_without_shard_feature() {
+        use self::publickey::{Generator, Random};
+        let key = Random.generate();
+        let t = TypedTransaction::ShardTransaction(ShardTransactionTx::new(
+            Transaction {
+                action: Action::Call(Address::from_low_u64_be(5)),
+                nonce: U256::from(42),
+                gas_price: U256::from(3000),
+                gas: U256::from(50_000),
+                value: U256::from(1),
+                data: b"Hello!".to_vec(),
+            },
+            1u64,
+            999u64,
+            0u64,
+            0u64,
+            Address::from_low_u64_be(1),
+            HashMap::new(),
+            Vec::new(),
+            String::new(),
+        ))
+        .sign(&key.secret(), Some(69));
+
+        assert!(TypedTransaction::decode(&t.encode()).is_err());
+    }
     #[test]
     fn should_encode_decode_access_list_tx() {
         use self::publickey::{Generator, Random};
@@ -36,7 +36,7 @@ impl TypedTxId {
             0 => Some(Self::Legacy),
             1 => Some(Self::AccessList),
             2 => Some(Self::EIP1559Transaction),
-            // #[cfg(feature = "shard")]
+            #[cfg(feature = "shard")]
             3 => Some(Self::ShardTransaction),
             _ => None,
         }
@@ -44,7 +44,7 @@ impl TypedTxId {
 
     pub fn try_from_wire_byte(n: u8) -> Result<Self, ()> {
         match n {
-            // #[cfg(feature = "shard")]
+            #[cfg(feature = "shard")]
             x if x == TypedTxId::ShardTransaction as u8 => Ok(TypedTxId::ShardTransaction),
             x if x == TypedTxId::EIP1559Transaction as u8 => Ok(TypedTxId::EIP1559Transaction),
             x if x == TypedTxId::AccessList as u8 => Ok(TypedTxId::AccessList),
@@ -60,7 +60,7 @@ impl TypedTxId {
             Some(0x00) => Some(Self::Legacy),
             Some(0x01) => Some(Self::AccessList),
             Some(0x02) => Some(Self::EIP1559Transaction),
-            // #[cfg(feature = "shard")]
+            #[cfg(feature = "shard")]
             Some(0x03) => Some(Self::ShardTransaction),
             _ => None,
         }
@@ -94,7 +94,13 @@ mod tests {
         );
         assert_eq!(Ok(TypedTxId::Legacy), TypedTxId::try_from_wire_byte(0x81));
         assert_eq!(Err(()), TypedTxId::try_from_wire_byte(0x00));
+        #[cfg(not(feature = "shard"))]
         assert_eq!(Err(()), TypedTxId::try_from_wire_byte(0x03));
+        #[cfg(feature = "shard")]
+        assert_eq!(
+            Ok(TypedTxId::ShardTransaction),
+            TypedTxId::try_from_wire_byte(0x03)
+        );
     }
 
     #[test]
@@ -121,7 +127,13 @@ mod tests {
             Some(TypedTxId::EIP1559Transaction),
             TypedTxId::from_U64_option_id(Some(U64::from(0x02)))
         );
+        #[cfg(not(feature = "shard"))]
         assert_eq!(None, TypedTxId::from_U64_option_id(Some(U64::from(0x03))));
+        #[cfg(feature = "shard")]
+        assert_eq!(
+            Some(TypedTxId::ShardTransaction),
+            TypedTxId::from_U64_option_id(Some(U64::from(0x03)))
+        );
     }
 
     #[test]
@@ -132,6 +144,9 @@ mod tests {
             Some(TypedTxId::EIP1559Transaction),
             TypedTxId::from_u8_id(2)
         );
+        #[cfg(not(feature = "shard"))]
         assert_eq!(None, TypedTxId::from_u8_id(3));
+        #[cfg(feature = "shard")]
+        assert_eq!(Some(TypedTxId::ShardTransaction), TypedTxId::from_u8_id(3));
     }
 }
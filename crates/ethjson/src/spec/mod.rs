@@ -44,7 +44,7 @@ pub use self::{
     genesis::Genesis,
     instant_seal::{InstantSeal, InstantSealParams},
     null_engine::{NullEngine, NullEngineParams},
-    params::Params,
+    params::{Params, ShardPlacement},
     seal::{AuthorityRoundSeal, Ethereum, Seal, TendermintSeal},
     spec::{ForkSpec, Spec},
     state::State,
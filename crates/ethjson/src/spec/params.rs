@@ -16,6 +16,8 @@
 
 //! Spec params deserialization.
 
+use std::collections::BTreeMap;
+
 use crate::{
     bytes::Bytes,
     hash::{Address, H256},
@@ -170,11 +172,42 @@ pub struct Params {
     pub eip1559_fee_collector_transition: Option<Uint>,
     /// Block at which zero gas price transactions start being checked with Certifier contract.
     pub validate_service_transactions_transition: Option<Uint>,
+    /// See `CommonParams` docs.
+    pub shard_count: Option<Uint>,
+    /// See `CommonParams` docs.
+    pub shard_round_length: Option<Uint>,
+    /// See `CommonParams` docs.
+    pub shard_authors: Option<BTreeMap<Address, Uint>>,
+    /// See `CommonParams` docs.
+    pub shard_vector_depth: Option<Uint>,
+    /// See `CommonParams` docs.
+    pub shard_placement: Option<ShardPlacement>,
+    /// See `CommonParams` docs.
+    pub shard_max_hops: Option<Uint>,
+    /// See `CommonParams` docs.
+    pub shard_shared_storage: Option<Vec<Address>>,
+}
+
+/// Placement of accounts on shards.
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "camelCase")]
+pub enum ShardPlacement {
+    /// Low 64 bits of the address modulo the shard count.
+    Modulo,
+    /// Address ranges, keyed by their lowest address.
+    PrefixRanges(BTreeMap<Address, Uint>),
+    /// Directory contract mapping addresses to shards.
+    Directory(Address),
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{spec::params::Params, uint::Uint};
+    use crate::{
+        hash::Address,
+        spec::params::{Params, ShardPlacement},
+        uint::Uint,
+    };
     use ethereum_types::U256;
     use serde_json;
 
@@ -215,6 +248,69 @@ mod tests {
         );
     }
 
+    #[test]
+    fn shard_params_deserialization() {
+        let s = r#"{
+			"maximumExtraDataSize": "0x20",
+			"networkID" : "0x1",
+			"minGasLimit": "0x1388",
+			"gasLimitBoundDivisor": "0x20",
+			"shardCount": "0x2",
+			"shardRoundLength": "0x4",
+			"shardVectorDepth": "0x14",
+			"shardMaxHops": "0x8",
+			"shardAuthors": {
+				"0x00bd138abd70e2f00903268f3db08f2d25677c9e": "0x0",
+				"0x00aa39d30f0d20ff03a22ccfc30b7efbfca597c2": "0x1"
+			},
+			"shardPlacement": {
+				"prefixRanges": {
+					"0x8000000000000000000000000000000000000000": "0x1"
+				}
+			},
+			"shardSharedStorage": [
+				"0x0000000000000000000000000000000000000042"
+			]
+		}"#;
+
+        let deserialized: Params = serde_json::from_str(s).unwrap();
+        assert_eq!(deserialized.shard_count, Some(Uint(U256::from(2))));
+        assert_eq!(deserialized.shard_round_length, Some(Uint(U256::from(4))));
+        assert_eq!(deserialized.shard_vector_depth, Some(Uint(U256::from(20))));
+        assert_eq!(deserialized.shard_max_hops, Some(Uint(U256::from(8))));
+        let authors = deserialized.shard_authors.unwrap();
+        assert_eq!(authors.len(), 2);
+        assert_eq!(
+            authors.get(&Address(
+                "00aa39d30f0d20ff03a22ccfc30b7efbfca597c2".parse().unwrap()
+            )),
+            Some(&Uint(U256::from(1)))
+        );
+        match deserialized.shard_placement {
+            Some(ShardPlacement::PrefixRanges(ranges)) => assert_eq!(ranges.len(), 1),
+            other => panic!("unexpected placement {:?}", other),
+        }
+        assert_eq!(
+            deserialized.shard_shared_storage,
+            Some(vec![Address(ethereum_types::H160::from_low_u64_be(0x42))])
+        );
+    }
+
+    #[test]
+    fn shard_placement_deserialization() {
+        let modulo: ShardPlacement = serde_json::from_str(r#""modulo""#).unwrap();
+        assert_eq!(modulo, ShardPlacement::Modulo);
+
+        let directory: ShardPlacement = serde_json::from_str(
+            r#"{ "directory": "0x0000000000000000000000000000000000000042" }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            directory,
+            ShardPlacement::Directory(Address(ethereum_types::H160::from_low_u64_be(0x42)))
+        );
+    }
+
     #[test]
     #[should_panic(expected = "a non-zero value")]
     fn test_zero_value_divisor() {
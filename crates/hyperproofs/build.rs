@@ -1,35 +1,33 @@
-// fn main() {
-//     println!("cargo:rustc-link-search=native={}", "./hyperproofs-go");
-//     println!("cargo:rustc-link-search=native={}", "/usr/local/lib");
-//     println!("cargo:rustc-link-lib=static={}", "shard");
-// }
+#[cfg(feature = "go-ffi")]
 extern crate bindgen;
 
-use std::env;
-use std::path::PathBuf;
-
+#[cfg(feature = "go-ffi")]
 fn main() {
+    use std::env;
+    use std::path::PathBuf;
+
+    println!("cargo:rerun-if-env-changed=HYPERPROOFS_LIB_DIR");
+    let go_dir = match env::var("HYPERPROOFS_LIB_DIR") {
+        Ok(dir) if !dir.is_empty() => dir,
+        _ => panic!(
+            "HYPERPROOFS_LIB_DIR must be set to the directory holding libshard.so and libshard.h \
+             when building with the `go-ffi` feature"
+        ),
+    };
+
     // Tell cargo to look for shared libraries in the specified directory
-    // println!("cargo:rustc-link-search=native={}", "./hyperproofs-go");
-    println!("cargo:rustc-link-search=native={}", "/home/srisht/libhyper/hyperproofs-go");
-    // println!("cargo:rustc-env=LD_LIBRARY_PATH=hyperproofs-go/");
-    // println!("cargo:rustc-link-search=native={}", "/usr/local/lib");
-    // Tell cargo to tell rustc to link the system bzip2
-    // shared library.
-    // println!("cargo:rustc-link-lib=static={}","shard");
-    println!("cargo:rustc-link-lib={}","shard");
-    // println!("cargo:rustc-link-lib=static={}","mcl");
+    println!("cargo:rustc-link-search=native={}", go_dir);
+    println!("cargo:rustc-link-lib={}", "shard");
+    println!("cargo:rustc-env=HYPERPROOFS_LIB_DIR={}", go_dir);
 
     // Tell cargo to invalidate the built crate whenever the wrapper changes
     println!("cargo:rerun-if-changed=wrapper.h");
 
-    // The bindgen::Builder is the main entry point
-    // to bindgen, and lets you build up options for
-    // the resulting bindings.
     let bindings = bindgen::Builder::default()
         // The input header we would like to generate
         // bindings for.
         .header("wrapper.h")
+        .clang_arg(format!("-I{}", go_dir))
         // Tell cargo to invalidate the built crate whenever any of the
         // included header files changed.
         .parse_callbacks(Box::new(bindgen::CargoCallbacks))
@@ -43,4 +41,7 @@ fn main() {
     bindings
         .write_to_file(out_path.join("bindings.rs"))
         .expect("Couldn't write bindings!");
-}
\ No newline at end of file
+}
+
+#[cfg(not(feature = "go-ffi"))]
+fn main() {}
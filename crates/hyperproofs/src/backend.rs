@@ -0,0 +1,70 @@
+//! Vector-commitment backend interface.
+
+use std::{error, fmt};
+
+use ethereum_types::H256;
+
+/// Result of a backend call.
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// Errors raised by a hyperproofs backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// A balance, delta or proof could not be decoded.
+    InvalidInput(String),
+    /// The backend library could not be loaded or called.
+    Library(String),
+    /// The queued deltas would take a balance below zero or beyond 256 bits.
+    BalanceOutOfRange(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidInput(ref msg) => write!(f, "invalid input: {}", msg),
+            Error::Library(ref msg) => write!(f, "library error: {}", msg),
+            Error::BalanceOutOfRange(ref msg) => write!(f, "balance out of range: {}", msg),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// Vector-commitment operations backing `ShardContext`.
+///
+/// Every node keeps one vector per shard. Balance deltas are queued with
+/// `push_address_delta` and folded into the vectors on `commit`; a shard aggregates
+/// proofs for its own vector with `agg` and other shards check them with `verify_proof`.
+pub trait HyperproofBackend: Send + Sync {
+    /// Reset all vectors, digests and pending lists.
+    fn init(&self, round: u64) -> Result<i64>;
+    /// Queue a signed decimal `delta` for leaf `address` of `shard`.
+    fn push_address_delta(&self, address: u64, delta: &str, shard: u64) -> Result<i64>;
+    /// Drop the deltas queued for `shard`.
+    fn reset_address_delta(&self, shard: u64) -> Result<i64>;
+    /// Add leaf `address` to the set proven by the next `agg` of `shard`.
+    fn push_address_commit(&self, address: u64, shard: u64) -> Result<i64>;
+    /// Clear the set proven by the next `agg` of `shard`.
+    fn reset_address_commit(&self, shard: u64) -> Result<i64>;
+    /// Add a claimed `balance` of leaf `address` to be checked by the next `verify_proof`.
+    fn push_address_balance_verify(&self, address: u64, balance: &str, shard: u64) -> Result<i64>;
+    /// Clear the claimed balances of `shard`.
+    fn reset_address_balance_verify(&self, shard: u64) -> Result<i64>;
+    /// Aggregate a proof for the pushed leaves of `native_shard`. The flag is false if no proof could be built.
+    fn agg(&self, native_shard: u64) -> Result<(String, bool)>;
+    /// Check `proof` against the claimed balances and the committed digest of `shard`.
+    ///
+    /// Proofs are not bound to a round: a proof is accepted against the current or the
+    /// previous digest of `shard`, so a proof aggregated before a commit stays valid until
+    /// the next one. Callers must not rely on a proof being fresher than that window.
+    fn verify_proof(&self, proof: &str, shard: u64, round: u64) -> Result<bool>;
+    /// Fold the queued deltas into the vectors and refresh their digests. Fails without
+    /// committing anything if a balance would drop below zero.
+    fn commit(&self, native_shard: u64, round: u64) -> Result<i64>;
+    /// Rebuild the proof tree of `native_shard` from its committed vector.
+    fn update_tree(&self, native_shard: u64) -> Result<i64>;
+    /// Forget the digests of the previous commit.
+    fn reset_prev_commit(&self) -> Result<i64>;
+    /// Committed digest of the vector of `shard`, or `None` before the first commit.
+    fn digest(&self, shard: u64) -> Result<Option<H256>>;
+}
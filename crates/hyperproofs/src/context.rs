@@ -0,0 +1,564 @@
+//! Per-node shard state.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use ethereum_types::{Address, H256, U256};
+use log::warn;
+
+use crate::backend::{HyperproofBackend, Result};
+use crate::{
+    LeafIndex, ModuloPlacement, PlacementConfig, PrefixPlacement, ShardConfig, ShardKey,
+    ShardPlacement, UNKNOWN_SHARD,
+};
+
+/// Round number reported before the first commit.
+const NO_COMMIT: u64 = u64::MAX;
+
+/// Number of hop buckets; transactions with more hops share the last bucket.
+pub const HOP_BUCKETS: usize = 7;
+
+/// Placement built from the chain spec alone. A directory contract can only be
+/// read once a client exists, until then addresses are placed by modulo.
+fn default_placement(config: &ShardConfig) -> Arc<dyn ShardPlacement> {
+    match config.placement {
+        PlacementConfig::PrefixRanges(ref ranges) => Arc::new(PrefixPlacement::new(ranges.clone())),
+        PlacementConfig::Modulo | PlacementConfig::Directory(_) => {
+            Arc::new(ModuloPlacement::new(config.shard_count))
+        }
+    }
+}
+
+#[cfg(feature = "go-ffi")]
+fn default_backend(_config: &ShardConfig) -> Arc<dyn HyperproofBackend> {
+    Arc::new(crate::GoBackend::from_env().expect("hyperproofs Go library could not be loaded"))
+}
+
+#[cfg(not(feature = "go-ffi"))]
+fn default_backend(config: &ShardConfig) -> Arc<dyn HyperproofBackend> {
+    Arc::new(crate::NativeBackend::with_depth(config.vector_depth))
+}
+
+/// How a sealed block ended up with respect to its aggregated proof.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProofOutcome {
+    /// The proof was ready in time and sealed into the block.
+    Ready,
+    /// The deadline passed and the block was sealed without a proof.
+    TimedOut,
+    /// Aggregation failed and the block was sealed without a proof.
+    Failed,
+}
+
+impl ProofOutcome {
+    /// All outcomes, in counter order.
+    pub const ALL: [ProofOutcome; 3] = [
+        ProofOutcome::Ready,
+        ProofOutcome::TimedOut,
+        ProofOutcome::Failed,
+    ];
+
+    /// Name used in logs and metric labels.
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ProofOutcome::Ready => "ready",
+            ProofOutcome::TimedOut => "timeout",
+            ProofOutcome::Failed => "failed",
+        }
+    }
+}
+
+/// Number of runs and total time of a timed operation.
+#[derive(Debug, Default)]
+pub struct OperationTimer {
+    count: AtomicU64,
+    micros: AtomicU64,
+}
+
+impl OperationTimer {
+    fn record(&self, elapsed: Duration) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.micros
+            .fetch_add(elapsed.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Number of recorded runs.
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Total time of all recorded runs, in microseconds.
+    pub fn micros(&self) -> u64 {
+        self.micros.load(Ordering::Relaxed)
+    }
+}
+
+/// Execution statistics of a shard node.
+#[derive(Debug, Default)]
+pub struct ShardCounters {
+    sload: AtomicU64,
+    sstore: AtomicU64,
+    bal_read: AtomicU64,
+    bal_write: AtomicU64,
+    hops: [AtomicU64; HOP_BUCKETS],
+    reverted: AtomicU64,
+    aggregation: OperationTimer,
+    verification: OperationTimer,
+    commit: OperationTimer,
+    proof_outcomes: [AtomicU64; 3],
+}
+
+impl ShardCounters {
+    fn hop_bucket(hop: u64) -> usize {
+        match hop {
+            1..=6 => hop as usize - 1,
+            _ => HOP_BUCKETS - 1,
+        }
+    }
+
+    pub fn incr_sload(&self, delta: u64) {
+        self.sload.fetch_add(delta, Ordering::Relaxed);
+    }
+
+    pub fn sload(&self) -> u64 {
+        self.sload.load(Ordering::Relaxed)
+    }
+
+    pub fn incr_sstore(&self, delta: u64) {
+        self.sstore.fetch_add(delta, Ordering::Relaxed);
+    }
+
+    pub fn sstore(&self) -> u64 {
+        self.sstore.load(Ordering::Relaxed)
+    }
+
+    pub fn incr_bal_read(&self, delta: u64) {
+        self.bal_read.fetch_add(delta, Ordering::Relaxed);
+    }
+
+    pub fn bal_read(&self) -> u64 {
+        self.bal_read.load(Ordering::Relaxed)
+    }
+
+    pub fn incr_bal_write(&self, delta: u64) {
+        self.bal_write.fetch_add(delta, Ordering::Relaxed);
+    }
+
+    pub fn bal_write(&self) -> u64 {
+        self.bal_write.load(Ordering::Relaxed)
+    }
+
+    /// Count a transaction completed after `hop` hops.
+    pub fn incr_hop(&self, hop: u64) {
+        self.hops[Self::hop_bucket(hop)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Transactions completed after `hop` hops, or after 7 or more for `hop >= 7`.
+    pub fn hop(&self, hop: u64) -> u64 {
+        self.hops[Self::hop_bucket(hop)].load(Ordering::Relaxed)
+    }
+
+    pub fn incr_reverted(&self) {
+        self.reverted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn reverted(&self) -> u64 {
+        self.reverted.load(Ordering::Relaxed)
+    }
+
+    /// Proof aggregations run by this node.
+    pub fn aggregation(&self) -> &OperationTimer {
+        &self.aggregation
+    }
+
+    /// Proof verifications run by this node.
+    pub fn verification(&self) -> &OperationTimer {
+        &self.verification
+    }
+
+    /// Round commits run by this node.
+    pub fn commit(&self) -> &OperationTimer {
+        &self.commit
+    }
+
+    /// Count a block sealed with the given proof outcome.
+    pub fn incr_proof_outcome(&self, outcome: ProofOutcome) {
+        self.proof_outcomes[outcome as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Blocks sealed with the given proof outcome.
+    pub fn proof_outcome(&self, outcome: ProofOutcome) -> u64 {
+        self.proof_outcomes[outcome as usize].load(Ordering::Relaxed)
+    }
+}
+
+/// Shard state of a single node: topology, local shard, commit progress,
+/// statistics, the vector-commitment backend and its leaf allocations.
+///
+/// Owned by the miner and shared with the client, state and externalities,
+/// so several shard nodes can live in one process.
+pub struct ShardContext {
+    config: ShardConfig,
+    backend: Arc<dyn HyperproofBackend>,
+    leaves: LeafIndex,
+    placement: RwLock<Arc<dyn ShardPlacement>>,
+    shard: AtomicU64,
+    last_commit_round: AtomicU64,
+    latest_imported_block: AtomicU64,
+    counters: ShardCounters,
+    verification: Mutex<()>,
+}
+
+impl ShardContext {
+    /// Create a context for `config` using the default backend.
+    pub fn new(config: ShardConfig) -> Self {
+        let backend = default_backend(&config);
+        ShardContext::with_backend(config, backend)
+    }
+
+    /// Create a context for `config` using the given backend.
+    pub fn with_backend(config: ShardConfig, backend: Arc<dyn HyperproofBackend>) -> Self {
+        ShardContext {
+            leaves: LeafIndex::new(config.vector_depth),
+            placement: RwLock::new(default_placement(&config)),
+            config,
+            backend,
+            shard: AtomicU64::new(0),
+            last_commit_round: AtomicU64::new(NO_COMMIT),
+            latest_imported_block: AtomicU64::new(0),
+            counters: ShardCounters::default(),
+            verification: Mutex::new(()),
+        }
+    }
+
+    pub fn config(&self) -> &ShardConfig {
+        &self.config
+    }
+
+    pub fn shard_count(&self) -> u64 {
+        self.config.shard_count
+    }
+
+    /// Number of blocks after which shard data is committed.
+    pub fn round_length(&self) -> u64 {
+        self.config.round_length
+    }
+
+    /// Hops a cross-shard call may take before it is finalized as reverted.
+    pub fn max_hops(&self) -> u64 {
+        self.config.max_hops
+    }
+
+    /// Shard sealed by the given author.
+    pub fn author_shard(&self, author: &Address) -> u64 {
+        self.config.author_shard(author)
+    }
+
+    /// Leaf allocations of the canonical chain.
+    pub fn leaves(&self) -> &LeafIndex {
+        &self.leaves
+    }
+
+    /// Shard holding the state of `address`.
+    pub fn address_shard(&self, address: &Address) -> u64 {
+        self.placement
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .shard_of(address)
+    }
+
+    /// Account whose shard holds the storage of `contract` touched while executing
+    /// for `origin`: the contract itself if its storage is shared, `origin` otherwise.
+    pub fn storage_owner(&self, contract: &Address, origin: &Address) -> Address {
+        if self.config.shared_storage.contains(contract) {
+            *contract
+        } else {
+            *origin
+        }
+    }
+
+    /// Replace the placement policy, e.g. once a directory contract can be read.
+    pub fn set_placement(&self, placement: Arc<dyn ShardPlacement>) {
+        *self.placement.write().unwrap_or_else(|e| e.into_inner()) = placement;
+    }
+
+    /// Shard of this node.
+    pub fn shard(&self) -> u64 {
+        self.shard.load(Ordering::SeqCst)
+    }
+
+    /// Make this node seal for the shard of `author`.
+    pub fn set_author_shard(&self, author: &Address) -> u64 {
+        let shard = self.author_shard(author);
+        if shard == UNKNOWN_SHARD {
+            warn!(target: "shard", "Author {:?} is not part of the shard topology", author);
+        }
+        self.set_shard(shard);
+        shard
+    }
+
+    /// Make this node execute for `shard` directly, e.g. to replay a shard transaction.
+    pub fn set_shard(&self, shard: u64) {
+        self.shard.store(shard, Ordering::SeqCst);
+    }
+
+    /// Block number of the last committed round.
+    pub fn last_commit_round(&self) -> u64 {
+        self.last_commit_round.load(Ordering::SeqCst)
+    }
+
+    pub fn set_last_commit_round(&self, round: u64) {
+        self.last_commit_round.store(round, Ordering::SeqCst);
+    }
+
+    pub fn latest_imported_block(&self) -> u64 {
+        self.latest_imported_block.load(Ordering::SeqCst)
+    }
+
+    pub fn set_latest_imported_block(&self, block: u64) {
+        self.latest_imported_block.store(block, Ordering::SeqCst);
+    }
+
+    pub fn counters(&self) -> &ShardCounters {
+        &self.counters
+    }
+
+    /// Vector-commitment backend of this node.
+    pub fn backend(&self) -> &dyn HyperproofBackend {
+        &*self.backend
+    }
+
+    /// Aggregate a proof for the vector of `native_shard`, recording its latency.
+    pub fn aggregate(&self, native_shard: u64) -> Result<(String, bool)> {
+        let start = Instant::now();
+        let result = self.backend.agg(native_shard);
+        self.counters.aggregation.record(start.elapsed());
+        result
+    }
+
+    /// Verify a proof against the vector of `shard`, recording its latency.
+    pub fn verify_proof(&self, proof: &str, shard: u64, round: u64) -> Result<bool> {
+        let start = Instant::now();
+        let result = self.backend.verify_proof(proof, shard, round);
+        self.counters.verification.record(start.elapsed());
+        result
+    }
+
+    /// Verify a proof of the claimed `balances` of keys of `shard`.
+    ///
+    /// The claims are pushed and checked under one lock, as the transaction pool and
+    /// block import verify proofs concurrently against the same backend.
+    pub fn verify_balances(
+        &self,
+        proof: &str,
+        shard: u64,
+        balances: &[(ShardKey, U256)],
+    ) -> Result<bool> {
+        let _verification = self.verification.lock().unwrap_or_else(|e| e.into_inner());
+        self.backend.reset_address_balance_verify(shard)?;
+        for (key, balance) in balances {
+            // a key without a leaf on the canonical chain cannot be proven
+            let leaf = match self.leaves.get(key) {
+                Some(leaf) => leaf,
+                None => return Ok(false),
+            };
+            self.backend
+                .push_address_balance_verify(leaf, &balance.to_string(), shard)?;
+        }
+        self.verify_proof(proof, shard, 0u64)
+    }
+
+    /// Committed vector root of `shard`, or `None` before the first commit.
+    pub fn vector_root(&self, shard: u64) -> Result<Option<H256>> {
+        self.backend.digest(shard)
+    }
+
+    /// Commit the pending deltas of this node's shard for the round ending at
+    /// `block_number`, recording its duration.
+    pub fn commit_round(&self, block_number: u64) -> Result<i64> {
+        let start = Instant::now();
+        let result = self.backend.commit(self.shard(), 0u64);
+        self.counters.commit.record(start.elapsed());
+        if result.is_ok() {
+            self.set_last_commit_round(block_number);
+        }
+        result
+    }
+}
+
+impl Default for ShardContext {
+    fn default() -> Self {
+        ShardContext::new(ShardConfig::default())
+    }
+}
+
+impl fmt::Debug for ShardContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ShardContext")
+            .field("config", &self.config)
+            .field("shard", &self.shard())
+            .field("last_commit_round", &self.last_commit_round())
+            .field("latest_imported_block", &self.latest_imported_block())
+            .field("counters", &self.counters)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn two_shard_config() -> ShardConfig {
+        let mut authors = BTreeMap::new();
+        authors.insert(Address::from_low_u64_be(1), 0u64);
+        authors.insert(Address::from_low_u64_be(2), 1u64);
+        ShardConfig {
+            shard_count: 2,
+            round_length: 2,
+            authors,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn contexts_are_independent() {
+        let first = ShardContext::new(two_shard_config());
+        let second = ShardContext::new(two_shard_config());
+
+        assert_eq!(first.set_author_shard(&Address::from_low_u64_be(1)), 0);
+        assert_eq!(second.set_author_shard(&Address::from_low_u64_be(2)), 1);
+        first.counters().incr_sload(3);
+        first.set_last_commit_round(4);
+
+        assert_eq!(first.shard(), 0);
+        assert_eq!(second.shard(), 1);
+        assert_eq!(first.counters().sload(), 3);
+        assert_eq!(second.counters().sload(), 0);
+        assert_eq!(second.last_commit_round(), NO_COMMIT);
+    }
+
+    #[test]
+    fn hops_beyond_six_share_a_bucket() {
+        let context = ShardContext::default();
+        context.counters().incr_hop(1);
+        context.counters().incr_hop(7);
+        context.counters().incr_hop(12);
+
+        assert_eq!(context.counters().hop(1), 1);
+        assert_eq!(context.counters().hop(2), 0);
+        assert_eq!(context.counters().hop(7), 2);
+    }
+
+    #[test]
+    fn commit_round_is_timed() {
+        let context = ShardContext::new(two_shard_config());
+        context.backend().init(0).unwrap();
+        context.commit_round(4).unwrap();
+
+        assert_eq!(context.last_commit_round(), 4);
+        assert_eq!(context.counters().commit().count(), 1);
+        assert_eq!(context.counters().aggregation().count(), 0);
+    }
+
+    #[test]
+    fn failed_commit_does_not_end_the_round() {
+        let context = ShardContext::new(two_shard_config());
+        context.backend().init(0).unwrap();
+        let leaf = context
+            .leaves()
+            .index(&ShardKey::from_low_u64_be(1))
+            .unwrap();
+        context.backend().push_address_delta(leaf, "-1", 0).unwrap();
+
+        assert!(context.commit_round(4).is_err());
+        assert_ne!(context.last_commit_round(), 4);
+    }
+
+    #[test]
+    fn verifies_claimed_balances() {
+        let context = ShardContext::default();
+        let key = ShardKey::from_low_u64_be(1);
+        let leaf = context.leaves().index(&key).unwrap();
+        context.backend().init(0).unwrap();
+        context
+            .backend()
+            .push_address_delta(leaf, "100", 0)
+            .unwrap();
+        context.commit_round(0).unwrap();
+        context.backend().update_tree(0).unwrap();
+        context.backend().push_address_commit(leaf, 0).unwrap();
+        let (proof, _) = context.aggregate(0).unwrap();
+
+        assert!(context
+            .verify_balances(&proof, 0, &[(key, 100.into())])
+            .unwrap());
+        assert!(!context
+            .verify_balances(&proof, 0, &[(key, 101.into())])
+            .unwrap());
+        assert!(!context
+            .verify_balances(&proof, 0, &[(ShardKey::from_low_u64_be(2), 0.into())])
+            .unwrap());
+        assert_eq!(context.counters().verification().count(), 2);
+    }
+
+    #[test]
+    fn address_shard_follows_shard_count() {
+        let context = ShardContext::new(two_shard_config());
+
+        assert_eq!(context.address_shard(&Address::from_low_u64_be(5)), 1);
+        assert_eq!(context.address_shard(&Address::from_low_u64_be(6)), 0);
+    }
+
+    #[test]
+    fn shard_can_be_set_without_an_author() {
+        let context = ShardContext::new(two_shard_config());
+        context.set_shard(1);
+
+        assert_eq!(context.shard(), 1);
+        assert_eq!(context.set_author_shard(&Address::from_low_u64_be(1)), 0);
+        assert_eq!(context.shard(), 0);
+    }
+
+    #[test]
+    fn address_shard_follows_placement() {
+        let mut ranges = BTreeMap::new();
+        ranges.insert(Address::from_low_u64_be(100), 1);
+        let config = ShardConfig {
+            placement: PlacementConfig::PrefixRanges(ranges),
+            ..two_shard_config()
+        };
+        let context = ShardContext::new(config);
+
+        assert_eq!(context.address_shard(&Address::from_low_u64_be(5)), 0);
+        assert_eq!(context.address_shard(&Address::from_low_u64_be(101)), 1);
+
+        context.set_placement(Arc::new(ModuloPlacement::new(2)));
+        assert_eq!(context.address_shard(&Address::from_low_u64_be(5)), 1);
+    }
+
+    #[test]
+    fn shared_storage_is_owned_by_the_contract() {
+        let pair = Address::from_low_u64_be(3);
+        let token = Address::from_low_u64_be(5);
+        let user = Address::from_low_u64_be(6);
+        let config = ShardConfig {
+            shared_storage: vec![pair].into_iter().collect(),
+            ..two_shard_config()
+        };
+        let context = ShardContext::new(config);
+
+        assert_eq!(context.storage_owner(&pair, &user), pair);
+        assert_eq!(context.storage_owner(&token, &user), user);
+        assert_eq!(
+            context.address_shard(&context.storage_owner(&pair, &user)),
+            1
+        );
+        assert_eq!(
+            context.address_shard(&context.storage_owner(&token, &user)),
+            0
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Backend calling into the Go hyperproofs library (`libshard.so`).
+
+#![allow(dead_code)]
+
+include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+
+use std::env;
+use std::ffi::{CStr, CString};
+
+use ethereum_types::H256;
+use libloading as lib;
+
+use crate::backend::{Error, HyperproofBackend, Result};
+
+/// Environment variable overriding the path of `libshard.so` at runtime.
+pub const LIBRARY_PATH_ENV: &str = "HYPERPROOFS_GO_LIB";
+
+fn library_error(e: ::std::io::Error) -> Error {
+    Error::Library(format!("{}", e))
+}
+
+fn go_string(s: &CString) -> GoString {
+    GoString {
+        p: s.as_ptr(),
+        n: s.as_bytes().len() as isize,
+    }
+}
+
+fn c_string(s: &str) -> Result<CString> {
+    CString::new(s).map_err(|e| Error::InvalidInput(format!("{}", e)))
+}
+
+/// Hyperproofs backend implemented by the Go library.
+pub struct GoBackend {
+    library: lib::Library,
+}
+
+impl GoBackend {
+    /// Load the library from `path`.
+    pub fn new(path: &str) -> Result<Self> {
+        let library = lib::Library::new(path).map_err(library_error)?;
+        Ok(GoBackend { library })
+    }
+
+    /// Load the library from `HYPERPROOFS_GO_LIB`, or from the directory it was linked against.
+    pub fn from_env() -> Result<Self> {
+        let path = env::var(LIBRARY_PATH_ENV)
+            .unwrap_or_else(|_| concat!(env!("HYPERPROOFS_LIB_DIR"), "/libshard.so").into());
+        GoBackend::new(&path)
+    }
+
+    fn symbol<T>(&self, name: &[u8]) -> Result<lib::Symbol<T>> {
+        unsafe { self.library.get(name).map_err(library_error) }
+    }
+}
+
+impl HyperproofBackend for GoBackend {
+    fn init(&self, round: u64) -> Result<i64> {
+        let func = self.symbol::<unsafe extern "C" fn(r: u64) -> i64>(b"initVc")?;
+        Ok(unsafe { func(round) })
+    }
+
+    fn push_address_delta(&self, address: u64, delta: &str, shard: u64) -> Result<i64> {
+        let c_delta = c_string(delta)?;
+        let func = self.symbol::<unsafe extern "C" fn(a: u64, d: GoString, s: u64) -> i64>(
+            b"pushAddressDeltaVc",
+        )?;
+        Ok(unsafe { func(address, go_string(&c_delta), shard) })
+    }
+
+    fn reset_address_delta(&self, shard: u64) -> Result<i64> {
+        let func = self.symbol::<unsafe extern "C" fn(s: u64) -> i64>(b"resetAddressDeltaVc")?;
+        Ok(unsafe { func(shard) })
+    }
+
+    fn push_address_commit(&self, address: u64, shard: u64) -> Result<i64> {
+        let func =
+            self.symbol::<unsafe extern "C" fn(a: u64, s: u64) -> i64>(b"pushAddressCommitVc")?;
+        Ok(unsafe { func(address, shard) })
+    }
+
+    fn reset_address_commit(&self, shard: u64) -> Result<i64> {
+        let func = self.symbol::<unsafe extern "C" fn(s: u64) -> i64>(b"resetAddressCommitVc")?;
+        Ok(unsafe { func(shard) })
+    }
+
+    fn push_address_balance_verify(&self, address: u64, balance: &str, shard: u64) -> Result<i64> {
+        let c_bal = c_string(balance)?;
+        let func = self.symbol::<unsafe extern "C" fn(a: u64, b: GoString, s: u64) -> i64>(
+            b"pushAddressBalanceVerifyVc",
+        )?;
+        Ok(unsafe { func(address, go_string(&c_bal), shard) })
+    }
+
+    fn reset_address_balance_verify(&self, shard: u64) -> Result<i64> {
+        let func =
+            self.symbol::<unsafe extern "C" fn(s: u64) -> i64>(b"resetAddressBalanceVerifyVc")?;
+        Ok(unsafe { func(shard) })
+    }
+
+    fn agg(&self, native_shard: u64) -> Result<(String, bool)> {
+        let func = self.symbol::<unsafe extern "C" fn(s: u64) -> aggVc_return>(b"aggVc")?;
+        unsafe {
+            let output = func(native_shard);
+            let proof = CStr::from_ptr(output.r0).to_string_lossy().into_owned();
+            Ok((proof, output.r1 == 1u8))
+        }
+    }
+
+    fn verify_proof(&self, proof: &str, shard: u64, round: u64) -> Result<bool> {
+        let c_input = c_string(proof)?;
+        let func = self.symbol::<unsafe extern "C" fn(i: GoString, s: u64, r: u64) -> u8>(
+            b"verifyProofVc",
+        )?;
+        Ok(unsafe { func(go_string(&c_input), shard, round) } == 1u8)
+    }
+
+    fn commit(&self, native_shard: u64, round: u64) -> Result<i64> {
+        let func = self.symbol::<unsafe extern "C" fn(n: u64, r: u64) -> i64>(b"commitVc")?;
+        Ok(unsafe { func(native_shard, round) })
+    }
+
+    fn update_tree(&self, native_shard: u64) -> Result<i64> {
+        let func =
+            self.symbol::<unsafe extern "C" fn(n: u64) -> i64>(b"updateShardProofTreeVc")?;
+        Ok(unsafe { func(native_shard) })
+    }
+
+    fn reset_prev_commit(&self) -> Result<i64> {
+        let func = self.symbol::<unsafe extern "C" fn() -> i64>(b"prevDigestResetVc")?;
+        Ok(unsafe { func() })
+    }
+
+    fn digest(&self, _shard: u64) -> Result<Option<H256>> {
+        Err(Error::Library(
+            "libshard does not export the vector digests".into(),
+        ))
+    }
+}
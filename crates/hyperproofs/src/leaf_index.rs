@@ -0,0 +1,264 @@
+//! Allocation of vector leaves to addresses and storage keys.
+//!
+//! A key starts probing at the leaf picked by its keccak hash and takes the
+//! first free leaf from there, so keys which do not collide always land on
+//! the same leaf and colliding keys are resolved in allocation order, which
+//! is the block execution order.
+//!
+//! Blocks allocate into their own `PendingLeaves`, on top of the allocations
+//! of their branch. The index only holds the allocations of the canonical
+//! chain: those of a block are loaded once it is enacted and released again
+//! when it is retracted.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, MutexGuard};
+
+use keccak_hash::keccak;
+use log::warn;
+
+use crate::backend::{Error, Result};
+use crate::ShardKey;
+
+#[derive(Default)]
+struct Table {
+    indexes: HashMap<ShardKey, u64>,
+    owners: HashMap<u64, ShardKey>,
+}
+
+/// Leaves allocated while executing a block.
+///
+/// A block on a branch other than the best chain also sees the allocations of its
+/// own ancestors instead of the canonical ones made since the fork.
+#[derive(Debug, Clone, Default)]
+pub struct PendingLeaves {
+    indexes: HashMap<ShardKey, u64>,
+    used: HashSet<u64>,
+    released: HashSet<ShardKey>,
+    fresh: Vec<(ShardKey, u64)>,
+}
+
+impl PendingLeaves {
+    /// Allocations seen by a block whose branch forked off the best chain: the
+    /// canonical allocations `retracted` since the fork do not exist on it, the ones
+    /// `enacted` by its own ancestors do.
+    pub fn on_branch<R, E>(retracted: R, enacted: E) -> Self
+    where
+        R: IntoIterator<Item = (ShardKey, u64)>,
+        E: IntoIterator<Item = (ShardKey, u64)>,
+    {
+        let mut pending = PendingLeaves::default();
+        pending
+            .released
+            .extend(retracted.into_iter().map(|(key, _)| key));
+        for (key, index) in enacted {
+            pending.indexes.insert(key, index);
+            pending.used.insert(index);
+        }
+        pending
+    }
+
+    /// Leaves allocated by the block, in allocation order.
+    pub fn fresh(&self) -> &[(ShardKey, u64)] {
+        &self.fresh
+    }
+}
+
+/// Collision-free mapping of keys to the leaves of a shard vector.
+pub struct LeafIndex {
+    capacity: u64,
+    table: Mutex<Table>,
+}
+
+impl LeafIndex {
+    /// Create an empty index for vectors of `2^depth` leaves.
+    pub fn new(depth: u8) -> Self {
+        LeafIndex {
+            capacity: 1u64 << depth,
+            table: Mutex::new(Table::default()),
+        }
+    }
+
+    fn table(&self) -> MutexGuard<'_, Table> {
+        self.table.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Number of leaves available.
+    pub fn capacity(&self) -> u64 {
+        self.capacity
+    }
+
+    /// Number of allocated leaves.
+    pub fn len(&self) -> usize {
+        self.table().indexes.len()
+    }
+
+    /// Whether no leaf has been allocated yet.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Leaf of `key` on the canonical chain, if one is allocated.
+    pub fn get(&self, key: &ShardKey) -> Option<u64> {
+        self.table().indexes.get(key).cloned()
+    }
+
+    /// Leaf of `key` as seen by the block allocating into `pending`, allocating one
+    /// to the block if needed. The index itself is left untouched.
+    pub fn allocate(&self, key: &ShardKey, pending: &mut PendingLeaves) -> Result<u64> {
+        if let Some(index) = pending.indexes.get(key) {
+            return Ok(*index);
+        }
+        let table = self.table();
+        if !pending.released.contains(key) {
+            if let Some(index) = table.indexes.get(key) {
+                return Ok(*index);
+            }
+        }
+        let is_free = |index: &u64| {
+            !pending.used.contains(index)
+                && match table.owners.get(index) {
+                    Some(owner) => pending.released.contains(owner),
+                    None => true,
+                }
+        };
+        let start = keccak(key.as_bytes()).to_low_u64_be() % self.capacity;
+        let index = (0..self.capacity)
+            .map(|i| (start + i) % self.capacity)
+            .find(is_free)
+            .ok_or_else(|| {
+                Error::InvalidInput(format!("all {} vector leaves are allocated", self.capacity))
+            })?;
+        pending.indexes.insert(*key, index);
+        pending.used.insert(index);
+        pending.fresh.push((*key, index));
+        Ok(index)
+    }
+
+    /// Leaf of `key`, allocating one straight into the index if needed. Only for
+    /// allocations every node makes the same way outside of blocks, such as the
+    /// genesis balances.
+    pub fn index(&self, key: &ShardKey) -> Result<u64> {
+        let mut pending = PendingLeaves::default();
+        let index = self.allocate(key, &mut pending)?;
+        self.load(pending.fresh);
+        Ok(index)
+    }
+
+    /// Add allocations of the canonical chain, e.g. of an enacted block or persisted
+    /// before a restart. Entries out of range of this index are dropped.
+    pub fn load<I: IntoIterator<Item = (ShardKey, u64)>>(&self, entries: I) {
+        let mut table = self.table();
+        for (key, index) in entries {
+            if index >= self.capacity {
+                warn!(target: "shard", "Dropping leaf {} of {:?}, vectors only have {} leaves", index, key, self.capacity);
+                continue;
+            }
+            if let Some(previous) = table.indexes.insert(key, index) {
+                table.owners.remove(&previous);
+            }
+            if let Some(owner) = table.owners.insert(index, key) {
+                if owner != key {
+                    warn!(target: "shard", "Leaf {} moves from {:?} to {:?}", index, owner, key);
+                    table.indexes.remove(&owner);
+                }
+            }
+        }
+    }
+
+    /// Remove allocations which are no longer canonical, e.g. of a retracted block.
+    pub fn release<I: IntoIterator<Item = (ShardKey, u64)>>(&self, entries: I) {
+        let mut table = self.table();
+        for (key, index) in entries {
+            if table.indexes.get(&key) == Some(&index) {
+                table.indexes.remove(&key);
+                table.owners.remove(&index);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethereum_types::Address;
+
+    fn key(low: u64) -> ShardKey {
+        ShardKey::from_low_u64_be(low)
+    }
+
+    #[test]
+    fn colliding_keys_get_distinct_leaves() {
+        let index = LeafIndex::new(1);
+        let keys: Vec<_> = (0..2).map(key).collect();
+
+        let first = index.index(&keys[0]).unwrap();
+        let second = index.index(&keys[1]).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(index.index(&keys[0]).unwrap(), first);
+        assert!(index.index(&key(2)).is_err());
+    }
+
+    #[test]
+    fn allocation_does_not_depend_on_low_address_bits() {
+        let index = LeafIndex::new(16);
+        let a = ShardKey::from(Address::from_low_u64_be(0x1_0001));
+        let b = ShardKey::from(Address::from_low_u64_be(0x2_0001));
+
+        assert_ne!(index.index(&a).unwrap(), index.index(&b).unwrap());
+    }
+
+    #[test]
+    fn loaded_allocations_are_kept() {
+        let index = LeafIndex::new(4);
+        let loaded = key(7);
+        index.load(vec![(loaded, 3), (key(8), 16)]);
+
+        assert_eq!(index.get(&loaded), Some(3));
+        assert_eq!(index.len(), 1);
+
+        let mut pending = PendingLeaves::default();
+        assert_eq!(index.allocate(&loaded, &mut pending).unwrap(), 3);
+        let other = key(9);
+        let leaf = index.allocate(&other, &mut pending).unwrap();
+        assert_ne!(leaf, 3);
+        assert_eq!(pending.fresh(), &[(other, leaf)][..]);
+    }
+
+    #[test]
+    fn block_allocations_stay_pending_until_loaded() {
+        let index = LeafIndex::new(1);
+        let mut first = PendingLeaves::default();
+        let mut second = PendingLeaves::default();
+
+        // competing blocks allocate independently of each other
+        let leaf = index.allocate(&key(1), &mut first).unwrap();
+        assert_eq!(index.allocate(&key(2), &mut second).unwrap(), leaf);
+        assert_eq!(index.get(&key(1)), None);
+
+        index.load(first.fresh().to_vec());
+        assert_eq!(index.get(&key(1)), Some(leaf));
+        assert_ne!(
+            index
+                .allocate(&key(2), &mut PendingLeaves::default())
+                .unwrap(),
+            leaf
+        );
+
+        index.release(first.fresh().to_vec());
+        assert!(index.is_empty());
+    }
+
+    #[test]
+    fn branch_sees_its_own_allocations() {
+        let index = LeafIndex::new(1);
+        let canonical = index.index(&key(1)).unwrap();
+        let mut branch =
+            PendingLeaves::on_branch(vec![(key(1), canonical)], vec![(key(2), canonical)]);
+
+        assert_eq!(index.allocate(&key(2), &mut branch).unwrap(), canonical);
+        // the leaf of the retracted key is taken on this branch
+        assert_eq!(index.allocate(&key(1), &mut branch).unwrap(), 1 - canonical);
+        assert_eq!(branch.fresh(), &[(key(1), 1 - canonical)][..]);
+    }
+}
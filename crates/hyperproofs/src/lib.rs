@@ -1,154 +1,89 @@
-
-
 #![allow(non_upper_case_globals)]
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
+mod backend;
+mod context;
+#[cfg(feature = "go-ffi")]
+mod go;
+mod leaf_index;
+mod native;
+mod placement;
+
+pub use backend::{Error, HyperproofBackend, Result};
+pub use context::{OperationTimer, ProofOutcome, ShardContext, ShardCounters, HOP_BUCKETS};
+#[cfg(feature = "go-ffi")]
+pub use go::GoBackend;
+pub use leaf_index::{LeafIndex, PendingLeaves};
+pub use native::NativeBackend;
+pub use placement::{ModuloPlacement, PlacementConfig, PrefixPlacement, ShardPlacement};
+
+use ethereum_types::{Address, H256, U256};
+use std::collections::{BTreeMap, BTreeSet};
+use keccak_hash::keccak;
 
-include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+/// Shard topology of the network, as declared in the chain spec.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShardConfig {
+    /// Number of shards in the network.
+    pub shard_count: u64,
+    /// Number of blocks in a commit round.
+    pub round_length: u64,
+    /// Mapping of block authors to the shard they seal for.
+    pub authors: BTreeMap<Address, u64>,
+    /// Depth of the shard vectors, which hold `2^vector_depth` leaves.
+    pub vector_depth: u8,
+    /// Placement of accounts on shards.
+    pub placement: PlacementConfig,
+    /// Hops a cross-shard call may take before it is finalized as reverted.
+    pub max_hops: u64,
+    /// Contracts whose storage belongs to the contract itself and is kept on its
+    /// home shard, rather than on the shard of the account it is executed for.
+    pub shared_storage: BTreeSet<Address>,
+}
+
+impl Default for ShardConfig {
+    fn default() -> Self {
+        ShardConfig {
+            shard_count: 1u64,
+            round_length: 1u64,
+            authors: BTreeMap::new(),
+            vector_depth: DEFAULT_VECTOR_DEPTH,
+            placement: PlacementConfig::Modulo,
+            max_hops: DEFAULT_MAX_HOPS,
+            shared_storage: BTreeSet::new(),
+        }
+    }
+}
 
-// pub fn init() {
+impl ShardConfig {
+    /// Shard sealed by the given author.
+    pub fn author_shard(&self, author: &Address) -> u64 {
+        self.authors.get(author).cloned().unwrap_or(UNKNOWN_SHARD)
+    }
+}
 
-// unsafe { initVc() };
+/// Depth of the shard vectors if the chain spec does not set one.
+pub const DEFAULT_VECTOR_DEPTH: u8 = 16;
 
-// }
-extern crate libloading as lib;
-use std::thread;
-use std::ffi::{CString, CStr};
-use std::sync::mpsc;
-use std::time::Duration;
-use ethereum_types::{Address, BigEndianHash, H160, H256, U256};
-use std::str::FromStr;
-use keccak_hash::keccak;
-use csv::Writer;
+/// Hop limit of cross-shard calls if the chain spec does not set one.
+pub const DEFAULT_MAX_HOPS: u64 = 16;
 
-static mut SHARD: u64 = 0u64;
-static mut LASTCOMMITROUND: u64 = 999u64;
-static mut GENESISCOMMIT: u64 = 0u64;
-static mut LATESTIMPORTEDBLOCK: u64 = 0u64;
-static mut SLOADCOUNT: u64 = 0u64;
-static mut SSTORECOUNT: u64 = 0u64;
-static mut BALREADCOUNT: u64 = 0u64;
-static mut BALWRITECOUNT: u64 = 0u64;
+/// Shard id reported for authors which are not part of the shard topology.
+pub const UNKNOWN_SHARD: u64 = 999u64;
 
-static mut HOPCOUNT_1: u64 = 0u64;
-static mut HOPCOUNT_2: u64 = 0u64;
-static mut HOPCOUNT_3: u64 = 0u64;
-static mut HOPCOUNT_4: u64 = 0u64;
-static mut HOPCOUNT_5: u64 = 0u64;
-static mut HOPCOUNT_6: u64 = 0u64;
-static mut HOPCOUNT_7: u64 = 0u64;
-static mut REVERTED: u64 = 0u64;
+/// Key of a balance or storage slot in the cross-shard data maps, the data lists of
+/// shard transactions and the vector leaves.
+pub type ShardKey = H256;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct AggProof{
     pub proof: String,
     pub ready: bool,
-    pub address: Vec<Address>,
+    pub address: Vec<ShardKey>,
     pub balance: Vec<U256>,
 }
 impl AggProof{
-    // pub fn write(){
-    //
-    // }
-    pub fn incr_hop_count(hop:u64){
-        match hop {
-            x if x==1u64  => unsafe{HOPCOUNT_1 += 1u64;},
-            x if x==2u64  => unsafe{HOPCOUNT_2 += 1u64;},
-            x if x==3u64  => unsafe{HOPCOUNT_3 += 1u64;},
-            x if x==4u64  => unsafe{HOPCOUNT_4 += 1u64;},
-            x if x==5u64  => unsafe{HOPCOUNT_5 += 1u64;},
-            x if x==6u64  => unsafe{HOPCOUNT_6 += 1u64;},
-            _  => unsafe{HOPCOUNT_7 += 1u64;},
-        }
-    }
-    pub fn incr_reverted_count(){
-            unsafe{REVERTED += 1u64;}
-    }
-
-    pub fn get_reverted_count()-> u64{
-        unsafe {
-            let o = REVERTED;
-            o
-        }
-    }
-
-    pub fn get_hop_count(hop:u64)->u64{
-        match hop {
-            x if x==1u64  => unsafe{let o = HOPCOUNT_1;
-                o},
-            x if x==2u64  => unsafe{let o = HOPCOUNT_2;
-                o},
-            x if x==3u64  => unsafe{let o = HOPCOUNT_3;
-                o},
-            x if x==4u64  => unsafe{let o = HOPCOUNT_4;
-                o},
-            x if x==5u64  => unsafe{let o = HOPCOUNT_5;
-                o},
-            x if x==6u64  => unsafe{let o = HOPCOUNT_6;
-                o},
-            _ => unsafe{let o = HOPCOUNT_7;
-                o},
-        }
-    }
-    pub fn get_latest_imported_block()->u64{
-        unsafe {
-            let o = LATESTIMPORTEDBLOCK;
-            o }
-    }
-    pub fn set_latest_imported_block(b: u64){
-        unsafe {
-             LATESTIMPORTEDBLOCK = b;
-             }
-    }
-    pub fn get_sload_count()->u64{
-        unsafe {
-            let o = SLOADCOUNT;
-            o }
-    }
-    pub fn incr_sload_count(delta: u64) {
-        unsafe{
-            let mut o = SLOADCOUNT;
-            o+= delta;
-            SLOADCOUNT = o;
-        }
-    }
-    pub fn get_sstore_count()->u64{
-        unsafe {
-            let o = SSTORECOUNT;
-            o }
-    }
-    pub fn incr_sstore_count(delta: u64) {
-        unsafe{
-            let mut o = SSTORECOUNT;
-            o+= delta;
-            SSTORECOUNT = o;
-        }
-    }
-    pub fn get_bal_read_count()->u64{
-        unsafe {
-            let o = BALREADCOUNT;
-            o }
-    }
-    pub fn incr_bal_read_count(delta: u64) {
-        unsafe{
-            let mut o = BALREADCOUNT;
-            o+= delta;
-            BALREADCOUNT = o;
-        }
-    }
-    pub fn get_bal_write_count()->u64{
-        unsafe {
-            let o = BALWRITECOUNT;
-            o }
-    }
-    pub fn incr_bal_write_count(delta: u64) {
-        unsafe{
-            let mut o = BALWRITECOUNT;
-            o+= delta;
-            BALWRITECOUNT = o;
-        }
-    }
     pub fn new() -> Self {
         AggProof{
             proof: String::new(),
@@ -157,346 +92,106 @@ impl AggProof{
             balance: Vec::new(),
         }
     }
-    pub fn concat_hash(x: H160, y: H256) -> H160{
-        let l = keccak([x.as_bytes(), y.as_bytes()].concat());
-        H160::from(l)
-    }
-    pub fn create_proof(&mut self) -> (){
-        if self.address.len() == 0{
-            return
-        }
-        self.ready = false;
-        for i in 0..self.address.len(){
-            pushAddressCommit(self.address[i].to_low_u64_be().rem_euclid(2u64.pow(16)),0u64);
-        }
-        match agg(0u64) {
-           Ok(T) => {
-               self.proof = T.0;
-               self.ready = true;
-           },
-            _ => {},
-        }
-
-    }
-    pub fn set_author_shard(address: Address) -> u64 {
-        let _s1 = Address::from_str("00bd138abd70e2f00903268f3db08f2d25677c9e").unwrap();
-        let _s2 = Address::from_str("00aa39d30f0d20ff03a22ccfc30b7efbfca597c2").unwrap();
-        let _s3 = Address::from_str("002e28950558fbede1a9675cb113f0bd20912019").unwrap();
-        let _s4 = Address::from_str("00a94ac799442fb13de8302026fd03068ba6a428").unwrap();
-        match address {
-            x if x==_s1  => unsafe{SHARD = 0u64;
-            0u64},
-            x if x==_s2 => unsafe{SHARD = 1u64;
-                1u64},
-            x if x==_s3 => unsafe{SHARD = 2u64;
-                2u64},
-            x if x==_s4 => unsafe{SHARD = 3u64;
-                3u64},
-            _ => unsafe{SHARD = 999u64; println!("panic!, shard not recognised");
-                999u64},
-        }
-    }
-    pub fn get_shard() -> u64 {
-        unsafe {
-            let o = SHARD;
-        o }
-    }
-    pub fn set_genesis_commit(status: u64) { unsafe{GENESISCOMMIT = status; } }
-    pub fn get_genesis_commit() -> u64 {
-        unsafe {
-            let o = GENESISCOMMIT;
-            o }
+    /// Cross-shard key of the balance of `address`.
+    pub fn balance_key(address: &Address) -> ShardKey {
+        ShardKey::from(*address)
     }
-    pub fn set_last_commit_shard(round: u64){
-        unsafe{LASTCOMMITROUND = round; }
+    /// Cross-shard key of the gas budget `sender` holds for its calls between two hops.
+    pub fn budget_key(sender: &Address) -> ShardKey {
+        keccak([b"budget".as_ref(), sender.as_bytes()].concat())
     }
-
-    pub fn get_last_commit_round() -> u64 {
-        unsafe {
-            let o = LASTCOMMITROUND;
-            o }
-    }
-
-    pub fn shard_count() -> u64 {
-        4u64
+    /// Cross-shard key of the storage `slot` of `contract` kept for `origin`.
+    ///
+    /// Covers the whole hash, so slots of different contracts or users never share a key.
+    pub fn storage_key(contract: &Address, origin: &Address, slot: &H256) -> ShardKey {
+        keccak([contract.as_bytes(), origin.as_bytes(), slot.as_bytes()].concat())
     }
-
-    pub fn block_data_count() -> u64 {128u64}
-    pub fn author_shard(address: Address) -> u64 {
-        let _s1 = Address::from_str("00bd138abd70e2f00903268f3db08f2d25677c9e").unwrap();
-        let _s2 = Address::from_str("00aa39d30f0d20ff03a22ccfc30b7efbfca597c2").unwrap();
-        let _s3 = Address::from_str("002e28950558fbede1a9675cb113f0bd20912019").unwrap();
-        let _s4 = Address::from_str("00a94ac799442fb13de8302026fd03068ba6a428").unwrap();
-        match address {
-           x if x==_s1  => 0u64,
-            x if x==_s2 => 1u64,
-            x if x==_s3 => 2u64,
-            x if x==_s4 => 3u64,
-            _ => 999u64,
-        }
-
-    }
-    pub fn init(round:u64) -> lib::Result<i64>{
-        let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-        unsafe {
-            let func: lib::Symbol<unsafe extern "C" fn(r:u64) -> i64 > = lib.get(b"initVc")?;
-            Ok(func(round))
+    /// Aggregate a proof of the balances of `address` on shard 0.
+    ///
+    /// Fails without touching the backend if a key has no vector leaf, since verifiers
+    /// reject a proof covering such a key.
+    pub fn create_proof(&mut self, context: &ShardContext) -> Result<()> {
+        if self.address.is_empty() {
+            return Ok(());
         }
+        self.ready = false;
+        let leaves = self
+            .address
+            .iter()
+            .map(|key| {
+                context.leaves().get(key).ok_or_else(|| {
+                    Error::InvalidInput(format!("no vector leaf for key {:?}", key))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let backend = context.backend();
+        for leaf in leaves {
+            backend.push_address_commit(leaf, 0u64)?;
+        }
+        let (proof, _) = context.aggregate(0u64)?;
+        self.proof = proof;
+        self.ready = true;
+        Ok(())
     }
-    pub fn agg(nativeShard: u64) -> lib::Result<(String,bool)>{
-        let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-        unsafe {
-            let func: lib::Symbol<unsafe extern "C" fn(s: u64) -> aggVc_return > = lib.get(b"aggVc")?;
-            match func(nativeShard) {
-                output => match output.r1 {
-                    1u8 => Ok((CStr::from_ptr(output.r0)
-                                   .to_string_lossy()
-                                   .into_owned(), true)
-                    ),
-                    _ => Ok((CStr::from_ptr(output.r0)
-                                 .to_string_lossy()
-                                 .into_owned(), false)
-                    ),
-                }
-            }
-        }
-    }
-
-    pub fn pushAddressDelta(address: u64, delta: String, shard: u64) -> lib::Result<i64>{
-        let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-        let c_delta = CString::new(delta)?;
-        let go_str_delta = GoString {
-            p: c_delta.as_ptr(),
-            n: c_delta.as_bytes().len() as isize,
-        };
-        unsafe {
-            let func: lib::Symbol<unsafe extern "C" fn(a: u64, d: GoString, s: u64) -> i64 > = lib.get(b"pushAddressDeltaVc")?;
-            Ok(func(address,go_str_delta,shard))
-        }
-    }
-    pub fn resetAddressDelta(shard: u64) -> lib::Result<i64>{
-        let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-        unsafe {
-            let func: lib::Symbol<unsafe extern "C" fn(s: u64) -> i64 > = lib.get(b"resetAddressDeltaVc")?;
-            Ok(func(shard))
-        }
-    }
-    //push address for which proof needs to be aggregated
-    pub fn pushAddressCommit(address: u64, shard: u64) -> lib::Result<i64>{
-        let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-        unsafe {
-            let func: lib::Symbol<unsafe extern "C" fn(a: u64, s: u64) -> i64 > = lib.get(b"pushAddressCommitVc")?;
-            Ok(func(address,shard))
-        }
-    }
-    //push address for which proof needs to be aggregated
-    pub fn resetAddressCommit(shard: u64) -> lib::Result<i64>{
-        let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-        unsafe {
-            let func: lib::Symbol<unsafe extern "C" fn(s: u64) -> i64 > = lib.get(b"resetAddressCommitVc")?;
-            Ok(func(shard))
-        }
-    }
-
-    pub fn pushAddressBalanceVerify(address: u64, bal: String, shard: u64) -> lib::Result<i64>{
-        let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-        let c_bal = CString::new(bal)?;
-        let go_str_bal = GoString {
-            p: c_bal.as_ptr(),
-            n: c_bal.as_bytes().len() as isize,
-        };
-        unsafe {
-            let func: lib::Symbol<unsafe extern "C" fn(a: u64, b: GoString, s: u64) -> i64 > = lib.get(b"pushAddressBalanceVerifyVc")?;
-            Ok(func(address,go_str_bal,shard))
-        }
-    }
-    pub fn resetAddressBalanceVerify(shard: u64) -> lib::Result<i64>{
-        let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-        unsafe {
-            let func: lib::Symbol<unsafe extern "C" fn(s: u64) -> i64 > = lib.get(b"resetAddressBalanceVerifyVc")?;
-            Ok(func(shard))
-        }
-    }
-    pub fn verifyProof(input: String, shard:u64,round:u64) -> lib::Result<bool>{
-        let c_input = CString::new(input)?;
-        let go_str_input = GoString {
-            p: c_input.as_ptr(),
-            n: c_input.as_bytes().len() as isize,
-        };
-        let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-        unsafe {
-            let func: lib::Symbol<unsafe extern "C" fn(i: GoString, s:u64, r:u64) -> u8> = lib.get(b"verifyProofVc")?;
-            match func(go_str_input, shard, round) {
-                1u8 => Ok(true),
-                _ => Ok(false)
-            }
-        }
-    }
-    pub fn commit(nativeShard: u64, round: u64) -> lib::Result<i64>{
-        let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-        unsafe {
-            let func: lib::Symbol<unsafe extern "C" fn(n: u64, r: u64) -> i64 > = lib.get(b"commitVc")?;
-            Ok(func(nativeShard,round))
-        }
-    }
-    pub fn updateTree(nativeShard: u64) -> lib::Result<i64>{
-        let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-        unsafe {
-            let func: lib::Symbol<unsafe extern "C" fn(n: u64) -> i64 > = lib.get(b"updateShardProofTreeVc")?;
-            Ok(func(nativeShard))
-        }
-    }
-    pub fn resetPrevCommit() -> lib::Result<i64>{
-        let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-        unsafe {
-            let func: lib::Symbol<unsafe extern "C" fn() -> i64 > = lib.get(b"prevDigestResetVc")?;
-            Ok(func())
-        }
-    }
-}
-pub fn init(round:u64) -> lib::Result<i64>{
-    let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-    unsafe {
-        let func: lib::Symbol<unsafe extern "C" fn(r:u64) -> i64 > = lib.get(b"initVc")?;
-        Ok(func(round))
-    }
-}
-
-pub fn pushAddressDelta(address: u64, delta: String, shard: u64) -> lib::Result<i64>{
-    let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-    let c_delta = CString::new(delta)?;
-    let go_str_delta = GoString {
-      p: c_delta.as_ptr(),
-      n: c_delta.as_bytes().len() as isize,
-    };
-    unsafe {
-        let func: lib::Symbol<unsafe extern "C" fn(a: u64, d: GoString, s: u64) -> i64 > = lib.get(b"pushAddressDeltaVc")?;
-        Ok(func(address,go_str_delta,shard))
-    }
-}
-//push address for which proof needs to be aggregated
-pub fn pushAddressCommit(address: u64, shard: u64) -> lib::Result<i64>{
-    let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-    unsafe {
-        let func: lib::Symbol<unsafe extern "C" fn(a: u64, s: u64) -> i64 > = lib.get(b"pushAddressCommitVc")?;
-        Ok(func(address,shard))
-    }
-}
-
-pub fn pushAddressBalanceVerify(address: u64, bal: String, shard: u64) -> lib::Result<i64>{
-    let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-    let c_bal = CString::new(bal)?;
-    let go_str_bal = GoString {
-      p: c_bal.as_ptr(),
-      n: c_bal.as_bytes().len() as isize,
-    };
-    unsafe {
-        let func: lib::Symbol<unsafe extern "C" fn(a: u64, b: GoString, s: u64) -> i64 > = lib.get(b"pushAddressBalanceVerifyVc")?;
-        Ok(func(address,go_str_bal,shard))
-    }
-}
-
-pub fn agg(nativeShard: u64) -> lib::Result<(String,bool)>{
-    let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-    unsafe {
-        let func: lib::Symbol<unsafe extern "C" fn(s: u64) -> aggVc_return > = lib.get(b"aggVc")?;
-        match func(nativeShard) {
-            output => match output.r1 {
-                1u8 => Ok((CStr::from_ptr(output.r0)
-                        .to_string_lossy()
-                        .into_owned(), true)
-                        ),
-                _ => Ok((CStr::from_ptr(output.r0)
-                        .to_string_lossy()
-                        .into_owned(), false)
-                        ),
-            } 
-        }
-            }
-}
-
-pub fn commit(nativeShard: u64, round: u64) -> lib::Result<i64>{
-    let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-    unsafe {
-        let func: lib::Symbol<unsafe extern "C" fn(n: u64, r: u64) -> i64 > = lib.get(b"commitVc")?;
-        Ok(func(nativeShard,round))
-    }
-}
-
-pub fn verifyProof(input: String, shard:u64,round:u64) -> lib::Result<bool>{
-    let c_input = CString::new(input)?;
-    let go_str_input = GoString {
-      p: c_input.as_ptr(),
-      n: c_input.as_bytes().len() as isize,
-    };
-    let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-    unsafe {        
-        let func: lib::Symbol<unsafe extern "C" fn(i: GoString, s:u64, r:u64) -> u8> = lib.get(b"verifyProofVc")?;
-        match func(go_str_input, shard, round) {
-            1u8 => Ok(true),
-            _ => Ok(false)
-        } 
-    }
-}
-
-pub fn demoProofShard() -> lib::Result<String>{
-    let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-    unsafe {        
-        let func: lib::Symbol<unsafe extern "C" fn() -> *mut ::std::os::raw::c_char> = lib.get(b"demoProof")?;
-        Ok(CStr::from_ptr(func()).to_string_lossy().into_owned())
-        // match func() {
-            // _ => return Ok("as".to_string()),
-            // GoString{p:_,n:_} => return Ok("as".to_string()), 
-            //return Ok(CStr::from_ptr(p).to_string_lossy().into_owned()),
-        // }
-    }
+    pub fn block_data_count() -> u64 {128u64}
 }
 
-pub fn demoVerifyShard(input: String) -> lib::Result<u8>{
-    let c_input = CString::new(input)?;
-    let go_str_input = GoString {
-      p: c_input.as_ptr(),
-      n: c_input.as_bytes().len() as isize,
-    };
-    let lib = lib::Library::new("/home/srisht/libhyper/hyperproofs-go/libshard.so")?;
-    unsafe {        
-        let func: lib::Symbol<unsafe extern "C" fn(i: GoString) -> u8> = lib.get(b"demoVerify")?;
-        Ok(func(go_str_input))
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn storage_keys_are_scoped_by_contract_and_origin() {
+        let token_a = Address::from_low_u64_be(0xa);
+        let token_b = Address::from_low_u64_be(0xb);
+        let user = Address::from_low_u64_be(1);
+        let other = Address::from_low_u64_be(2);
+        let slot = H256::zero();
+
+        let key = AggProof::storage_key(&token_a, &user, &slot);
+        assert_ne!(key, AggProof::storage_key(&token_b, &user, &slot));
+        assert_ne!(key, AggProof::storage_key(&token_a, &other, &slot));
+        assert_ne!(
+            key,
+            AggProof::storage_key(&token_a, &user, &H256::from_low_u64_be(1))
+        );
+        assert_eq!(key, AggProof::storage_key(&token_a, &user, &slot));
+    }
+
+    #[test]
+    fn proofs_need_a_leaf_for_every_key() {
+        let context = ShardContext::default();
+        let key = ShardKey::from_low_u64_be(1);
+        let leaf = context.leaves().index(&key).unwrap();
+        context.backend().init(0).unwrap();
+        context.backend().push_address_delta(leaf, "100", 0).unwrap();
+        context.commit_round(0).unwrap();
+        context.backend().update_tree(0).unwrap();
+
+        let mut proof = AggProof::new();
+        proof.address = vec![key, ShardKey::from_low_u64_be(2)];
+        proof.balance = vec![100.into(), 0.into()];
+        assert!(proof.create_proof(&context).is_err());
+        assert!(!proof.ready);
+
+        proof.address.truncate(1);
+        proof.balance.truncate(1);
+        proof.create_proof(&context).unwrap();
+        assert!(proof.ready);
+        assert!(context
+            .verify_balances(&proof.proof, 0, &[(key, 100.into())])
+            .unwrap());
+    }
+
+    #[test]
+    fn balance_key_keeps_the_address() {
+        let address = Address::from_low_u64_be(0x1234);
+        let key = AggProof::balance_key(&address);
+
+        assert_eq!(Address::from(key), address);
+        assert_ne!(
+            key,
+            AggProof::storage_key(&address, &address, &H256::zero())
+        );
     }
 }
-// pub fn update(){
-//     unsafe { BenchmarkVCSCommit() };
-// }
-
-fn main() {
-    // let (tx, rx) = mpsc::channel();
-    // let proof = demoProofShard();
-    // // let mut proof2;
-    // let handle = thread::spawn(move|| {
-    //     let proof2 = demoProofShard();
-    //     tx.send(proof2).unwrap();
-    //     // match proof2 {
-    //     //     Ok(p) => println!("{:?}",demoVerifyShard(p)),
-    //     //     _ => println!("error fuck"),
-    //     // };
-
-    // });
-    // thread::sleep(Duration::from_secs(10));
-    // match proof {
-    //     Ok(p) => println!("proof is {:?}",demoVerifyShard(p)),
-    //     _ => println!("error fuck"),
-    // };
-
-    // let received = rx.recv().unwrap();
-       
-    // match received {
-    //     Ok(p) => println!("proof2 is {:?}",demoVerifyShard(p)),
-    //     _ => println!("error fuck"),
-    // };
-
-    // // handle.join().unwrap();
-    // // match proof2 {
-    // //     Ok(p) => println!("{:?}",demoVerifyShard(p)),
-    // //     _ => println!("error fuck"),
-    // // };    
-}
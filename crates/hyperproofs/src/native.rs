@@ -0,0 +1,502 @@
+//! Native vector-commitment backend.
+//!
+//! Each shard vector is committed to with a sparse keccak Merkle tree of the
+//! depth set in the chain spec. An aggregated proof is the list of
+//! authentication paths of the pushed leaves, hex encoded so it can travel
+//! inside a shard transaction.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use ethereum_types::{H256, U256};
+use keccak_hash::keccak;
+use rustc_hex::{FromHex, ToHex};
+
+use crate::backend::{Error, HyperproofBackend, Result};
+use crate::DEFAULT_VECTOR_DEPTH;
+
+const INDEX_LEN: usize = 8;
+
+fn leaf_hash(value: &U256) -> H256 {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    keccak(&bytes[..])
+}
+
+fn node_hash(left: &H256, right: &H256) -> H256 {
+    keccak([left.as_bytes(), right.as_bytes()].concat())
+}
+
+/// Hashes of empty subtrees of a tree of the given depth, indexed by level.
+fn empty_hashes(depth: usize) -> Vec<H256> {
+    let mut hashes = vec![leaf_hash(&U256::zero())];
+    for level in 0..depth {
+        let h = node_hash(&hashes[level], &hashes[level]);
+        hashes.push(h);
+    }
+    hashes
+}
+
+fn parse_signed(value: &str) -> Result<(bool, U256)> {
+    let (negative, digits) = match value.trim() {
+        v if v.starts_with('-') => (true, &v[1..]),
+        v => (false, v),
+    };
+    U256::from_dec_str(digits)
+        .map(|v| (negative, v))
+        .map_err(|_| Error::InvalidInput(format!("not a decimal number: {}", value)))
+}
+
+/// Non-empty nodes of a sparse Merkle tree, level 0 being the leaves.
+struct Tree {
+    levels: Vec<HashMap<u64, H256>>,
+}
+
+impl Tree {
+    fn build(leaves: &BTreeMap<u64, U256>, empty: &[H256]) -> Self {
+        let mut levels = vec![leaves
+            .iter()
+            .map(|(index, value)| (*index, leaf_hash(value)))
+            .collect::<HashMap<_, _>>()];
+        for level in 0..empty.len() - 1 {
+            let mut parents = HashMap::new();
+            {
+                let nodes = &levels[level];
+                for index in nodes.keys() {
+                    let parent = index >> 1;
+                    if parents.contains_key(&parent) {
+                        continue;
+                    }
+                    let left = nodes.get(&(parent << 1)).unwrap_or(&empty[level]);
+                    let right = nodes.get(&((parent << 1) | 1)).unwrap_or(&empty[level]);
+                    parents.insert(parent, node_hash(left, right));
+                }
+            }
+            levels.push(parents);
+        }
+        Tree { levels }
+    }
+
+    fn depth(&self) -> usize {
+        self.levels.len() - 1
+    }
+
+    fn root(&self, empty: &[H256]) -> H256 {
+        self.levels[self.depth()]
+            .get(&0)
+            .cloned()
+            .unwrap_or(empty[self.depth()])
+    }
+
+    fn path(&self, index: u64, empty: &[H256]) -> Vec<H256> {
+        (0..self.depth())
+            .map(|level| {
+                let sibling = (index >> level) ^ 1;
+                self.levels[level]
+                    .get(&sibling)
+                    .cloned()
+                    .unwrap_or(empty[level])
+            })
+            .collect()
+    }
+}
+
+fn root_from_path(index: u64, value: &U256, path: &[H256]) -> H256 {
+    path.iter()
+        .enumerate()
+        .fold(leaf_hash(value), |node, (level, sibling)| {
+            if (index >> level) & 1 == 0 {
+                node_hash(&node, sibling)
+            } else {
+                node_hash(sibling, &node)
+            }
+        })
+}
+
+#[derive(Default)]
+struct ShardVector {
+    leaves: BTreeMap<u64, U256>,
+    deltas: Vec<(u64, bool, U256)>,
+    digest: Option<H256>,
+    prev_digest: Option<H256>,
+    tree: Option<Tree>,
+    commit_list: Vec<u64>,
+    verify_list: Vec<(u64, U256)>,
+}
+
+impl ShardVector {
+    /// Leaves of the vector with the queued deltas folded in. Credits and debits of a leaf
+    /// are netted over the whole round, so the order they were queued in does not matter.
+    fn applied_leaves(&self, shard: u64) -> Result<BTreeMap<u64, U256>> {
+        let mut leaves = self.leaves.clone();
+        let mut debits = BTreeMap::new();
+        for &(index, negative, delta) in &self.deltas {
+            let total = if negative {
+                debits.entry(index).or_insert_with(U256::zero)
+            } else {
+                leaves.entry(index).or_insert_with(U256::zero)
+            };
+            *total = total.checked_add(delta).ok_or_else(|| {
+                Error::BalanceOutOfRange(format!(
+                    "leaf {} of shard {} overflows",
+                    index, shard
+                ))
+            })?;
+        }
+        for (index, debit) in debits {
+            let value = leaves.remove(&index).unwrap_or_default();
+            let value = value.checked_sub(debit).ok_or_else(|| {
+                Error::BalanceOutOfRange(format!(
+                    "leaf {} of shard {} holds {} but is debited {}",
+                    index, shard, value, debit
+                ))
+            })?;
+            leaves.insert(index, value);
+        }
+        leaves.retain(|_, value| !value.is_zero());
+        Ok(leaves)
+    }
+}
+
+#[derive(Default)]
+struct Vectors {
+    shards: BTreeMap<u64, ShardVector>,
+    committed: bool,
+}
+
+impl Vectors {
+    fn shard(&mut self, shard: u64) -> &mut ShardVector {
+        self.shards.entry(shard).or_insert_with(Default::default)
+    }
+}
+
+/// Pure Rust implementation of the hyperproofs vector commitments.
+pub struct NativeBackend {
+    depth: usize,
+    empty: Vec<H256>,
+    vectors: Mutex<Vectors>,
+}
+
+impl Default for NativeBackend {
+    fn default() -> Self {
+        NativeBackend::with_depth(DEFAULT_VECTOR_DEPTH)
+    }
+}
+
+impl NativeBackend {
+    /// Create a backend whose vectors hold `2^depth` leaves.
+    pub fn with_depth(depth: u8) -> Self {
+        let depth = depth as usize;
+        NativeBackend {
+            depth,
+            empty: empty_hashes(depth),
+            vectors: Mutex::new(Vectors::default()),
+        }
+    }
+
+    fn vectors(&self) -> ::std::sync::MutexGuard<'_, Vectors> {
+        self.vectors.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Length of the authentication path of a single leaf in a proof.
+    fn path_len(&self) -> usize {
+        INDEX_LEN + self.depth * 32
+    }
+
+    fn check_index(&self, address: u64) -> Result<u64> {
+        if address >> self.depth != 0 {
+            return Err(Error::InvalidInput(format!(
+                "leaf index {} out of range",
+                address
+            )));
+        }
+        Ok(address)
+    }
+}
+
+impl HyperproofBackend for NativeBackend {
+    fn init(&self, _round: u64) -> Result<i64> {
+        *self.vectors() = Vectors::default();
+        Ok(0)
+    }
+
+    fn push_address_delta(&self, address: u64, delta: &str, shard: u64) -> Result<i64> {
+        let index = self.check_index(address)?;
+        let (negative, delta) = parse_signed(delta)?;
+        self.vectors()
+            .shard(shard)
+            .deltas
+            .push((index, negative, delta));
+        Ok(0)
+    }
+
+    fn reset_address_delta(&self, shard: u64) -> Result<i64> {
+        self.vectors().shard(shard).deltas.clear();
+        Ok(0)
+    }
+
+    fn push_address_commit(&self, address: u64, shard: u64) -> Result<i64> {
+        let index = self.check_index(address)?;
+        self.vectors().shard(shard).commit_list.push(index);
+        Ok(0)
+    }
+
+    fn reset_address_commit(&self, shard: u64) -> Result<i64> {
+        self.vectors().shard(shard).commit_list.clear();
+        Ok(0)
+    }
+
+    fn push_address_balance_verify(&self, address: u64, balance: &str, shard: u64) -> Result<i64> {
+        let index = self.check_index(address)?;
+        let balance = match parse_signed(balance)? {
+            (false, balance) => balance,
+            (true, _) => {
+                return Err(Error::InvalidInput(format!(
+                    "negative balance: {}",
+                    balance
+                )))
+            }
+        };
+        self.vectors()
+            .shard(shard)
+            .verify_list
+            .push((index, balance));
+        Ok(0)
+    }
+
+    fn reset_address_balance_verify(&self, shard: u64) -> Result<i64> {
+        self.vectors().shard(shard).verify_list.clear();
+        Ok(0)
+    }
+
+    fn agg(&self, native_shard: u64) -> Result<(String, bool)> {
+        let mut vectors = self.vectors();
+        let vector = vectors.shard(native_shard);
+        if vector.commit_list.is_empty() {
+            return Ok((String::new(), false));
+        }
+        if vector.tree.is_none() {
+            vector.tree = Some(Tree::build(&vector.leaves, &self.empty));
+        }
+        let tree = vector.tree.as_ref().expect("tree was just built; qed");
+        let mut bytes = Vec::with_capacity(vector.commit_list.len() * self.path_len());
+        for index in &vector.commit_list {
+            bytes.extend_from_slice(&index.to_be_bytes());
+            for sibling in tree.path(*index, &self.empty) {
+                bytes.extend_from_slice(sibling.as_bytes());
+            }
+        }
+        Ok((bytes.to_hex(), true))
+    }
+
+    /// The proof is accepted against the current or the previous digest of `shard`, as a
+    /// proof aggregated at the end of a round is included after the next commit.
+    fn verify_proof(&self, proof: &str, shard: u64, _round: u64) -> Result<bool> {
+        let bytes: Vec<u8> = proof
+            .from_hex()
+            .map_err(|e| Error::InvalidInput(format!("proof is not hex: {}", e)))?;
+        let path_len = self.path_len();
+        let mut vectors = self.vectors();
+        let empty_root = self.empty[self.depth];
+        let vector = vectors.shard(shard);
+        if vector.verify_list.is_empty() || bytes.len() != vector.verify_list.len() * path_len {
+            return Ok(false);
+        }
+        let digests = [
+            vector.digest.unwrap_or(empty_root),
+            vector.prev_digest.unwrap_or(empty_root),
+        ];
+        let valid = vector
+            .verify_list
+            .iter()
+            .zip(bytes.chunks(path_len))
+            .all(|(&(index, ref balance), chunk)| {
+                let mut index_bytes = [0u8; INDEX_LEN];
+                index_bytes.copy_from_slice(&chunk[..INDEX_LEN]);
+                if u64::from_be_bytes(index_bytes) != index {
+                    return false;
+                }
+                let path: Vec<H256> = chunk[INDEX_LEN..]
+                    .chunks(32)
+                    .map(H256::from_slice)
+                    .collect();
+                digests.contains(&root_from_path(index, balance, &path))
+            });
+        Ok(valid)
+    }
+
+    /// Nothing is committed if the deltas of any shard would take a leaf below zero; the
+    /// deltas stay queued until they are reset.
+    fn commit(&self, native_shard: u64, _round: u64) -> Result<i64> {
+        let mut vectors = self.vectors();
+        let applied = vectors
+            .shards
+            .iter()
+            .map(|(shard, vector)| vector.applied_leaves(*shard))
+            .collect::<Result<Vec<_>>>()?;
+        for (vector, leaves) in vectors.shards.values_mut().zip(applied) {
+            vector.leaves = leaves;
+            vector.deltas.clear();
+            let digest = Tree::build(&vector.leaves, &self.empty).root(&self.empty);
+            vector.prev_digest = vector.digest.take();
+            vector.digest = Some(digest);
+        }
+        vectors.shard(native_shard).tree = None;
+        vectors.committed = true;
+        Ok(0)
+    }
+
+    fn update_tree(&self, native_shard: u64) -> Result<i64> {
+        let mut vectors = self.vectors();
+        let vector = vectors.shard(native_shard);
+        vector.tree = Some(Tree::build(&vector.leaves, &self.empty));
+        Ok(0)
+    }
+
+    fn reset_prev_commit(&self) -> Result<i64> {
+        for vector in self.vectors().shards.values_mut() {
+            vector.prev_digest = None;
+        }
+        Ok(0)
+    }
+
+    /// A shard without any leaves commits to the empty vector.
+    fn digest(&self, shard: u64) -> Result<Option<H256>> {
+        let vectors = self.vectors();
+        if !vectors.committed {
+            return Ok(None);
+        }
+        let digest = vectors.shards.get(&shard).and_then(|vector| vector.digest);
+        Ok(Some(digest.unwrap_or(self.empty[self.depth])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn committed_backend(balances: &[(u64, &str)]) -> NativeBackend {
+        let backend = NativeBackend::default();
+        backend.init(0).unwrap();
+        for &(index, balance) in balances {
+            backend.push_address_delta(index, balance, 1).unwrap();
+        }
+        backend.commit(1, 0).unwrap();
+        backend.update_tree(1).unwrap();
+        backend
+    }
+
+    fn proof_for(backend: &NativeBackend, indexes: &[u64]) -> String {
+        backend.reset_address_commit(1).unwrap();
+        for index in indexes {
+            backend.push_address_commit(*index, 1).unwrap();
+        }
+        let (proof, ready) = backend.agg(1).unwrap();
+        assert!(ready);
+        proof
+    }
+
+    fn verify(backend: &NativeBackend, proof: &str, balances: &[(u64, &str)]) -> bool {
+        backend.reset_address_balance_verify(1).unwrap();
+        for &(index, balance) in balances {
+            backend.push_address_balance_verify(index, balance, 1).unwrap();
+        }
+        backend.verify_proof(proof, 1, 0).unwrap()
+    }
+
+    #[test]
+    fn verifies_aggregated_proof() {
+        let backend = committed_backend(&[(3, "100"), (7, "250"), (3, "-40")]);
+        let proof = proof_for(&backend, &[3, 7, 9]);
+
+        assert!(verify(&backend, &proof, &[(3, "60"), (7, "250"), (9, "0")]));
+        assert!(!verify(&backend, &proof, &[(3, "60"), (7, "251"), (9, "0")]));
+        assert!(!verify(&backend, &proof, &[(7, "250"), (3, "60"), (9, "0")]));
+    }
+
+    #[test]
+    fn rejects_tampered_proof() {
+        let backend = committed_backend(&[(3, "100")]);
+        let mut proof = proof_for(&backend, &[3]);
+        let last = if proof.pop() == Some('0') { '1' } else { '0' };
+        proof.push(last);
+
+        assert!(!verify(&backend, &proof, &[(3, "100")]));
+        assert!(!verify(&backend, &proof[2..], &[(3, "100")]));
+    }
+
+    #[test]
+    fn accepts_proofs_of_previous_commit_only() {
+        let backend = committed_backend(&[(3, "100")]);
+        let proof = proof_for(&backend, &[3]);
+
+        backend.push_address_delta(3, "5", 1).unwrap();
+        backend.commit(1, 0).unwrap();
+        assert!(verify(&backend, &proof, &[(3, "100")]));
+
+        backend.reset_prev_commit().unwrap();
+        assert!(!verify(&backend, &proof, &[(3, "100")]));
+        let proof = proof_for(&backend, &[3]);
+        assert!(verify(&backend, &proof, &[(3, "105")]));
+    }
+
+    #[test]
+    fn rejects_commit_of_underflowing_deltas() {
+        let backend = committed_backend(&[(3, "100")]);
+        let proof = proof_for(&backend, &[3]);
+
+        backend.push_address_delta(3, "-150", 1).unwrap();
+        backend.push_address_delta(4, "5", 1).unwrap();
+        match backend.commit(1, 0) {
+            Err(Error::BalanceOutOfRange(_)) => (),
+            other => panic!("unexpected commit result {:?}", other),
+        }
+        // the vector and its digest are left untouched
+        assert!(verify(&backend, &proof, &[(3, "100")]));
+
+        backend.push_address_delta(3, "60", 1).unwrap();
+        backend.commit(1, 0).unwrap();
+        backend.update_tree(1).unwrap();
+        let proof = proof_for(&backend, &[3, 4]);
+        assert!(verify(&backend, &proof, &[(3, "10"), (4, "5")]));
+    }
+
+    #[test]
+    fn digests_are_known_after_first_commit() {
+        let backend = NativeBackend::default();
+        backend.init(0).unwrap();
+        backend.push_address_delta(3, "100", 1).unwrap();
+        assert_eq!(backend.digest(1).unwrap(), None);
+
+        backend.commit(1, 0).unwrap();
+        let digest = backend.digest(1).unwrap().unwrap();
+        assert_ne!(digest, backend.empty[backend.depth]);
+        // shards without leaves commit to the empty vector
+        assert_eq!(backend.digest(0).unwrap(), Some(backend.empty[backend.depth]));
+    }
+
+    #[test]
+    fn vector_size_follows_depth() {
+        let backend = NativeBackend::with_depth(4);
+        backend.init(0).unwrap();
+        backend.push_address_delta(15, "10", 1).unwrap();
+        assert!(backend.push_address_delta(16, "10", 1).is_err());
+        backend.commit(1, 0).unwrap();
+        backend.push_address_commit(15, 1).unwrap();
+        let (proof, _) = backend.agg(1).unwrap();
+
+        backend.push_address_balance_verify(15, "10", 1).unwrap();
+        assert!(backend.verify_proof(&proof, 1, 0).unwrap());
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let backend = NativeBackend::default();
+        assert!(backend.push_address_delta(1, "ten", 0).is_err());
+        assert!(backend
+            .push_address_delta(1 << DEFAULT_VECTOR_DEPTH, "1", 0)
+            .is_err());
+        assert!(backend.push_address_balance_verify(1, "-1", 0).is_err());
+        assert!(backend.verify_proof("zz", 0, 0).is_err());
+    }
+}
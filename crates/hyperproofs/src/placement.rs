@@ -0,0 +1,99 @@
+//! Placement of accounts on shards.
+
+use std::collections::BTreeMap;
+
+use ethereum_types::Address;
+
+/// Decides which shard holds the state of an address.
+pub trait ShardPlacement: Send + Sync {
+    /// Shard holding the state of `address`.
+    fn shard_of(&self, address: &Address) -> u64;
+}
+
+/// Placement policy declared in the chain spec.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum PlacementConfig {
+    /// Low 64 bits of the address modulo the shard count.
+    #[default]
+    Modulo,
+    /// Address ranges, keyed by their lowest address.
+    PrefixRanges(BTreeMap<Address, u64>),
+    /// Directory contract mapping addresses to shards.
+    Directory(Address),
+}
+
+/// Places addresses by the low 64 bits of the address modulo the shard count.
+#[derive(Debug, Clone)]
+pub struct ModuloPlacement {
+    shard_count: u64,
+}
+
+impl ModuloPlacement {
+    pub fn new(shard_count: u64) -> Self {
+        ModuloPlacement {
+            shard_count: shard_count.max(1),
+        }
+    }
+}
+
+impl ShardPlacement for ModuloPlacement {
+    fn shard_of(&self, address: &Address) -> u64 {
+        address.to_low_u64_be().rem_euclid(self.shard_count)
+    }
+}
+
+/// Places addresses by contiguous address ranges.
+///
+/// Every range starts at its key and ends before the next key, addresses
+/// below the first range belong to shard 0.
+#[derive(Debug, Clone)]
+pub struct PrefixPlacement {
+    ranges: BTreeMap<Address, u64>,
+}
+
+impl PrefixPlacement {
+    pub fn new(ranges: BTreeMap<Address, u64>) -> Self {
+        PrefixPlacement { ranges }
+    }
+}
+
+impl ShardPlacement for PrefixPlacement {
+    fn shard_of(&self, address: &Address) -> u64 {
+        self.ranges
+            .range(..=*address)
+            .next_back()
+            .map_or(0, |(_, shard)| *shard)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(first_byte: u8) -> Address {
+        let mut address = Address::zero();
+        address.as_bytes_mut()[0] = first_byte;
+        address
+    }
+
+    #[test]
+    fn modulo_uses_low_bits() {
+        let placement = ModuloPlacement::new(4);
+
+        assert_eq!(placement.shard_of(&Address::from_low_u64_be(6)), 2);
+        assert_eq!(placement.shard_of(&address(0xff)), 0);
+    }
+
+    #[test]
+    fn prefix_ranges_cover_the_address_space() {
+        let mut ranges = BTreeMap::new();
+        ranges.insert(address(0x40), 1);
+        ranges.insert(address(0x80), 2);
+        let placement = PrefixPlacement::new(ranges);
+
+        assert_eq!(placement.shard_of(&address(0x3f)), 0);
+        assert_eq!(placement.shard_of(&address(0x40)), 1);
+        assert_eq!(placement.shard_of(&address(0x7f)), 1);
+        assert_eq!(placement.shard_of(&address(0xff)), 2);
+    }
+}
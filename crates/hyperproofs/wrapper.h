@@ -1 +1 @@
-#include "/home/srisht/libhyper/hyperproofs-go/libshard.h"
+#include "libshard.h"
@@ -112,6 +112,10 @@ impl super::Accounts for Signer {
                 shard_data_list: HashMap::new(),
                 shard_proof_list: Vec::new(),
                 shard_proof: String::new(),
+                original_hash: Default::default(),
+                gas_used: Default::default(),
+                hop_gas_used: Default::default(),
+                suspended_frame: Default::default(),
             }),
             None => return Err(Error::new(ErrorCode::InvalidParams)),
         };
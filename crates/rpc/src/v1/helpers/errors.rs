@@ -411,7 +411,11 @@ pub fn transaction_message(error: &TransactionError) -> String {
         SenderIsNotEOA => "Transaction sender is not an EOA (see EIP-3607)".into(),
         // #[cfg(feature = "shard")]
         SenderInvalidShard => "Transaction sender's shard is different from Block producer".into(),
-        BlockDataLimitExceeded => "Block cannot aggregate more data elements".into()
+        BlockDataLimitExceeded => "Block cannot aggregate more data elements".into(),
+        InvalidShardProof => "Shard balance proof does not verify.".into(),
+        ShardProofUnverifiable(ref err) => format!("Shard balance proof could not be verified: {}", err),
+        UnauthorizedShardTransaction { signer, shard } => format!("{:?} is not a validator of shard {}.", signer, shard),
+        InvalidSuspendedFrame => "Shard transaction carries a frame it may not resume.".into(),
 	}
 }
 
@@ -82,6 +82,10 @@ pub fn sign_call(request: CallRequest) -> Result<SignedTransaction, Error> {
             shard_data_list:HashMap::new(),
             shard_proof_list:Vec::new(),
             shard_proof: String::new(),
+            original_hash: Default::default(),
+            gas_used: Default::default(),
+            hop_gas_used: Default::default(),
+            suspended_frame: Default::default(),
         }),
         _ => return Err(Error::new(ErrorCode::InvalidParams)),
     };
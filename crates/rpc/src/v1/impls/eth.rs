@@ -593,6 +593,26 @@ where
     }
 }
 
+/// Route a raw transaction to the shards its call touches.
+#[cfg(feature = "shard")]
+fn into_shard_transaction<C>(client: &C, tx: SignedTransaction) -> SignedTransaction
+where
+    C: BlockChainClient,
+{
+    trace!(target: "rpc/v1/impls", "send_raw_transaction and dispatching before shard: {:?}", tx);
+    let shard_context = client.shard_context();
+    tx.to_shard_txn(&|address| shard_context.address_shard(address))
+}
+
+/// Raw transactions are dispatched as signed without sharding.
+#[cfg(not(feature = "shard"))]
+fn into_shard_transaction<C>(_client: &C, tx: SignedTransaction) -> SignedTransaction
+where
+    C: BlockChainClient,
+{
+    tx
+}
+
 const MAX_QUEUE_SIZE_TO_MINE_ON: usize = 4; // because uncles go back 6.
 
 impl<C, SN: ?Sized, S: ?Sized, M, EM, T: StateInfo + 'static> Eth for EthClient<C, SN, S, M, EM>
@@ -1332,10 +1352,7 @@ where
         TypedTransaction::decode(&raw.into_vec())
             .map_err(errors::rlp)
             .and_then(|tx| SignedTransaction::new(tx).map_err(errors::transaction))
-            // #[cfg(feature = "shard")]
-            .and_then(|tx|
-                          {trace!(target: "rpc/v1/impls", "send_raw_transaction and dispatching before shard: {:?}", tx);
-                Ok(tx.to_shard_txn())})
+            .map(|tx| into_shard_transaction(&*self.client, tx))
             .and_then(|signed_transaction| {
                 trace!(target: "rpc/v1/impls", "send_raw_transaction and dispatching: {:?}", signed_transaction);
                 FullDispatcher::dispatch_transaction(
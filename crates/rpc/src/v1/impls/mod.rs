@@ -31,6 +31,8 @@ mod pubsub;
 mod rpc;
 #[cfg(any(test, feature = "accounts"))]
 mod secretstore;
+#[cfg(feature = "shard")]
+mod shard;
 mod signer;
 mod signing;
 mod signing_unsafe;
@@ -45,6 +47,8 @@ pub use self::parity_set::accounts::ParitySetAccountsClient;
 pub use self::personal::PersonalClient;
 #[cfg(any(test, feature = "accounts"))]
 pub use self::secretstore::SecretStoreClient;
+#[cfg(feature = "shard")]
+pub use self::shard::ShardClient;
 pub use self::{
     debug::DebugClient,
     eth::{EthClient, EthClientOptions},
@@ -0,0 +1,111 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Shard rpc implementation.
+use std::{collections::BTreeMap, sync::Arc};
+
+use ethcore::client::{BlockChainClient, PrepareOpenBlock, TransactionId};
+use ethereum_types::{H256, U256, U64};
+use jsonrpc_core::Result;
+use types::transaction::SignedTransaction;
+use v1::{
+    traits::Shard,
+    types::{ShardIncompleteTransactions, ShardRoundInfo, ShardTransaction},
+};
+
+/// Shard rpc implementation.
+pub struct ShardClient<C> {
+    client: Arc<C>,
+}
+
+impl<C> ShardClient<C>
+where
+    C: BlockChainClient + PrepareOpenBlock,
+{
+    /// Creates new ShardClient.
+    pub fn new(client: &Arc<C>) -> Self {
+        ShardClient {
+            client: client.clone(),
+        }
+    }
+
+    /// Looks up a shard transaction among the incomplete ones first, then in the chain.
+    fn transaction(&self, hash: &H256) -> Option<ShardTransaction> {
+        let queued = self
+            .client
+            .export_incomplete_txn()
+            .into_iter()
+            .chain(self.client.get_pending_incomplete_txn())
+            .find(|tx| tx.hash() == *hash);
+
+        match queued {
+            Some(tx) => ShardTransaction::from_unverified(&tx),
+            None => self
+                .client
+                .block_transaction(TransactionId::Hash(*hash))
+                .and_then(|tx| ShardTransaction::from_unverified(&tx)),
+        }
+    }
+}
+
+impl<C> Shard for ShardClient<C>
+where
+    C: BlockChainClient + PrepareOpenBlock + 'static,
+{
+    fn id(&self) -> Result<U64> {
+        Ok(self.client.shard_context().shard().into())
+    }
+
+    fn round_info(&self) -> Result<ShardRoundInfo> {
+        let context = self.client.shard_context();
+        let block_number = self.client.chain_info().best_block_number;
+        let last_commit_round = context.last_commit_round();
+
+        Ok(ShardRoundInfo {
+            shard: context.shard().into(),
+            shard_count: context.shard_count().into(),
+            round_length: context.round_length().into(),
+            block_number: block_number.into(),
+            round: (block_number / context.round_length()).into(),
+            last_commit_round: if last_commit_round == u64::max_value() {
+                None
+            } else {
+                Some(last_commit_round.into())
+            },
+        })
+    }
+
+    fn pending_incomplete(&self) -> Result<ShardIncompleteTransactions> {
+        let to_rpc = |txs: Vec<SignedTransaction>| -> Vec<ShardTransaction> {
+            txs.iter()
+                .filter_map(|tx| ShardTransaction::from_unverified(tx))
+                .collect()
+        };
+
+        Ok(ShardIncompleteTransactions {
+            incomplete: to_rpc(self.client.export_incomplete_txn()),
+            pending: to_rpc(self.client.get_pending_incomplete_txn()),
+        })
+    }
+
+    fn transaction_hops(&self, hash: H256) -> Result<Option<U64>> {
+        Ok(self.transaction(&hash).map(|tx| tx.hop_count))
+    }
+
+    fn data_list(&self, hash: H256) -> Result<Option<BTreeMap<H256, U256>>> {
+        Ok(self.transaction(&hash).map(|tx| tx.data_list))
+    }
+}
@@ -41,6 +41,8 @@ pub mod informant;
 pub mod metadata;
 pub mod traits;
 
+#[cfg(feature = "shard")]
+pub use self::traits::Shard;
 pub use self::{
     extractors::{RpcExtractor, WsDispatcher, WsExtractor, WsStats},
     helpers::{block_import, dispatch, NetworkSettings},
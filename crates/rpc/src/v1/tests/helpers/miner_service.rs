@@ -302,6 +302,10 @@ impl MinerService for TestMinerService {
             .collect()
     }
 
+    fn foreign_shard_transactions(&self) -> Vec<Arc<VerifiedTransaction>> {
+        Vec::new()
+    }
+
     fn queued_transaction_hashes(&self) -> Vec<H256> {
         self.pending_transactions
             .lock()
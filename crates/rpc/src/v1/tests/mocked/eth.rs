@@ -1184,6 +1184,11 @@ fn rpc_eth_send_raw_transaction() {
         r#"{"jsonrpc":"2.0","result":""#.to_owned() + &format!("0x{:x}", t.hash()) + r#"","id":1}"#;
 
     assert_eq!(tester.io.handle_request_sync(&req), Some(res));
+
+    // only a sharded node routes the call as a shard transaction
+    let imported = tester.miner.imported_transactions.lock();
+    assert_eq!(imported.len(), 1);
+    assert_eq!(imported[0].is_shard(), cfg!(feature = "shard"));
 }
 
 #[test]
@@ -1230,6 +1235,7 @@ fn rpc_eth_transaction_receipt() {
         log_bloom: Bloom::zero(),
         outcome: TransactionOutcome::StateRoot(H256::zero()),
         effective_gas_price: Default::default(),
+        shard: None,
     };
 
     let hash =
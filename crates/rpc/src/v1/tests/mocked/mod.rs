@@ -32,6 +32,8 @@ mod pubsub;
 mod rpc;
 #[cfg(any(test, feature = "accounts"))]
 mod secretstore;
+#[cfg(feature = "shard")]
+mod shard;
 mod signer;
 #[cfg(any(test, feature = "accounts"))]
 mod signing;
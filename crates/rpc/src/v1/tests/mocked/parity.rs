@@ -562,6 +562,7 @@ fn rpc_parity_block_receipts() {
             to: None,
             from: Address::from_low_u64_be(9),
             effective_gas_price: Default::default(),
+            shard: None,
         },
     );
     let io = deps.default_client();
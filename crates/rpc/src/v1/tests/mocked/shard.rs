@@ -0,0 +1,79 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+use ethcore::client::TestBlockChainClient;
+
+use jsonrpc_core::IoHandler;
+use v1::{Shard, ShardClient};
+
+fn io() -> IoHandler {
+    let client = Arc::new(TestBlockChainClient::new());
+
+    let mut io = IoHandler::new();
+    io.extend_with(ShardClient::new(&client).to_delegate());
+    io
+}
+
+#[test]
+fn rpc_shard_id() {
+    let request = r#"{"jsonrpc": "2.0", "method": "shard_id", "params": [], "id": 1}"#;
+    let response = r#"{"jsonrpc":"2.0","result":"0x0","id":1}"#;
+
+    assert_eq!(io().handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_shard_round_info() {
+    let request = r#"{"jsonrpc": "2.0", "method": "shard_roundInfo", "params": [], "id": 1}"#;
+    let response = r#"{"jsonrpc":"2.0","result":{"blockNumber":"0x0","lastCommitRound":null,"round":"0x0","roundLength":"0x1","shard":"0x0","shardCount":"0x1"},"id":1}"#;
+
+    assert_eq!(io().handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_shard_pending_incomplete() {
+    let request =
+        r#"{"jsonrpc": "2.0", "method": "shard_pendingIncomplete", "params": [], "id": 1}"#;
+    let response = r#"{"jsonrpc":"2.0","result":{"incomplete":[],"pending":[]},"id":1}"#;
+
+    assert_eq!(io().handle_request_sync(request), Some(response.to_owned()));
+}
+
+#[test]
+fn rpc_shard_unknown_transaction() {
+    let hash = "0x0000000000000000000000000000000000000000000000000000000000000001";
+
+    let request = format!(
+        r#"{{"jsonrpc": "2.0", "method": "shard_getTransactionHops", "params": ["{}"], "id": 1}}"#,
+        hash
+    );
+    let response = r#"{"jsonrpc":"2.0","result":null,"id":1}"#;
+    assert_eq!(
+        io().handle_request_sync(&request),
+        Some(response.to_owned())
+    );
+
+    let request = format!(
+        r#"{{"jsonrpc": "2.0", "method": "shard_getDataList", "params": ["{}"], "id": 1}}"#,
+        hash
+    );
+    assert_eq!(
+        io().handle_request_sync(&request),
+        Some(response.to_owned())
+    );
+}
@@ -29,10 +29,14 @@ pub mod personal;
 pub mod pubsub;
 pub mod rpc;
 pub mod secretstore;
+#[cfg(feature = "shard")]
+pub mod shard;
 pub mod signer;
 pub mod traces;
 pub mod web3;
 
+#[cfg(feature = "shard")]
+pub use self::shard::Shard;
 pub use self::{
     debug::Debug,
     eth::{Eth, EthFilter},
@@ -0,0 +1,50 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Shard rpc interface.
+use std::collections::BTreeMap;
+
+use ethereum_types::{H256, U256, U64};
+use jsonrpc_core::Result;
+use jsonrpc_derive::rpc;
+
+use v1::types::{ShardIncompleteTransactions, ShardRoundInfo};
+
+/// Shard rpc interface.
+#[rpc(server)]
+pub trait Shard {
+    /// Returns the shard of this node.
+    #[rpc(name = "shard_id")]
+    fn id(&self) -> Result<U64>;
+
+    /// Returns shard topology and round progress of this node.
+    #[rpc(name = "shard_roundInfo")]
+    fn round_info(&self) -> Result<ShardRoundInfo>;
+
+    /// Returns incomplete transactions waiting to be continued on this node's shard.
+    #[rpc(name = "shard_pendingIncomplete")]
+    fn pending_incomplete(&self) -> Result<ShardIncompleteTransactions>;
+
+    /// Returns the number of hops of a shard transaction.
+    /// `null` for unknown and non-shard transactions.
+    #[rpc(name = "shard_getTransactionHops")]
+    fn transaction_hops(&self, _: H256) -> Result<Option<U64>>;
+
+    /// Returns the data a shard transaction carries between shards.
+    /// `null` for unknown and non-shard transactions.
+    #[rpc(name = "shard_getDataList")]
+    fn data_list(&self, _: H256) -> Result<Option<BTreeMap<H256, U256>>>;
+}
@@ -40,6 +40,9 @@ pub use self::{
     receipt::Receipt,
     rpc_settings::RpcSettings,
     secretstore::EncryptedDocumentKey,
+    shard::{
+        ShardIncompleteTransactions, ShardReceipt, ShardRoundInfo, ShardStatus, ShardTransaction,
+    },
     sync::{
         ChainStatus, EthProtocolInfo, PeerInfo, PeerNetworkInfo, PeerProtocolsInfo, Peers,
         SyncInfo, SyncStatus, TransactionStats,
@@ -73,6 +76,7 @@ mod provenance;
 mod receipt;
 mod rpc_settings;
 mod secretstore;
+mod shard;
 mod sync;
 mod trace;
 mod trace_filter;
@@ -16,7 +16,7 @@
 
 use ethereum_types::{Bloom as H2048, H160, H256, U256, U64};
 use types::receipt::{LocalizedReceipt, RichReceipt, TransactionOutcome, TypedReceipt};
-use v1::types::Log;
+use v1::types::{Log, ShardReceipt};
 
 /// Receipt
 #[derive(Debug, Serialize)]
@@ -57,6 +57,9 @@ pub struct Receipt {
     pub status_code: Option<U64>,
     /// Effective gas price
     pub effective_gas_price: U256,
+    /// Cross-shard lifecycle, for shard transactions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shard: Option<ShardReceipt>,
 }
 
 impl Receipt {
@@ -93,6 +96,7 @@ impl From<LocalizedReceipt> for Receipt {
             state_root: Self::outcome_to_state_root(r.outcome),
             logs_bloom: r.log_bloom,
             effective_gas_price: r.effective_gas_price,
+            shard: r.shard.map(Into::into),
         }
     }
 }
@@ -115,6 +119,7 @@ impl From<RichReceipt> for Receipt {
             state_root: Self::outcome_to_state_root(r.outcome),
             logs_bloom: r.log_bloom,
             effective_gas_price: r.effective_gas_price,
+            shard: None,
         }
     }
 }
@@ -139,6 +144,7 @@ impl From<TypedReceipt> for Receipt {
             state_root: Self::outcome_to_state_root(legacy_receipt.outcome),
             logs_bloom: legacy_receipt.log_bloom,
             effective_gas_price: Default::default(),
+            shard: None,
         }
     }
 }
@@ -147,8 +153,11 @@ impl From<TypedReceipt> for Receipt {
 mod tests {
     use ethereum_types::{Bloom, H256};
     use serde_json;
-    use types::transaction::TypedTxId;
-    use v1::types::{Log, Receipt};
+    use types::{
+        receipt::{self, ShardTransactionStatus},
+        transaction::TypedTxId,
+    };
+    use v1::types::{Log, Receipt, ShardReceipt};
 
     #[test]
     fn receipt_serialization() {
@@ -197,9 +206,30 @@ mod tests {
             state_root: Some(H256::from_low_u64_be(10)),
             status_code: Some(1u64.into()),
             effective_gas_price: Default::default(),
+            shard: None,
         };
 
         let serialized = serde_json::to_string(&receipt).unwrap();
         assert_eq!(serialized, s);
     }
+
+    #[test]
+    fn shard_receipt_serialization() {
+        let s = r#"{"originalTransactionHash":"0x0000000000000000000000000000000000000000000000000000000000000001","latestTransactionHash":"0x0000000000000000000000000000000000000000000000000000000000000002","originShard":"0x0","finalShard":null,"nextShard":"0x1","hopCount":"0x1","gasUsed":"0x5208","status":"pending"}"#;
+
+        let shard: ShardReceipt = receipt::ShardReceipt {
+            original_transaction_hash: H256::from_low_u64_be(1),
+            latest_transaction_hash: H256::from_low_u64_be(2),
+            origin_shard: 0,
+            final_shard: None,
+            next_shard: Some(1),
+            hop_count: 1,
+            gas_used: 21000.into(),
+            status: ShardTransactionStatus::Pending,
+        }
+        .into();
+
+        let serialized = serde_json::to_string(&shard).unwrap();
+        assert_eq!(serialized, s);
+    }
 }
@@ -0,0 +1,157 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Shard RPC types.
+
+use std::collections::BTreeMap;
+
+use ethereum_types::{H160, H256, U256, U64};
+use types::{
+    receipt::{self, ShardTransactionStatus},
+    transaction::{TypedTransaction, UnverifiedTransaction},
+};
+
+/// Shard topology and round progress of a node.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShardRoundInfo {
+    /// Shard of the node.
+    pub shard: U64,
+    /// Number of shards.
+    pub shard_count: U64,
+    /// Number of blocks in a round.
+    pub round_length: U64,
+    /// Best block number.
+    pub block_number: U64,
+    /// Round of the best block.
+    pub round: U64,
+    /// Block number of the last committed round, if any.
+    pub last_commit_round: Option<U64>,
+}
+
+/// Cross-shard fields of a shard transaction.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShardTransaction {
+    /// Transaction hash.
+    pub hash: H256,
+    /// Shard the transaction is executed on.
+    pub shard: U64,
+    /// Shard the transaction continues on.
+    pub next_shard: U64,
+    /// Number of hops so far.
+    pub hop_count: U64,
+    /// Gas used by all hops so far.
+    pub gas_used: U256,
+    /// Whether the transaction still has to continue on another shard.
+    pub incomplete: bool,
+    /// Sender of the first hop.
+    pub original_sender: H160,
+    /// Data carried between shards, keyed by balance or storage key.
+    pub data_list: BTreeMap<H256, U256>,
+}
+
+impl ShardTransaction {
+    /// Cross-shard fields of `tx`, or `None` if it is not a shard transaction.
+    pub fn from_unverified(tx: &UnverifiedTransaction) -> Option<Self> {
+        match tx.unsigned {
+            TypedTransaction::ShardTransaction(ref shard_tx) => Some(ShardTransaction {
+                hash: tx.hash(),
+                shard: shard_tx.shard.into(),
+                next_shard: shard_tx.next_shard.into(),
+                hop_count: shard_tx.hop_count.into(),
+                gas_used: shard_tx.gas_used,
+                incomplete: shard_tx.incomplete != 0,
+                original_sender: shard_tx.original_sender,
+                data_list: shard_tx
+                    .shard_data_list
+                    .iter()
+                    .map(|(key, value)| (*key, *value))
+                    .collect(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Incomplete transactions waiting to be continued on a node's shard.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShardIncompleteTransactions {
+    /// Transactions to be included in the next block sealed by the node.
+    pub incomplete: Vec<ShardTransaction>,
+    /// Transactions that did not fit in a block yet.
+    pub pending: Vec<ShardTransaction>,
+}
+
+/// Completion status of a cross-shard transaction.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShardStatus {
+    /// Waiting to continue on another shard.
+    Pending,
+    /// Finished on its final shard.
+    Complete,
+    /// Reverted on its final shard.
+    Reverted,
+}
+
+impl From<ShardTransactionStatus> for ShardStatus {
+    fn from(s: ShardTransactionStatus) -> Self {
+        match s {
+            ShardTransactionStatus::Pending => ShardStatus::Pending,
+            ShardTransactionStatus::Complete => ShardStatus::Complete,
+            ShardTransactionStatus::Reverted => ShardStatus::Reverted,
+        }
+    }
+}
+
+/// Cross-shard lifecycle reported in transaction receipts.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShardReceipt {
+    /// Hash of the transaction that started the cross-shard call.
+    pub original_transaction_hash: H256,
+    /// Hash of the latest hop of the call.
+    pub latest_transaction_hash: H256,
+    /// Shard the call started on.
+    pub origin_shard: U64,
+    /// Shard the call finished on, unless it is still pending.
+    pub final_shard: Option<U64>,
+    /// Shard the call continues on while pending.
+    pub next_shard: Option<U64>,
+    /// Number of hops so far.
+    pub hop_count: U64,
+    /// Gas used by all hops so far.
+    pub gas_used: U256,
+    /// Completion status.
+    pub status: ShardStatus,
+}
+
+impl From<receipt::ShardReceipt> for ShardReceipt {
+    fn from(r: receipt::ShardReceipt) -> Self {
+        ShardReceipt {
+            original_transaction_hash: r.original_transaction_hash,
+            latest_transaction_hash: r.latest_transaction_hash,
+            origin_shard: r.origin_shard.into(),
+            final_shard: r.final_shard.map(Into::into),
+            next_shard: r.next_shard.map(Into::into),
+            hop_count: r.hop_count.into(),
+            gas_used: r.gas_used,
+            status: r.status.into(),
+        }
+    }
+}
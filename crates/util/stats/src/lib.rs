@@ -67,6 +67,45 @@ impl PrometheusRegistry {
             .expect("prometheus identifiers must be are unique");
     }
 
+    /// Adds a new prometheus counter with the specified value and constant labels
+    pub fn register_labeled_counter(
+        &mut self,
+        name: &str,
+        help: &str,
+        labels: &[(&str, &str)],
+        value: i64,
+    ) {
+        let c = prometheus::IntCounter::with_opts(self.labeled_opts(name, help, labels))
+            .expect("name and help must be non-empty");
+        c.inc_by(value);
+        self.registry
+            .register(Box::new(c))
+            .expect("prometheus identifiers must be unique");
+    }
+
+    /// Adds a new prometheus gauge with the specified value and constant labels
+    pub fn register_labeled_gauge(
+        &mut self,
+        name: &str,
+        help: &str,
+        labels: &[(&str, &str)],
+        value: i64,
+    ) {
+        let g = prometheus::IntGauge::with_opts(self.labeled_opts(name, help, labels))
+            .expect("name and help must be non-empty");
+        g.set(value);
+        self.registry
+            .register(Box::new(g))
+            .expect("prometheus identifiers must be unique");
+    }
+
+    fn labeled_opts(&self, name: &str, help: &str, labels: &[(&str, &str)]) -> prometheus::Opts {
+        labels.iter().fold(
+            prometheus::Opts::new(format!("{}{}", self.prefix, name), help.to_string()),
+            |opts, &(label, value)| opts.const_label(label, value),
+        )
+    }
+
     /// Adds a new prometheus counter with the time spent in running the specified function
     pub fn register_optime<F: Fn() -> T, T>(&mut self, name: &str, f: &F) -> T {
         let start = Instant::now();
@@ -218,6 +257,19 @@ where
 mod tests {
     use super::*;
 
+    #[test]
+    fn labeled_metrics_share_a_name() {
+        let mut registry = PrometheusRegistry::new("oe_".into());
+        registry.register_labeled_counter("hops", "Hops", &[("shard", "0"), ("hops", "1")], 2);
+        registry.register_labeled_counter("hops", "Hops", &[("shard", "0"), ("hops", "2")], 3);
+        registry.register_labeled_gauge("depth", "Depth", &[("shard", "0")], 4);
+
+        let families = registry.registry().gather();
+        assert_eq!(families.len(), 2);
+        let hops = families.iter().find(|f| f.get_name() == "oe_hops").unwrap();
+        assert_eq!(hops.get_metric().len(), 2);
+    }
+
     #[test]
     fn check_corpus() {
         let corpus = Corpus::from(vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10]);
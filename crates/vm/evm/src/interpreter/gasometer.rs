@@ -111,6 +111,9 @@ impl<Gas: evm::CostType> Gasometer<Gas> {
     /// We guarantee that the final element of the returned tuple (`provided`) will be `Some`
     /// iff the `instruction` is one of `CREATE`, or any of the `CALL` variants. In this case,
     /// it will be the amount of gas that the current context provides to the child context.
+    ///
+    /// When `sharded`, storage writes are priced without reading the current value, which
+    /// may live on another shard.
     pub fn requirements(
         &mut self,
         ext: &dyn vm::Ext,
@@ -119,6 +122,7 @@ impl<Gas: evm::CostType> Gasometer<Gas> {
         stack: &VecStack<U256>,
         current_address: &Address,
         current_mem_size: usize,
+        sharded: bool,
     ) -> vm::Result<InstructionRequirements<Gas>> {
         let schedule = ext.schedule();
 
@@ -141,15 +145,20 @@ impl<Gas: evm::CostType> Gasometer<Gas> {
                 }
                 let key = BigEndianHash::from_uint(stack.peek(0));
                 let newval = stack.peek(1);
-                // #[cfg(feature = "shard")]
-                let val = U256::zero();
-                //let val = ext.storage_at(&key)?.into_uint();
+                let val = if sharded {
+                    U256::zero()
+                } else {
+                    ext.storage_at(&key)?.into_uint()
+                };
 
                 let is_cold = !ext.al_contains_storage_key(current_address, &key);
 
                 let gas = if schedule.eip1283 {
-                    //let orig = ext.initial_storage_at(&key)?.into_uint();
-                    let orig = U256::zero();
+                    let orig = if sharded {
+                        U256::zero()
+                    } else {
+                        ext.initial_storage_at(&key)?.into_uint()
+                    };
                     calculate_eip1283_eip2929_sstore_gas(schedule, is_cold, &orig, &val, &newval)
                 } else {
                     if val.is_zero() && !newval.is_zero() {
@@ -383,7 +392,7 @@ impl<Gas: evm::CostType> Gasometer<Gas> {
         })
     }
 
-    fn mem_gas_cost(
+    pub fn mem_gas_cost(
         &self,
         schedule: &Schedule,
         current_mem_size: usize,
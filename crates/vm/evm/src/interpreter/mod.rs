@@ -31,7 +31,8 @@ use std::{cmp, marker::PhantomData, mem, sync::Arc};
 
 use vm::{
     self, ActionParams, ActionValue, CallType, ContractCreateResult, CreateContractAddress,
-    GasLeft, MessageCallResult, ParamsType, ReturnData, Schedule, TrapError, TrapKind,
+    GasLeft, MessageCallResult, ParamsType, ReturnData, Schedule, SuspendedFrame, TrapError,
+    TrapKind,
 };
 use hyperproofs::AggProof;
 use evm::CostType;
@@ -99,6 +100,16 @@ impl CodeReader {
     fn len(&self) -> usize {
         self.code.len()
     }
+
+    /// Whether an instruction starts at `position`, rather than the data of a PUSH.
+    fn is_instruction_start(&self, position: ProgramCounter) -> bool {
+        let mut pc = 0;
+        while pc < position {
+            let push_bytes = Instruction::from_u8(self.code[pc]).and_then(|i| i.push_bytes());
+            pc += 1 + push_bytes.unwrap_or(0);
+        }
+        pc == position
+    }
 }
 
 enum InstructionResult<Gas> {
@@ -118,6 +129,11 @@ enum InstructionResult<Gas> {
         apply: bool,
     },
     StopExecution,
+    /// Stop the call to continue on another shard, which runs the instruction again.
+    SuspendExecution {
+        /// Whether the next shard can resume the frame instead of re-executing the call.
+        resumable: bool,
+    },
     Trap(TrapKind),
 }
 
@@ -196,11 +212,21 @@ pub struct Interpreter<Cost: CostType> {
     resume_output_range: Option<(U256, U256)>,
     resume_result: Option<InstructionResult<Cost>>,
     last_stack_ret_len: usize,
+    depth: usize,
     _type: PhantomData<Cost>,
 }
 
 impl<Cost: 'static + CostType> vm::Exec for Interpreter<Cost> {
     fn exec(mut self: Box<Self>, ext: &mut dyn vm::Ext) -> vm::ExecTrapResult<GasLeft> {
+        if self.depth == 0 {
+            if let Some(ext) = shard_ext(ext) {
+                if let Some(frame) = ext.resume_frame() {
+                    if let Err(err) = self.resume_from(frame, ext) {
+                        return Ok(Err(err));
+                    }
+                }
+            }
+        }
         loop {
             let result = self.step(ext);
             match result {
@@ -324,6 +350,7 @@ impl<Cost: CostType> Interpreter<Cost> {
             last_stack_ret_len: 0,
             resume_output_range: None,
             resume_result: None,
+            depth,
             _type: PhantomData,
         }
     }
@@ -394,6 +421,7 @@ impl<Cost: CostType> Interpreter<Cost> {
                 };
 
                 // Calculate gas cost
+                let sharded = shard_ext(ext).is_some();
                 let requirements = match self
                     .gasometer
                     .as_mut()
@@ -405,6 +433,7 @@ impl<Cost: CostType> Interpreter<Cost> {
                         &self.stack,
                         &self.params.address,
                         self.mem.size(),
+                        sharded,
                     ) {
                     Ok(t) => t,
                     Err(e) => return InterpreterResult::Done(Err(e)),
@@ -464,6 +493,13 @@ impl<Cost: CostType> Interpreter<Cost> {
                     }
                     Ok(x) => x,
                 };
+                if let InstructionResult::SuspendExecution { .. } = result {
+                    // the instruction is charged again on the shard it runs on
+                    self.gasometer.as_mut().expect(GASOMETER_PROOF).current_gas =
+                        self.gasometer.as_mut().expect(GASOMETER_PROOF).current_gas
+                            + requirements.gas_cost;
+                    self.reader.position -= 1;
+                }
                 evm_debug!({ self.informant.after_instruction(instruction) });
                 result
             }
@@ -554,6 +590,20 @@ impl<Cost: CostType> Interpreter<Cost> {
                         .as_u256(),
                 )));
             }
+            InstructionResult::SuspendExecution { resumable } => {
+                if resumable {
+                    if let Some(ext) = shard_ext(ext) {
+                        ext.suspend_frame(self.suspended_frame());
+                    }
+                }
+                return InterpreterResult::Done(Ok(GasLeft::Known(
+                    self.gasometer
+                        .as_mut()
+                        .expect(GASOMETER_PROOF)
+                        .current_gas
+                        .as_u256(),
+                )));
+            }
             _ => {}
         }
 
@@ -570,6 +620,97 @@ impl<Cost: CostType> Interpreter<Cost> {
         InterpreterResult::Continue
     }
 
+    /// Stops the call to continue on `shard`.
+    ///
+    /// Only the first stop of a top-level frame is resumable; a frame continuing after a
+    /// nested call already stopped is executed again from the start on the next shard.
+    fn stop_for_shard(&self, ext: &mut dyn vm::ShardExt, shard: u64) -> InstructionResult<Cost> {
+        let resumable = self.depth == 0 && ext.txn_complete_status() != Some(false);
+        ext.set_txn_incomplete();
+        ext.set_next_shard(shard);
+        InstructionResult::SuspendExecution { resumable }
+    }
+
+    /// Frame of the call stopped at the current instruction.
+    fn suspended_frame(&self) -> SuspendedFrame {
+        let gasometer = self.gasometer.as_ref().expect(GASOMETER_PROOF);
+        SuspendedFrame {
+            code_hash: self.params.code_hash.unwrap_or_default(),
+            depth: self.depth as u64,
+            pc: self.reader.position as u64,
+            gas: gasometer.current_gas.as_u256(),
+            mem_gas: gasometer.current_mem_gas.as_u256(),
+            stack: self.stack.peek_top(self.stack.size()).to_vec(),
+            return_stack: self.return_stack.iter().map(|pos| *pos as u64).collect(),
+            memory: self.mem.clone(),
+            return_data: self.return_data.to_vec(),
+            storage_writes: Vec::new(),
+            storage_deltas: Vec::new(),
+        }
+    }
+
+    /// Restores a frame suspended on another shard, along with the storage writes of the
+    /// executed hops.
+    ///
+    /// A frame which does not stop this code at an instruction fails the call, which must
+    /// not run again from the start over the writes of the executed hops. The gas left is
+    /// capped by the frame, as the transaction carries at most the gas left of the previous
+    /// hop, and the gas paid for memory follows from the size of the memory.
+    fn resume_from(&mut self, frame: SuspendedFrame, ext: &mut dyn vm::ShardExt) -> vm::Result<()> {
+        let invalid =
+            |reason: &str| vm::Error::Internal(format!("Invalid suspended frame: {}", reason));
+        if frame.depth != self.depth as u64 || self.params.code_hash != Some(frame.code_hash) {
+            return Err(invalid("frame of other code"));
+        }
+        if frame.pc >= self.reader.len() as u64
+            || !self.reader.is_instruction_start(frame.pc as usize)
+        {
+            return Err(invalid("not stopped at an instruction"));
+        }
+        if frame.stack.len() > ext.schedule().stack_limit
+            || frame.return_stack.len() > MAX_SUB_STACK_SIZE
+        {
+            return Err(invalid("stack too deep"));
+        }
+        if frame.memory.len() % 32 != 0 {
+            return Err(invalid("memory not of whole words"));
+        }
+        let origin = ext.origin_address();
+        if frame.storage_writes.iter().any(|write| write.1 != origin)
+            || frame
+                .storage_deltas
+                .iter()
+                .any(|delta| !frame.storage_writes.iter().any(|write| write.0 == delta.0))
+        {
+            return Err(invalid("storage writes of another call"));
+        }
+        let gasometer = match self.gasometer.as_mut() {
+            Some(gasometer) => gasometer,
+            None => return Ok(()),
+        };
+        if let Ok(gas) = Cost::from_u256(frame.gas) {
+            gasometer.current_gas = cmp::min(gasometer.current_gas, gas);
+        }
+        let (_, mem_gas, _) =
+            gasometer.mem_gas_cost(ext.schedule(), 0, &Cost::from(frame.memory.len()))?;
+        gasometer.current_mem_gas = mem_gas;
+        self.reader.position = frame.pc as usize;
+        for item in frame.stack {
+            self.stack.push(item);
+        }
+        self.return_stack = frame.return_stack.iter().map(|pos| *pos as usize).collect();
+        let return_data_len = frame.return_data.len();
+        self.return_data = ReturnData::new(frame.return_data, 0, return_data_len);
+        self.mem = frame.memory;
+        for (key, address, slot, value) in frame.storage_writes {
+            ext.push_temp_sstore_val(key, address, slot, value);
+        }
+        for (key, delta, shard) in frame.storage_deltas {
+            ext.push_temp_sstore_delta(key, delta, shard);
+        }
+        Ok(())
+    }
+
     fn verify_instruction(
         &self,
         ext: &dyn vm::Ext,
@@ -709,7 +850,9 @@ impl<Cost: CostType> Interpreter<Cost> {
                 // clear return data buffer before creating new call frame.
                 self.return_data = ReturnData::empty();
                 // no shard here coz create happens in all shards
-                AggProof::incr_bal_read_count(1u64);
+                if let Some(ext) = shard_ext(ext) {
+                    ext.shard_context().counters().incr_bal_read(1u64);
+                }
                 let can_create = ext.balance(&self.params.address)? >= endowment
                     && ext.depth() < ext.schedule().max_depth;
                 if !can_create {
@@ -803,7 +946,7 @@ impl<Cost: CostType> Interpreter<Cost> {
                         if ext.is_static() && value.map_or(false, |v| !v.is_zero()) {
                             return Err(vm::Error::MutableCallInStaticContext);
                         }
-                        // AggProof::incr_bal_read_count(1u64);
+                        // ext.shard_context().counters().incr_bal_read(1u64);
                         let has_balance = ext.balance(&self.params.address)?
                             >= value.expect("value set for all but delegate call; qed");
                         (
@@ -814,7 +957,7 @@ impl<Cost: CostType> Interpreter<Cost> {
                         )
                     }
                     instructions::CALLCODE => {
-                        // AggProof::incr_bal_read_count(1u64);
+                        // ext.shard_context().counters().incr_bal_read(1u64);
                         let has_balance = ext.balance(&self.params.address)?
                             >= value.expect("value set for all but delegate call; qed");
                         (
@@ -900,7 +1043,6 @@ impl<Cost: CostType> Interpreter<Cost> {
                 };
             }
             instructions::RETURN => {
-                println!("transaction RETURN");
                 let init_off = self.stack.pop_back();
                 let init_size = self.stack.pop_back();
 
@@ -912,8 +1054,9 @@ impl<Cost: CostType> Interpreter<Cost> {
                 });
             }
             instructions::REVERT => {
-                println!("transaction reverted");
-                ext.reverted(true);
+                if let Some(ext) = shard_ext(ext) {
+                    ext.reverted(true);
+                }
                 let init_off = self.stack.pop_back();
                 let init_size = self.stack.pop_back();
 
@@ -925,11 +1068,9 @@ impl<Cost: CostType> Interpreter<Cost> {
                 });
             }
             instructions::STOP => {
-                println!("transaction STOP");
                 return Ok(InstructionResult::StopExecution);
             }
             instructions::SUICIDE => {
-                println!("transaction SUICIDE");
                 let address = u256_to_address(&self.stack.pop_back());
                 ext.al_insert_address(address.clone());
                 ext.suicide(&address)?;
@@ -1016,294 +1157,328 @@ impl<Cost: CostType> Interpreter<Cost> {
                 self.stack.push(k.into_uint());
             }
             instructions::SLOAD => {
-                if ext.is_create_txn() {
-                    let key = BigEndianHash::from_uint(&self.stack.pop_back());
-                    let key_shard = AggProof::concat_hash(ext.origin_address(), key);
-                    let word = ext.storage_at(&key)?.into_uint();
-                    AggProof::incr_sload_count(1u64);
-                    println!("SLOAD (CREATE) fetching storage at {}", key);
-                    self.stack.push(word);
-
-                    ext.al_insert_storage_key(self.params.address, key);
-                } else {
-                    let key = BigEndianHash::from_uint(&self.stack.pop_back());
+                if let Some(ext) = shard_ext(ext) {
+                    if ext.is_create_txn() {
+                        let key = BigEndianHash::from_uint(&self.stack.pop_back());
+                        let owner = ext.shard_context().storage_owner(&self.params.address, &ext.origin_address());
+                        let key_shard = AggProof::storage_key(&self.params.address, &owner, &key);
+                        let word = ext.storage_at(&key)?.into_uint();
+                        ext.shard_context().counters().incr_sload(1u64);
+                        trace!(target: "evm", "SLOAD (CREATE) fetching storage at {}", key);
+                        self.stack.push(word);
+
+                        ext.al_insert_storage_key(self.params.address, key);
+                    } else {
+                        let key = BigEndianHash::from_uint(&self.stack.pop_back());
 
-                    // #[cfg(feature = "shard")]
-                    let key_shard = AggProof::concat_hash(ext.origin_address(), key);
-                    println!("trying to load at key {} and key_shard {} and address {}",key, key_shard, ext.origin_address());
-                    let val = ext.hash_map_storage_at(&key_shard);
-                    let word = if val.1 {
-                        print!("found the key inside the hashmap");
-                        val.0
+                        // #[cfg(feature = "shard")]
+                        let owner = ext.shard_context().storage_owner(&self.params.address, &ext.origin_address());
+                        let key_shard = AggProof::storage_key(&self.params.address, &owner, &key);
+                        let val = ext.hash_map_storage_at(&key_shard);
+                        let word = if val.1 {
+                            val.0
 
-                    } else {
-                        if ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()) == AggProof::get_shard() {
-                            AggProof::incr_sload_count(1u64);
-                            let word_temp = ext.storage_at(&key)?.into_uint();
-                            let mut begin_round_word = ext.hash_map_beginning_storage_at(&key_shard);
-                            if begin_round_word.1 {
-                                ext.hash_map_txn_insert(key_shard, begin_round_word.0);
+                        } else {
+                            if ext.shard_context().address_shard(&owner) == ext.shard_context().shard() {
+                                ext.shard_context().counters().incr_sload(1u64);
+                                let word_temp = ext.storage_at(&key)?.into_uint();
+                                let mut begin_round_word = ext.hash_map_beginning_storage_at(&key_shard);
+                                if begin_round_word.1 {
+                                    ext.hash_map_txn_insert(key_shard, begin_round_word.0);
+                                } else {
+                                    ext.hash_map_txn_insert(key_shard, word_temp);
+                                }
+                                //in any case, push the address in the state address vec
+                                ext.push_address_txn_vec(key_shard);
+                                word_temp
                             } else {
-                                ext.hash_map_txn_insert(key_shard, word_temp);
+                                trace!(target: "evm", "Stopping execution from SLOAD at address {}", owner);
+                                self.stack.push(key.into_uint());
+                                let shard = ext.shard_context().address_shard(&owner);
+                                return Ok(self.stop_for_shard(ext, shard));
                             }
-                            //in any case, push the address in the state address vec
-                            ext.push_address_txn_vec(key_shard);
-                            word_temp
-                        } else {
-                            ext.set_txn_incomplete();
-                            ext.set_next_shard(ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()));
-                            println!("Stopping execution from SLOAD at address {}", ext.origin_address());
-                            return Ok(InstructionResult::StopExecution);
-                        }
-                    };
-                    // let word = ext.storage_at(&key)?.into_uint();
-                    println!("SLOAD fetching storage at key {} and address {} with value {}", key, ext.origin_address(), word);
+                        };
+                        trace!(target: "evm", "SLOAD fetching storage at key {} and address {} with value {}", key, ext.origin_address(), word);
+                        self.stack.push(word);
+
+                        ext.al_insert_storage_key(self.params.address, key);
+                    }
+                } else {
+                    let key = BigEndianHash::from_uint(&self.stack.pop_back());
+                    let word = ext.storage_at(&key)?.into_uint();
                     self.stack.push(word);
 
                     ext.al_insert_storage_key(self.params.address, key);
                 }
             }
             instructions::SSTORE => {
-                if ext.is_create_txn(){
-                    let key = BigEndianHash::from_uint(&self.stack.pop_back());
-                    let val = self.stack.pop_back();
-                    // #[cfg(feature = "shard")]
-                    let key_shard = AggProof::concat_hash(ext.origin_address(), key);
-                    //word is the current val
-                    AggProof::incr_sload_count(1u64);
-                    let word = ext.storage_at(&key)?.into_uint();
-                    let mut delta = val.checked_sub(word);
-                    let delta_string:String;
-                    if delta == None {
-                        delta = word.checked_sub(val);
-                        let mut neg = String::from("-");
-                        let v = delta.unwrap().to_string();
-                        neg.push_str(&v);
-                        delta_string = neg.clone();
+                if let Some(ext) = shard_ext(ext) {
+                    if ext.is_create_txn(){
+                        let key = BigEndianHash::from_uint(&self.stack.pop_back());
+                        let val = self.stack.pop_back();
+                        // #[cfg(feature = "shard")]
+                        let owner = ext.shard_context().storage_owner(&self.params.address, &ext.origin_address());
+                        let key_shard = AggProof::storage_key(&self.params.address, &owner, &key);
+                        //word is the current val
+                        ext.shard_context().counters().incr_sload(1u64);
+                        let word = ext.storage_at(&key)?.into_uint();
+                        let mut delta = val.checked_sub(word);
+                        let delta_string:String;
+                        if delta == None {
+                            delta = word.checked_sub(val);
+                            let mut neg = String::from("-");
+                            let v = delta.unwrap().to_string();
+                            neg.push_str(&v);
+                            delta_string = neg.clone();
+                        }else {
+                            delta_string = delta.unwrap().to_string();
+                        }
+                        ext.shard_context().counters().incr_sstore(1u64);
+                        ext.set_storage(key, BigEndianHash::from_uint(&val))?;
+                        trace!(target: "evm", "SSTORE setting storage at {} with val {}", key, val);
+                        let delta_shard = ext.shard_context().address_shard(&owner);
+                        ext.push_shard_delta(&key_shard, &delta_string, delta_shard)?;
+                        trace!(target: "evm", "delta {} from address {} in shard {}", delta_string, key_shard, delta_shard);
                     }else {
-                        delta_string = delta.unwrap().to_string();
-                    }
-                    AggProof::incr_sstore_count(1u64);
-                    ext.set_storage(key, BigEndianHash::from_uint(&val))?;
-                    println!("SSTORE setting storage at {} with val {}", key, val);
-                    AggProof::pushAddressDelta(key_shard.to_low_u64_be().rem_euclid(2u64.pow(16)), delta_string.clone(), ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()));
-                    println!("delta {} from address {} in shard {}", delta_string, key_shard , ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()));
-                }else {
-                    let key = BigEndianHash::from_uint(&self.stack.pop_back());
-                    let val = self.stack.pop_back();
-                    // #[cfg(feature = "shard")]
-                    let key_shard = AggProof::concat_hash(ext.origin_address(), key);
-
-                    // ext.hash_map_insert(key_shard, val.clone());
+                        let key = BigEndianHash::from_uint(&self.stack.pop_back());
+                        let val = self.stack.pop_back();
+                        // #[cfg(feature = "shard")]
+                        let owner = ext.shard_context().storage_owner(&self.params.address, &ext.origin_address());
+                        let key_shard = AggProof::storage_key(&self.params.address, &owner, &key);
+
+                        // ext.hash_map_insert(key_shard, val.clone());
+
+                        // #[cfg(feature = "shard")]
+                        //we are ignoring this eip
+                        // let current_val = ext.storage_at(&key)?.into_uint();
+                        // // Increase refund for clear
+                        // if ext.schedule().eip1283 {
+                        //     let original_val = ext.initial_storage_at(&key)?.into_uint();
+                        //     gasometer::handle_eip1283_sstore_clears_refund(
+                        //         ext,
+                        //         &original_val,
+                        //         &current_val,
+                        //         &val,
+                        //     );
+                        // } else {
+                        //     if !current_val.is_zero() && val.is_zero() {
+                        //         let sstore_clears_schedule = ext.schedule().sstore_refund_gas;
+                        //         ext.add_sstore_refund(sstore_clears_schedule);
+                        //     }
+                        // }
+                        match ext.txn_complete_status(){
+                            Some(T) => if T {
+                                //next, retrieve current val
+                                let current_val = ext.hash_map_storage_at(&key_shard);
+                                let word = if current_val.1{
+                                    current_val.0
+                                } else {
+                                    if ext.shard_context().address_shard(&owner) == ext.shard_context().shard(){
+                                        ext.shard_context().counters().incr_sload(1u64);
+                                        let word_temp = ext.storage_at(&key)?.into_uint();
+                                        let mut begin_round_word = ext.hash_map_beginning_storage_at(&key_shard);
+                                        if begin_round_word.1{
+                                            ext.hash_map_txn_insert(key_shard, begin_round_word.0);
+                                        }else{
+                                            ext.hash_map_txn_insert(key_shard, word_temp);
+                                        }
+                                        //in any case, push the address in the state address vec
+                                        ext.push_address_txn_vec(key_shard);
+                                        word_temp
+                                    } else{
+                                        trace!(target: "evm", "Stopping execution from SSTORE");
+                                        self.stack.push(val);
+                                        self.stack.push(key.into_uint());
+                                        let shard = ext.shard_context().address_shard(&owner);
+                                        return Ok(self.stop_for_shard(ext, shard));
 
-                    // #[cfg(feature = "shard")]
-                    //we are ignoring this eip
-                    // let current_val = ext.storage_at(&key)?.into_uint();
-                    // // Increase refund for clear
-                    // if ext.schedule().eip1283 {
-                    //     let original_val = ext.initial_storage_at(&key)?.into_uint();
-                    //     gasometer::handle_eip1283_sstore_clears_refund(
-                    //         ext,
-                    //         &original_val,
-                    //         &current_val,
-                    //         &val,
-                    //     );
-                    // } else {
-                    //     if !current_val.is_zero() && val.is_zero() {
-                    //         let sstore_clears_schedule = ext.schedule().sstore_refund_gas;
-                    //         ext.add_sstore_refund(sstore_clears_schedule);
-                    //     }
-                    // }
-                    match ext.txn_complete_status(){
-                        Some(T) => if T {
-                            //next, retrieve current val
-                            let current_val = ext.hash_map_storage_at(&key_shard);
-                            let word = if current_val.1{
-                                current_val.0
-                            } else {
-                                if ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()) == AggProof::get_shard(){
-                                    AggProof::incr_sload_count(1u64);
-                                    let word_temp = ext.storage_at(&key)?.into_uint();
-                                    let mut begin_round_word = ext.hash_map_beginning_storage_at(&key_shard);
-                                    if begin_round_word.1{
-                                        ext.hash_map_txn_insert(key_shard, begin_round_word.0);
-                                    }else{
-                                        ext.hash_map_txn_insert(key_shard, word_temp);
                                     }
-                                    //in any case, push the address in the state address vec
-                                    ext.push_address_txn_vec(key_shard);
-                                    word_temp
-                                } else{
-                                    ext.set_txn_incomplete();
-                                    ext.set_next_shard(ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()));
-                                    println!("Stopping execution from SSTORE");
-                                    return Ok(InstructionResult::StopExecution);
 
+                                };
+                                let mut delta = val.checked_sub(word);
+                                let delta_string:String;
+                                if delta == None{
+                                    delta = word.checked_sub(val);
+                                    let mut neg = String::from("-");
+                                    let v = delta.unwrap().to_string();
+                                    neg.push_str(&v);
+                                    delta_string = neg.clone();
+                                }else {
+                                    delta_string = delta.unwrap().to_string();
                                 }
+                                // uptil this part, we caculate delta. Only when it is a complete transaction.
 
-                            };
-                            println!("current val for calculating delta is {}", word);
-                            let mut delta = val.checked_sub(word);
-                            let delta_string:String;
-                            if delta == None{
-                                delta = word.checked_sub(val);
-                                let mut neg = String::from("-");
-                                let v = delta.unwrap().to_string();
-                                neg.push_str(&v);
-                                delta_string = neg.clone();
-                            }else {
-                                delta_string = delta.unwrap().to_string();
-                            }
-                            // uptil this part, we caculate delta. Only when it is a complete transaction.
-
-                            //***************************************************
-                            ext.hash_map_cache_insert(key_shard, val.clone());
-                            ext.hash_map_global_insert(key_shard, val.clone());
-                            if ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()) == AggProof::get_shard() {
-                                if !ext.hash_map_beginning_storage_at(&key_shard).1{
-                                    // let current_val = ext.storage_at(&key)?.into_uint();
-                                    // ext.hash_map_beginning_insert(key_shard, current_val.clone());
-                                    // use the previous fetched value
-                                    ext.hash_map_beginning_insert(key_shard, word.clone());
-                                }
-                                AggProof::incr_sstore_count(1u64);
-                                ext.set_storage(key, BigEndianHash::from_uint(&val))?;
-                                println!("SSTORE (Some(true)) setting storage at {} with val {} and code address", key, val);
-                            }
-                            AggProof::pushAddressDelta(key_shard.to_low_u64_be().rem_euclid(2u64.pow(16)), delta_string.clone(), ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()));
-                            println!("delta {} from address {} in shard {}", delta_string, key_shard , ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()));
-                        } else {
-
-                            // should be able to retrieve the value
-                            let current_val = ext.hash_map_storage_at(&key_shard);
-                            let word = if current_val.1{
-                                current_val.0
-                            } else {
-                                if ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()) == AggProof::get_shard(){
-                                    AggProof::incr_sload_count(1u64);
-                                    let word_temp = ext.storage_at(&key)?.into_uint();
-                                    let mut begin_round_word = ext.hash_map_beginning_storage_at(&key_shard);
-                                    if begin_round_word.1{
-                                        ext.hash_map_txn_insert(key_shard, begin_round_word.0);
-                                    }else{
-                                        ext.hash_map_txn_insert(key_shard, word_temp);
-                                    }
-                                    //in any case, push the address in the state address vec
-                                    ext.push_address_txn_vec(key_shard);
-                                    word_temp
-                                } else{
-                                    ext.set_txn_incomplete();
-                                    ext.set_next_shard(ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()));
-                                    println!("Stopping execution from SSTORE");
-                                    return Ok(InstructionResult::StopExecution);
-
-                                }
-
-                            };
-                            println!("current val for calculating delta is {}", word);
-                            let mut delta = val.checked_sub(word);
-                            let delta_string:String;
-                            if delta == None{
-                                delta = word.checked_sub(val);
-                                let mut neg = String::from("-");
-                                let v = delta.unwrap().to_string();
-                                neg.push_str(&v);
-                                delta_string = neg.clone();
-                            }else {
-                                delta_string = delta.unwrap().to_string();
-                            }
-                            // uptil this part, we caculate delta. Only when it is a complete transaction.
-
-                            //***************************************************
-                            // ext.hash_map_cache_insert(key_shard, val.clone());
-                            // ext.hash_map_global_insert(key_shard, val.clone());
-                            ext.hash_map_cache_insert(key_shard, val.clone());
-                            if ext.static_flag(){
-                                return  Err(vm::Error::MutableCallInStaticContext)
-                            } else {
-                                ext.push_temp_sstore_val(key_shard, ext.origin_address(), key, val.clone());
-                                ext.push_temp_sstore_delta(key_shard.to_low_u64_be().rem_euclid(2u64.pow(16)), delta_string.clone(), ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()));
-                                if ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()) == AggProof::get_shard() {
+                                //***************************************************
+                                ext.hash_map_cache_insert(key_shard, val.clone());
+                                ext.hash_map_global_insert(key_shard, val.clone());
+                                if ext.shard_context().address_shard(&owner) == ext.shard_context().shard() {
                                     if !ext.hash_map_beginning_storage_at(&key_shard).1{
                                         // let current_val = ext.storage_at(&key)?.into_uint();
                                         // ext.hash_map_beginning_insert(key_shard, current_val.clone());
                                         // use the previous fetched value
                                         ext.hash_map_beginning_insert(key_shard, word.clone());
                                     }
-                                    // ext.set_storage(key, BigEndianHash::from_uint(&val))?;
-                                    // println!("SSTORE setting storage at {} with val {} and code address", key, val);
+                                    ext.shard_context().counters().incr_sstore(1u64);
+                                    ext.set_storage(key, BigEndianHash::from_uint(&val))?;
+                                    trace!(target: "evm", "SSTORE setting storage at {} with val {}", key, val);
                                 }
-                            }
-
-                        }
-                        None => {
-                            // ext.hash_map_cache_insert(key_shard, val.clone());
-                            // should be able to retrieve the value
-                            let current_val = ext.hash_map_storage_at(&key_shard);
-                            let word = if current_val.1{
-                                current_val.0
+                                let delta_shard = ext.shard_context().address_shard(&owner);
+                                ext.push_shard_delta(&key_shard, &delta_string, delta_shard)?;
+                                trace!(target: "evm", "delta {} from address {} in shard {}", delta_string, key_shard, delta_shard);
                             } else {
-                                if ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()) == AggProof::get_shard(){
-                                    AggProof::incr_sload_count(1u64);
-                                    let word_temp = ext.storage_at(&key)?.into_uint();
-                                    let mut begin_round_word = ext.hash_map_beginning_storage_at(&key_shard);
-                                    if begin_round_word.1{
-                                        ext.hash_map_txn_insert(key_shard, begin_round_word.0);
-                                    }else{
-                                        ext.hash_map_txn_insert(key_shard, word_temp);
+
+                                // should be able to retrieve the value
+                                let current_val = ext.hash_map_storage_at(&key_shard);
+                                let word = if current_val.1{
+                                    current_val.0
+                                } else {
+                                    if ext.shard_context().address_shard(&owner) == ext.shard_context().shard(){
+                                        ext.shard_context().counters().incr_sload(1u64);
+                                        let word_temp = ext.storage_at(&key)?.into_uint();
+                                        let mut begin_round_word = ext.hash_map_beginning_storage_at(&key_shard);
+                                        if begin_round_word.1{
+                                            ext.hash_map_txn_insert(key_shard, begin_round_word.0);
+                                        }else{
+                                            ext.hash_map_txn_insert(key_shard, word_temp);
+                                        }
+                                        //in any case, push the address in the state address vec
+                                        ext.push_address_txn_vec(key_shard);
+                                        word_temp
+                                    } else{
+                                        trace!(target: "evm", "Stopping execution from SSTORE");
+                                        self.stack.push(val);
+                                        self.stack.push(key.into_uint());
+                                        let shard = ext.shard_context().address_shard(&owner);
+                                        return Ok(self.stop_for_shard(ext, shard));
+
                                     }
-                                    //in any case, push the address in the state address vec
-                                    ext.push_address_txn_vec(key_shard);
-                                    word_temp
-                                } else{
-                                    ext.set_txn_incomplete();
-                                    ext.set_next_shard(ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()));
-                                    println!("Stopping execution from SSTORE");
-                                    return Ok(InstructionResult::StopExecution);
 
+                                };
+                                let mut delta = val.checked_sub(word);
+                                let delta_string:String;
+                                if delta == None{
+                                    delta = word.checked_sub(val);
+                                    let mut neg = String::from("-");
+                                    let v = delta.unwrap().to_string();
+                                    neg.push_str(&v);
+                                    delta_string = neg.clone();
+                                }else {
+                                    delta_string = delta.unwrap().to_string();
+                                }
+                                // uptil this part, we caculate delta. Only when it is a complete transaction.
+
+                                //***************************************************
+                                // ext.hash_map_cache_insert(key_shard, val.clone());
+                                // ext.hash_map_global_insert(key_shard, val.clone());
+                                ext.hash_map_cache_insert(key_shard, val.clone());
+                                if ext.is_static(){
+                                    return  Err(vm::Error::MutableCallInStaticContext)
+                                } else {
+                                    ext.push_temp_sstore_val(key_shard, ext.origin_address(), key, val.clone());
+                                    let delta_shard = ext.shard_context().address_shard(&owner);
+                                    ext.push_temp_sstore_delta(key_shard, delta_string.clone(), delta_shard);
+                                    if ext.shard_context().address_shard(&owner) == ext.shard_context().shard() {
+                                        if !ext.hash_map_beginning_storage_at(&key_shard).1{
+                                            // let current_val = ext.storage_at(&key)?.into_uint();
+                                            // ext.hash_map_beginning_insert(key_shard, current_val.clone());
+                                            // use the previous fetched value
+                                            ext.hash_map_beginning_insert(key_shard, word.clone());
+                                        }
+                                        // ext.set_storage(key, BigEndianHash::from_uint(&val))?;
+                                    }
                                 }
 
-                            };
-                            println!("current val for calculating delta is {}", word);
-                            let mut delta = val.checked_sub(word);
-                            let delta_string:String;
-                            if delta == None{
-                                delta = word.checked_sub(val);
-                                let mut neg = String::from("-");
-                                let v = delta.unwrap().to_string();
-                                neg.push_str(&v);
-                                delta_string = neg.clone();
-                            }else {
-                                delta_string = delta.unwrap().to_string();
                             }
-                            // uptil this part, we caculate delta. Only when it is a complete transaction.
+                            None => {
+                                // ext.hash_map_cache_insert(key_shard, val.clone());
+                                // should be able to retrieve the value
+                                let current_val = ext.hash_map_storage_at(&key_shard);
+                                let word = if current_val.1{
+                                    current_val.0
+                                } else {
+                                    if ext.shard_context().address_shard(&owner) == ext.shard_context().shard(){
+                                        ext.shard_context().counters().incr_sload(1u64);
+                                        let word_temp = ext.storage_at(&key)?.into_uint();
+                                        let mut begin_round_word = ext.hash_map_beginning_storage_at(&key_shard);
+                                        if begin_round_word.1{
+                                            ext.hash_map_txn_insert(key_shard, begin_round_word.0);
+                                        }else{
+                                            ext.hash_map_txn_insert(key_shard, word_temp);
+                                        }
+                                        //in any case, push the address in the state address vec
+                                        ext.push_address_txn_vec(key_shard);
+                                        word_temp
+                                    } else{
+                                        trace!(target: "evm", "Stopping execution from SSTORE");
+                                        self.stack.push(val);
+                                        self.stack.push(key.into_uint());
+                                        let shard = ext.shard_context().address_shard(&owner);
+                                        return Ok(self.stop_for_shard(ext, shard));
 
-                            //***************************************************
-                            ext.hash_map_cache_insert(key_shard, val.clone());
-                            // ext.hash_map_global_insert(key_shard, val.clone());
-                            if ext.static_flag(){
-                               return  Err(vm::Error::MutableCallInStaticContext)
-                            } else {
-                                ext.push_temp_sstore_val(key_shard, ext.origin_address(),key, val.clone());
-                                ext.push_temp_sstore_delta(key_shard.to_low_u64_be().rem_euclid(2u64.pow(16)), delta_string.clone(), ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()));
-                                if ext.origin_address().to_low_u64_be().rem_euclid(AggProof::shard_count()) == AggProof::get_shard() {
-                                    if !ext.hash_map_beginning_storage_at(&key_shard).1{
-                                        // let current_val = ext.storage_at(&key)?.into_uint();
-                                        // ext.hash_map_beginning_insert(key_shard, current_val.clone());
-                                        // use the previous fetched value
-                                        ext.hash_map_beginning_insert(key_shard, word.clone());
                                     }
-                                    // ext.set_storage(key, BigEndianHash::from_uint(&val))?;
-                                    // println!("SSTORE setting storage at {} with val {} and code address", key, val);
+
+                                };
+                                let mut delta = val.checked_sub(word);
+                                let delta_string:String;
+                                if delta == None{
+                                    delta = word.checked_sub(val);
+                                    let mut neg = String::from("-");
+                                    let v = delta.unwrap().to_string();
+                                    neg.push_str(&v);
+                                    delta_string = neg.clone();
+                                }else {
+                                    delta_string = delta.unwrap().to_string();
+                                }
+                                // uptil this part, we caculate delta. Only when it is a complete transaction.
+
+                                //***************************************************
+                                ext.hash_map_cache_insert(key_shard, val.clone());
+                                // ext.hash_map_global_insert(key_shard, val.clone());
+                                if ext.is_static(){
+                                   return  Err(vm::Error::MutableCallInStaticContext)
+                                } else {
+                                    ext.push_temp_sstore_val(key_shard, ext.origin_address(),key, val.clone());
+                                    let delta_shard = ext.shard_context().address_shard(&owner);
+                                    ext.push_temp_sstore_delta(key_shard, delta_string.clone(), delta_shard);
+                                    if ext.shard_context().address_shard(&owner) == ext.shard_context().shard() {
+                                        if !ext.hash_map_beginning_storage_at(&key_shard).1{
+                                            // let current_val = ext.storage_at(&key)?.into_uint();
+                                            // ext.hash_map_beginning_insert(key_shard, current_val.clone());
+                                            // use the previous fetched value
+                                            ext.hash_map_beginning_insert(key_shard, word.clone());
+                                        }
+                                        // ext.set_storage(key, BigEndianHash::from_uint(&val))?;
+                                    }
                                 }
-                            }
 
 
+                            }
                         }
+                        ext.al_insert_storage_key(self.params.address, key);
                     }
+                } else {
+                    let key = BigEndianHash::from_uint(&self.stack.pop_back());
+                    let val = self.stack.pop_back();
+
+                    let current_val = ext.storage_at(&key)?.into_uint();
+                    // Increase refund for clear
+                    if ext.schedule().eip1283 {
+                        let original_val = ext.initial_storage_at(&key)?.into_uint();
+                        gasometer::handle_eip1283_sstore_clears_refund(
+                            ext,
+                            &original_val,
+                            &current_val,
+                            &val,
+                        );
+                    } else {
+                        if !current_val.is_zero() && val.is_zero() {
+                            let sstore_clears_schedule = ext.schedule().sstore_refund_gas;
+                            ext.add_sstore_refund(sstore_clears_schedule);
+                        }
+                    }
+                    ext.set_storage(key, BigEndianHash::from_uint(&val))?;
                     ext.al_insert_storage_key(self.params.address, key);
                 }
-
             }
             instructions::PC => {
                 self.stack.push(U256::from(self.reader.position - 1));
@@ -1319,36 +1494,43 @@ impl<Cost: CostType> Interpreter<Cost> {
                 self.stack.push(address_to_u256(self.params.origin.clone()));
             }
             instructions::BALANCE => {
-                let address = u256_to_address(&self.stack.pop_back());
-                // #[cfg(feature = "shard")]
-                let val = ext.hash_map_storage_at(&address);
-                let balance = if val.1{
-                    val.0
-                } else {
-                    if address.to_low_u64_be().rem_euclid(AggProof::shard_count()) == AggProof::get_shard(){
-                        AggProof::incr_bal_read_count(1u64);
-                        let balance_temp = ext.balance(&address)?;
-                        let mut begin_round_word = ext.hash_map_beginning_storage_at(&address);
-                        if begin_round_word.1{
-                            ext.hash_map_txn_insert(address, begin_round_word.0);
-                        }else{
-                            ext.hash_map_txn_insert(address, balance_temp);
-                        }
-                        //in any case, push the address in the state address vec
-                        ext.push_address_txn_vec(address);
-                        balance_temp
-                    } else{
-                        ext.set_txn_incomplete();
-                        ext.set_next_shard(address.to_low_u64_be().rem_euclid(AggProof::shard_count()));
-                        println!("stopping execution from balance");
-                        return Ok(InstructionResult::StopExecution);
+                if let Some(ext) = shard_ext(ext) {
+                    let address = u256_to_address(&self.stack.pop_back());
+                    // #[cfg(feature = "shard")]
+                    let key_balance = AggProof::balance_key(&address);
+                    let val = ext.hash_map_storage_at(&key_balance);
+                    let balance = if val.1{
+                        val.0
+                    } else {
+                        if ext.shard_context().address_shard(&address) == ext.shard_context().shard(){
+                            ext.shard_context().counters().incr_bal_read(1u64);
+                            let balance_temp = ext.balance(&address)?;
+                            let mut begin_round_word = ext.hash_map_beginning_storage_at(&key_balance);
+                            if begin_round_word.1{
+                                ext.hash_map_txn_insert(key_balance, begin_round_word.0);
+                            }else{
+                                ext.hash_map_txn_insert(key_balance, balance_temp);
+                            }
+                            //in any case, push the address in the state address vec
+                            ext.push_address_txn_vec(key_balance);
+                            balance_temp
+                        } else{
+                            trace!(target: "evm", "Stopping execution from BALANCE of {}", address);
+                            self.stack.push(address_to_u256(address));
+                            let shard = ext.shard_context().address_shard(&address);
+                            return Ok(self.stop_for_shard(ext, shard));
 
-                    }
+                        }
 
-                };
-                // let balance = ext.balance(&address)?;
-                self.stack.push(balance);
-                ext.al_insert_address(address);
+                    };
+                    self.stack.push(balance);
+                    ext.al_insert_address(address);
+                } else {
+                    let address = u256_to_address(&self.stack.pop_back());
+                    let balance = ext.balance(&address)?;
+                    self.stack.push(balance);
+                    ext.al_insert_address(address);
+                }
             }
             instructions::CALLER => {
                 self.stack.push(address_to_u256(self.params.sender.clone()));
@@ -1458,34 +1640,37 @@ impl<Cost: CostType> Interpreter<Cost> {
             }
             instructions::CHAINID => self.stack.push(ext.chain_id().into()),
             instructions::SELFBALANCE => {
-                // #[cfg(feature = "shard")]
-                let val = ext.hash_map_storage_at(&self.params.address);
-                let balance = if val.1{
-                    val.0
-                } else {
-                    if self.params.address.to_low_u64_be().rem_euclid(AggProof::shard_count()) == AggProof::get_shard(){
-                        AggProof::incr_bal_read_count(1u64);
-                        let balance_temp = ext.balance(&self.params.address)?;
-                        let mut begin_round_word = ext.hash_map_beginning_storage_at(&self.params.address);
-                        if begin_round_word.1{
-                            ext.hash_map_txn_insert(self.params.address, begin_round_word.0);
-                        }else{
-                            ext.hash_map_txn_insert(self.params.address, balance_temp);
-                        }
-                        //in any case, push the address in the state address vec
-                        ext.push_address_txn_vec(self.params.address);
-                        balance_temp
-                    } else{
-                        ext.set_txn_incomplete();
-                        ext.set_next_shard(self.params.address.to_low_u64_be().rem_euclid(AggProof::shard_count()));
-                        print!("stopping execution SELFBALANCE");
-                        return Ok(InstructionResult::StopExecution);
+                if let Some(ext) = shard_ext(ext) {
+                    // #[cfg(feature = "shard")]
+                    let key_balance = AggProof::balance_key(&self.params.address);
+                    let val = ext.hash_map_storage_at(&key_balance);
+                    let balance = if val.1{
+                        val.0
+                    } else {
+                        if ext.shard_context().address_shard(&self.params.address) == ext.shard_context().shard(){
+                            ext.shard_context().counters().incr_bal_read(1u64);
+                            let balance_temp = ext.balance(&self.params.address)?;
+                            let mut begin_round_word = ext.hash_map_beginning_storage_at(&key_balance);
+                            if begin_round_word.1{
+                                ext.hash_map_txn_insert(key_balance, begin_round_word.0);
+                            }else{
+                                ext.hash_map_txn_insert(key_balance, balance_temp);
+                            }
+                            //in any case, push the address in the state address vec
+                            ext.push_address_txn_vec(key_balance);
+                            balance_temp
+                        } else{
+                            trace!(target: "evm", "Stopping execution from SELFBALANCE");
+                            let shard = ext.shard_context().address_shard(&self.params.address);
+                            return Ok(self.stop_for_shard(ext, shard));
 
-                    }
+                        }
 
-                };
-                self.stack.push(balance);
-                // self.stack.push(ext.balance(&self.params.address)?);
+                    };
+                    self.stack.push(balance);
+                } else {
+                    self.stack.push(ext.balance(&self.params.address)?);
+                }
             }
             instructions::BASEFEE => {
                 self.stack.push(ext.env_info().base_fee.unwrap_or_default());
@@ -1852,6 +2037,15 @@ fn set_sign(value: U256, sign: bool) -> U256 {
     }
 }
 
+/// Cross-shard hooks of `ext`, only used when built for a sharded network.
+fn shard_ext(ext: &mut dyn vm::Ext) -> Option<&mut dyn vm::ShardExt> {
+    if cfg!(feature = "shard") {
+        ext.shard_ext()
+    } else {
+        None
+    }
+}
+
 #[inline]
 fn u256_to_address(value: &U256) -> Address {
     let addr: H256 = BigEndianHash::from_uint(value);
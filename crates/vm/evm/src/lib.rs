@@ -31,7 +31,7 @@ extern crate hyperproofs;
 #[macro_use]
 extern crate lazy_static;
 
-#[cfg_attr(feature = "evm-debug", macro_use)]
+#[macro_use]
 extern crate log;
 
 #[cfg(test)]
@@ -18,6 +18,9 @@ use super::interpreter::MAX_SUB_STACK_SIZE;
 use ethereum_types::{Address, H256, U256};
 use factory::Factory;
 use hex_literal::hex;
+#[cfg(feature = "shard")]
+use hyperproofs::AggProof;
+use hyperproofs::{ShardConfig, ShardContext};
 use rustc_hex::FromHex;
 use std::{
     collections::{HashMap, HashSet},
@@ -26,6 +29,8 @@ use std::{
     str::FromStr,
     sync::Arc,
 };
+#[cfg(feature = "shard")]
+use vm::SuspendedFrame;
 use vm::{
     self,
     tests::{test_finalize, FakeCall, FakeCallType, FakeExt},
@@ -1670,6 +1675,237 @@ fn test_access_list_sload_sstore(factory: super::Factory) {
     assert_eq!(gas_left, U256::from(0));
 }
 
+#[cfg(feature = "shard")]
+evm_test! {test_shard_storage_keys_of_two_tokens: test_shard_storage_keys_of_two_tokens_int}
+#[cfg(feature = "shard")]
+fn test_shard_storage_keys_of_two_tokens(factory: super::Factory) {
+    let token_a = Address::from_low_u64_be(0xa);
+    let token_b = Address::from_low_u64_be(0xb);
+    let mut ext = FakeExt::new();
+    ext.sharded = true;
+
+    // 60vv 6000 55  sstore(loc: 0x00, val: vv), interleaved between the two tokens
+    for &(token, value) in &[(token_a, 1u8), (token_b, 2u8), (token_a, 3u8)] {
+        let mut params = ActionParams::default();
+        params.gas = U256::from(100_000);
+        params.code = Some(Arc::new(vec![0x60, value, 0x60, 0x00, 0x55]));
+        params.address = token;
+        let vm = factory.create(params, ext.schedule(), ext.depth());
+        test_finalize(vm.exec(&mut ext).ok().unwrap()).unwrap();
+    }
+
+    let origin = vm::ShardExt::origin_address(&ext);
+    let cache = ext.shard_cache.borrow();
+    assert_eq!(cache.len(), 2);
+    assert_eq!(
+        cache.get(&AggProof::storage_key(&token_a, &origin, &H256::zero())),
+        Some(&U256::from(3))
+    );
+    assert_eq!(
+        cache.get(&AggProof::storage_key(&token_b, &origin, &H256::zero())),
+        Some(&U256::from(2))
+    );
+}
+
+#[cfg(feature = "shard")]
+evm_test! {test_shared_storage_hops_to_home_shard: test_shared_storage_hops_to_home_shard_int}
+#[cfg(feature = "shard")]
+fn test_shared_storage_hops_to_home_shard(factory: super::Factory) {
+    // both contracts live on shard 1, the account they are executed for on shard 0
+    let pair = Address::from_low_u64_be(0x3);
+    let token = Address::from_low_u64_be(0x5);
+    let sload = |ext: &mut FakeExt, contract: Address| {
+        // 6000 54  sload(loc: 0x00)
+        let mut params = ActionParams::default();
+        params.gas = U256::from(100_000);
+        params.code = Some(Arc::new(hex!("600054").to_vec()));
+        params.address = contract;
+        let vm = factory.create(params, ext.schedule(), ext.depth());
+        test_finalize(vm.exec(ext).ok().unwrap()).unwrap();
+    };
+    let mut ext = FakeExt::new();
+    ext.sharded = true;
+    ext.shard_context = ShardContext::new(ShardConfig {
+        shard_count: 2,
+        shared_storage: vec![pair].into_iter().collect(),
+        ..Default::default()
+    });
+
+    sload(&mut ext, token);
+    assert_eq!(ext.next_shard, None);
+
+    sload(&mut ext, pair);
+    assert_eq!(ext.next_shard, Some(1));
+}
+
+evm_test! {test_unsharded_storage_stays_local: test_unsharded_storage_stays_local_int}
+fn test_unsharded_storage_stays_local(factory: super::Factory) {
+    // storage of the pair lives on shard 1, but the hooks are only used when sharded
+    let pair = Address::from_low_u64_be(0x3);
+    let mut params = ActionParams::default();
+    params.gas = U256::from(100_000);
+    // 6000 54 6001 55  sstore(loc: 0x01, val: sload(loc: 0x00))
+    params.code = Some(Arc::new(hex!("600054600155").to_vec()));
+    params.address = pair;
+    let mut ext = FakeExt::new();
+    ext.shard_context = ShardContext::new(ShardConfig {
+        shard_count: 2,
+        shared_storage: vec![pair].into_iter().collect(),
+        ..Default::default()
+    });
+    ext.store.insert(H256::zero(), H256::from_low_u64_be(5));
+    let vm = factory.create(params, ext.schedule(), ext.depth());
+    test_finalize(vm.exec(&mut ext).ok().unwrap()).unwrap();
+
+    assert_eq!(ext.next_shard, None);
+    assert!(ext.shard_cache.borrow().is_empty());
+    assert_eq!(
+        ext.store.get(&H256::from_low_u64_be(1)),
+        Some(&H256::from_low_u64_be(5))
+    );
+}
+
+#[cfg(not(feature = "shard"))]
+evm_test! {test_shard_hooks_need_shard_feature: test_shard_hooks_need_shard_feature_int}
+#[cfg(not(feature = "shard"))]
+fn test_shard_hooks_need_shard_feature(factory: super::Factory) {
+    // storage of the pair lives on shard 1, which a sharded build would stop for
+    let pair = Address::from_low_u64_be(0x3);
+    let mut params = ActionParams::default();
+    params.gas = U256::from(100_000);
+    // 6000 54 6001 55  sstore(loc: 0x01, val: sload(loc: 0x00))
+    params.code = Some(Arc::new(hex!("600054600155").to_vec()));
+    params.address = pair;
+    let mut ext = FakeExt::new();
+    ext.sharded = true;
+    ext.shard_context = ShardContext::new(ShardConfig {
+        shard_count: 2,
+        shared_storage: vec![pair].into_iter().collect(),
+        ..Default::default()
+    });
+    ext.store.insert(H256::zero(), H256::from_low_u64_be(5));
+    let vm = factory.create(params, ext.schedule(), ext.depth());
+    test_finalize(vm.exec(&mut ext).ok().unwrap()).unwrap();
+
+    assert_eq!(ext.next_shard, None);
+    assert_eq!(ext.suspended_frame, None);
+    assert_eq!(
+        ext.store.get(&H256::from_low_u64_be(1)),
+        Some(&H256::from_low_u64_be(5))
+    );
+}
+
+#[cfg(feature = "shard")]
+evm_test! {test_shard_stop_resumes_on_next_shard: test_shard_stop_resumes_on_next_shard_int}
+#[cfg(feature = "shard")]
+fn test_shard_stop_resumes_on_next_shard(factory: super::Factory) {
+    // 602a 6000 52  mstore(0x00, 0x2a)
+    // 6007          push 0x07
+    // 6000 54       sload(0x00), kept on shard 1
+    // 01            add
+    // 6000 51       mload(0x00)
+    // 01            add
+    // 6001 55       sstore(0x01, 0x2a + 0x07 + sload(0x00))
+    let code = hex!("602a60005260076000540160005101600155").to_vec();
+    let pair = Address::from_low_u64_be(0x3);
+    let config = ShardConfig {
+        shard_count: 2,
+        shared_storage: vec![pair].into_iter().collect(),
+        ..Default::default()
+    };
+    let run = |ext: &mut FakeExt, gas: U256| {
+        let mut params = ActionParams::default();
+        params.gas = gas;
+        params.code = Some(Arc::new(code.clone()));
+        params.address = pair;
+        let vm = factory.create(params, ext.schedule(), ext.depth());
+        test_finalize(vm.exec(ext).ok().unwrap()).unwrap()
+    };
+
+    let mut ext = FakeExt::new();
+    ext.sharded = true;
+    ext.shard_context = ShardContext::new(config.clone());
+    let gas_left = run(&mut ext, U256::from(100_000));
+    let frame = ext.suspended_frame.take().unwrap();
+
+    assert_eq!(ext.next_shard, Some(1));
+    assert_eq!(frame.pc, 9);
+    assert_eq!(frame.stack, vec![U256::from(7), U256::zero()]);
+    assert_eq!(frame.memory.len(), 32);
+    assert_eq!(frame.memory[31], 0x2a);
+    // the stopped sload is paid for on the shard it runs on
+    assert_eq!(frame.gas, gas_left);
+    assert_eq!(gas_left, U256::from(100_000 - 3 - 3 - 3 - 3 - 3 - 3));
+
+    let mut ext = FakeExt::new();
+    ext.sharded = true;
+    ext.shard_context = ShardContext::new(config);
+    ext.shard_context.set_shard(1);
+    ext.store.insert(H256::zero(), H256::from_low_u64_be(5));
+    ext.suspended_frame = Some(frame);
+    run(&mut ext, gas_left);
+
+    let written = AggProof::storage_key(&pair, &pair, &H256::from_low_u64_be(1));
+    assert_eq!(ext.next_shard, None);
+    assert_eq!(ext.suspended_frame, None);
+    assert_eq!(
+        ext.shard_cache.borrow().get(&written),
+        Some(&U256::from(0x2a + 0x07 + 5))
+    );
+}
+
+#[cfg(feature = "shard")]
+evm_test! {test_shard_frame_must_stop_at_an_instruction: test_shard_frame_must_stop_at_an_instruction_int}
+#[cfg(feature = "shard")]
+fn test_shard_frame_must_stop_at_an_instruction(factory: super::Factory) {
+    // 6000 54  sload(0x00)
+    let code = hex!("600054").to_vec();
+    let run = |frame: SuspendedFrame| {
+        let mut params = ActionParams::default();
+        params.gas = U256::from(100_000);
+        params.code = Some(Arc::new(code.clone()));
+        let mut ext = FakeExt::new();
+        ext.sharded = true;
+        ext.suspended_frame = Some(SuspendedFrame {
+            code_hash: params.code_hash.unwrap_or_default(),
+            gas: params.gas,
+            ..frame
+        });
+        let vm = factory.create(params, ext.schedule(), ext.depth());
+        test_finalize(vm.exec(&mut ext).ok().unwrap())
+    };
+
+    assert!(run(SuspendedFrame {
+        pc: 2,
+        stack: vec![U256::zero()],
+        ..Default::default()
+    })
+    .is_ok());
+    // the operand of the push
+    match run(SuspendedFrame {
+        pc: 1,
+        ..Default::default()
+    }) {
+        Err(vm::Error::Internal(_)) => (),
+        _ => assert!(false, "Expected the frame to be rejected"),
+    }
+    // writes of a call of another origin
+    match run(SuspendedFrame {
+        pc: 2,
+        stack: vec![U256::zero()],
+        storage_writes: vec![(
+            Default::default(),
+            Address::from_low_u64_be(0x3),
+            H256::zero(),
+            U256::one(),
+        )],
+        ..Default::default()
+    }) {
+        Err(vm::Error::Internal(_)) => (),
+        _ => assert!(false, "Expected the frame to be rejected"),
+    }
+}
+
 evm_test! {test_access_list_cheap_expensive_cheap: test_access_list_cheap_expensive_cheap_int}
 fn test_access_list_cheap_expensive_cheap(factory: super::Factory) {
     let code =
@@ -22,9 +22,11 @@ use call_type::CallType;
 use env_info::EnvInfo;
 use error::{Result, TrapKind};
 use ethereum_types::{Address, H256, U256};
+use hyperproofs::{ShardContext, ShardKey};
 use return_data::ReturnData;
 use schedule::Schedule;
 use std::sync::Arc;
+use suspended_frame::SuspendedFrame;
 
 #[derive(Debug)]
 /// Result of externalities create function.
@@ -203,37 +205,54 @@ pub trait Ext {
 
     /// Inserts an address into the list
     fn al_insert_address(&mut self, address: Address);
-    // #[cfg(feature = "shard")]
+
+    /// Cross-shard hooks, `None` when not executing as a node of a sharded network.
+    fn shard_ext(&mut self) -> Option<&mut dyn ShardExt> {
+        None
+    }
+}
+
+/// Cross-shard hooks of the externalities.
+///
+/// The interpreter only tracks shard data and stops calls needing data of another shard
+/// when the externalities provide them, see `Ext::shard_ext`.
+pub trait ShardExt: Ext {
     /// returns address of the code
     fn origin_address(&self) -> Address;
     ///retrives hashmap for storage
-    fn hash_map_storage_at(&self, key: &Address) ->(U256, bool);
+    fn hash_map_storage_at(&self, key: &ShardKey) ->(U256, bool);
     /// looks for already present value at the beginning of the round;
-    fn hash_map_beginning_storage_at(&self, key: &Address) ->(U256, bool);
+    fn hash_map_beginning_storage_at(&self, key: &ShardKey) ->(U256, bool);
     /// insert key, val in beginning round hashmap
-    fn hash_map_beginning_insert(&self, key: Address, val: U256);
+    fn hash_map_beginning_insert(&self, key: ShardKey, val: U256);
     /// insert key, val in the hashmap cache
-    fn hash_map_cache_insert(&self, key: Address, val: U256);
+    fn hash_map_cache_insert(&self, key: ShardKey, val: U256);
     /// inserts key,val in the hashmap
-    fn hash_map_txn_insert(&self, key: Address, val: U256);
+    fn hash_map_txn_insert(&self, key: ShardKey, val: U256);
     /// insert key,val in hashmap global insert
-    fn hash_map_global_insert(&self, key: Address, val: U256);
+    fn hash_map_global_insert(&self, key: ShardKey, val: U256);
     ///set a transaction incomplete;
     fn set_txn_incomplete(&mut self);
     /// set next shard for an incomplete transaction
     fn set_next_shard(&mut self, shard: u64);
+    /// keeps the frame of a call stopped for another shard, to resume it there
+    fn suspend_frame(&mut self, frame: SuspendedFrame);
+    /// takes the frame the transaction resumes from, if any
+    fn resume_frame(&mut self) -> Option<SuspendedFrame>;
     /// returns complete status from status
     fn txn_complete_status(&mut self) -> Option<bool>;
     ///pushes in the address vec
-    fn push_address_txn_vec(&mut self, a: Address);
+    fn push_address_txn_vec(&mut self, a: ShardKey);
     /// pushes in the temp sstore val
-    fn push_temp_sstore_val(&mut self, k: Address, code: Address, ah: H256, v: U256);
+    fn push_temp_sstore_val(&mut self, k: ShardKey, code: Address, ah: H256, v: U256);
     ///pushes in the temp sstore delta
-    fn push_temp_sstore_delta(&mut self, a: u64, s: String, sh: u64);
-    /// returns static flag
-    fn static_flag(&self) -> bool ;
+    fn push_temp_sstore_delta(&mut self, k: ShardKey, s: String, sh: u64);
+    /// queues a delta of a storage key of shard `sh` for the next commit round
+    fn push_shard_delta(&mut self, k: &ShardKey, s: &str, sh: u64) -> Result<()>;
     ///tells whether it is a create transaction
     fn is_create_txn(&self)->bool;
-
+    /// marks the transaction as reverted
     fn reverted(&mut self, s: bool);
+    /// shard state of the executing node
+    fn shard_context(&self) -> &ShardContext;
 }
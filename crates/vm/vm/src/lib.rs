@@ -18,6 +18,7 @@
 
 extern crate ethereum_types;
 extern crate ethjson;
+extern crate hyperproofs;
 extern crate keccak_hash as hash;
 extern crate parity_bytes as bytes;
 extern crate patricia_trie_ethereum as ethtrie;
@@ -31,6 +32,7 @@ mod error;
 mod ext;
 mod return_data;
 pub mod schedule;
+mod suspended_frame;
 
 pub mod tests;
 
@@ -39,9 +41,10 @@ pub use action_params::{ActionParams, ActionValue, ParamsType};
 pub use call_type::CallType;
 pub use env_info::{EnvInfo, LastHashes};
 pub use error::{Error, ExecTrapError, ExecTrapResult, Result, TrapError, TrapKind, TrapResult};
-pub use ext::{ContractCreateResult, CreateContractAddress, Ext, MessageCallResult};
+pub use ext::{ContractCreateResult, CreateContractAddress, Ext, MessageCallResult, ShardExt};
 pub use return_data::{GasLeft, ReturnData};
 pub use schedule::{CleanDustMode, Schedule, WasmCosts};
+pub use suspended_frame::SuspendedFrame;
 
 /// Virtual Machine interface
 pub trait Exec {
@@ -0,0 +1,161 @@
+// Copyright 2015-2020 Parity Technologies (UK) Ltd.
+// This file is part of OpenEthereum.
+
+// OpenEthereum is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// OpenEthereum is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
+
+//! State of a call suspended on one shard, carried to the shard it continues on.
+
+use bytes::Bytes;
+use ethereum_types::{Address, H256, U256};
+use hyperproofs::ShardKey;
+use rlp::{Decodable, DecoderError, Encodable, Rlp, RlpStream};
+
+/// Top-level interpreter frame stopped at an instruction which needs data of another shard.
+///
+/// The next shard restores the frame and executes the stopped instruction again instead
+/// of running the call from the start.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SuspendedFrame {
+    /// Hash of the executed code, the frame is only resumed into the same code.
+    pub code_hash: H256,
+    /// Call depth of the frame.
+    pub depth: u64,
+    /// Position of the stopped instruction.
+    pub pc: u64,
+    /// Gas left before the stopped instruction.
+    pub gas: U256,
+    /// Gas paid for the memory in use.
+    pub mem_gas: U256,
+    /// Stack, bottom first.
+    pub stack: Vec<U256>,
+    /// Subroutine return stack.
+    pub return_stack: Vec<u64>,
+    /// Memory.
+    pub memory: Bytes,
+    /// Data returned by the last call.
+    pub return_data: Bytes,
+    /// Storage writes of the executed hops, `(key, origin, slot, value)`, added by the
+    /// externalities. They are applied once the call completes.
+    pub storage_writes: Vec<(ShardKey, Address, H256, U256)>,
+    /// Vector deltas of the storage writes, `(key, delta, shard)`.
+    pub storage_deltas: Vec<(ShardKey, String, u64)>,
+}
+
+impl Encodable for SuspendedFrame {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(11);
+        s.append(&self.code_hash);
+        s.append(&self.depth);
+        s.append(&self.pc);
+        s.append(&self.gas);
+        s.append(&self.mem_gas);
+        s.append_list(&self.stack);
+        s.append_list(&self.return_stack);
+        s.append(&self.memory);
+        s.append(&self.return_data);
+        s.begin_list(self.storage_writes.len());
+        for &(ref key, ref address, ref slot, ref value) in &self.storage_writes {
+            s.begin_list(4);
+            s.append(key);
+            s.append(address);
+            s.append(slot);
+            s.append(value);
+        }
+        s.begin_list(self.storage_deltas.len());
+        for &(ref key, ref delta, ref shard) in &self.storage_deltas {
+            s.begin_list(3);
+            s.append(key);
+            s.append(delta);
+            s.append(shard);
+        }
+    }
+}
+
+impl Decodable for SuspendedFrame {
+    fn decode(rlp: &Rlp) -> Result<Self, DecoderError> {
+        if rlp.item_count()? != 11 {
+            return Err(DecoderError::RlpIncorrectListLen);
+        }
+        let storage_writes = rlp
+            .at(9)?
+            .iter()
+            .map(|write| {
+                Ok((
+                    write.val_at(0)?,
+                    write.val_at(1)?,
+                    write.val_at(2)?,
+                    write.val_at(3)?,
+                ))
+            })
+            .collect::<Result<_, DecoderError>>()?;
+        let storage_deltas = rlp
+            .at(10)?
+            .iter()
+            .map(|delta| Ok((delta.val_at(0)?, delta.val_at(1)?, delta.val_at(2)?)))
+            .collect::<Result<_, DecoderError>>()?;
+        Ok(SuspendedFrame {
+            code_hash: rlp.val_at(0)?,
+            depth: rlp.val_at(1)?,
+            pc: rlp.val_at(2)?,
+            gas: rlp.val_at(3)?,
+            mem_gas: rlp.val_at(4)?,
+            stack: rlp.list_at(5)?,
+            return_stack: rlp.list_at(6)?,
+            memory: rlp.val_at(7)?,
+            return_data: rlp.val_at(8)?,
+            storage_writes,
+            storage_deltas,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SuspendedFrame;
+    use ethereum_types::{Address, H256, U256};
+    use rlp::*;
+
+    #[test]
+    fn should_encode_and_decode_suspended_frame() {
+        let original = SuspendedFrame {
+            code_hash: H256::from_low_u64_be(1),
+            depth: 0,
+            pc: 42,
+            gas: U256::from(90_000),
+            mem_gas: U256::from(6),
+            stack: vec![U256::from(1), U256::from(2)],
+            return_stack: vec![7],
+            memory: vec![0u8; 32],
+            return_data: vec![1, 2, 3],
+            storage_writes: vec![(
+                H256::from_low_u64_be(2),
+                Address::from_low_u64_be(3),
+                H256::from_low_u64_be(4),
+                U256::from(5),
+            )],
+            storage_deltas: vec![(H256::from_low_u64_be(6), "-5".to_owned(), 1)],
+        };
+        let encoded = encode(&original);
+        let decoded: SuspendedFrame = decode(&encoded).expect("failure decoding SuspendedFrame");
+        assert_eq!(original, decoded);
+    }
+
+    #[test]
+    fn should_reject_truncated_suspended_frame() {
+        let mut s = RlpStream::new_list(2);
+        s.append(&H256::zero());
+        s.append(&0u64);
+        assert!(decode::<SuspendedFrame>(&s.out()).is_err());
+    }
+}
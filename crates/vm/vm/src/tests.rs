@@ -15,6 +15,7 @@
 // along with OpenEthereum.  If not, see <http://www.gnu.org/licenses/>.
 
 use std::{
+    cell::RefCell,
     collections::{HashMap, HashSet},
     sync::Arc,
 };
@@ -24,6 +25,7 @@ use bytes::Bytes;
 use error::TrapKind;
 use ethereum_types::{Address, H256, U256};
 use hash::keccak;
+use hyperproofs::{ShardContext, ShardKey};
 use CallType;
 use ContractCreateResult;
 use CreateContractAddress;
@@ -34,6 +36,8 @@ use MessageCallResult;
 use Result;
 use ReturnData;
 use Schedule;
+use ShardExt;
+use SuspendedFrame;
 
 pub struct FakeLogEntry {
     pub topics: Vec<H256>,
@@ -78,6 +82,13 @@ pub struct FakeExt {
     pub tracing: bool,
     pub is_static: bool,
     pub access_list: AccessList,
+    pub shard_context: ShardContext,
+    pub shard_cache: RefCell<HashMap<ShardKey, U256>>,
+    /// Execute with the shard hooks, as a node of a sharded network does.
+    pub sharded: bool,
+    pub next_shard: Option<u64>,
+    /// Frame kept by the last shard stop, or to be resumed by the next execution.
+    pub suspended_frame: Option<SuspendedFrame>,
 
     chain_id: u64,
 }
@@ -168,52 +179,6 @@ impl FakeExt {
 }
 
 impl Ext for FakeExt {
-
-    fn origin_address(&self) -> Address{
-        Address::zero()
-    }
-    fn push_address_txn_vec(&mut self, a: Address) {    }
-    fn hash_map_storage_at(&self, key: &Address) ->(U256, bool){
-        (U256::zero(), true)
-    }
-    fn hash_map_beginning_storage_at(&self, key: &Address) ->(U256, bool){(U256::zero(), true)}
-    fn hash_map_txn_insert(&self, key: Address, val: U256){
-
-    }
-    fn is_create_txn(&self) -> bool {
-        false
-    }
-    fn hash_map_beginning_insert(&self, key: Address, val: U256){
-
-    }
-    fn hash_map_cache_insert(&self, key: Address, val: U256){
-
-    }
-    fn hash_map_global_insert(&self, key: Address, val: U256){
-
-    }
-    fn set_txn_incomplete(&mut self){
-
-    }
-
-    fn push_temp_sstore_val(&mut self, k: Address, code: Address, ah: H256, v: U256){
-
-    }
-    fn static_flag(&self) -> bool {
-        false
-    }
-    fn push_temp_sstore_delta(&mut self, a: u64, s: String, sh: u64){
-
-    }
-    fn reverted(&mut self, s: bool) {
-
-    }
-    fn set_next_shard(&mut self, shard: u64){
-
-    }
-    fn txn_complete_status(&mut self) -> Option<bool>{
-        None
-    }
     fn initial_storage_at(&self, key: &H256) -> Result<H256> {
         match self.initial_store.get(key) {
             Some(value) => Ok(*value),
@@ -384,4 +349,72 @@ impl Ext for FakeExt {
     fn al_insert_address(&mut self, address: Address) {
         self.access_list.insert_address(address)
     }
+
+    fn shard_ext(&mut self) -> Option<&mut dyn ShardExt> {
+        if self.sharded {
+            Some(self)
+        } else {
+            None
+        }
+    }
+}
+
+impl ShardExt for FakeExt {
+    fn origin_address(&self) -> Address{
+        Address::zero()
+    }
+    fn push_address_txn_vec(&mut self, a: ShardKey) {    }
+    fn hash_map_storage_at(&self, key: &ShardKey) ->(U256, bool){
+        match self.shard_cache.borrow().get(key) {
+            Some(value) => (*value, true),
+            None => (U256::zero(), false),
+        }
+    }
+    fn hash_map_beginning_storage_at(&self, key: &ShardKey) ->(U256, bool){(U256::zero(), false)}
+    fn hash_map_txn_insert(&self, key: ShardKey, val: U256){
+
+    }
+    fn is_create_txn(&self) -> bool {
+        false
+    }
+    fn hash_map_beginning_insert(&self, key: ShardKey, val: U256){
+
+    }
+    fn hash_map_cache_insert(&self, key: ShardKey, val: U256){
+        self.shard_cache.borrow_mut().insert(key, val);
+    }
+    fn hash_map_global_insert(&self, key: ShardKey, val: U256){
+
+    }
+    fn set_txn_incomplete(&mut self){
+
+    }
+
+    fn push_temp_sstore_val(&mut self, k: ShardKey, code: Address, ah: H256, v: U256){
+
+    }
+    fn push_temp_sstore_delta(&mut self, k: ShardKey, s: String, sh: u64){
+
+    }
+    fn push_shard_delta(&mut self, k: &ShardKey, s: &str, sh: u64) -> Result<()> {
+        Ok(())
+    }
+    fn reverted(&mut self, s: bool) {
+
+    }
+    fn shard_context(&self) -> &ShardContext {
+        &self.shard_context
+    }
+    fn set_next_shard(&mut self, shard: u64){
+        self.next_shard = Some(shard);
+    }
+    fn suspend_frame(&mut self, frame: SuspendedFrame){
+        self.suspended_frame = Some(frame);
+    }
+    fn resume_frame(&mut self) -> Option<SuspendedFrame>{
+        self.suspended_frame.take()
+    }
+    fn txn_complete_status(&mut self) -> Option<bool>{
+        None
+    }
 }